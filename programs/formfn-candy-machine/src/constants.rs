@@ -3,7 +3,15 @@ use mpl_token_metadata::state::{
 };
 use solana_program::pubkey::Pubkey;
 
-use crate::{HIDDEN_SETTINGS_SPACE, SPL_TOKEN_ALLOWLIST_SETTINGS_SPACE};
+use crate::{
+    BONDING_CURVE_SETTINGS_SPACE, COLLECTION_ALLOWLIST_SETTINGS_SPACE,
+    COMPRESSED_NFT_SETTINGS_SPACE, CONFIG_LINE_SETTINGS_SPACE, DUTCH_AUCTION_REBATE_STATE_SPACE,
+    DUTCH_AUCTION_SETTINGS_SPACE, HIDDEN_SETTINGS_SPACE, MERKLE_ALLOWLIST_ROOT_SPACE,
+    MINT_CAPS_SPACE, MINT_PHASE_SETTINGS_SPACE, PAYMENT_OPTION_SPACE, PHASE_LIMITS_SPACE,
+    PRIMARY_SALE_SPLIT_SPACE, PROGRAMMABLE_NFT_SETTINGS_SPACE, SHUFFLE_SETTINGS_SPACE,
+    SPL_TOKEN_ALLOWLIST_SETTINGS_SPACE, SPL_TOKEN_ALLOWLIST_TOKEN_SPACE,
+    TRUSTED_RANDOMNESS_ACCOUNT_SPACE,
+};
 
 pub const EXPIRE_OFFSET: i64 = 10 * 60;
 pub const PREFIX: &str = "candy_machine";
@@ -15,14 +23,29 @@ pub const FREEZE_FEE: u64 = 0; //100000; // 0.0001 SOL
 
 pub const MAX_FREEZE_TIME: i64 = 60 * 60 * 24 * 31; // 1 month
 
+// Bits of CandyMachine.features.
 pub const COLLECTIONS_FEATURE_INDEX: usize = 0;
 pub const FREEZE_FEATURE_INDEX: usize = 1;
 pub const FREEZE_LOCK_FEATURE_INDEX: usize = 2;
 
+// Current layout version of CandyMachine.features.
+pub const FEATURES_VERSION: u8 = 1;
+// Legacy feature flags are the first 6 characters of the uuid.
+pub const LEGACY_FEATURE_FLAGS_LEN: usize = 6;
+
 pub const COLLECTION_PDA_SIZE: usize = 8 + 32 + 32;
 
+// Size of a config line without ConfigLineSettings.
 pub const CONFIG_LINE_SIZE: usize = 4 + MAX_NAME_LENGTH + 4 + MAX_URI_LENGTH;
 
+// In practice the quantity is also limited by the transaction size and the
+// compute budget.
+pub const MAX_MINT_MANY_QUANTITY: usize = 5;
+// mint, metadata, master_edition and recipient_token_account.
+pub const MINT_MANY_ACCOUNTS_PER_NFT: usize = 4;
+// The named accounts of MintManyNFT, which precede its remaining_accounts.
+pub const MINT_MANY_NAMED_ACCOUNTS: usize = 15;
+
 pub const A_TOKEN: Pubkey = solana_program::pubkey!("ATokenGPvbdGVxr1b2hvZbsiqW5xWH25efTNsLJA8knL");
 pub const COMPUTE_BUDGET: Pubkey =
     solana_program::pubkey!("ComputeBudget111111111111111111111111111111");
@@ -36,20 +59,45 @@ pub const ANTI_BOT_MAINNET_AUTHORITY: Pubkey =
 pub const MERKLE_ROOT_SIZE: usize = 32;
 pub const NUMBER_OF_MERKLE_ROOTS_TO_STORE: usize = 100;
 pub const MERKLE_ALLOWLIST_ROOT_LIST_SPACE: usize =
-    MERKLE_ROOT_SIZE * NUMBER_OF_MERKLE_ROOTS_TO_STORE;
+    MERKLE_ALLOWLIST_ROOT_SPACE * NUMBER_OF_MERKLE_ROOTS_TO_STORE;
 
 // This value is limited by the max transaction size.
 pub const MAX_ROOT_NUMBER_PER_APPEND_MERKLE_ALLOWLIST_TX: usize = 10;
 
 pub const MAX_OMNI_MINT_WALLETS: usize = 5;
 
+pub const MAX_MINT_PHASES: usize = 5;
+pub const MINT_PHASES_SPACE: usize = MINT_PHASE_SETTINGS_SPACE * MAX_MINT_PHASES;
+
+pub const MAX_PRIMARY_SALE_SPLITS: usize = 5;
+pub const PRIMARY_SALE_SPLITS_SPACE: usize = PRIMARY_SALE_SPLIT_SPACE * MAX_PRIMARY_SALE_SPLITS;
+pub const PRIMARY_SALE_SPLITS_TOTAL_BASIS_POINTS: u16 = 10000;
+
+pub const MAX_PLATFORM_FEE_BASIS_POINTS: u16 = 10000;
+
+pub const MAX_PAYMENT_OPTIONS: usize = 3;
+pub const PAYMENT_OPTIONS_SPACE: usize = PAYMENT_OPTION_SPACE * MAX_PAYMENT_OPTIONS;
+
+pub const MAX_SPL_TOKEN_ALLOWLIST_TOKENS: usize = 3;
+pub const SPL_TOKEN_ALLOWLIST_TOKENS_SPACE: usize =
+    SPL_TOKEN_ALLOWLIST_TOKEN_SPACE * MAX_SPL_TOKEN_ALLOWLIST_TOKENS;
+
+// Config array start of the current account version, see
+// layout::get_config_array_start for older versions.
 pub const CONFIG_ARRAY_START: usize = 8 + // key
 // CandyMachine:
+1 + // version
 32 + // formfn_authority
 32 + // creator_authority
 32 + // wallet
 33 + // token mint
 8 + // items redeemed
+8 + // allowlist_items_sold
+2 + // platform_fee_basis_points
+33 + // platform_fee_wallet
+DUTCH_AUCTION_REBATE_STATE_SPACE + // dutch_auction_rebate_state
+1 + // features_version
+8 + // features
 // CandyMachineData:
 4 + 6 + // uuid
 8 + // price
@@ -68,6 +116,21 @@ pub const CONFIG_ARRAY_START: usize = 8 + // key
 HIDDEN_SETTINGS_SPACE + // hidden_settings
 1 + // bot_protection_enabled
 2 + // limit_per_address
-1 + // sequential_mint_order_enabled
+1 + // mint_order
 4 + MERKLE_ALLOWLIST_ROOT_LIST_SPACE + // merkle_allowlist_root_list vec
-SPL_TOKEN_ALLOWLIST_SETTINGS_SPACE; // spl_token_allowlist_settings
+SPL_TOKEN_ALLOWLIST_SETTINGS_SPACE + // spl_token_allowlist_settings
+4 + MINT_PHASES_SPACE + // mint_phases vec
+4 + PRIMARY_SALE_SPLITS_SPACE + // primary_sale_splits vec
+DUTCH_AUCTION_SETTINGS_SPACE + // dutch_auction_settings
+BONDING_CURVE_SETTINGS_SPACE + // bonding_curve_settings
+4 + PAYMENT_OPTIONS_SPACE + // payment_options vec
+PROGRAMMABLE_NFT_SETTINGS_SPACE + // programmable_nft_settings
+COMPRESSED_NFT_SETTINGS_SPACE + // compressed_nft_settings
+1 + // open_edition_enabled
+CONFIG_LINE_SETTINGS_SPACE + // config_line_settings
+TRUSTED_RANDOMNESS_ACCOUNT_SPACE + // trusted_randomness_account
+SHUFFLE_SETTINGS_SPACE + // shuffle_settings
+PHASE_LIMITS_SPACE + // phase_limits
+MINT_CAPS_SPACE + // mint_caps
+COLLECTION_ALLOWLIST_SETTINGS_SPACE + // collection_allowlist_settings
+4 + SPL_TOKEN_ALLOWLIST_TOKENS_SPACE; // spl_token_allowlist_tokens vec
@@ -119,4 +119,154 @@ pub enum CandyError {
     InvalidMintPrice,
     #[msg("Invalid allowlist settings. Can only enable a single allowlist feature at a time.")]
     InvalidAllowlistSettings,
+    #[msg("Too many mint phases provided.")]
+    TooManyMintPhases,
+    #[msg("Invalid merkle allowlist root settings provided.")]
+    InvalidMerkleAllowlistRootSettings,
+    #[msg("The merkle allowlist root for the provided proof is not live.")]
+    MerkleAllowlistRootNotLive,
+    #[msg("Invalid mint quantity provided.")]
+    InvalidMintQuantity,
+    #[msg("The recipient must sign the mint, gasless minting is not supported when the NFT is frozen or SPL allowlist tokens are burned.")]
+    RecipientMustSign,
+    #[msg("Can only provide up to 5 primary sale splits.")]
+    TooManyPrimarySaleSplits,
+    #[msg("Primary sale split basis points must be non-zero and add up to 10000.")]
+    InvalidPrimarySaleSplits,
+    #[msg("Primary sale split account does not match the split address.")]
+    PrimarySaleSplitMismatch,
+    #[msg("Invalid platform fee settings provided.")]
+    InvalidPlatformFee,
+    #[msg("Platform fee account does not match the platform fee wallet.")]
+    PlatformFeeWalletMismatch,
+    #[msg("Invalid Dutch auction settings provided.")]
+    InvalidDutchAuctionSettings,
+    #[msg("Dutch auction settings cannot be changed after minting has started.")]
+    CannotChangeDutchAuctionAfterMint,
+    #[msg("Dutch auction rebates are not enabled for this candy machine.")]
+    DutchAuctionRebateNotEnabled,
+    #[msg("The Dutch auction has not ended yet.")]
+    DutchAuctionNotEnded,
+    #[msg("There is no Dutch auction rebate to claim.")]
+    NoDutchAuctionRebate,
+    #[msg("The Dutch auction proceeds have already been released.")]
+    DutchAuctionProceedsAlreadyReleased,
+    #[msg("Can't withdraw before the Dutch auction proceeds are released.")]
+    NoWithdrawWithDutchAuctionProceeds,
+    #[msg("Invalid bonding curve settings provided.")]
+    InvalidBondingCurveSettings,
+    #[msg("Too many payment options provided.")]
+    TooManyPaymentOptions,
+    #[msg("Payment options can't be combined with a Dutch auction or bonding curve.")]
+    InvalidPaymentOptions,
+    #[msg("Invalid payment option index provided.")]
+    InvalidPaymentOptionIndex,
+    #[msg("Treasury wallet doesn't match the selected payment option.")]
+    TreasuryWalletMismatch,
+    #[msg("The refund window has closed.")]
+    RefundWindowClosed,
+    #[msg("This NFT can't be refunded.")]
+    RefundNotAvailable,
+    #[msg("Refund receipt doesn't match the NFT being thawed.")]
+    RefundReceiptMismatch,
+    #[msg("Programmable NFT settings cannot be changed after minting has started or a collection is set.")]
+    CannotChangeProgrammableNftSettings,
+    #[msg("Rule set account doesn't match the candy machine rule set.")]
+    RuleSetMismatch,
+    #[msg("Failed to build a token metadata instruction.")]
+    InstructionBuilderFailed,
+    #[msg("Compressed NFTs can't also be programmable NFTs.")]
+    InvalidCompressedNftSettings,
+    #[msg("Compressed NFT settings cannot be changed after minting has started or while freeze or collections are enabled.")]
+    CannotChangeCompressedNftSettings,
+    #[msg("Merkle tree account doesn't match the candy machine Merkle tree.")]
+    MerkleTreeMismatch,
+    #[msg("This isn't supported for candy machines which mint compressed NFTs.")]
+    NotSupportedForCompressedNfts,
+    #[msg("Open editions can't use hidden settings or mint programmable or compressed NFTs.")]
+    InvalidOpenEditionSettings,
+    #[msg("Open edition mode can't be turned on or off after the candy machine is created.")]
+    CannotChangeOpenEditionMode,
+    #[msg("Master edition must have no prints and a max supply of at least items_available.")]
+    InvalidOpenEditionMasterEdition,
+    #[msg("Master edition doesn't match the candy machine open edition.")]
+    OpenEditionMismatch,
+    #[msg("Can't change the open edition master edition while the sale is live.")]
+    NoChangingOpenEditionDuringMint,
+    #[msg("This isn't supported for open edition candy machines.")]
+    NotSupportedForOpenEditions,
+    #[msg("Candy machine doesn't mint open editions.")]
+    OpenEditionNotEnabled,
+    #[msg("Incorrect master edition authority.")]
+    IncorrectOpenEditionAuthority,
+    #[msg("Unsupported candy machine account version.")]
+    UnsupportedCandyMachineVersion,
+    #[msg("Candy machine account must be migrated with migrate_candy_machine first.")]
+    CandyMachineNeedsMigration,
+    #[msg("Candy machine account is already on the latest version.")]
+    CandyMachineAlreadyMigrated,
+    #[msg("Candy machine can only be compacted once it has sold out or the sale has ended.")]
+    CannotCompactCandyMachine,
+    #[msg("Candy machine doesn't have config lines to compact.")]
+    NothingToCompact,
+    #[msg("Can't compact Candy Machine while freeze is active. Disable freeze first.")]
+    NoCompactWithFreeze,
+    #[msg("Candy machine config lines were removed by compact_candy_machine.")]
+    CandyMachineCompacted,
+    #[msg("Config line name is longer than the max name length.")]
+    ConfigLineNameTooLong,
+    #[msg("Config line uri is longer than the max uri length.")]
+    ConfigLineUriTooLong,
+    #[msg("Can't change config lines after items have begun to be minted.")]
+    NoChangingConfigLinesDuringMint,
+    #[msg("Config line hasn't been added yet.")]
+    ConfigLineNotLoaded,
+    #[msg("Can't resize the candy machine after items have begun to be minted.")]
+    NoResizingAfterMint,
+    #[msg("Items available must be greater than zero.")]
+    InvalidItemsAvailable,
+    #[msg("Config line prefixes and lengths must fit in the max name and uri lengths, and can't be used with hidden settings or open editions.")]
+    InvalidConfigLineSettings,
+    #[msg("Can't change the config line settings of a candy machine.")]
+    CannotChangeConfigLineSettings,
+    #[msg("Randomness account doesn't match the candy machine's trusted randomness account.")]
+    IncorrectTrustedRandomnessAccount,
+    #[msg("Trusted randomness account is too small for the randomness offset.")]
+    InvalidTrustedRandomnessAccountData,
+    #[msg("Shuffle settings are required for a shuffled mint order with hidden settings, and can't be used otherwise.")]
+    InvalidShuffleSettings,
+    #[msg("Can't change the shuffle settings after items have begun to be minted.")]
+    CannotChangeShuffleSettingsAfterMint,
+    #[msg(
+        "The candy machine must sell out or its sale must end before the shuffle seed is revealed."
+    )]
+    CandyMachineNotSoldOut,
+    #[msg("Candy machine doesn't have a shuffled mint order.")]
+    MintOrderNotShuffled,
+    #[msg("Shuffle seed has already been revealed.")]
+    ShuffleSeedAlreadyRevealed,
+    #[msg("Shuffle seed doesn't match the seed commitment.")]
+    InvalidShuffleSeed,
+    #[msg("The allowlist phase has sold all of the items it's capped at.")]
+    AllowlistItemsCapReached,
+    #[msg("Recipient doesn't hold a verified NFT from the allowlist collection.")]
+    NoCollectionAllowlistNft,
+    #[msg("This NFT from the allowlist collection has already been used to mint.")]
+    CollectionAllowlistNftAlreadyUsed,
+    #[msg("Too many SPL allowlist tokens.")]
+    TooManySplTokenAllowlistTokens,
+    #[msg("Can't add, remove or reorder mint phases after items have begun to be minted.")]
+    CannotChangeMintPhasesAfterMint,
+    #[msg("Bonding curve settings cannot be changed after minting has started.")]
+    CannotChangeBondingCurveAfterMint,
+    #[msg("Can't change the trusted randomness account after items have begun to be minted.")]
+    CannotChangeTrustedRandomnessAccountAfterMint,
+    #[msg("Can't change the mint order after items have begun to be minted.")]
+    CannotChangeMintOrderAfterMint,
+    #[msg("Candy machine can only grow by MAX_PERMITTED_DATA_INCREASE bytes per resize.")]
+    ResizeExceedsMaxDataIncrease,
+    #[msg("Can't withdraw before every Dutch auction rebate is claimed.")]
+    NoWithdrawWithUnclaimedDutchAuctionRebates,
+    #[msg("Primary sale splits can't be used with freeze, the frozen funds are unlocked to a single wallet.")]
+    NoPrimarySaleSplitsWithFreeze,
 }
@@ -0,0 +1,194 @@
+use anchor_lang::prelude::*;
+use mpl_token_metadata::state::{MAX_CREATOR_LEN, MAX_CREATOR_LIMIT, MAX_SYMBOL_LENGTH};
+use solana_program::pubkey::PUBKEY_BYTES;
+
+use crate::{
+    constants::{
+        CONFIG_ARRAY_START, MAX_OMNI_MINT_WALLETS, MERKLE_ROOT_SIZE,
+        NUMBER_OF_MERKLE_ROOTS_TO_STORE,
+    },
+    CandyError, CandyMachine, CandyMachineData, Creator, DutchAuctionRebateState, HiddenSettings,
+    MerkleAllowlistRoot, MintOrder, SplTokenAllowlistSettings, HIDDEN_SETTINGS_SPACE,
+    SPL_TOKEN_ALLOWLIST_SETTINGS_SPACE,
+};
+
+// Byte layout of CandyMachine accounts before and after the account was
+// versioned. The config lines, config count and mint bitmask all follow the
+// config array start, so migrating an account moves them as one block.
+
+/// Version of newly created candy machines.
+pub const CANDY_MACHINE_VERSION: u8 = 1;
+
+/// The version byte directly follows the account discriminator, so it's read
+/// from the same offset in every version. Version 0 accounts have no version
+/// byte, their formfn_authority starts here.
+pub const VERSION_OFFSET: usize = 8;
+
+/// Config array start of version 0 accounts, which are laid out as
+/// CandyMachineV0.
+const V0_CONFIG_ARRAY_START: usize = 8 + // key
+// CandyMachine:
+32 + // formfn_authority
+32 + // creator_authority
+32 + // wallet
+33 + // token mint
+8 + // items redeemed
+// CandyMachineData:
+4 + 6 + // uuid
+8 + // price
+9 + // optional premint_price
+9 + // optional allowlist_price
+4 + MAX_SYMBOL_LENGTH + // u32 len + symbol
+8 + // items available
+2 + // seller fee basis points
+8 + // max supply
+1 + // is mutable
+9 + // allowlist_sale_start_time
+8 + // public_sale_start_time
+8 + // public_sale_end_time
+4 + MAX_CREATOR_LIMIT * MAX_CREATOR_LEN + // creators vec
+4 + 32 * MAX_OMNI_MINT_WALLETS + // omni_mint_wallets vec
+HIDDEN_SETTINGS_SPACE + // hidden_settings
+1 + // bot_protection_enabled
+2 + // limit_per_address
+1 + // sequential_mint_order_enabled
+4 + MERKLE_ROOT_SIZE * NUMBER_OF_MERKLE_ROOTS_TO_STORE + // merkle_allowlist_root_list vec
+SPL_TOKEN_ALLOWLIST_SETTINGS_SPACE; // spl_token_allowlist_settings
+
+/// Returns the offset of the config array for the given account version.
+pub fn get_config_array_start(version: u8) -> Result<usize> {
+    match version {
+        0 => Ok(V0_CONFIG_ARRAY_START),
+        CANDY_MACHINE_VERSION => Ok(CONFIG_ARRAY_START),
+        _ => err!(CandyError::UnsupportedCandyMachineVersion),
+    }
+}
+
+/// Returns the formfn_authority of an account of the given version.
+pub fn get_formfn_authority(data: &[u8], version: u8) -> Result<Pubkey> {
+    let offset = match version {
+        0 => VERSION_OFFSET,
+        CANDY_MACHINE_VERSION => VERSION_OFFSET + 1,
+        _ => return err!(CandyError::UnsupportedCandyMachineVersion),
+    };
+    let formfn_authority = data
+        .get(offset..offset + PUBKEY_BYTES)
+        .and_then(|bytes| Pubkey::try_from(bytes).ok())
+        .ok_or(CandyError::UnsupportedCandyMachineVersion)?;
+
+    Ok(formfn_authority)
+}
+
+/// Converts the bytes in front of the config array of a version 0 account
+/// into the layout of the current version.
+///
+/// The old header is deserialized and the account is serialized again into
+/// zeroed space, since the bytes after a serialized vec or option which has
+/// shrunk aren't cleared when an account is written.
+pub fn migrate_header(header: &[u8]) -> Result<Vec<u8>> {
+    let mut buf = header
+        .get(VERSION_OFFSET..)
+        .ok_or(CandyError::UnsupportedCandyMachineVersion)?;
+    let candy_machine = CandyMachineV0::deserialize(&mut buf)?.migrate();
+
+    let mut new_header = vec![0; CONFIG_ARRAY_START];
+    candy_machine.try_serialize(&mut new_header.as_mut_slice())?;
+
+    Ok(new_header)
+}
+
+/// CandyMachine account before the account was versioned.
+#[derive(AnchorDeserialize)]
+struct CandyMachineV0 {
+    formfn_authority: Pubkey,
+    creator_authority: Pubkey,
+    treasury_wallet: Pubkey,
+    treasury_mint: Option<Pubkey>,
+    items_redeemed: u64,
+    data: CandyMachineDataV0,
+}
+
+#[derive(AnchorDeserialize)]
+struct CandyMachineDataV0 {
+    uuid: String,
+    price: u64,
+    premint_price: Option<u64>,
+    allowlist_price: Option<u64>,
+    symbol: String,
+    seller_fee_basis_points: u16,
+    max_supply: u64,
+    items_available: u64,
+    is_mutable: bool,
+    allowlist_sale_start_time: Option<i64>,
+    public_sale_start_time: i64,
+    public_sale_end_time: i64,
+    creators: Vec<Creator>,
+    omni_mint_wallets: Vec<Pubkey>,
+    hidden_settings: Option<HiddenSettings>,
+    bot_protection_enabled: bool,
+    limit_per_address: u16,
+    sequential_mint_order_enabled: bool,
+    merkle_allowlist_root_list: Vec<[u8; 32]>,
+    spl_token_allowlist_settings: Option<SplTokenAllowlistSettings>,
+}
+
+impl CandyMachineV0 {
+    /// Converts the account into the current version, which added the version
+    /// byte and every field which was added since.
+    fn migrate(self) -> CandyMachine {
+        let data = self.data;
+        CandyMachine {
+            version: CANDY_MACHINE_VERSION,
+            formfn_authority: self.formfn_authority,
+            creator_authority: self.creator_authority,
+            treasury_wallet: self.treasury_wallet,
+            treasury_mint: self.treasury_mint,
+            items_redeemed: self.items_redeemed,
+            // Items sold during the allowlist phase weren't counted.
+            allowlist_items_sold: 0,
+            // The platform fee starts out disabled.
+            platform_fee_basis_points: 0,
+            platform_fee_wallet: None,
+            dutch_auction_rebate_state: DutchAuctionRebateState::default(),
+            // The feature flags stay in the uuid until migrate_feature_flags
+            // moves them to the features bitfield.
+            features_version: 0,
+            features: 0,
+            data: CandyMachineData {
+                uuid: data.uuid,
+                price: data.price,
+                premint_price: data.premint_price,
+                allowlist_price: data.allowlist_price,
+                symbol: data.symbol,
+                seller_fee_basis_points: data.seller_fee_basis_points,
+                max_supply: data.max_supply,
+                items_available: data.items_available,
+                is_mutable: data.is_mutable,
+                allowlist_sale_start_time: data.allowlist_sale_start_time,
+                public_sale_start_time: data.public_sale_start_time,
+                public_sale_end_time: data.public_sale_end_time,
+                creators: data.creators,
+                omni_mint_wallets: data.omni_mint_wallets,
+                hidden_settings: data.hidden_settings,
+                bot_protection_enabled: data.bot_protection_enabled,
+                limit_per_address: data.limit_per_address,
+                mint_order: if data.sequential_mint_order_enabled {
+                    MintOrder::SequentialPremint
+                } else {
+                    MintOrder::Random
+                },
+                // Roots were bound to optional windows and prices, a plain
+                // root is live during the whole allowlist phase.
+                merkle_allowlist_root_list: data
+                    .merkle_allowlist_root_list
+                    .into_iter()
+                    .map(MerkleAllowlistRoot::from)
+                    .collect(),
+                spl_token_allowlist_settings: data.spl_token_allowlist_settings,
+                // The settings which were added to CandyMachineData start
+                // out unset.
+                ..CandyMachineData::default()
+            },
+        }
+    }
+}
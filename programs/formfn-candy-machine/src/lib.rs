@@ -1,5 +1,6 @@
 pub mod constants;
 pub mod errors;
+pub mod layout;
 pub mod processor;
 pub mod state;
 pub mod utils;
@@ -54,9 +55,28 @@ pub mod formfn_candy_machine {
         handle_update_authority(ctx, new_authority)
     }
 
+    pub fn update_platform_fee(
+        ctx: Context<UpdatePlatformFee>,
+        platform_fee_basis_points: u16,
+        platform_fee_wallet: Option<Pubkey>,
+    ) -> Result<()> {
+        handle_update_platform_fee(ctx, platform_fee_basis_points, platform_fee_wallet)
+    }
+
+    pub fn migrate_feature_flags(ctx: Context<MigrateFeatureFlags>) -> Result<()> {
+        handle_migrate_feature_flags(ctx)
+    }
+
+    pub fn migrate_candy_machine(
+        ctx: Context<MigrateCandyMachine>,
+        from_version: u8,
+    ) -> Result<()> {
+        handle_migrate_candy_machine(ctx, from_version)
+    }
+
     pub fn append_merkle_allowlist_roots(
         ctx: Context<AppendMerkleAllowlistRoots>,
-        roots_to_append: Vec<[u8; 32]>,
+        roots_to_append: Vec<MerkleAllowlistRoot>,
     ) -> Result<()> {
         handle_append_merkle_allowlist_roots(ctx, roots_to_append)
     }
@@ -73,20 +93,60 @@ pub mod formfn_candy_machine {
         handle_add_config_lines(ctx, index, config_lines)
     }
 
-    pub fn set_collection(ctx: Context<SetCollection>) -> Result<()> {
+    pub fn remove_config_lines(
+        ctx: Context<RemoveConfigLines>,
+        index: u32,
+        count: u32,
+    ) -> Result<()> {
+        handle_remove_config_lines(ctx, index, count)
+    }
+
+    pub fn replace_config_lines(
+        ctx: Context<ReplaceConfigLines>,
+        index: u32,
+        config_lines: Vec<ConfigLine>,
+    ) -> Result<()> {
+        handle_replace_config_lines(ctx, index, config_lines)
+    }
+
+    pub fn resize_candy_machine(
+        ctx: Context<ResizeCandyMachine>,
+        items_available: u64,
+    ) -> Result<()> {
+        handle_resize_candy_machine(ctx, items_available)
+    }
+
+    pub fn reveal_shuffle_seed(ctx: Context<RevealShuffleSeed>, seed: [u8; 32]) -> Result<()> {
+        handle_reveal_shuffle_seed(ctx, seed)
+    }
+
+    pub fn set_collection<'info>(
+        ctx: Context<'_, '_, '_, 'info, SetCollection<'info>>,
+    ) -> Result<()> {
         handle_set_collection(ctx)
     }
 
-    pub fn remove_collection(ctx: Context<RemoveCollection>) -> Result<()> {
+    pub fn remove_collection<'info>(
+        ctx: Context<'_, '_, '_, 'info, RemoveCollection<'info>>,
+    ) -> Result<()> {
         handle_remove_collection(ctx)
     }
 
+    pub fn set_open_edition(ctx: Context<SetOpenEdition>) -> Result<()> {
+        handle_set_open_edition(ctx)
+    }
+
+    pub fn remove_open_edition(ctx: Context<RemoveOpenEdition>) -> Result<()> {
+        handle_remove_open_edition(ctx)
+    }
+
     pub fn mint_nft<'info>(
         ctx: Context<'_, '_, '_, 'info, MintNFT<'info>>,
         creator_bump: u8,
         buyer_info_account_bump: u8,
         buyer_merkle_allowlist_proof_data: Option<BuyerMerkleAllowlistProofData>,
         expected_price: u64,
+        payment_option_index: Option<u8>,
     ) -> Result<()> {
         handle_mint_nft(
             ctx,
@@ -94,13 +154,50 @@ pub mod formfn_candy_machine {
             buyer_info_account_bump,
             buyer_merkle_allowlist_proof_data,
             expected_price,
+            payment_option_index,
         )
     }
 
-    pub fn set_collection_during_mint(ctx: Context<SetCollectionDuringMint>) -> Result<()> {
+    pub fn mint_many_nft<'info>(
+        ctx: Context<'_, '_, '_, 'info, MintManyNFT<'info>>,
+        creator_bump: u8,
+        buyer_info_account_bump: u8,
+        buyer_merkle_allowlist_proof_data: Option<BuyerMerkleAllowlistProofData>,
+        quantity: u8,
+        expected_price: u64,
+        payment_option_index: Option<u8>,
+    ) -> Result<()> {
+        handle_mint_many_nft(
+            ctx,
+            creator_bump,
+            buyer_info_account_bump,
+            buyer_merkle_allowlist_proof_data,
+            quantity,
+            expected_price,
+            payment_option_index,
+        )
+    }
+
+    pub fn set_collection_during_mint<'info>(
+        ctx: Context<'_, '_, '_, 'info, SetCollectionDuringMint<'info>>,
+    ) -> Result<()> {
         handle_set_collection_during_mint(ctx)
     }
 
+    pub fn claim_dutch_auction_rebate(ctx: Context<ClaimDutchAuctionRebate>) -> Result<()> {
+        handle_claim_dutch_auction_rebate(ctx)
+    }
+
+    pub fn release_dutch_auction_proceeds<'info>(
+        ctx: Context<'_, '_, '_, 'info, ReleaseDutchAuctionProceeds<'info>>,
+    ) -> Result<()> {
+        handle_release_dutch_auction_proceeds(ctx)
+    }
+
+    pub fn compact_candy_machine(ctx: Context<CompactCandyMachine>) -> Result<()> {
+        handle_compact_candy_machine(ctx)
+    }
+
     pub fn withdraw_funds<'info>(
         ctx: Context<'_, '_, '_, 'info, WithdrawFunds<'info>>,
     ) -> Result<()> {
@@ -115,10 +212,14 @@ pub mod formfn_candy_machine {
         handle_remove_freeze(ctx)
     }
 
-    pub fn thaw_nft(ctx: Context<ThawNFT>) -> Result<()> {
+    pub fn thaw_nft<'info>(ctx: Context<'_, '_, '_, 'info, ThawNFT<'info>>) -> Result<()> {
         handle_thaw_nft(ctx)
     }
 
+    pub fn refund_nft<'info>(ctx: Context<'_, '_, '_, 'info, RefundNFT<'info>>) -> Result<()> {
+        handle_refund_nft(ctx)
+    }
+
     pub fn unlock_funds<'info>(ctx: Context<'_, '_, '_, 'info, UnlockFunds<'info>>) -> Result<()> {
         handle_unlock_funds(ctx)
     }
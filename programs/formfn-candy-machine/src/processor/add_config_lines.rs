@@ -5,8 +5,7 @@ use arrayref::array_ref;
 use mpl_token_metadata::state::{MAX_NAME_LENGTH, MAX_URI_LENGTH};
 
 use crate::{
-    constants::{CONFIG_ARRAY_START, CONFIG_LINE_SIZE},
-    CandyError, CandyMachine, ConfigLine,
+    constants::CONFIG_ARRAY_START, CandyError, CandyMachine, CandyMachineData, ConfigLine,
 };
 
 /// Add multiple config lines to the candy machine.
@@ -23,10 +22,15 @@ pub fn handle_add_config_lines(
     config_lines: Vec<ConfigLine>,
 ) -> Result<()> {
     let candy_machine = &mut ctx.accounts.candy_machine;
+    candy_machine.assert_current_version()?;
+    candy_machine.assert_not_compacted(&candy_machine.to_account_info())?;
+    // Open editions are allocated without space for config lines.
+    if candy_machine.is_open_edition() {
+        return err!(CandyError::NotSupportedForOpenEditions);
+    }
     let account = candy_machine.to_account_info();
     let current_count = get_config_count(&account.data.borrow_mut())?;
     let mut data = account.data.borrow_mut();
-    let mut fixed_config_lines = Vec::with_capacity(config_lines.len());
     // No risk overflow because you literally cant store this many in an account
     // going beyond u32 only happens with the hidden store candies, which dont use this.
     if index > (candy_machine.data.items_available as u32) - 1 {
@@ -35,59 +39,17 @@ pub fn handle_add_config_lines(
     if candy_machine.data.hidden_settings.is_some() {
         return err!(CandyError::HiddenSettingsConfigsDoNotHaveConfigLines);
     }
-    for line in &config_lines {
-        let array_of_zeroes = vec![0u8; MAX_NAME_LENGTH - line.name.len()];
-        let name = line.name.clone() + std::str::from_utf8(&array_of_zeroes).unwrap();
-
-        let array_of_zeroes = vec![0u8; MAX_URI_LENGTH - line.uri.len()];
-        let uri = line.uri.clone() + std::str::from_utf8(&array_of_zeroes).unwrap();
-        fixed_config_lines.push(ConfigLine { name, uri })
-    }
-
-    let as_vec = fixed_config_lines.try_to_vec()?;
-    // remove unneeded u32 because we're just gonna edit the u32 at the front
-    let serialized: &[u8] = &as_vec.as_slice()[4..];
-
-    let position = CONFIG_ARRAY_START + 4 + (index as usize) * CONFIG_LINE_SIZE;
-
-    let array_slice: &mut [u8] =
-        &mut data[position..position + fixed_config_lines.len() * CONFIG_LINE_SIZE];
-
-    array_slice.copy_from_slice(serialized);
+    assert_config_lines_fit(candy_machine, index, config_lines.len())?;
 
-    let bit_mask_vec_start = CONFIG_ARRAY_START
-        + 4
-        + (candy_machine.data.items_available as usize) * CONFIG_LINE_SIZE
-        + 4;
+    write_config_lines(&mut data, &candy_machine.data, index, &config_lines)?;
 
     let mut new_count = current_count;
-    for i in 0..fixed_config_lines.len() {
+    for i in 0..config_lines.len() {
         let position = (index as usize)
             .checked_add(i)
             .ok_or(CandyError::NumericalOverflowError)?;
-        let my_position_in_vec = bit_mask_vec_start
-            + position
-                .checked_div(8)
-                .ok_or(CandyError::NumericalOverflowError)?;
-        let position_from_right = 7 - position
-            .checked_rem(8)
-            .ok_or(CandyError::NumericalOverflowError)?;
-        let mask = u8::pow(2, position_from_right as u32);
-
-        let old_value_in_vec = data[my_position_in_vec];
-        data[my_position_in_vec] |= mask;
-        msg!(
-            "My position in vec is {} my mask is going to be {}, the old value is {}",
-            position,
-            mask,
-            old_value_in_vec
-        );
-        msg!(
-            "My new value is {} and my position from right is {}",
-            data[my_position_in_vec],
-            position_from_right
-        );
-        if old_value_in_vec != data[my_position_in_vec] {
+        let was_loaded = set_config_line_loaded(&mut data, &candy_machine.data, position, true)?;
+        if !was_loaded {
             msg!("Increasing count");
             new_count = new_count
                 .checked_add(1)
@@ -95,9 +57,7 @@ pub fn handle_add_config_lines(
         }
     }
 
-    // plug in new count.
-    data[CONFIG_ARRAY_START..CONFIG_ARRAY_START + 4]
-        .copy_from_slice(&(new_count as u32).to_le_bytes());
+    set_config_count(&mut data, new_count);
 
     Ok(())
 }
@@ -105,3 +65,129 @@ pub fn handle_add_config_lines(
 pub fn get_config_count(data: &RefMut<&mut [u8]>) -> Result<usize> {
     Ok(u32::from_le_bytes(*array_ref![data, CONFIG_ARRAY_START, 4]) as usize)
 }
+
+pub(crate) fn set_config_count(data: &mut RefMut<&mut [u8]>, count: usize) {
+    data[CONFIG_ARRAY_START..CONFIG_ARRAY_START + 4].copy_from_slice(&(count as u32).to_le_bytes());
+}
+
+/// Checks that config lines written from index stay within items_available.
+pub(crate) fn assert_config_lines_fit(
+    candy_machine: &CandyMachine,
+    index: u32,
+    count: usize,
+) -> Result<()> {
+    let end = (index as usize)
+        .checked_add(count)
+        .ok_or(CandyError::NumericalOverflowError)?;
+    if end > candy_machine.data.items_available as usize {
+        return err!(CandyError::IndexGreaterThanLength);
+    }
+
+    Ok(())
+}
+
+/// Writes the config lines from index, padding each name and uri with zeroes
+/// to their max length.
+pub(crate) fn write_config_lines(
+    data: &mut RefMut<&mut [u8]>,
+    candy_data: &CandyMachineData,
+    index: u32,
+    config_lines: &[ConfigLine],
+) -> Result<()> {
+    // Without config line settings the names and uris are stored as borsh
+    // strings, and with them as the bare suffixes.
+    let (name_length, uri_length, with_length_prefix) = match &candy_data.config_line_settings {
+        Some(settings) => (
+            settings.name_length as usize,
+            settings.uri_length as usize,
+            false,
+        ),
+        None => (MAX_NAME_LENGTH, MAX_URI_LENGTH, true),
+    };
+
+    let mut position = get_config_line_start(candy_data, index as usize);
+    for line in config_lines {
+        if line.name.len() > name_length {
+            return err!(CandyError::ConfigLineNameTooLong);
+        }
+        if line.uri.len() > uri_length {
+            return err!(CandyError::ConfigLineUriTooLong);
+        }
+
+        position = write_padded_string(data, position, &line.name, name_length, with_length_prefix);
+        position = write_padded_string(data, position, &line.uri, uri_length, with_length_prefix);
+    }
+
+    Ok(())
+}
+
+fn write_padded_string(
+    data: &mut RefMut<&mut [u8]>,
+    position: usize,
+    value: &str,
+    length: usize,
+    with_length_prefix: bool,
+) -> usize {
+    let mut position = position;
+    if with_length_prefix {
+        data[position..position + 4].copy_from_slice(&(length as u32).to_le_bytes());
+        position += 4;
+    }
+    data[position..position + value.len()].copy_from_slice(value.as_bytes());
+    data[position + value.len()..position + length].fill(0);
+
+    position + length
+}
+
+/// Returns the offset of the config line at index.
+pub(crate) fn get_config_line_start(candy_data: &CandyMachineData, index: usize) -> usize {
+    CONFIG_ARRAY_START + 4 + index * candy_data.get_config_line_size()
+}
+
+/// Returns the offset of the bitmask of loaded config lines.
+pub(crate) fn get_config_bit_mask_start(candy_data: &CandyMachineData) -> usize {
+    get_config_line_start(candy_data, candy_data.items_available as usize) + 4
+}
+
+/// Writes the length of the bitmask of loaded config lines, which precedes
+/// it.
+pub(crate) fn set_config_bit_mask_len(
+    data: &mut RefMut<&mut [u8]>,
+    candy_data: &CandyMachineData,
+) -> Result<()> {
+    let bit_mask_start = get_config_bit_mask_start(candy_data);
+    let bit_mask_len = candy_data
+        .items_available
+        .checked_div(8)
+        .ok_or(CandyError::NumericalOverflowError)? as u32;
+    data[bit_mask_start - 4..bit_mask_start].copy_from_slice(&bit_mask_len.to_le_bytes());
+    Ok(())
+}
+
+/// Sets or clears the bit of the config line at position in the bitmask of
+/// loaded config lines. Returns whether the config line was loaded before.
+pub(crate) fn set_config_line_loaded(
+    data: &mut RefMut<&mut [u8]>,
+    candy_data: &CandyMachineData,
+    position: usize,
+    loaded: bool,
+) -> Result<bool> {
+    let bit_mask_vec_start = get_config_bit_mask_start(candy_data);
+    let my_position_in_vec = bit_mask_vec_start
+        + position
+            .checked_div(8)
+            .ok_or(CandyError::NumericalOverflowError)?;
+    let position_from_right = 7 - position
+        .checked_rem(8)
+        .ok_or(CandyError::NumericalOverflowError)?;
+    let mask = u8::pow(2, position_from_right as u32);
+
+    let was_loaded = (data[my_position_in_vec] & mask) != 0;
+    if loaded {
+        data[my_position_in_vec] |= mask;
+    } else {
+        data[my_position_in_vec] &= !mask;
+    }
+
+    Ok(was_loaded)
+}
@@ -1,13 +1,14 @@
 use anchor_lang::prelude::*;
 use mpl_token_metadata::{
-    instruction::revoke_collection_authority,
+    instruction::{
+        builders::RevokeBuilder, revoke_collection_authority, InstructionBuilder, RevokeArgs,
+    },
     state::{Metadata, TokenMetadataAccount},
 };
 use solana_program::program::invoke;
 
 use crate::{
-    cmp_pubkeys, constants::COLLECTIONS_FEATURE_INDEX, remove_feature_flag, CandyError,
-    CandyMachine, CollectionPda,
+    cmp_pubkeys, constants::COLLECTIONS_FEATURE_INDEX, CandyError, CandyMachine, CollectionPda,
 };
 
 /// Set the collection PDA for the candy machine
@@ -15,6 +16,7 @@ use crate::{
 pub struct RemoveCollection<'info> {
     #[account(mut, has_one = formfn_authority)]
     candy_machine: Account<'info, CandyMachine>,
+    #[account(mut)]
     formfn_authority: Signer<'info>,
     #[account(
         mut,
@@ -36,11 +38,17 @@ pub struct RemoveCollection<'info> {
     /// CHECK: account checked in CPI
     #[account(address = mpl_token_metadata::id())]
     token_metadata_program: UncheckedAccount<'info>,
+    // > Only needed if the candy machine mints programmable NFTs
+    // system_program
+    // instruction_sysvar_account
 }
 
-pub fn handle_remove_collection(ctx: Context<RemoveCollection>) -> Result<()> {
+pub fn handle_remove_collection<'info>(
+    ctx: Context<'_, '_, '_, 'info, RemoveCollection<'info>>,
+) -> Result<()> {
     let mint = ctx.accounts.mint.to_account_info();
     let candy_machine = &mut ctx.accounts.candy_machine;
+    candy_machine.assert_current_version()?;
     candy_machine.assert_not_minted(error!(CandyError::NoChangingCollectionDuringMint))?;
 
     let metadata: Metadata = Metadata::from_account_info(&ctx.accounts.metadata.to_account_info())?;
@@ -54,6 +62,45 @@ pub fn handle_remove_collection(ctx: Context<RemoveCollection>) -> Result<()> {
         return err!(CandyError::MintMismatch);
     }
     let authority_record = ctx.accounts.collection_authority_record.to_account_info();
+    if candy_machine.is_programmable() {
+        // The collection_authority_record is the collection delegate record of
+        // the CollectionPda, see set_collection.
+        let (system_program, instruction_sysvar) = match ctx.remaining_accounts {
+            [system_program, instruction_sysvar] => (system_program, instruction_sysvar),
+            _ => return err!(CandyError::IncorrectRemainingAccountsLen),
+        };
+        msg!(
+            "About to revoke collection delegate for {}.",
+            ctx.accounts.metadata.key()
+        );
+        let revoke_ix = RevokeBuilder::new()
+            .delegate_record(authority_record.key())
+            .delegate(ctx.accounts.collection_pda.key())
+            .metadata(ctx.accounts.metadata.key())
+            .mint(mint.key())
+            .authority(ctx.accounts.formfn_authority.key())
+            .payer(ctx.accounts.formfn_authority.key())
+            .system_program(system_program.key())
+            .sysvar_instructions(instruction_sysvar.key())
+            .build(RevokeArgs::CollectionV1)
+            .map_err(|_| CandyError::InstructionBuilderFailed)?
+            .instruction();
+        invoke(
+            &revoke_ix,
+            &[
+                authority_record,
+                ctx.accounts.collection_pda.to_account_info(),
+                ctx.accounts.metadata.to_account_info(),
+                mint,
+                ctx.accounts.formfn_authority.to_account_info(),
+                system_program.clone(),
+                instruction_sysvar.clone(),
+                ctx.accounts.token_metadata_program.to_account_info(),
+            ],
+        )?;
+        candy_machine.remove_feature(COLLECTIONS_FEATURE_INDEX);
+        return Ok(());
+    }
     let revoke_collection_infos = vec![
         authority_record.clone(),
         ctx.accounts.collection_pda.to_account_info(),
@@ -76,6 +123,6 @@ pub fn handle_remove_collection(ctx: Context<RemoveCollection>) -> Result<()> {
         ),
         revoke_collection_infos.as_slice(),
     )?;
-    remove_feature_flag(&mut candy_machine.data.uuid, COLLECTIONS_FEATURE_INDEX);
+    candy_machine.remove_feature(COLLECTIONS_FEATURE_INDEX);
     Ok(())
 }
@@ -1,7 +1,10 @@
 use anchor_lang::prelude::*;
 use mpl_token_metadata::{
     assertions::collection::assert_master_edition,
-    instruction::{approve_collection_authority, update_metadata_accounts_v2},
+    instruction::{
+        approve_collection_authority, builders::DelegateBuilder, update_metadata_accounts_v2,
+        DelegateArgs, InstructionBuilder,
+    },
     state::{Metadata, TokenMetadataAccount},
     utils::create_or_allocate_account_raw,
 };
@@ -10,7 +13,7 @@ use solana_program::program::invoke;
 use crate::{
     cmp_pubkeys,
     constants::{COLLECTIONS_FEATURE_INDEX, COLLECTION_PDA_SIZE},
-    set_feature_flag, CandyError, CandyMachine, CollectionPda,
+    CandyError, CandyMachine, CollectionPda,
 };
 
 /// Set the collection PDA for the candy machine
@@ -41,9 +44,13 @@ pub struct SetCollection<'info> {
     /// CHECK: account checked in CPI
     #[account(address = mpl_token_metadata::id())]
     token_metadata_program: UncheckedAccount<'info>,
+    // > Only needed if the candy machine mints programmable NFTs
+    // instruction_sysvar_account
 }
 
-pub fn handle_set_collection(ctx: Context<SetCollection>) -> Result<()> {
+pub fn handle_set_collection<'info>(
+    ctx: Context<'_, '_, '_, 'info, SetCollection<'info>>,
+) -> Result<()> {
     let mint = ctx.accounts.mint.to_account_info();
     let metadata: Metadata = Metadata::from_account_info(&ctx.accounts.metadata.to_account_info())?;
     if !cmp_pubkeys(
@@ -58,10 +65,61 @@ pub fn handle_set_collection(ctx: Context<SetCollection>) -> Result<()> {
     let edition = ctx.accounts.edition.to_account_info();
     let authority_record = ctx.accounts.collection_authority_record.to_account_info();
     let candy_machine = &mut ctx.accounts.candy_machine;
+    candy_machine.assert_current_version()?;
     candy_machine.assert_not_minted(error!(CandyError::NoChangingCollectionDuringMint))?;
+    if candy_machine.is_compressed() {
+        return err!(CandyError::NotSupportedForCompressedNfts);
+    }
+    // Print editions inherit the collection of their master edition.
+    if candy_machine.is_open_edition() {
+        return err!(CandyError::NotSupportedForOpenEditions);
+    }
     assert_master_edition(&metadata, &edition)?;
 
-    if authority_record.data_is_empty() {
+    // Programmable NFTs can only be verified by a collection delegate, whose
+    // delegate record is derived from the collection update_authority, so the
+    // update_authority is left unchanged.
+    if authority_record.data_is_empty() && candy_machine.is_programmable() {
+        let instruction_sysvar = ctx
+            .remaining_accounts
+            .get(0)
+            .ok_or(CandyError::IncorrectRemainingAccountsLen)?;
+        msg!(
+            "About to delegate collection for {} to {}.",
+            ctx.accounts.metadata.key(),
+            ctx.accounts.collection_pda.key
+        );
+        let delegate_ix = DelegateBuilder::new()
+            .delegate_record(authority_record.key())
+            .delegate(ctx.accounts.collection_pda.key())
+            .metadata(ctx.accounts.metadata.key())
+            .master_edition(edition.key())
+            .mint(mint.key())
+            .authority(ctx.accounts.formfn_authority.key())
+            .payer(ctx.accounts.payer.key())
+            .system_program(ctx.accounts.system_program.key())
+            .sysvar_instructions(instruction_sysvar.key())
+            .build(DelegateArgs::CollectionV1 {
+                authorization_data: None,
+            })
+            .map_err(|_| CandyError::InstructionBuilderFailed)?
+            .instruction();
+        invoke(
+            &delegate_ix,
+            &[
+                authority_record.clone(),
+                ctx.accounts.collection_pda.to_account_info(),
+                ctx.accounts.metadata.to_account_info(),
+                edition.clone(),
+                mint.clone(),
+                ctx.accounts.formfn_authority.to_account_info(),
+                ctx.accounts.payer.to_account_info(),
+                ctx.accounts.system_program.to_account_info(),
+                instruction_sysvar.clone(),
+                ctx.accounts.token_metadata_program.to_account_info(),
+            ],
+        )?;
+    } else if authority_record.data_is_empty() {
         let approve_collection_infos = vec![
             authority_record.clone(),
             ctx.accounts.collection_pda.to_account_info(),
@@ -136,6 +194,6 @@ pub fn handle_set_collection(ctx: Context<SetCollection>) -> Result<()> {
     collection_pda_object.mint = mint.key();
     collection_pda_object.candy_machine = candy_machine.key();
     collection_pda_object.try_serialize(&mut data_ref)?;
-    set_feature_flag(&mut candy_machine.data.uuid, COLLECTIONS_FEATURE_INDEX);
+    candy_machine.set_feature(COLLECTIONS_FEATURE_INDEX);
     Ok(())
 }
@@ -1,12 +1,25 @@
-use anchor_lang::prelude::*;
+use anchor_lang::{prelude::*, Discriminator};
 use mpl_token_metadata::{instruction::set_and_verify_collection, utils::assert_derivation};
 use solana_program::{
-    program::invoke_signed, sysvar, sysvar::instructions::get_instruction_relative,
+    instruction::Instruction, program::invoke_signed, sysvar,
+    sysvar::instructions::get_instruction_relative,
 };
 
-use crate::{cmp_pubkeys, CandyMachine, CollectionPda};
+use crate::{
+    cmp_pubkeys,
+    constants::MINT_MANY_NAMED_ACCOUNTS,
+    processor::mint::{
+        get_allowlist_accounts_layout, get_mint_payment_option, get_remaining_account_index,
+        is_payer_buyer_info_account_needed, MintRemainingAccountsLayout, NftRemainingAccounts,
+        RemainingAccounts,
+    },
+    verify_programmable_collection, CandyError, CandyMachine, CollectionPda,
+    VerifyProgrammableCollectionParams,
+};
 
-/// Sets and verifies the collection during a candy machine mint
+/// Sets and verifies the collection during a candy machine mint. A mint_nft
+/// instruction is followed by one set_collection_during_mint instruction, a
+/// mint_many_nft instruction by one for each NFT, in the same order.
 #[derive(Accounts)]
 pub struct SetCollectionDuringMint<'info> {
     #[account(has_one = creator_authority)]
@@ -32,11 +45,27 @@ pub struct SetCollectionDuringMint<'info> {
     creator_authority: UncheckedAccount<'info>,
     /// CHECK: account checked in CPI
     collection_authority_record: UncheckedAccount<'info>,
+    // > Only needed if the candy machine mints programmable NFTs
+    // system_program
 }
 
-pub fn handle_set_collection_during_mint(ctx: Context<SetCollectionDuringMint>) -> Result<()> {
+pub fn handle_set_collection_during_mint<'info>(
+    ctx: Context<'_, '_, '_, 'info, SetCollectionDuringMint<'info>>,
+) -> Result<()> {
+    ctx.accounts.candy_machine.assert_current_version()?;
     let ixs = &ctx.accounts.instruction_sysvar_account;
-    let previous_instruction = get_instruction_relative(-1, ixs)?;
+    // The number of set_collection_during_mint instructions between this one
+    // and the mint, which is the index of the NFT minted by a mint_many_nft
+    // instruction.
+    let mut nft_index: usize = 0;
+    let mut previous_instruction = get_instruction_relative(-1, ixs)?;
+    while cmp_pubkeys(&previous_instruction.program_id, &crate::id())
+        && previous_instruction.data.get(0..8)
+            == Some(&crate::instruction::SetCollectionDuringMint::discriminator()[..])
+    {
+        nft_index += 1;
+        previous_instruction = get_instruction_relative(-1 - nft_index as i64, ixs)?;
+    }
     if !cmp_pubkeys(&previous_instruction.program_id, &crate::id()) {
         msg!(
             "Transaction had ix with program id {}.",
@@ -52,14 +81,25 @@ pub fn handle_set_collection_during_mint(ctx: Context<SetCollectionDuringMint>)
     }
 
     let discriminator = &previous_instruction.data[0..8];
-    if discriminator != [211, 57, 6, 167, 15, 219, 35, 251] {
-        msg!("Transaction had ix with data {:?}.", discriminator);
-        return Ok(());
-    }
+    let mint_ix_metadata =
+        if discriminator == crate::instruction::MintNft::discriminator() && nft_index == 0 {
+            previous_instruction.accounts[5].pubkey
+        } else if discriminator == crate::instruction::MintManyNft::discriminator() {
+            match get_mint_many_nft_metadata(
+                &ctx.accounts.candy_machine,
+                &previous_instruction,
+                nft_index,
+            )? {
+                Some(mint_ix_metadata) => mint_ix_metadata,
+                None => return Ok(()),
+            }
+        } else {
+            msg!("Transaction had ix with data {:?}.", discriminator);
+            return Ok(());
+        };
 
     let mint_ix_accounts = previous_instruction.accounts;
     let mint_ix_cm = mint_ix_accounts[0].pubkey;
-    let mint_ix_metadata = mint_ix_accounts[4].pubkey;
     let signer = mint_ix_accounts[2].pubkey;
     let candy_key = ctx.accounts.candy_machine.key();
     let metadata = ctx.accounts.metadata.key();
@@ -102,6 +142,29 @@ pub fn handle_set_collection_during_mint(ctx: Context<SetCollectionDuringMint>)
         candy_key.as_ref(),
         &[bump],
     ];
+
+    // The collection of a programmable NFT is set when it is minted, and the
+    // collection_authority_record is the collection delegate record of the
+    // CollectionPda.
+    if ctx.accounts.candy_machine.is_programmable() {
+        let system_program = ctx
+            .remaining_accounts
+            .get(0)
+            .ok_or(CandyError::IncorrectRemainingAccountsLen)?;
+        return verify_programmable_collection(VerifyProgrammableCollectionParams {
+            metadata: ctx.accounts.metadata.to_account_info(),
+            authority: collection_pda.to_account_info(),
+            authority_signer_seeds: &signer_seeds,
+            delegate_record: ctx.accounts.collection_authority_record.to_account_info(),
+            collection_mint,
+            collection_metadata: ctx.accounts.collection_metadata.to_account_info(),
+            collection_master_edition: ctx.accounts.collection_master_edition.to_account_info(),
+            system_program: system_program.clone(),
+            instruction_sysvar: ctx.accounts.instruction_sysvar_account.to_account_info(),
+            token_metadata_program: ctx.accounts.token_metadata_program.to_account_info(),
+        });
+    }
+
     let set_collection_infos = vec![
         ctx.accounts.metadata.to_account_info(),
         collection_pda.to_account_info(),
@@ -129,3 +192,54 @@ pub fn handle_set_collection_during_mint(ctx: Context<SetCollectionDuringMint>)
     )?;
     Ok(())
 }
+
+/// Returns the metadata of the NFT at nft_index of a mint_many_nft
+/// instruction, which is passed in its remaining_accounts.
+fn get_mint_many_nft_metadata(
+    candy_machine: &CandyMachine,
+    mint_ix: &Instruction,
+    nft_index: usize,
+) -> Result<Option<Pubkey>> {
+    let mint_ix_args = crate::instruction::MintManyNft::try_from_slice(&mint_ix.data[8..])
+        .map_err(|_| error!(ErrorCode::InstructionDidNotDeserialize))?;
+    if nft_index >= mint_ix_args.quantity as usize {
+        msg!(
+            "Mint ix only mints {} NFTs, no NFT at index {}.",
+            mint_ix_args.quantity,
+            nft_index
+        );
+        return Ok(None);
+    }
+
+    let (_, treasury_mint) = get_mint_payment_option(
+        candy_machine,
+        mint_ix_args.payment_option_index,
+        &mint_ix.accounts[4].pubkey,
+    )?;
+    // The mint ran in the same transaction, so it saw the same mint phase.
+    let (spl_token_allowlist_accounts, collection_allowlist_accounts) =
+        get_allowlist_accounts_layout(candy_machine, Clock::get()?.unix_timestamp);
+    let layout = MintRemainingAccountsLayout {
+        treasury_mint,
+        quantity: mint_ix_args.quantity as usize,
+        nft_accounts_in_remaining_accounts: true,
+        payer_buyer_info_account: is_payer_buyer_info_account_needed(
+            candy_machine,
+            &mint_ix.accounts[2].pubkey,
+            &mint_ix.accounts[3].pubkey,
+        ),
+        spl_token_allowlist_accounts,
+        collection_allowlist_accounts,
+    };
+    let metadata_index = MINT_MANY_NAMED_ACCOUNTS
+        + get_remaining_account_index(
+            candy_machine,
+            &layout,
+            RemainingAccounts::Nft(nft_index, NftRemainingAccounts::Metadata),
+        );
+
+    Ok(mint_ix
+        .accounts
+        .get(metadata_index)
+        .map(|account_meta| account_meta.pubkey))
+}
@@ -0,0 +1,50 @@
+use anchor_lang::prelude::*;
+
+use crate::{
+    constants::{CONFIG_ARRAY_START, FREEZE_FEATURE_INDEX},
+    transfer_lamports_from_program_account, CandyError, CandyMachine,
+};
+
+/// Remove the config lines and mint bitmask of a sold out or expired candy
+/// machine and refund their rent. Unlike withdraw_funds, the candy machine
+/// settings and items_redeemed stay on chain.
+#[derive(Accounts)]
+pub struct CompactCandyMachine<'info> {
+    #[account(mut, has_one = formfn_authority)]
+    candy_machine: Account<'info, CandyMachine>,
+    #[account(mut)]
+    formfn_authority: Signer<'info>,
+}
+
+pub fn handle_compact_candy_machine(ctx: Context<CompactCandyMachine>) -> Result<()> {
+    let candy_machine = &ctx.accounts.candy_machine;
+    let candy_machine_info = candy_machine.to_account_info();
+    candy_machine.assert_current_version()?;
+
+    if !candy_machine.has_config_lines() || candy_machine.is_compacted(&candy_machine_info) {
+        return err!(CandyError::NothingToCompact);
+    }
+    if !candy_machine.is_sale_over(Clock::get()?.unix_timestamp) {
+        return err!(CandyError::CannotCompactCandyMachine);
+    }
+    // Refunds and thaws still need the mint bitmask.
+    if candy_machine.is_feature_active(FREEZE_FEATURE_INDEX) {
+        return err!(CandyError::NoCompactWithFreeze);
+    }
+
+    // Only the rent of the removed bytes is refunded. The rest of the
+    // lamports may be held for a Dutch auction.
+    let rent = Rent::get()?;
+    let refund = rent
+        .minimum_balance(candy_machine_info.data_len())
+        .saturating_sub(rent.minimum_balance(CONFIG_ARRAY_START));
+
+    candy_machine_info.realloc(CONFIG_ARRAY_START, false)?;
+    transfer_lamports_from_program_account(
+        &candy_machine_info,
+        &ctx.accounts.formfn_authority.to_account_info(),
+        refund,
+    )?;
+
+    Ok(())
+}
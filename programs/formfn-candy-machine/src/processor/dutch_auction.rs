@@ -0,0 +1,164 @@
+use anchor_lang::prelude::*;
+
+use crate::{
+    processor::mint::{
+        get_mint_payments, get_platform_fee_account, get_primary_sale_split_accounts,
+    },
+    transfer_lamports_from_program_account, BuyerInfoAccount, CandyError, CandyMachine,
+    BUYER_INFO_ACCOUNT_PREFIX,
+};
+
+/// Claim the rebate owed to a buyer once a Dutch auction with rebates ends.
+/// Rebates are owed to whoever paid for the mint, not the recipient of the NFT.
+#[derive(Accounts)]
+pub struct ClaimDutchAuctionRebate<'info> {
+    #[account(mut)]
+    candy_machine: Account<'info, CandyMachine>,
+    #[account(mut)]
+    buyer: Signer<'info>,
+    #[account(
+        mut,
+        seeds = [
+            BUYER_INFO_ACCOUNT_PREFIX.as_bytes(),
+            candy_machine.key().as_ref(),
+            buyer.key().as_ref()
+        ],
+        bump
+    )]
+    buyer_info_account: Account<'info, BuyerInfoAccount>,
+}
+
+/// Release the Dutch auction proceeds held above the floor price once the
+/// auction ends. The proceeds are paid out like a mint payment, including the
+/// platform fee and primary sale splits.
+#[derive(Accounts)]
+pub struct ReleaseDutchAuctionProceeds<'info> {
+    #[account(mut, has_one = treasury_wallet)]
+    candy_machine: Account<'info, CandyMachine>,
+    /// CHECK: treasury_wallet is checked against the candy machine
+    #[account(mut)]
+    treasury_wallet: UncheckedAccount<'info>,
+    // > Only needed if the candy machine has a platform fee
+    // platform fee wallet
+    // > Only needed if the candy machine has primary_sale_splits, in the same order
+    // split addresses
+}
+
+pub fn handle_claim_dutch_auction_rebate(ctx: Context<ClaimDutchAuctionRebate>) -> Result<()> {
+    let candy_machine = &ctx.accounts.candy_machine;
+    candy_machine.assert_current_version()?;
+    let buyer_info_account = &mut ctx.accounts.buyer_info_account;
+    let clock = Clock::get()?;
+
+    assert_dutch_auction_rebate_ended(candy_machine, clock.unix_timestamp)?;
+
+    // Every NFT ends up costing the clearing price, which is the lowest price
+    // paid during the auction.
+    let clearing_price_total = candy_machine
+        .dutch_auction_rebate_state
+        .clearing_price
+        .checked_mul(buyer_info_account.dutch_auction_number_bought as u64)
+        .ok_or(CandyError::NumericalOverflowError)?;
+    let rebate = buyer_info_account
+        .dutch_auction_amount_paid
+        .checked_sub(clearing_price_total)
+        .ok_or(CandyError::NumericalOverflowError)?;
+
+    if rebate == 0 {
+        return err!(CandyError::NoDutchAuctionRebate);
+    }
+
+    buyer_info_account.dutch_auction_amount_paid = 0;
+    buyer_info_account.dutch_auction_number_bought = 0;
+    let candy_machine = &mut ctx.accounts.candy_machine;
+    candy_machine
+        .dutch_auction_rebate_state
+        .release_amount_held(rebate)?;
+
+    transfer_lamports_from_program_account(
+        &candy_machine.to_account_info(),
+        &ctx.accounts.buyer.to_account_info(),
+        rebate,
+    )?;
+
+    msg!(
+        "Claimed Dutch auction rebate of {} lamports for {}.",
+        rebate,
+        ctx.accounts.buyer.key()
+    );
+
+    Ok(())
+}
+
+pub fn handle_release_dutch_auction_proceeds<'info>(
+    ctx: Context<'_, '_, '_, 'info, ReleaseDutchAuctionProceeds<'info>>,
+) -> Result<()> {
+    let candy_machine = &mut ctx.accounts.candy_machine;
+    candy_machine.assert_current_version()?;
+    let clock = Clock::get()?;
+
+    assert_dutch_auction_rebate_ended(candy_machine, clock.unix_timestamp)?;
+
+    if candy_machine.dutch_auction_rebate_state.proceeds_released {
+        return err!(CandyError::DutchAuctionProceedsAlreadyReleased);
+    }
+
+    // Validated to be set by assert_dutch_auction_rebate_ended.
+    let floor_price = candy_machine
+        .data
+        .dutch_auction_settings
+        .as_ref()
+        .map_or(0, |settings| settings.floor_price);
+    let proceeds = candy_machine
+        .dutch_auction_rebate_state
+        .get_proceeds(floor_price)?;
+
+    candy_machine.dutch_auction_rebate_state.proceeds_released = true;
+    candy_machine
+        .dutch_auction_rebate_state
+        .release_amount_held(proceeds)?;
+
+    // Dutch auctions with rebates are always paid in SOL.
+    let remaining_accounts = ctx.remaining_accounts;
+    let end_index = candy_machine.get_platform_fee_wallet().is_some() as usize
+        + candy_machine.data.primary_sale_splits.len();
+    if remaining_accounts.len() != end_index {
+        return err!(CandyError::IncorrectRemainingAccountsLen);
+    }
+    let payments = get_mint_payments(
+        candy_machine,
+        None,
+        ctx.accounts.treasury_wallet.to_account_info(),
+        false,
+        get_platform_fee_account(candy_machine, remaining_accounts, end_index),
+        get_primary_sale_split_accounts(candy_machine, remaining_accounts, end_index),
+        proceeds,
+    )?;
+
+    for (destination, amount) in payments.iter() {
+        transfer_lamports_from_program_account(
+            &candy_machine.to_account_info(),
+            destination,
+            *amount,
+        )?;
+    }
+
+    Ok(())
+}
+
+fn assert_dutch_auction_rebate_ended(candy_machine: &CandyMachine, now: i64) -> Result<()> {
+    let is_rebate_enabled = candy_machine
+        .data
+        .dutch_auction_settings
+        .as_ref()
+        .map_or(false, |settings| settings.rebate_enabled);
+    if !is_rebate_enabled {
+        return err!(CandyError::DutchAuctionRebateNotEnabled);
+    }
+
+    if !candy_machine.has_dutch_auction_ended(now) {
+        return err!(CandyError::DutchAuctionNotEnded);
+    }
+
+    Ok(())
+}
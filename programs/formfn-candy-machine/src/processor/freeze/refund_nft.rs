@@ -0,0 +1,213 @@
+use anchor_lang::prelude::*;
+use anchor_spl::token::{Mint, Token, TokenAccount};
+use mpl_token_metadata::instruction::thaw_delegated_account;
+use solana_program::program::invoke_signed;
+
+use crate::{
+    assert_is_ata, constants::FREEZE_FEATURE_INDEX, is_programmable_edition,
+    processor::mint::release_config_line, spl_token_burn, spl_token_transfer,
+    transfer_lamports_from_program_account, BuyerInfoAccount, CandyError, CandyMachine, FreezePda,
+    RefundReceipt, TokenBurnParams, TokenTransferParams, BUYER_INFO_ACCOUNT_PREFIX,
+};
+
+/// Refund an NFT which is still frozen. The NFT is burned and the payment
+/// held by the FreezePda is returned to the payer of the mint. The platform
+/// fee was paid out by the mint and isn't refunded. The NFT no longer counts
+/// towards the limits per address of its recipient or the allowlist items cap.
+#[derive(Accounts)]
+pub struct RefundNFT<'info> {
+    #[account(mut)]
+    candy_machine: Box<Account<'info, CandyMachine>>,
+    #[account(mut, seeds = [FreezePda::PREFIX.as_bytes(), candy_machine.key().as_ref()], bump, has_one = candy_machine)]
+    freeze_pda: Account<'info, FreezePda>,
+    #[account(
+        mut,
+        close = payer,
+        seeds = [
+            RefundReceipt::PREFIX.as_bytes(),
+            candy_machine.key().as_ref(),
+            mint.key().as_ref()
+        ],
+        bump,
+        has_one = candy_machine,
+        has_one = mint,
+        has_one = payer
+    )]
+    refund_receipt: Account<'info, RefundReceipt>,
+    #[account(mut, has_one = mint, has_one = owner)]
+    token_account: Account<'info, TokenAccount>,
+    owner: Signer<'info>,
+    #[account(mut)]
+    mint: Account<'info, Mint>,
+    /// CHECK: account checked in CPI
+    edition: UncheckedAccount<'info>,
+    /// CHECK: checked in refund_receipt constraints
+    #[account(mut)]
+    payer: UncheckedAccount<'info>,
+    /// CHECK: checked in account constraints, only read if the NFT was
+    /// counted in it
+    #[account(
+        mut,
+        seeds = [
+            BUYER_INFO_ACCOUNT_PREFIX.as_bytes(),
+            candy_machine.key().as_ref(),
+            refund_receipt.recipient.as_ref()
+        ],
+        bump
+    )]
+    buyer_info_account: UncheckedAccount<'info>,
+    token_program: Program<'info, Token>,
+    /// CHECK: checked in account constraints
+    #[account(address = mpl_token_metadata::id())]
+    token_metadata_program: UncheckedAccount<'info>,
+    // > Only needed if the NFT was paid for in an SPL token
+    // freeze_ata
+    // payer_ata
+}
+
+pub fn handle_refund_nft<'info>(ctx: Context<'_, '_, '_, 'info, RefundNFT<'info>>) -> Result<()> {
+    let candy_machine = &mut ctx.accounts.candy_machine;
+    candy_machine.assert_current_version()?;
+    let freeze_pda = &mut ctx.accounts.freeze_pda;
+    let refund_receipt = &ctx.accounts.refund_receipt;
+    let token_account = &ctx.accounts.token_account;
+    let mint = &ctx.accounts.mint;
+    let payer = &ctx.accounts.payer;
+    let token_program = &ctx.accounts.token_program;
+    let current_timestamp = Clock::get()?.unix_timestamp;
+
+    if !candy_machine.is_feature_active(FREEZE_FEATURE_INDEX)
+        || freeze_pda.thaw_eligible(current_timestamp, candy_machine)
+    {
+        return err!(CandyError::RefundWindowClosed);
+    }
+
+    // Dutch auction rebates are tracked per buyer rather than per NFT, so
+    // those NFTs can't be refunded individually. Programmable NFTs are locked
+    // rather than frozen and can't be burned by the FreezePda. Print edition
+    // numbers can't be reused, so open editions aren't refundable either, and
+    // neither are NFTs whose item was fixed by revealing the shuffle seed.
+    let is_rebate_enabled = candy_machine
+        .data
+        .dutch_auction_settings
+        .as_ref()
+        .map_or(false, |settings| settings.rebate_enabled);
+    if is_rebate_enabled
+        || candy_machine.is_open_edition()
+        || candy_machine.is_shuffle_seed_revealed()
+        || !token_account.is_frozen()
+        || is_programmable_edition(&ctx.accounts.edition.to_account_info())
+    {
+        return err!(CandyError::RefundNotAvailable);
+    }
+
+    let candy_key = candy_machine.key();
+    let freeze_seeds = [
+        FreezePda::PREFIX.as_bytes(),
+        candy_key.as_ref(),
+        &[*ctx.bumps.get("freeze_pda").unwrap()],
+    ];
+
+    invoke_signed(
+        &thaw_delegated_account(
+            mpl_token_metadata::ID,
+            freeze_pda.key(),
+            token_account.key(),
+            ctx.accounts.edition.key(),
+            mint.key(),
+        ),
+        &[
+            freeze_pda.to_account_info(),
+            token_account.to_account_info(),
+            ctx.accounts.edition.to_account_info(),
+            mint.to_account_info(),
+            token_program.to_account_info(),
+            ctx.accounts.token_metadata_program.to_account_info(),
+        ],
+        &[&freeze_seeds],
+    )?;
+
+    // The FreezePda is still the delegate of the token account.
+    spl_token_burn(TokenBurnParams {
+        mint: mint.to_account_info(),
+        source: token_account.to_account_info(),
+        amount: 1,
+        authority: freeze_pda.to_account_info(),
+        authority_signer_seeds: Some(&freeze_seeds),
+        token_program: token_program.to_account_info(),
+    })?;
+
+    match refund_receipt.treasury_mint {
+        Some(treasury_mint) => {
+            if ctx.remaining_accounts.len() != 2 {
+                return err!(CandyError::IncorrectRemainingAccountsLen);
+            }
+            let freeze_ata = &ctx.remaining_accounts[0];
+            let payer_ata = &ctx.remaining_accounts[1];
+            assert_is_ata(freeze_ata, &freeze_pda.key(), &treasury_mint)?;
+            assert_is_ata(payer_ata, &payer.key(), &treasury_mint)?;
+
+            spl_token_transfer(TokenTransferParams {
+                source: freeze_ata.to_account_info(),
+                destination: payer_ata.to_account_info(),
+                authority: freeze_pda.to_account_info(),
+                authority_signer_seeds: &freeze_seeds,
+                token_program: token_program.to_account_info(),
+                amount: refund_receipt.amount,
+            })?;
+        }
+        None => {
+            transfer_lamports_from_program_account(
+                &freeze_pda.to_account_info(),
+                &payer.to_account_info(),
+                refund_receipt.amount,
+            )?;
+        }
+    }
+
+    // The freeze fee would otherwise be paid out when the NFT is thawed.
+    transfer_lamports_from_program_account(
+        &freeze_pda.to_account_info(),
+        &payer.to_account_info(),
+        freeze_pda.freeze_fee,
+    )?;
+
+    freeze_pda.frozen_count = freeze_pda
+        .frozen_count
+        .checked_sub(1)
+        .ok_or(CandyError::NumericalOverflowError)?;
+
+    // The config line of the refunded NFT can be minted again.
+    if let Some(config_index) = refund_receipt.config_index {
+        release_config_line(candy_machine, config_index as usize)?;
+    }
+    candy_machine.items_redeemed = candy_machine
+        .items_redeemed
+        .checked_sub(1)
+        .ok_or(CandyError::NumericalOverflowError)?;
+
+    let purchase_counters = &refund_receipt.purchase_counters;
+    if purchase_counters.is_buyer_info_account_counted() {
+        let mut buyer_info_account: Account<BuyerInfoAccount> =
+            Account::try_from(&ctx.accounts.buyer_info_account.to_account_info())?;
+        buyer_info_account.release_purchase(purchase_counters)?;
+
+        // This re-serializes the account to persist the changes.
+        buyer_info_account.exit(&crate::id())?;
+    }
+    if purchase_counters.allowlist_items_sold {
+        candy_machine.allowlist_items_sold = candy_machine
+            .allowlist_items_sold
+            .checked_sub(1)
+            .ok_or(CandyError::NumericalOverflowError)?;
+    }
+
+    msg!(
+        "Refunded {} to {} for NFT {}.",
+        refund_receipt.amount,
+        payer.key(),
+        mint.key()
+    );
+
+    Ok(())
+}
@@ -3,7 +3,7 @@ use anchor_lang::AccountsClose;
 
 use crate::{
     constants::{FREEZE_FEATURE_INDEX, FREEZE_LOCK_FEATURE_INDEX},
-    remove_feature_flag, CandyError, CandyMachine, FreezePda,
+    CandyError, CandyMachine, FreezePda,
 };
 
 /// Removes the freeze flag from candy machine without closing the freeze pda unless no NFTs have been minted
@@ -19,9 +19,10 @@ pub struct RemoveFreeze<'info> {
 
 pub fn handle_remove_freeze(ctx: Context<RemoveFreeze>) -> Result<()> {
     let candy_machine = &mut ctx.accounts.candy_machine;
+    candy_machine.assert_current_version()?;
     let freeze_pda = &mut ctx.accounts.freeze_pda;
     freeze_pda.allow_thaw = true;
-    remove_feature_flag(&mut candy_machine.data.uuid, FREEZE_FEATURE_INDEX);
+    candy_machine.remove_feature(FREEZE_FEATURE_INDEX);
 
     // Closes the account to enable editing if minting hasn't started
     if candy_machine
@@ -29,7 +30,7 @@ pub fn handle_remove_freeze(ctx: Context<RemoveFreeze>) -> Result<()> {
         .is_ok()
     {
         freeze_pda.close(ctx.accounts.formfn_authority.to_account_info())?;
-        remove_feature_flag(&mut candy_machine.data.uuid, FREEZE_LOCK_FEATURE_INDEX);
+        candy_machine.remove_feature(FREEZE_LOCK_FEATURE_INDEX);
     }
     Ok(())
 }
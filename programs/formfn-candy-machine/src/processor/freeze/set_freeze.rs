@@ -3,7 +3,7 @@ use anchor_lang::prelude::*;
 use crate::{
     assert_is_ata,
     constants::{FREEZE_FEATURE_INDEX, FREEZE_LOCK_FEATURE_INDEX, MAX_FREEZE_TIME},
-    set_feature_flag, CandyError, CandyMachine, FreezePda,
+    CandyError, CandyMachine, FreezePda,
 };
 
 /// Set the Freeze PDA for the candy machine
@@ -26,28 +26,37 @@ pub struct SetFreeze<'info> {
     )]
     freeze_pda: Account<'info, FreezePda>,
     system_program: Program<'info, System>,
-    // > Only needed if spl token mint is enabled
+    // > One per SPL token accepted as payment, starting with the treasury mint
+    // followed by the payment_options in order
     // freeze_ata
 }
 
 pub fn handle_set_freeze(ctx: Context<SetFreeze>, freeze_time: i64) -> Result<()> {
     let candy_machine = &mut ctx.accounts.candy_machine;
+    candy_machine.assert_current_version()?;
     candy_machine.assert_not_minted(error!(CandyError::NoChangingFreezeDuringMint))?;
+    if candy_machine.is_compressed() {
+        return err!(CandyError::NotSupportedForCompressedNfts);
+    }
+    // unlock_funds pays out the frozen funds without the primary sale splits.
+    if !candy_machine.data.primary_sale_splits.is_empty() {
+        return err!(CandyError::NoPrimarySaleSplitsWithFreeze);
+    }
     let freeze_pda = &mut ctx.accounts.freeze_pda;
     if freeze_time > MAX_FREEZE_TIME {
         return err!(CandyError::EnteredFreezeIsMoreThanMaxFreeze);
     }
     freeze_pda.init(candy_machine.key(), None, freeze_time);
 
-    if let Some(mint_pubkey) = candy_machine.treasury_mint {
+    for (index, mint_pubkey) in candy_machine.get_payment_mints().iter().enumerate() {
         let freeze_ata = ctx
             .remaining_accounts
-            .get(0)
+            .get(index)
             .ok_or(CandyError::MissingFreezeAta)?;
-        assert_is_ata(freeze_ata, freeze_pda.to_account_info().key, &mint_pubkey)
+        assert_is_ata(freeze_ata, freeze_pda.to_account_info().key, mint_pubkey)
             .map_err(|_| CandyError::IncorrectFreezeAta)?;
     }
-    set_feature_flag(&mut candy_machine.data.uuid, FREEZE_FEATURE_INDEX);
-    set_feature_flag(&mut candy_machine.data.uuid, FREEZE_LOCK_FEATURE_INDEX);
+    candy_machine.set_feature(FREEZE_FEATURE_INDEX);
+    candy_machine.set_feature(FREEZE_LOCK_FEATURE_INDEX);
     Ok(())
 }
@@ -1,11 +1,23 @@
 use anchor_lang::prelude::*;
 use anchor_lang::system_program::{transfer, Transfer};
+use anchor_lang::AccountsClose;
 use anchor_spl::token::{Mint, Token, TokenAccount};
-use mpl_token_metadata::instruction::thaw_delegated_account;
+use mpl_token_metadata::{
+    instruction::{
+        builders::{RevokeBuilder, UnlockBuilder},
+        thaw_delegated_account, InstructionBuilder, RevokeArgs, UnlockArgs,
+    },
+    state::{TokenState, TOKEN_STATE_INDEX},
+};
 use solana_program::program::{invoke, invoke_signed};
 use spl_token::instruction::revoke;
 
-use crate::{cmp_pubkeys, CandyError, CandyMachine, FreezePda};
+use crate::{
+    cmp_pubkeys, is_programmable_edition, CandyError, CandyMachine, FreezePda, RefundReceipt,
+};
+
+/// Number of remaining accounts needed to thaw a programmable NFT.
+const PROGRAMMABLE_NFT_THAW_ACCOUNTS_LEN: usize = 5;
 
 /// Set the collection PDA for the candy machine
 #[derive(Accounts)]
@@ -29,9 +41,19 @@ pub struct ThawNFT<'info> {
     #[account(address = mpl_token_metadata::id())]
     token_metadata_program: UncheckedAccount<'info>,
     system_program: Program<'info, System>,
+    // > Only needed if the NFT is a programmable NFT. The token metadata program
+    // > is passed as the authorization rules accounts if it has no rule set.
+    // metadata
+    // token_record
+    // instruction_sysvar_account
+    // authorization_rules_program
+    // authorization_rules
+    // > Only needed to close the RefundReceipt of the NFT
+    // refund_receipt
+    // refund_receipt_payer
 }
 
-pub fn handle_thaw_nft(ctx: Context<ThawNFT>) -> Result<()> {
+pub fn handle_thaw_nft<'info>(ctx: Context<'_, '_, '_, 'info, ThawNFT<'info>>) -> Result<()> {
     let freeze_pda = &mut ctx.accounts.freeze_pda;
     let candy_machine = &mut ctx.accounts.candy_machine;
     let current_timestamp = Clock::get()?.unix_timestamp;
@@ -41,6 +63,7 @@ pub fn handle_thaw_nft(ctx: Context<ThawNFT>) -> Result<()> {
     } else {
         let candy_struct: Account<CandyMachine> =
             Account::try_from(&candy_machine.to_account_info())?;
+        candy_struct.assert_current_version()?;
         freeze_pda.thaw_eligible(current_timestamp, &candy_struct)
     };
     msg!("Can thaw: {}", can_thaw);
@@ -59,26 +82,97 @@ pub fn handle_thaw_nft(ctx: Context<ThawNFT>) -> Result<()> {
         candy_machine.key.as_ref(),
         &[*ctx.bumps.get("freeze_pda").unwrap()],
     ];
-    if token_account.is_frozen() {
+    let is_programmable = is_programmable_edition(&edition.to_account_info());
+    let (programmable_accounts, remaining_accounts) = if is_programmable {
+        if ctx.remaining_accounts.len() < PROGRAMMABLE_NFT_THAW_ACCOUNTS_LEN {
+            return err!(CandyError::IncorrectRemainingAccountsLen);
+        }
+        let (programmable_accounts, remaining_accounts) = ctx
+            .remaining_accounts
+            .split_at(PROGRAMMABLE_NFT_THAW_ACCOUNTS_LEN);
+        (Some(programmable_accounts), remaining_accounts)
+    } else {
+        (None, ctx.remaining_accounts)
+    };
+    // The token account of a programmable NFT is always frozen, so its token
+    // record tracks whether it is locked instead.
+    let is_frozen = match programmable_accounts {
+        Some(programmable_accounts) => {
+            let token_record_data = programmable_accounts[1].data.borrow();
+            token_record_data.len() > TOKEN_STATE_INDEX
+                && token_record_data[TOKEN_STATE_INDEX] == TokenState::Locked as u8
+        }
+        None => token_account.is_frozen(),
+    };
+    if is_frozen {
         msg!("Token account is frozen! Now attempting to thaw!");
-        invoke_signed(
-            &thaw_delegated_account(
-                mpl_token_metadata::ID,
-                freeze_pda.key(),
-                token_account.key(),
-                edition.key(),
-                mint.key(),
-            ),
-            &[
-                freeze_pda.to_account_info(),
-                token_account.to_account_info(),
-                edition.to_account_info(),
-                mint.to_account_info(),
-                token_program.to_account_info(),
-                token_metadata_program.to_account_info(),
-            ],
-            &[&freeze_seeds],
-        )?;
+        if let Some(programmable_accounts) = programmable_accounts {
+            let (metadata, token_record, instruction_sysvar, auth_rules_program, auth_rules) = (
+                &programmable_accounts[0],
+                &programmable_accounts[1],
+                &programmable_accounts[2],
+                &programmable_accounts[3],
+                &programmable_accounts[4],
+            );
+            let unlock_ix = UnlockBuilder::new()
+                .authority(freeze_pda.key())
+                .token_owner(owner.key())
+                .token(token_account.key())
+                .mint(mint.key())
+                .metadata(metadata.key())
+                .edition(edition.key())
+                .token_record(token_record.key())
+                .payer(payer.key())
+                .system_program(ctx.accounts.system_program.key())
+                .sysvar_instructions(instruction_sysvar.key())
+                .spl_token_program(token_program.key())
+                .authorization_rules_program(auth_rules_program.key())
+                .authorization_rules(auth_rules.key())
+                .build(UnlockArgs::V1 {
+                    authorization_data: None,
+                })
+                .map_err(|_| CandyError::InstructionBuilderFailed)?
+                .instruction();
+            invoke_signed(
+                &unlock_ix,
+                &[
+                    freeze_pda.to_account_info(),
+                    owner.to_account_info(),
+                    token_account.to_account_info(),
+                    mint.to_account_info(),
+                    metadata.clone(),
+                    edition.to_account_info(),
+                    token_record.clone(),
+                    payer.to_account_info(),
+                    ctx.accounts.system_program.to_account_info(),
+                    instruction_sysvar.clone(),
+                    token_program.to_account_info(),
+                    auth_rules_program.clone(),
+                    auth_rules.clone(),
+                    token_metadata_program.to_account_info(),
+                ],
+                &[&freeze_seeds],
+            )?;
+        } else {
+            invoke_signed(
+                &thaw_delegated_account(
+                    mpl_token_metadata::ID,
+                    freeze_pda.key(),
+                    token_account.key(),
+                    edition.key(),
+                    mint.key(),
+                ),
+                &[
+                    freeze_pda.to_account_info(),
+                    token_account.to_account_info(),
+                    edition.to_account_info(),
+                    mint.to_account_info(),
+                    token_program.to_account_info(),
+                    token_metadata_program.to_account_info(),
+                ],
+                &[&freeze_seeds],
+            )?;
+        }
         if freeze_pda.freeze_fee > 0 && freeze_pda.frozen_count > 0 {
             transfer(
                 CpiContext::new(
@@ -98,8 +192,65 @@ pub fn handle_thaw_nft(ctx: Context<ThawNFT>) -> Result<()> {
     } else {
         msg!("Token account is not frozen!");
     }
+    // The NFT can no longer be refunded, so the rent of its refund receipt is
+    // returned to the payer of the mint.
+    if let [refund_receipt_info, refund_receipt_payer] = remaining_accounts {
+        let refund_receipt: Account<RefundReceipt> = Account::try_from(refund_receipt_info)?;
+        if !cmp_pubkeys(&refund_receipt.candy_machine, &candy_machine.key())
+            || !cmp_pubkeys(&refund_receipt.mint, &mint.key())
+            || !cmp_pubkeys(&refund_receipt.payer, refund_receipt_payer.key)
+        {
+            return err!(CandyError::RefundReceiptMismatch);
+        }
+        refund_receipt.close(refund_receipt_payer.clone())?;
+    }
     if cmp_pubkeys(&payer.key(), &owner.key()) {
         msg!("Revoking authority");
+        if let Some(programmable_accounts) = programmable_accounts {
+            let (metadata, token_record, instruction_sysvar, auth_rules_program, auth_rules) = (
+                &programmable_accounts[0],
+                &programmable_accounts[1],
+                &programmable_accounts[2],
+                &programmable_accounts[3],
+                &programmable_accounts[4],
+            );
+            let revoke_ix = RevokeBuilder::new()
+                .delegate(freeze_pda.key())
+                .metadata(metadata.key())
+                .master_edition(edition.key())
+                .token_record(token_record.key())
+                .mint(mint.key())
+                .token(token_account.key())
+                .authority(payer.key())
+                .payer(payer.key())
+                .system_program(ctx.accounts.system_program.key())
+                .sysvar_instructions(instruction_sysvar.key())
+                .spl_token_program(token_program.key())
+                .authorization_rules_program(auth_rules_program.key())
+                .authorization_rules(auth_rules.key())
+                .build(RevokeArgs::UtilityV1)
+                .map_err(|_| CandyError::InstructionBuilderFailed)?
+                .instruction();
+            invoke(
+                &revoke_ix,
+                &[
+                    freeze_pda.to_account_info(),
+                    metadata.clone(),
+                    edition.to_account_info(),
+                    token_record.clone(),
+                    mint.to_account_info(),
+                    token_account.to_account_info(),
+                    payer.to_account_info(),
+                    ctx.accounts.system_program.to_account_info(),
+                    instruction_sysvar.clone(),
+                    token_program.to_account_info(),
+                    auth_rules_program.clone(),
+                    auth_rules.clone(),
+                    token_metadata_program.to_account_info(),
+                ],
+            )?;
+            return Ok(());
+        }
         invoke(
             &revoke(&spl_token::ID, &token_account.key(), &payer.key(), &[])?,
             &[token_account.to_account_info(), payer.to_account_info()],
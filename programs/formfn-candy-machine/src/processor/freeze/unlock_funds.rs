@@ -2,8 +2,8 @@ use anchor_lang::prelude::*;
 use anchor_spl::token::{close_account, CloseAccount, Token};
 
 use crate::{
-    assert_is_ata, constants::FREEZE_FEATURE_INDEX, remove_feature_flag, spl_token_transfer,
-    CandyError, CandyMachine, FreezePda, TokenTransferParams,
+    assert_is_ata, constants::FREEZE_FEATURE_INDEX, spl_token_transfer, CandyError, CandyMachine,
+    FreezePda, TokenTransferParams,
 };
 
 /// Unlocks the funds from mint stuck in the FreezePda
@@ -24,11 +24,11 @@ pub struct UnlockFunds<'info> {
     )]
     freeze_pda: Account<'info, FreezePda>,
     system_program: Program<'info, System>,
-    // > Only needed if candy machine has a mint set
+    // > Only needed if candy machine accepts an SPL token as payment
     // token_program
-    // > Only needed if candy machine has a mint set
+    // > The following pair is needed for each SPL token accepted as payment,
+    // starting with the treasury mint followed by the payment_options in order
     // freeze_ata
-    // > Only needed if candy machine has a mint set
     // destination_ata
 }
 
@@ -36,6 +36,7 @@ pub fn handle_unlock_funds<'info>(
     ctx: Context<'_, '_, '_, 'info, UnlockFunds<'info>>,
 ) -> Result<()> {
     let candy_machine = &mut ctx.accounts.candy_machine;
+    candy_machine.assert_current_version()?;
     let freeze_pda = &mut ctx.accounts.freeze_pda;
     let authority = &mut ctx.accounts.formfn_authority;
     if freeze_pda.frozen_count > 0 {
@@ -45,50 +46,57 @@ pub fn handle_unlock_funds<'info>(
     if !freeze_pda.allow_thaw {
         freeze_pda.allow_thaw = true;
     }
-    if let Some(mint) = &candy_machine.treasury_mint {
-        if ctx.remaining_accounts.len() != 3 {
+    let payment_mints = candy_machine.get_payment_mints();
+    if !payment_mints.is_empty() {
+        if ctx.remaining_accounts.len() != 1 + 2 * payment_mints.len() {
             return err!(CandyError::MissingRemoveFreezeTokenAccounts);
         }
         let token_program = &ctx.remaining_accounts[0];
         require_keys_eq!(token_program.key(), Token::id());
 
-        let freeze_ata_info = &ctx.remaining_accounts[1];
-        let freeze_ata = assert_is_ata(freeze_ata_info, &freeze_pda.key(), mint)?;
-
-        let destination_ata = &ctx.remaining_accounts[2];
-        require_keys_neq!(
-            freeze_ata_info.key(),
-            destination_ata.key(),
-            CandyError::InvalidFreezeWithdrawTokenAddress
-        );
-
         let candy_key = candy_machine.key();
         let freeze_seeds = [
             FreezePda::PREFIX.as_bytes(),
             candy_key.as_ref(),
             &[*ctx.bumps.get("freeze_pda").unwrap()],
         ];
-        spl_token_transfer(TokenTransferParams {
-            source: freeze_ata_info.to_account_info(),
-            destination: destination_ata.to_account_info(),
-            authority: freeze_pda.to_account_info(),
-            authority_signer_seeds: &freeze_seeds,
-            token_program: token_program.to_account_info(),
-            amount: freeze_ata.amount,
-        })?;
 
-        close_account(CpiContext::new_with_signer(
-            token_program.to_account_info(),
-            CloseAccount {
-                account: freeze_ata_info.to_account_info(),
-                destination: authority.to_account_info(),
+        for (mint, token_accounts) in payment_mints
+            .iter()
+            .zip(ctx.remaining_accounts[1..].chunks(2))
+        {
+            let freeze_ata_info = &token_accounts[0];
+            let freeze_ata = assert_is_ata(freeze_ata_info, &freeze_pda.key(), mint)?;
+
+            let destination_ata = &token_accounts[1];
+            require_keys_neq!(
+                freeze_ata_info.key(),
+                destination_ata.key(),
+                CandyError::InvalidFreezeWithdrawTokenAddress
+            );
+
+            spl_token_transfer(TokenTransferParams {
+                source: freeze_ata_info.to_account_info(),
+                destination: destination_ata.to_account_info(),
                 authority: freeze_pda.to_account_info(),
-            },
-            &[&freeze_seeds],
-        ))?;
+                authority_signer_seeds: &freeze_seeds,
+                token_program: token_program.to_account_info(),
+                amount: freeze_ata.amount,
+            })?;
+
+            close_account(CpiContext::new_with_signer(
+                token_program.to_account_info(),
+                CloseAccount {
+                    account: freeze_ata_info.to_account_info(),
+                    destination: authority.to_account_info(),
+                    authority: freeze_pda.to_account_info(),
+                },
+                &[&freeze_seeds],
+            ))?;
+        }
     }
 
-    remove_feature_flag(&mut candy_machine.data.uuid, FREEZE_FEATURE_INDEX);
-    remove_feature_flag(&mut candy_machine.data.uuid, FREEZE_FEATURE_INDEX);
+    candy_machine.remove_feature(FREEZE_FEATURE_INDEX);
+    candy_machine.remove_feature(FREEZE_FEATURE_INDEX);
     Ok(())
 }
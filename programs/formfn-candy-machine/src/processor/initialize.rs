@@ -4,9 +4,14 @@ use spl_token::state::Mint;
 
 use crate::{
     assert_initialized, assert_owned_by, cmp_pubkeys,
-    constants::{CONFIG_ARRAY_START, CONFIG_LINE_SIZE, MAX_OMNI_MINT_WALLETS},
-    validate_candy_machine_allowlist_state, validate_mint_phase_times, CandyError, CandyMachine,
-    CandyMachineData,
+    constants::{CONFIG_ARRAY_START, FEATURES_VERSION, MAX_OMNI_MINT_WALLETS},
+    layout::CANDY_MACHINE_VERSION,
+    processor::add_config_lines::set_config_bit_mask_len,
+    validate_bonding_curve_settings, validate_candy_machine_allowlist_state,
+    validate_compressed_nft_settings, validate_config_line_settings,
+    validate_dutch_auction_settings, validate_mint_phase_times, validate_open_edition_settings,
+    validate_payment_options, validate_primary_sale_splits, validate_shuffle_settings, CandyError,
+    CandyMachine, CandyMachineData, DutchAuctionRebateState, ShuffleSettings,
 };
 
 /// Create a new candy machine.
@@ -46,17 +51,41 @@ pub fn handle_initialize_candy_machine(
 
     validate_candy_machine_allowlist_state(&data)?;
 
+    validate_primary_sale_splits(&data)?;
+
+    validate_bonding_curve_settings(&data)?;
+
+    validate_payment_options(&data)?;
+
+    validate_compressed_nft_settings(&data)?;
+
+    validate_open_edition_settings(&data)?;
+
+    validate_config_line_settings(&data)?;
+
+    validate_shuffle_settings(&data)?;
+
+    // The seed is only set by reveal_shuffle_seed.
+    if let Some(ShuffleSettings { seed: Some(_), .. }) = &data.shuffle_settings {
+        return err!(CandyError::InvalidShuffleSettings);
+    }
+
     let mut candy_machine = CandyMachine {
+        version: CANDY_MACHINE_VERSION,
         data,
         formfn_authority: ctx.accounts.formfn_authority.key(),
         creator_authority: ctx.accounts.creator_authority.key(),
         treasury_wallet: ctx.accounts.treasury_wallet.key(),
         treasury_mint: None,
         items_redeemed: 0,
+        allowlist_items_sold: 0,
+        platform_fee_basis_points: 0,
+        platform_fee_wallet: None,
+        dutch_auction_rebate_state: DutchAuctionRebateState::default(),
+        features_version: FEATURES_VERSION,
+        features: 0,
     };
 
-    candy_machine.data.uuid = "000000".to_string();
-
     if !ctx.remaining_accounts.is_empty() {
         let treasury_mint_info = &ctx.remaining_accounts[0];
         let _treasury_mint: Mint = assert_initialized(treasury_mint_info)?;
@@ -73,6 +102,8 @@ pub fn handle_initialize_candy_machine(
         candy_machine.treasury_mint = Some(*treasury_mint_info.key);
     }
 
+    validate_dutch_auction_settings(&candy_machine.data, candy_machine.treasury_mint)?;
+
     let mut array_of_zeroes = vec![];
     while array_of_zeroes.len() < MAX_SYMBOL_LENGTH - candy_machine.data.symbol.len() {
         array_of_zeroes.push(0u8);
@@ -97,33 +128,22 @@ pub fn handle_initialize_candy_machine(
         data[i] = new_data[i];
     }
 
-    // only if we are not using hidden settings we will have space for
-    // the config lines
-    if candy_machine.data.hidden_settings.is_none() {
-        let vec_start = CONFIG_ARRAY_START
-            + 4
-            + (candy_machine.data.items_available as usize) * CONFIG_LINE_SIZE;
-        let as_bytes = (candy_machine
-            .data
-            .items_available
-            .checked_div(8)
-            .ok_or(CandyError::NumericalOverflowError)? as u32)
-            .to_le_bytes();
-        for i in 0..4 {
-            data[vec_start + i] = as_bytes[i]
-        }
+    // only if we are not using hidden settings or open editions we will have
+    // space for the config lines
+    if candy_machine.data.hidden_settings.is_none() && !candy_machine.data.open_edition_enabled {
+        set_config_bit_mask_len(&mut data, &candy_machine.data)?;
     }
 
     Ok(())
 }
 
 pub fn get_space_for_candy(data: CandyMachineData) -> Result<usize> {
-    let num = if data.hidden_settings.is_some() {
+    let num = if data.hidden_settings.is_some() || data.open_edition_enabled {
         CONFIG_ARRAY_START
     } else {
         CONFIG_ARRAY_START
             + 4
-            + (data.items_available as usize) * CONFIG_LINE_SIZE
+            + (data.items_available as usize) * data.get_config_line_size()
             + 8
             + 2 * ((data
                 .items_available
@@ -1,6 +1,10 @@
 use anchor_lang::prelude::*;
 
-use crate::{constants::NUMBER_OF_MERKLE_ROOTS_TO_STORE, CandyError, CandyMachine};
+use crate::{
+    constants::NUMBER_OF_MERKLE_ROOTS_TO_STORE,
+    utils::{validate_candy_machine_allowlist_state, validate_payment_options},
+    CandyError, CandyMachine, MerkleAllowlistRoot,
+};
 
 /// Append roots to the candy machine merkle allowlist root list.
 #[derive(Accounts)]
@@ -15,12 +19,25 @@ pub struct AppendMerkleAllowlistRoots<'info> {
 
 pub fn handle_append_merkle_allowlist_roots(
     ctx: Context<AppendMerkleAllowlistRoots>,
-    mut roots_to_append: Vec<[u8; 32]>,
+    mut roots_to_append: Vec<MerkleAllowlistRoot>,
 ) -> Result<()> {
     let candy_machine = &mut ctx.accounts.candy_machine;
-
-    if candy_machine.data.spl_token_allowlist_settings.is_some() {
-        return Err(CandyError::InvalidAllowlistSettings.into());
+    candy_machine.assert_current_version()?;
+
+    for merkle_allowlist_root in roots_to_append.iter() {
+        if let (Some(start_time), Some(end_time)) = (
+            merkle_allowlist_root.start_time,
+            merkle_allowlist_root.end_time,
+        ) {
+            if start_time >= end_time {
+                msg!(
+                    "Invalid merkle allowlist root times, start_time = {} and end_time = {}.",
+                    start_time,
+                    end_time
+                );
+                return err!(CandyError::InvalidMerkleAllowlistRootSettings);
+            }
+        }
     }
 
     let merkle_allowlist_root_list = &mut candy_machine.data.merkle_allowlist_root_list;
@@ -38,11 +55,13 @@ pub fn handle_append_merkle_allowlist_roots(
     }
 
     merkle_allowlist_root_list.append(&mut roots_to_append);
+    validate_candy_machine_allowlist_state(&candy_machine.data)?;
+    validate_payment_options(&candy_machine.data)?;
 
     msg!(
         "Successfully appended {} new roots to the merkle allowlist root list. Total root list length = {}.",
         roots_to_append_length,
-        merkle_allowlist_root_list.len()
+        list_length_after_append
     );
 
     Ok(())
@@ -62,10 +81,11 @@ pub struct ClearMerkleAllowlistRoots<'info> {
 
 pub fn handle_clear_merkle_allowlist_roots(ctx: Context<ClearMerkleAllowlistRoots>) -> Result<()> {
     let candy_machine = &mut ctx.accounts.candy_machine;
+    candy_machine.assert_current_version()?;
 
     let existing_root_list_length = candy_machine.data.merkle_allowlist_root_list.len();
 
-    let empty_root_list: Vec<[u8; 32]> = Vec::new();
+    let empty_root_list: Vec<MerkleAllowlistRoot> = Vec::new();
     candy_machine.data.merkle_allowlist_root_list = empty_root_list;
 
     msg!(
@@ -0,0 +1,96 @@
+use anchor_lang::{prelude::*, Discriminator};
+use solana_program::{program::invoke, system_instruction};
+
+use crate::{
+    cmp_pubkeys,
+    constants::CONFIG_ARRAY_START,
+    layout::{
+        get_config_array_start, get_formfn_authority, migrate_header, CANDY_MACHINE_VERSION,
+        VERSION_OFFSET,
+    },
+    CandyError, CandyMachine,
+};
+
+/// Migrate a candy machine account from the layout it had before it was
+/// versioned to the current account version. The account header is rewritten
+/// in the current layout, and the account is reallocated so its config lines
+/// and mint bitmask move to the config array start of the current version.
+#[derive(Accounts)]
+pub struct MigrateCandyMachine<'info> {
+    /// CHECK: account is deserialized according to its version in the handler
+    #[account(mut, owner = crate::id())]
+    candy_machine: UncheckedAccount<'info>,
+    #[account(mut)]
+    formfn_authority: Signer<'info>,
+    system_program: Program<'info, System>,
+}
+
+pub fn handle_migrate_candy_machine(
+    ctx: Context<MigrateCandyMachine>,
+    // Version 0 accounts don't store their version, so the current version of
+    // the account is passed in and checked against the stored formfn_authority.
+    from_version: u8,
+) -> Result<()> {
+    let candy_machine_info = ctx.accounts.candy_machine.to_account_info();
+
+    {
+        let data = candy_machine_info.data.borrow();
+        if data.len() <= VERSION_OFFSET || data[..8] != CandyMachine::discriminator() {
+            return err!(ErrorCode::AccountDiscriminatorMismatch);
+        }
+        if from_version >= CANDY_MACHINE_VERSION {
+            return err!(CandyError::CandyMachineAlreadyMigrated);
+        }
+        let formfn_authority = get_formfn_authority(&data, from_version)?;
+        if !cmp_pubkeys(&formfn_authority, &ctx.accounts.formfn_authority.key()) {
+            return err!(ErrorCode::ConstraintHasOne);
+        }
+    }
+
+    let old_config_array_start = get_config_array_start(from_version)?;
+    let old_len = candy_machine_info.data_len();
+    let new_len = old_len
+        .checked_add(CONFIG_ARRAY_START)
+        .and_then(|len| len.checked_sub(old_config_array_start))
+        .ok_or(CandyError::NumericalOverflowError)?;
+
+    let new_header = {
+        let data = candy_machine_info.data.borrow();
+        let header = data
+            .get(..old_config_array_start)
+            .ok_or(CandyError::UnsupportedCandyMachineVersion)?;
+        migrate_header(header)?
+    };
+
+    if new_len > old_len {
+        candy_machine_info.realloc(new_len, false)?;
+    }
+    {
+        let mut data = candy_machine_info.data.borrow_mut();
+        data.copy_within(old_config_array_start..old_len, CONFIG_ARRAY_START);
+        data[..CONFIG_ARRAY_START].copy_from_slice(&new_header);
+    }
+    if new_len < old_len {
+        candy_machine_info.realloc(new_len, false)?;
+    }
+
+    // The formfn_authority pays for the extra account space.
+    let rent_exempt_lamports = Rent::get()?.minimum_balance(candy_machine_info.data_len());
+    let lamports = candy_machine_info.lamports();
+    if lamports < rent_exempt_lamports {
+        invoke(
+            &system_instruction::transfer(
+                &ctx.accounts.formfn_authority.key(),
+                &candy_machine_info.key(),
+                rent_exempt_lamports - lamports,
+            ),
+            &[
+                ctx.accounts.formfn_authority.to_account_info(),
+                candy_machine_info.clone(),
+                ctx.accounts.system_program.to_account_info(),
+            ],
+        )?;
+    }
+
+    Ok(())
+}
@@ -1,18 +1,28 @@
 use std::cell::RefMut;
+use std::cmp;
+use std::result::Result as StandardResult;
 
-use anchor_lang::prelude::*;
+use anchor_lang::{prelude::*, Discriminator};
 use anchor_spl::associated_token::AssociatedToken;
 use anchor_spl::token::{Mint, Token};
 use arrayref::array_ref;
-use mpl_token_metadata::instruction::freeze_delegated_account;
-use mpl_token_metadata::state::DataV2;
-use mpl_token_metadata::utils::create_or_allocate_account_raw;
-use mpl_token_metadata::{
-    instruction::{
-        create_master_edition_v3, create_metadata_accounts_v3, update_metadata_accounts_v2,
-    },
-    state::{MAX_NAME_LENGTH, MAX_URI_LENGTH},
+use mpl_bubblegum::state::metaplex_adapter::{
+    Creator as BubblegumCreator, MetadataArgs, TokenProgramVersion,
+    TokenStandard as BubblegumTokenStandard,
+};
+use mpl_token_metadata::instruction::{
+    builders::{CreateBuilder, DelegateBuilder, LockBuilder, MintBuilder, UpdateBuilder},
+    freeze_delegated_account, CollectionDetailsToggle, CollectionToggle, CreateArgs, DelegateArgs,
+    InstructionBuilder, LockArgs, MintArgs, RuleSetToggle, UpdateArgs, UsesToggle,
+};
+use mpl_token_metadata::instruction::{
+    create_master_edition_v3, create_metadata_accounts_v3,
+    mint_new_edition_from_master_edition_via_token, update_metadata_accounts_v2,
+};
+use mpl_token_metadata::state::{
+    AssetData, Collection, Data, DataV2, Metadata, PrintSupply, TokenMetadataAccount, TokenStandard,
 };
+use mpl_token_metadata::utils::create_or_allocate_account_raw;
 use solana_program::{
     clock::Clock,
     program::{invoke, invoke_signed},
@@ -22,15 +32,19 @@ use solana_program::{
 };
 use spl_token::instruction::{approve, initialize_mint, mint_to};
 
-use crate::constants::{COMPUTE_BUDGET, FREEZE_FEATURE_INDEX};
-use crate::MintPhase;
+use crate::constants::{
+    COMPUTE_BUDGET, FREEZE_FEATURE_INDEX, MAX_PLATFORM_FEE_BASIS_POINTS,
+    MINT_MANY_ACCOUNTS_PER_NFT, PRIMARY_SALE_SPLITS_TOTAL_BASIS_POINTS,
+};
 use crate::{
-    constants::{
-        A_TOKEN, BOT_FEE, COLLECTIONS_FEATURE_INDEX, CONFIG_ARRAY_START, CONFIG_LINE_SIZE, PREFIX,
-    },
+    constants::{A_TOKEN, BOT_FEE, COLLECTIONS_FEATURE_INDEX, PREFIX},
+    processor::add_config_lines::{get_config_bit_mask_start, get_config_line_start},
     utils::*,
-    BuyerInfoAccount, BuyerMerkleAllowlistProofData, CandyError, CandyMachine, ConfigLine,
-    FreezePda, SplTokenAllowlistMode, BUYER_INFO_ACCOUNT_PREFIX, BUYER_INFO_ACCOUNT_SPACE,
+    BuyerInfoAccount, BuyerMerkleAllowlistProofData, CandyError, CandyMachine, CandyMachineData,
+    CollectionAllowlistRecord, CollectionAllowlistSettings, CollectionPda, ConfigLine,
+    DutchAuctionSettings, FreezePda, MintOrder, MintPhase, MintPhaseAllowlistSource,
+    MintPhaseSettings, OpenEditionPda, PaymentOption, PurchaseCounters, RefundReceipt,
+    SplTokenAllowlistToken, BUYER_INFO_ACCOUNT_PREFIX, BUYER_INFO_ACCOUNT_SPACE,
 };
 
 /// Mint a new NFT pseudo-randomly from the config array.
@@ -39,7 +53,6 @@ use crate::{
 pub struct MintNFT<'info> {
     #[account(
         mut,
-        has_one = treasury_wallet,
         has_one = creator_authority,
     )]
     candy_machine: Box<Account<'info, CandyMachine>>,
@@ -52,8 +65,10 @@ pub struct MintNFT<'info> {
         bump = creator_bump
     )]
     candy_machine_creator: UncheckedAccount<'info>,
-    buyer: Signer<'info>,
-    /// CHECK: treasury_wallet can be any account and is not written to or read
+    payer: Signer<'info>,
+    /// CHECK: recipient can be any account, it only receives the NFT
+    recipient: UncheckedAccount<'info>,
+    /// CHECK: treasury_wallet is checked against the selected payment option
     #[account(mut)]
     treasury_wallet: UncheckedAccount<'info>,
     // With the following accounts we aren't using anchor macros because they are CPI'd
@@ -75,7 +90,8 @@ pub struct MintNFT<'info> {
     token_program: Program<'info, Token>,
     system_program: Program<'info, System>,
     rent: Sysvar<'info, Rent>,
-    /// CHECK: checked in program.
+    /// CHECK: checked in program. The SlotHashes sysvar, or the trusted
+    /// randomness account if the candy machine has one.
     recent_slothashes: UncheckedAccount<'info>,
     /// CHECK: account constraints checked in account trait
     #[account(address = sysvar::instructions::id())]
@@ -88,34 +104,237 @@ pub struct MintNFT<'info> {
         seeds = [
             BUYER_INFO_ACCOUNT_PREFIX.as_bytes(),
             candy_machine.key().as_ref(),
-            buyer.key().as_ref()
+            recipient.key().as_ref()
         ],
         bump
     )]
     buyer_info_account: UncheckedAccount<'info>,
     /// CHECK: This account is validated in the instruction handler.
     #[account(mut)]
-    buyer_token_account: UncheckedAccount<'info>,
+    recipient_token_account: UncheckedAccount<'info>,
     ata_program: Program<'info, AssociatedToken>,
     // Some additional remaining_accounts may also be included. See the enum
     // below for details.
 }
 
 // Note: If these accounts are added, they need to be added in the order they
-// are listed in the enum.
-enum RemainingAccounts {
-    // Only needed if candy machine has spl_token_allowlist_settings.
-    SplTokenAllowlistTokenAccount,
-    // Only needed if candy machine has spl_token_allowlist_settings and mode is BurnEveryTime.
-    SplTokenAllowlistTokenMint,
-    // Only needed if candy machine has a treasury mint (uses an SPL token).
+// are listed in the enum. mint_nft and mint_many_nft share this layout, see
+// MintRemainingAccountsLayout.
+pub(crate) enum RemainingAccounts {
+    // Needed for each SPL allowlist token during allowlist phases which check
+    // the SPL token allowlist, see CandyMachineData::get_spl_token_allowlist_tokens
+    // and get_allowlist_phase_checks. The recipient's token account of the
+    // token at this index.
+    SplTokenAllowlistTokenAccount(usize),
+    // Needed for each SPL allowlist token which is burned, directly after its
+    // token account.
+    SplTokenAllowlistTokenMint(usize),
+    // Only needed if candy machine has collection_allowlist_settings, during
+    // allowlist phases which check the collection allowlist. The recipient's
+    // token account of the gating NFT.
+    CollectionAllowlistTokenAccount,
+    // Only needed if CollectionAllowlistTokenAccount is.
+    CollectionAllowlistMetadata,
+    // Only needed if CollectionAllowlistTokenAccount is.
+    CollectionAllowlistEdition,
+    // Only needed if CollectionAllowlistTokenAccount is and use_once is set.
+    // Created by the mint, see CollectionAllowlistRecord.
+    CollectionAllowlistRecord,
+    // Only needed if the mint is paid in an SPL token, either the treasury mint
+    // or the mint of the selected payment option.
     TreasuryTokenAccount,
     // Only needed if freeze feature is active.
-    BuyerNftMintTokenAccount,
-    // Only needed if freeze feature is active.
     FreezePda,
-    // Only needed if spl token mint is enabled.
+    // Only needed if freeze feature is active and the mint is paid in an SPL token.
     FreezeAta,
+    // Needed for each NFT, see NftRemainingAccounts.
+    Nft(usize, NftRemainingAccounts),
+    // Only needed if the candy machine mints programmable NFTs and has a rule
+    // set.
+    AuthorizationRulesProgram,
+    // Only needed if the candy machine mints programmable NFTs and has a rule
+    // set.
+    AuthorizationRules,
+    // Only needed if the candy machine mints programmable NFTs and the
+    // collections feature is active. The collection is set when the NFT is
+    // created, and verified by set_collection_during_mint.
+    CollectionPda,
+    // Only needed if the candy machine has a Dutch auction with rebates and the
+    // payer is not the recipient. The payer's BuyerInfoAccount, which records
+    // the rebate owed to the payer. Passed after the compressed NFT or open
+    // edition accounts. Created by the mint if it doesn't exist.
+    PayerBuyerInfoAccount,
+}
+
+// Note: If the candy machine has a platform fee, the platform fee wallet (or its
+// treasury mint ATA for SPL token mints) is passed after the FreezeAta. If the
+// candy machine has primary_sale_splits, one account per split (the split
+// address, or its treasury mint ATA for SPL token mints) is passed in the same
+// order after that. The accounts of each NFT follow.
+
+// Note: These accounts are passed for each NFT, in the order they are listed
+// in the enum.
+#[derive(Clone, Copy)]
+pub(crate) enum NftRemainingAccounts {
+    // Only passed by mint_many_nft, mint_nft has the mint (signer), metadata,
+    // master_edition and recipient_token_account as named accounts.
+    Mint,
+    Metadata,
+    MasterEdition,
+    RecipientTokenAccount,
+    // Only needed if freeze feature is active. Created by the mint, see RefundReceipt.
+    RefundReceipt,
+    // Only needed if the candy machine mints programmable NFTs. The token
+    // record of the recipient's NFT token account.
+    TokenRecord,
+}
+
+// If the candy machine mints compressed NFTs, the accounts listed in the enum
+// below are passed last. The mint, metadata, master_edition and
+// recipient_token_account accounts are unused.
+enum CompressedRemainingAccounts {
+    // The Bubblegum tree config of the merkle tree.
+    TreeAuthority,
+    MerkleTree,
+    LogWrapper,
+    CompressionProgram,
+    BubblegumProgram,
+}
+
+const COMPRESSED_NFT_REMAINING_ACCOUNTS_COUNT: usize = 5;
+
+// If the candy machine mints open editions, the accounts listed in the enum
+// below are passed last. The mint, metadata and master_edition accounts are
+// those of the new print edition.
+enum OpenEditionRemainingAccounts {
+    OpenEditionPda,
+    MasterEditionMint,
+    MasterEditionMetadata,
+    MasterEdition,
+    // The OpenEditionPda ATA which holds the master edition token.
+    OpenEditionTokenAccount,
+    EditionMarker,
+}
+
+const OPEN_EDITION_REMAINING_ACCOUNTS_COUNT: usize = 6;
+
+/// Describes the remaining_accounts of a mint, which mint_nft and
+/// mint_many_nft share.
+pub(crate) struct MintRemainingAccountsLayout {
+    // The SPL token the mint is paid in, if any.
+    pub treasury_mint: Option<Pubkey>,
+    // The number of NFTs minted.
+    pub quantity: usize,
+    // Whether the mint, metadata, master_edition and recipient_token_account
+    // of each NFT are passed in the remaining_accounts (mint_many_nft).
+    pub nft_accounts_in_remaining_accounts: bool,
+    // Whether the payer's BuyerInfoAccount is passed, see
+    // is_payer_buyer_info_account_needed.
+    pub payer_buyer_info_account: bool,
+    // Whether the SPL token allowlist accounts are passed, see
+    // get_allowlist_accounts_layout.
+    pub spl_token_allowlist_accounts: bool,
+    // Whether the collection allowlist accounts are passed, see
+    // get_allowlist_accounts_layout.
+    pub collection_allowlist_accounts: bool,
+}
+
+/// Named accounts shared by mint_nft and mint_many_nft.
+pub(crate) struct MintCommonAccounts<'a, 'info> {
+    pub candy_machine: &'a mut Account<'info, CandyMachine>,
+    pub candy_machine_creator: AccountInfo<'info>,
+    pub payer: AccountInfo<'info>,
+    pub recipient: AccountInfo<'info>,
+    pub treasury_wallet: AccountInfo<'info>,
+    pub creator_authority: AccountInfo<'info>,
+    pub token_metadata_program: AccountInfo<'info>,
+    pub token_program: AccountInfo<'info>,
+    pub system_program: AccountInfo<'info>,
+    pub rent: AccountInfo<'info>,
+    pub recent_slothashes: AccountInfo<'info>,
+    pub instruction_sysvar_account: AccountInfo<'info>,
+    pub bot_signer_authority: AccountInfo<'info>,
+    pub buyer_info_account: AccountInfo<'info>,
+    pub ata_program: AccountInfo<'info>,
+}
+
+/// The accounts of a single NFT, which mint_nft passes as named accounts and
+/// mint_many_nft in the remaining_accounts.
+pub(crate) struct NftAccounts<'info> {
+    pub mint: AccountInfo<'info>,
+    pub metadata: AccountInfo<'info>,
+    pub master_edition: AccountInfo<'info>,
+    pub recipient_token_account: AccountInfo<'info>,
+}
+
+/// Instruction arguments shared by mint_nft and mint_many_nft.
+pub(crate) struct MintParams {
+    pub creator_bump: u8,
+    pub buyer_info_account_bump: u8,
+    pub buyer_merkle_allowlist_proof_data: Option<BuyerMerkleAllowlistProofData>,
+    pub quantity: u8,
+    // Price of a single NFT.
+    pub expected_price: u64,
+    pub payment_option_index: Option<u8>,
+}
+
+/// Accounts used to create a single NFT during a mint.
+pub(crate) struct MintNftAccounts<'info> {
+    pub candy_machine_creator: AccountInfo<'info>,
+    pub payer: AccountInfo<'info>,
+    pub recipient: AccountInfo<'info>,
+    pub mint: AccountInfo<'info>,
+    pub metadata: AccountInfo<'info>,
+    pub master_edition: AccountInfo<'info>,
+    pub recipient_token_account: AccountInfo<'info>,
+    pub token_metadata_program: AccountInfo<'info>,
+    pub token_program: AccountInfo<'info>,
+    pub system_program: AccountInfo<'info>,
+    pub ata_program: AccountInfo<'info>,
+    pub rent: AccountInfo<'info>,
+    pub recent_slothashes: AccountInfo<'info>,
+    // Only set if the candy machine mints programmable NFTs.
+    pub programmable: Option<ProgrammableNftAccounts<'info>>,
+    // Only set if the candy machine mints compressed NFTs.
+    pub compressed: Option<CompressedNftAccounts<'info>>,
+    // Only set if the candy machine mints open editions.
+    pub open_edition: Option<OpenEditionAccounts<'info>>,
+}
+
+/// Additional accounts used to print an edition of the open edition master
+/// edition.
+pub(crate) struct OpenEditionAccounts<'info> {
+    pub open_edition_pda: AccountInfo<'info>,
+    pub open_edition_bump: u8,
+    pub master_edition_mint: AccountInfo<'info>,
+    pub master_edition_metadata: AccountInfo<'info>,
+    pub master_edition: AccountInfo<'info>,
+    pub open_edition_token_account: AccountInfo<'info>,
+    pub edition_marker: AccountInfo<'info>,
+    // Print editions are created with the update_authority of the master
+    // edition, which set_open_edition sets to the creator_authority.
+    pub creator_authority: AccountInfo<'info>,
+}
+
+/// Additional accounts used to mint a compressed NFT with Bubblegum.
+pub(crate) struct CompressedNftAccounts<'info> {
+    pub tree_authority: AccountInfo<'info>,
+    pub merkle_tree: AccountInfo<'info>,
+    pub log_wrapper: AccountInfo<'info>,
+    pub compression_program: AccountInfo<'info>,
+    pub bubblegum_program: AccountInfo<'info>,
+}
+
+/// Additional accounts used to create and freeze a programmable NFT.
+pub(crate) struct ProgrammableNftAccounts<'info> {
+    pub token_record: AccountInfo<'info>,
+    pub instruction_sysvar: AccountInfo<'info>,
+    // The token auth rules program and the rule set, only set if the candy
+    // machine has a rule set.
+    pub authorization_rules: Option<(AccountInfo<'info>, AccountInfo<'info>)>,
+    // The collection set when the NFT is created, if the collections feature
+    // is active.
+    pub collection_mint: Option<Pubkey>,
 }
 
 pub fn handle_mint_nft<'info>(
@@ -125,34 +344,137 @@ pub fn handle_mint_nft<'info>(
     buyer_merkle_allowlist_proof_data: Option<BuyerMerkleAllowlistProofData>,
     // Sole purpose of passing this in is to make this ix easier to parse.
     expected_price: u64,
+    // Index into the candy machine's payment_options, or None to pay with
+    // the treasury mint.
+    payment_option_index: Option<u8>,
+) -> Result<()> {
+    let nft_accounts = NftAccounts {
+        mint: ctx.accounts.mint.to_account_info(),
+        metadata: ctx.accounts.metadata.to_account_info(),
+        master_edition: ctx.accounts.master_edition.to_account_info(),
+        recipient_token_account: ctx.accounts.recipient_token_account.to_account_info(),
+    };
+    let accounts = MintCommonAccounts {
+        candy_machine: &mut *ctx.accounts.candy_machine,
+        candy_machine_creator: ctx.accounts.candy_machine_creator.to_account_info(),
+        payer: ctx.accounts.payer.to_account_info(),
+        recipient: ctx.accounts.recipient.to_account_info(),
+        treasury_wallet: ctx.accounts.treasury_wallet.to_account_info(),
+        creator_authority: ctx.accounts.creator_authority.to_account_info(),
+        token_metadata_program: ctx.accounts.token_metadata_program.to_account_info(),
+        token_program: ctx.accounts.token_program.to_account_info(),
+        system_program: ctx.accounts.system_program.to_account_info(),
+        rent: ctx.accounts.rent.to_account_info(),
+        recent_slothashes: ctx.accounts.recent_slothashes.to_account_info(),
+        instruction_sysvar_account: ctx.accounts.instruction_sysvar_account.to_account_info(),
+        bot_signer_authority: ctx.accounts.bot_signer_authority.to_account_info(),
+        buyer_info_account: ctx.accounts.buyer_info_account.to_account_info(),
+        ata_program: ctx.accounts.ata_program.to_account_info(),
+    };
+
+    process_mint(
+        ctx.program_id,
+        accounts,
+        ctx.remaining_accounts,
+        Some(nft_accounts),
+        MintParams {
+            creator_bump,
+            buyer_info_account_bump,
+            buyer_merkle_allowlist_proof_data,
+            quantity: 1,
+            expected_price,
+            payment_option_index,
+        },
+    )
+}
+
+/// Mints quantity NFTs to the recipient. This is shared by mint_nft and
+/// mint_many_nft, which only differ in how the accounts of each NFT are
+/// passed.
+pub(crate) fn process_mint<'info>(
+    program_id: &Pubkey,
+    accounts: MintCommonAccounts<'_, 'info>,
+    remaining_accounts: &[AccountInfo<'info>],
+    // Only set by mint_nft, which passes the accounts of its NFT as named
+    // accounts.
+    named_nft_accounts: Option<NftAccounts<'info>>,
+    params: MintParams,
 ) -> Result<()> {
-    let candy_pubkey = ctx.accounts.candy_machine.key();
-    let candy_machine = &mut ctx.accounts.candy_machine;
-    let candy_machine_creator = &ctx.accounts.candy_machine_creator;
-    let treasury_wallet = ctx.accounts.treasury_wallet.to_account_info();
-    let buyer = &ctx.accounts.buyer;
-    let token_program = &ctx.accounts.token_program;
+    let MintParams {
+        creator_bump,
+        buyer_info_account_bump,
+        buyer_merkle_allowlist_proof_data,
+        quantity,
+        expected_price,
+        payment_option_index,
+    } = params;
+    let candy_machine = accounts.candy_machine;
+    let candy_pubkey = candy_machine.key();
+    candy_machine.assert_current_version()?;
+    candy_machine.assert_not_compacted(&candy_machine.to_account_info())?;
+    // Buyers would know which items are left once the shuffle seed is out.
+    if candy_machine.is_shuffle_seed_revealed() {
+        return err!(CandyError::ShuffleSeedAlreadyRevealed);
+    }
+    let treasury_wallet = accounts.treasury_wallet;
+    let payer = accounts.payer;
+    let recipient = accounts.recipient;
+    let token_program = accounts.token_program;
+    let system_program = accounts.system_program;
+    let buyer_info_account = &accounts.buyer_info_account;
     let clock = Clock::get()?;
-    let recent_slothashes = &ctx.accounts.recent_slothashes;
-    let instruction_sysvar_account = &ctx.accounts.instruction_sysvar_account;
-    let instruction_sysvar_account_info = instruction_sysvar_account.to_account_info();
+    let recent_slothashes = accounts.recent_slothashes;
+    let instruction_sysvar_account_info = accounts.instruction_sysvar_account;
     let instruction_sysvar = instruction_sysvar_account_info.data.borrow();
     let current_ix = get_instruction_relative(0, &instruction_sysvar_account_info).unwrap();
+
+    let mint_phase = CandyMachine::get_mint_phase(candy_machine, clock.unix_timestamp);
+    let active_mint_phase_index = candy_machine.get_active_mint_phase_index(clock.unix_timestamp);
+    let active_mint_phase_settings = active_mint_phase_index
+        .map(|mint_phase_index| candy_machine.data.mint_phases[mint_phase_index].clone());
+    let (is_merkle_allowlist_phase, is_spl_token_allowlist_phase, is_collection_allowlist_phase) =
+        get_allowlist_phase_checks(&mint_phase, active_mint_phase_settings.as_ref());
+
+    let (payment_option, treasury_mint) =
+        get_mint_payment_option(candy_machine, payment_option_index, treasury_wallet.key)?;
+    let layout = MintRemainingAccountsLayout {
+        treasury_mint,
+        quantity: quantity as usize,
+        nft_accounts_in_remaining_accounts: named_nft_accounts.is_none(),
+        payer_buyer_info_account: is_payer_buyer_info_account_needed(
+            candy_machine,
+            payer.key,
+            recipient.key,
+        ),
+        spl_token_allowlist_accounts: is_spl_token_allowlist_phase,
+        collection_allowlist_accounts: is_collection_allowlist_phase,
+    };
+    let nft_accounts_list = match named_nft_accounts {
+        Some(nft_accounts) => vec![nft_accounts],
+        None => (0..layout.quantity)
+            .map(|nft_index| {
+                get_nft_accounts(candy_machine, &layout, remaining_accounts, nft_index)
+            })
+            .collect::<Result<Vec<_>>>()?,
+    };
+
     // We must ensure the metadata cannot be passed in with data in it, this must remain the first check before any bot taxes
-    if !ctx.accounts.metadata.data_is_empty() {
-        return err!(CandyError::MetadataAccountMustBeEmpty);
+    for nft_accounts in nft_accounts_list.iter() {
+        if !nft_accounts.metadata.data_is_empty() {
+            return err!(CandyError::MetadataAccountMustBeEmpty);
+        }
     }
 
-    let bot_signer_authority = &ctx.accounts.bot_signer_authority;
+    let bot_signer_authority = &accounts.bot_signer_authority;
     let is_bot_signer_authority_valid =
         assert_valid_bot_signer_authority(&bot_signer_authority.key());
 
     if let Err(_e) = is_bot_signer_authority_valid {
         punish_bots(
             CandyError::InvalidBotSignerAuthority,
-            buyer.to_account_info(),
-            ctx.accounts.candy_machine.to_account_info(),
-            ctx.accounts.system_program.to_account_info(),
+            payer.to_account_info(),
+            candy_machine.to_account_info(),
+            system_program.to_account_info(),
             BOT_FEE,
         )?;
         return Ok(());
@@ -161,106 +483,100 @@ pub fn handle_mint_nft<'info>(
     if candy_machine.data.bot_protection_enabled && !bot_signer_authority.is_signer {
         punish_bots(
             CandyError::InvalidBotSignerAuthority,
-            buyer.to_account_info(),
-            ctx.accounts.candy_machine.to_account_info(),
-            ctx.accounts.system_program.to_account_info(),
+            payer.to_account_info(),
+            candy_machine.to_account_info(),
+            system_program.to_account_info(),
             BOT_FEE,
         )?;
         return Ok(());
     }
 
-    if get_expected_remaining_accounts_count(candy_machine) < ctx.remaining_accounts.len() {
+    if get_expected_remaining_accounts_count(candy_machine, &layout) < remaining_accounts.len() {
         punish_bots(
             CandyError::IncorrectRemainingAccountsLen,
-            buyer.to_account_info(),
-            ctx.accounts.candy_machine.to_account_info(),
-            ctx.accounts.system_program.to_account_info(),
+            payer.to_account_info(),
+            candy_machine.to_account_info(),
+            system_program.to_account_info(),
             BOT_FEE,
         )?;
         return Ok(());
     }
 
-    if candy_machine.items_redeemed >= candy_machine.data.items_available {
+    let items_remaining = candy_machine
+        .data
+        .items_available
+        .saturating_sub(candy_machine.items_redeemed);
+    if items_remaining < quantity as u64 {
+        msg!(
+            "Cannot mint {} NFTs, only {} remaining.",
+            quantity,
+            items_remaining
+        );
         return err!(CandyError::CandyMachineEmpty);
     }
 
-    if !cmp_pubkeys(&recent_slothashes.key(), &SlotHashes::id()) {
-        return err!(CandyError::IncorrectSlotHashesPubkey);
-    }
+    assert_randomness_account(candy_machine, &recent_slothashes.key())?;
 
     if !cmp_pubkeys(&current_ix.program_id, &crate::id()) {
         punish_bots(
             CandyError::SuspiciousTransaction,
-            buyer.to_account_info(),
-            ctx.accounts.candy_machine.to_account_info(),
-            ctx.accounts.system_program.to_account_info(),
+            payer.to_account_info(),
+            candy_machine.to_account_info(),
+            system_program.to_account_info(),
             BOT_FEE,
         )?;
         return Ok(());
     }
-    let next_ix = get_instruction_relative(1, &instruction_sysvar_account_info);
-    match next_ix {
-        Ok(ix) => {
-            let discriminator = &ix.data[0..8];
-            let after_collection_ix = get_instruction_relative(2, &instruction_sysvar_account_info);
-
-            if !cmp_pubkeys(&ix.program_id, &crate::id())
-                || discriminator != [103, 17, 200, 25, 118, 95, 125, 61]
-                || after_collection_ix.is_ok()
-            {
-                // We fail here. Its much cheaper to fail here than to allow a malicious user to add an ix at the end and then fail.
-                msg!("Failing and halting here due to an extra unauthorized instruction from program ID {}.", ix.program_id.to_string());
-                return err!(CandyError::SuspiciousTransaction);
-            }
-        }
-        Err(_) => {
-            if is_feature_active(&candy_machine.data.uuid, COLLECTIONS_FEATURE_INDEX) {
-                punish_bots(
-                    CandyError::MissingSetCollectionDuringMint,
-                    buyer.to_account_info(),
-                    ctx.accounts.candy_machine.to_account_info(),
-                    ctx.accounts.system_program.to_account_info(),
-                    BOT_FEE,
-                )?;
-                return Ok(());
-            }
+
+    // The mint may only be followed by a set_collection_during_mint
+    // instruction for each NFT, in the same order.
+    let mut set_collection_ixs_count: usize = 0;
+    while let Ok(ix) = get_instruction_relative(
+        1 + set_collection_ixs_count as i64,
+        &instruction_sysvar_account_info,
+    ) {
+        if !cmp_pubkeys(&ix.program_id, &crate::id())
+            || ix.data.get(0..8)
+                != Some(&crate::instruction::SetCollectionDuringMint::discriminator()[..])
+            || set_collection_ixs_count == layout.quantity
+        {
+            // We fail here. Its much cheaper to fail here than to allow a malicious user to add an ix at the end and then fail.
+            msg!("Failing and halting here due to an extra unauthorized instruction from program ID {}.", ix.program_id.to_string());
+            return err!(CandyError::SuspiciousTransaction);
         }
+        set_collection_ixs_count += 1;
     }
-    let mut idx = 0;
-    let num_instructions =
-        read_u16(&mut idx, &instruction_sysvar).map_err(|_| ProgramError::InvalidAccountData)?;
-
-    for index in 0..num_instructions {
-        let mut current = 2 + (index * 2) as usize;
-        let start = read_u16(&mut current, &instruction_sysvar).unwrap();
 
-        current = start as usize;
-        let num_accounts = read_u16(&mut current, &instruction_sysvar).unwrap();
-        current += (num_accounts as usize) * (1 + 32);
-        let program_id = read_pubkey(&mut current, &instruction_sysvar).unwrap();
+    if candy_machine.is_feature_active(COLLECTIONS_FEATURE_INDEX)
+        && set_collection_ixs_count < layout.quantity
+    {
+        punish_bots(
+            CandyError::MissingSetCollectionDuringMint,
+            payer.to_account_info(),
+            candy_machine.to_account_info(),
+            system_program.to_account_info(),
+            BOT_FEE,
+        )?;
+        return Ok(());
+    }
 
-        if !cmp_pubkeys(&program_id, &crate::id())
-            && !cmp_pubkeys(&program_id, &spl_token::id())
-            && !cmp_pubkeys(&program_id, &solana_program::system_program::ID)
-            && !cmp_pubkeys(&program_id, &A_TOKEN)
-            && !cmp_pubkeys(&program_id, &COMPUTE_BUDGET)
-        {
-            msg!("Transaction had ix with program id {}.", program_id);
-            punish_bots(
-                CandyError::SuspiciousTransaction,
-                buyer.to_account_info(),
-                ctx.accounts.candy_machine.to_account_info(),
-                ctx.accounts.system_program.to_account_info(),
-                BOT_FEE,
-            )?;
-            return Ok(());
-        }
+    if let Some(program_id) = find_unauthorized_program_id(&instruction_sysvar)? {
+        msg!("Transaction had ix with program id {}.", program_id);
+        punish_bots(
+            CandyError::SuspiciousTransaction,
+            payer.to_account_info(),
+            candy_machine.to_account_info(),
+            system_program.to_account_info(),
+            BOT_FEE,
+        )?;
+        return Ok(());
     }
 
-    let mint_phase = CandyMachine::get_mint_phase(candy_machine, clock.unix_timestamp);
+    // Omni minters and the limits per address both follow the recipient.
     let is_mint_phase_valid = validate_mint_phase(
-        buyer,
+        recipient.key,
         &mint_phase,
+        active_mint_phase_settings.as_ref(),
         candy_machine,
         &buyer_merkle_allowlist_proof_data,
     );
@@ -268,47 +584,128 @@ pub fn handle_mint_nft<'info>(
     if let Err(candy_error) = is_mint_phase_valid {
         punish_bots(
             candy_error,
-            buyer.to_account_info(),
-            ctx.accounts.candy_machine.to_account_info(),
-            ctx.accounts.system_program.to_account_info(),
+            payer.to_account_info(),
+            candy_machine.to_account_info(),
+            system_program.to_account_info(),
             BOT_FEE,
         )?;
         return Ok(());
     }
 
-    let price = CandyMachine::get_mint_price(candy_machine, &mint_phase);
+    let is_recipient_omni_minter = is_omni_minter(recipient.key, candy_machine);
 
-    if price != expected_price {
-        msg!(
-            "Invalid mint price for mint_phase {:?}: actual mint price = {}, expected price = {}.",
-            mint_phase,
-            price,
-            expected_price
-        );
-        return Err(CandyError::InvalidMintPrice.into());
-    }
+    validate_allowlist_items_cap(
+        candy_machine,
+        &mint_phase,
+        is_recipient_omni_minter,
+        quantity as u64,
+    )?;
+
+    let (wallet_to_use, mut freeze_pda) = if candy_machine.is_feature_active(FREEZE_FEATURE_INDEX) {
+        let freeze_pda_info = get_remaining_account(
+            candy_machine,
+            &layout,
+            remaining_accounts,
+            RemainingAccounts::FreezePda,
+        )?;
+        let freeze_ata = treasury_mint
+            .map(|_| {
+                get_remaining_account(
+                    candy_machine,
+                    &layout,
+                    remaining_accounts,
+                    RemainingAccounts::FreezeAta,
+                )
+            })
+            .transpose()?;
+        get_mint_payment_destination(
+            candy_machine,
+            treasury_mint,
+            treasury_wallet,
+            &freeze_pda_info,
+            freeze_ata,
+            clock.unix_timestamp,
+        )?
+    } else {
+        (treasury_wallet, None)
+    };
+
+    validate_recipient_signature(
+        candy_machine,
+        &recipient,
+        freeze_pda.is_some(),
+        is_spl_token_allowlist_phase && !is_recipient_omni_minter,
+    )?;
+
+    let price = match &payment_option {
+        Some(payment_option) => payment_option.price,
+        None => get_buyer_mint_price(
+            candy_machine,
+            &mint_phase,
+            clock.unix_timestamp,
+            is_merkle_allowlist_phase && !is_recipient_omni_minter,
+            &buyer_merkle_allowlist_proof_data,
+        ),
+    };
+
+    assert_expected_mint_price(
+        candy_machine,
+        &mint_phase,
+        clock.unix_timestamp,
+        price,
+        expected_price,
+    )?;
+
+    // With a bonding curve, each NFT is priced by its position on the curve.
+    let bonding_curve_total_price = match payment_option {
+        Some(_) => None,
+        None => candy_machine.get_bonding_curve_total_price(&mint_phase, quantity as u64),
+    };
+    let total_price = match bonding_curve_total_price {
+        Some(total_price) => total_price,
+        None => price
+            .checked_mul(quantity as u64)
+            .ok_or(CandyError::NumericalOverflowError)?,
+    };
 
-    let buyer_info_account = &ctx.accounts.buyer_info_account;
     let limit_per_address = candy_machine.data.limit_per_address;
+    let mint_phase_limit_per_address = active_mint_phase_settings
+        .as_ref()
+        .map_or(0, |settings| settings.limit_per_address);
+    let phase_limit_per_address =
+        get_phase_limit_per_address(candy_machine, &mint_phase, is_recipient_omni_minter);
+    let total_limit_per_address = candy_machine.data.mint_caps.total_limit_per_address;
 
     let provided_merkle_allowlist_proof = buyer_merkle_allowlist_proof_data.is_some();
 
+    let dutch_auction_rebate_settings = candy_machine
+        .get_dutch_auction_rebate_settings(&mint_phase)
+        .cloned();
+
     // Only create the BuyerInfoAccount if the edition has a limit_per_address
-    // OR if the buyer provided an allowlist proof.
-    let should_create_buyer_info_account = limit_per_address > 0 || provided_merkle_allowlist_proof;
+    // or total limit per address, the active mint phase or the premint or
+    // allowlist phase has a limit per address, the buyer provided an allowlist
+    // proof OR the mint is part of a Dutch auction with rebates paid for by the
+    // recipient. The account tracks the recipient, not the payer.
+    let should_create_buyer_info_account = limit_per_address > 0
+        || mint_phase_limit_per_address > 0
+        || phase_limit_per_address > 0
+        || total_limit_per_address > 0
+        || provided_merkle_allowlist_proof
+        || (dutch_auction_rebate_settings.is_some() && !layout.payer_buyer_info_account);
     if should_create_buyer_info_account && buyer_info_account.data_is_empty() {
         let signer_seeds = [
             BUYER_INFO_ACCOUNT_PREFIX.as_bytes(),
-            &candy_machine.key().to_bytes(),
-            &buyer.key().to_bytes(),
+            &candy_pubkey.to_bytes(),
+            &recipient.key().to_bytes(),
             &[buyer_info_account_bump],
         ];
 
         create_or_allocate_account_raw(
-            *ctx.program_id,
+            *program_id,
             buyer_info_account,
-            &ctx.accounts.system_program,
-            buyer,
+            &system_program,
+            &payer,
             BUYER_INFO_ACCOUNT_SPACE,
             &signer_seeds,
         )?;
@@ -316,315 +713,1279 @@ pub fn handle_mint_nft<'info>(
         write_anchor_account_discriminator::<BuyerInfoAccount>(buyer_info_account)?;
     }
 
-    // Allowlist checks only apply during allowlist mint phase.
-    let is_allowlist_phase = mint_phase == MintPhase::Allowlist;
-
-    let is_buyer_omni_minter = is_omni_minter(buyer, candy_machine);
+    // The counters each NFT is counted in, which the RefundReceipt records so
+    // refunding the NFT can uncount it.
+    let purchase_counters = PurchaseCounters {
+        merkle_allowlist_phase: is_merkle_allowlist_phase && provided_merkle_allowlist_proof,
+        public_phase: mint_phase == MintPhase::Public
+            && !is_recipient_omni_minter
+            && limit_per_address > 0,
+        mint_phase_index: active_mint_phase_index
+            .filter(|_| !is_recipient_omni_minter && mint_phase_limit_per_address > 0)
+            .map(|mint_phase_index| mint_phase_index as u8),
+        premint_phase: phase_limit_per_address > 0 && mint_phase == MintPhase::Premint,
+        allowlist_phase: phase_limit_per_address > 0 && mint_phase != MintPhase::Premint,
+        // The total is counted whenever the account exists, including for omni
+        // minters, so a total limit set later still counts earlier purchases.
+        total: !buyer_info_account.data_is_empty(),
+        allowlist_items_sold: mint_phase == MintPhase::Allowlist,
+    };
 
     // Only check the Merkle allowlist proof if the allowlist proof data is provided.
     if let (true, false, Some(proof_data)) = (
-        is_allowlist_phase,
-        is_buyer_omni_minter,
-        buyer_merkle_allowlist_proof_data,
+        is_merkle_allowlist_phase,
+        is_recipient_omni_minter,
+        &buyer_merkle_allowlist_proof_data,
     ) {
-        let amount = proof_data.amount;
-        let proof = proof_data.proof;
-        let root_index_for_proof = proof_data.root_index_for_proof as usize;
-
-        let roots_list = &candy_machine.data.merkle_allowlist_root_list;
-        if roots_list.is_empty() {
-            msg!("Invalid allowlist proof provided, the current roots list is empty.");
-            return err!(CandyError::InvalidAllowlistProof);
-        } else if root_index_for_proof >= roots_list.len() {
-            msg!(
-                "Invalid root_index_for_proof provided, received: {}, roots_list length = {}.",
-                root_index_for_proof,
-                roots_list.len()
-            );
-            return err!(CandyError::InvalidAllowlistProof);
-        }
-
-        let leaf = solana_program::keccak::hashv(&[
-            &[0x00],
-            &buyer.key().to_bytes(),
-            &candy_machine.key().to_bytes(),
-            &amount.to_le_bytes(),
-        ]);
-
-        let root: [u8; 32] = roots_list[root_index_for_proof];
-
-        let is_proof_valid = verify_merkle_proof(&proof, root, leaf.0);
-        if !is_proof_valid {
-            msg!(
-                "Invalid proof provided for root_index_for_proof: {}.",
-                root_index_for_proof
-            );
-            return err!(CandyError::InvalidAllowlistProof);
-        }
+        verify_buyer_merkle_allowlist_proof(
+            &recipient.key(),
+            &candy_pubkey,
+            candy_machine,
+            proof_data,
+            clock.unix_timestamp,
+        )?;
 
         let buyer_info_account: Account<BuyerInfoAccount> = Account::try_from(buyer_info_account)?;
+        let number_bought_for_merkle_allowlist = buyer_info_account
+            .number_bought_merkle_allowlist_phase
+            .checked_add(quantity as u16)
+            .ok_or(CandyError::NumericalOverflowError)?;
         require!(
-            buyer_info_account.number_bought_merkle_allowlist_phase < amount,
+            number_bought_for_merkle_allowlist <= proof_data.amount,
             CandyError::AllowlistMintsAlreadyUsed
         );
 
         msg!(
-            "Valid merkle allowlist proof submitted by {:?} with root index {}.",
-            buyer.key(),
-            root_index_for_proof
+            "Valid merkle allowlist proof submitted for {:?} with root index {}.",
+            recipient.key(),
+            proof_data.root_index_for_proof
         );
     }
 
-    if let (true, false, Some(spl_token_allowlist_settings)) = (
-        is_allowlist_phase,
-        is_buyer_omni_minter,
-        &candy_machine.data.spl_token_allowlist_settings,
-    ) {
-        let spl_token_allowlist_token_account = get_remaining_account(
-            candy_machine,
-            ctx.remaining_accounts,
-            RemainingAccounts::SplTokenAllowlistTokenAccount,
-        );
-
-        let buyer_allowlist_token_account = assert_is_token_account(
-            &spl_token_allowlist_token_account,
-            &buyer.key(),
-            &spl_token_allowlist_settings.mint,
-        );
-
-        if buyer_allowlist_token_account.is_err() {
-            punish_bots(
-                CandyError::NoSplAllowlistToken,
-                buyer.to_account_info(),
-                ctx.accounts.candy_machine.to_account_info(),
-                ctx.accounts.system_program.to_account_info(),
-                BOT_FEE,
-            )?;
-            return Ok(());
-        }
-        // Unwrap to keep code less indented. Err is checked above.
-        let buyer_allowlist_token_account = buyer_allowlist_token_account.unwrap();
-
-        if buyer_allowlist_token_account.amount == 0 {
-            punish_bots(
-                CandyError::NoSplAllowlistToken,
-                buyer.to_account_info(),
-                ctx.accounts.candy_machine.to_account_info(),
-                ctx.accounts.system_program.to_account_info(),
-                BOT_FEE,
-            )?;
-            return Ok(());
-        }
+    if is_spl_token_allowlist_phase && !is_recipient_omni_minter {
+        let spl_token_allowlist_tokens = candy_machine.data.get_spl_token_allowlist_tokens();
+        let mut tokens_to_burn = Vec::new();
 
-        if buyer_allowlist_token_account.amount > 0
-            && spl_token_allowlist_settings.mode == SplTokenAllowlistMode::BurnEveryTime
+        for (token_index, spl_token_allowlist_token) in
+            spl_token_allowlist_tokens.iter().enumerate()
         {
-            let allowlist_token_mint = get_remaining_account(
+            let spl_token_allowlist_token_account = get_remaining_account(
                 candy_machine,
-                ctx.remaining_accounts,
-                RemainingAccounts::SplTokenAllowlistTokenMint,
-            );
-
-            let key_check = assert_keys_equal(
-                &allowlist_token_mint.key(),
-                &spl_token_allowlist_settings.mint,
+                &layout,
+                remaining_accounts,
+                RemainingAccounts::SplTokenAllowlistTokenAccount(token_index),
+            )?;
+            let allowlist_token_mint = if spl_token_allowlist_token.burn_amount > 0 {
+                Some(get_remaining_account(
+                    candy_machine,
+                    &layout,
+                    remaining_accounts,
+                    RemainingAccounts::SplTokenAllowlistTokenMint(token_index),
+                )?)
+            } else {
+                None
+            };
+
+            let is_spl_token_allowlist_valid = validate_spl_token_allowlist_accounts(
+                spl_token_allowlist_token,
+                &recipient.key(),
+                &spl_token_allowlist_token_account,
+                allowlist_token_mint.as_ref(),
+                quantity as u64,
             );
 
-            if key_check.is_err() {
+            if let Err(candy_error) = is_spl_token_allowlist_valid {
                 punish_bots(
-                    CandyError::MintMismatch,
-                    buyer.to_account_info(),
-                    ctx.accounts.candy_machine.to_account_info(),
-                    ctx.accounts.system_program.to_account_info(),
+                    candy_error,
+                    payer.to_account_info(),
+                    candy_machine.to_account_info(),
+                    system_program.to_account_info(),
                     BOT_FEE,
                 )?;
                 return Ok(());
             }
 
+            if let Some(allowlist_token_mint) = allowlist_token_mint {
+                let burn_amount = spl_token_allowlist_token
+                    .burn_amount
+                    .checked_mul(quantity as u64)
+                    .ok_or(CandyError::NumericalOverflowError)?;
+                tokens_to_burn.push((
+                    allowlist_token_mint,
+                    spl_token_allowlist_token_account,
+                    burn_amount,
+                ));
+            }
+        }
+
+        // Tokens are only burned once the recipient is known to hold all of them.
+        for (allowlist_token_mint, spl_token_allowlist_token_account, burn_amount) in tokens_to_burn
+        {
             spl_token_burn(TokenBurnParams {
-                mint: allowlist_token_mint.clone(),
-                source: spl_token_allowlist_token_account.clone(),
-                amount: 1,
-                authority: buyer.to_account_info(),
+                mint: allowlist_token_mint,
+                source: spl_token_allowlist_token_account,
+                amount: burn_amount,
+                authority: recipient.clone(),
                 authority_signer_seeds: None,
-                token_program: token_program.to_account_info(),
+                token_program: token_program.clone(),
             })?;
         }
     }
 
-    let (wallet_to_use, freeze_pda): (AccountInfo, Option<Account<FreezePda>>) =
-        if is_feature_active(&candy_machine.data.uuid, FREEZE_FEATURE_INDEX) {
-            if let Some(mint) = candy_machine.treasury_mint {
-                let freeze_pda_info = get_remaining_account(
-                    candy_machine,
-                    ctx.remaining_accounts,
-                    RemainingAccounts::FreezePda,
-                );
-                let freeze_ata = get_remaining_account(
-                    candy_machine,
-                    ctx.remaining_accounts,
-                    RemainingAccounts::FreezeAta,
-                );
-                assert_is_ata(&freeze_ata, freeze_pda_info.key, &mint)?;
-                let freeze_pda: Account<FreezePda> = Account::try_from(&freeze_pda_info)?;
-                if freeze_pda.thaw_eligible(clock.unix_timestamp, candy_machine) {
-                    (treasury_wallet, None)
-                } else {
-                    let freeze_ata = freeze_ata.to_account_info();
-                    (freeze_ata, Some(freeze_pda))
-                }
-            } else {
-                let freeze_pda_info = get_remaining_account(
-                    candy_machine,
-                    ctx.remaining_accounts,
-                    RemainingAccounts::FreezePda,
-                );
-                let freeze_pda: Account<FreezePda> = Account::try_from(&freeze_pda_info)?;
-                if freeze_pda.thaw_eligible(clock.unix_timestamp, candy_machine) {
-                    (treasury_wallet, None)
-                } else {
-                    (freeze_pda_info, Some(freeze_pda))
-                }
-            }
-        } else {
-            (treasury_wallet, None)
-        };
-
-    if let Some(mint) = candy_machine.treasury_mint {
-        let token_account_info = get_remaining_account(
-            candy_machine,
-            ctx.remaining_accounts,
-            RemainingAccounts::TreasuryTokenAccount,
+    if let (true, false, Some(collection_allowlist_settings)) = (
+        is_collection_allowlist_phase,
+        is_recipient_omni_minter,
+        &candy_machine.data.collection_allowlist_settings,
+    ) {
+        let is_collection_allowlist_valid = validate_collection_allowlist_accounts(
+            collection_allowlist_settings,
+            &recipient.key(),
+            &get_remaining_account(
+                candy_machine,
+                &layout,
+                remaining_accounts,
+                RemainingAccounts::CollectionAllowlistTokenAccount,
+            )?,
+            &get_remaining_account(
+                candy_machine,
+                &layout,
+                remaining_accounts,
+                RemainingAccounts::CollectionAllowlistMetadata,
+            )?,
+            &get_remaining_account(
+                candy_machine,
+                &layout,
+                remaining_accounts,
+                RemainingAccounts::CollectionAllowlistEdition,
+            )?,
         );
 
-        let token_account = assert_is_ata(&token_account_info, &buyer.key(), &mint)?;
+        let gating_mint = match is_collection_allowlist_valid {
+            Ok(gating_mint) => gating_mint,
+            Err(candy_error) => {
+                punish_bots(
+                    candy_error,
+                    payer.to_account_info(),
+                    candy_machine.to_account_info(),
+                    system_program.to_account_info(),
+                    BOT_FEE,
+                )?;
+                return Ok(());
+            }
+        };
 
-        if token_account.amount < price {
-            msg!(
-                "The mint price is {} SPL tokens but the buyer only had {}.",
-                price,
-                token_account.amount
-            );
-            return err!(CandyError::NotEnoughTokens);
-        }
+        if collection_allowlist_settings.use_once {
+            // Each gating NFT only covers a single mint.
+            if quantity > 1 {
+                return err!(CandyError::CollectionAllowlistNftAlreadyUsed);
+            }
 
-        spl_token_transfer(TokenTransferParams {
-            source: token_account_info.clone(),
-            destination: wallet_to_use.to_account_info(),
-            authority: buyer.to_account_info(),
-            authority_signer_seeds: &[],
-            token_program: token_program.to_account_info(),
-            amount: price,
-        })?;
-    } else {
-        if ctx.accounts.buyer.lamports() < price {
-            msg!(
-                "The mint price is {} SOL but the buyer only had {}.",
-                price,
-                ctx.accounts.buyer.lamports()
-            );
-            return err!(CandyError::NotEnoughSOL);
+            let collection_allowlist_record = get_remaining_account(
+                candy_machine,
+                &layout,
+                remaining_accounts,
+                RemainingAccounts::CollectionAllowlistRecord,
+            )?;
+            create_collection_allowlist_record(
+                &collection_allowlist_record,
+                &candy_pubkey,
+                &gating_mint,
+                &recipient.key(),
+                &payer,
+                &system_program,
+            )?;
         }
-        invoke(
-            &system_instruction::transfer(&ctx.accounts.buyer.key(), &wallet_to_use.key(), price),
-            &[
-                ctx.accounts.buyer.to_account_info(),
-                wallet_to_use.to_account_info(),
-                ctx.accounts.system_program.to_account_info(),
-            ],
-        )?;
     }
 
-    // *** BEGIN CREATE ATA ***
-    let mint = &ctx.accounts.mint;
-    let buyer_token_account = &ctx.accounts.buyer_token_account;
-    let ata_program = &ctx.accounts.ata_program;
-    let system_program = &ctx.accounts.system_program;
-    let rent = &ctx.accounts.rent;
-    let rent_struct = &Rent::from_account_info(&rent.to_account_info())?;
-    let min_rent_lamports = rent_struct.minimum_balance(Mint::LEN).max(1);
-    invoke_signed(
-        &system_instruction::create_account(
-            &buyer.key(),
-            &mint.key(),
-            min_rent_lamports,
-            Mint::LEN as u64,
-            &token_program.key(),
-        ),
-        &[
-            buyer.to_account_info(),
-            mint.to_account_info(),
-            system_program.to_account_info(),
-        ],
-        &[],
-    )?;
-
-    invoke_signed(
-        &initialize_mint(
-            &token_program.key(),
-            &mint.key(),
-            &buyer.key(),
-            Some(&buyer.key()),
-            0,
-        )
-        .unwrap(),
-        &[
-            mint.to_account_info(),
-            rent.to_account_info(),
-            token_program.to_account_info(),
-        ],
-        &[],
+    let treasury_token_account = treasury_mint
+        .map(|_| {
+            get_remaining_account(
+                candy_machine,
+                &layout,
+                remaining_accounts,
+                RemainingAccounts::TreasuryTokenAccount,
+            )
+        })
+        .transpose()?;
+
+    // During a Dutch auction with rebates, the amount paid above the floor
+    // price is held by the candy machine until the auction ends.
+    let (mint_payment_amount, dutch_auction_escrow_amount) = get_dutch_auction_rebate_payments(
+        dutch_auction_rebate_settings.as_ref(),
+        total_price,
+        quantity as u64,
     )?;
 
-    make_ata(
-        buyer_token_account.to_account_info(),
-        buyer.to_account_info(),
-        mint.to_account_info(),
-        buyer.to_account_info(),
-        ata_program.to_account_info(),
-        token_program.to_account_info(),
-        system_program.to_account_info(),
-        rent.to_account_info(),
-        &[],
+    let nft_remaining_accounts_start = get_nft_remaining_accounts_start(candy_machine, &layout);
+    let wallet_to_use_key = wallet_to_use.key();
+    let mint_payments = get_mint_payments(
+        candy_machine,
+        treasury_mint,
+        wallet_to_use,
+        freeze_pda.is_some(),
+        get_platform_fee_account(
+            candy_machine,
+            remaining_accounts,
+            nft_remaining_accounts_start,
+        ),
+        get_primary_sale_split_accounts(
+            candy_machine,
+            remaining_accounts,
+            nft_remaining_accounts_start,
+        ),
+        mint_payment_amount,
     )?;
 
-    invoke_signed(
-        &mint_to(
-            &token_program.key(),
-            &mint.key(),
-            &buyer_token_account.key(),
-            &buyer.key(),
-            &[],
-            1,
-        )
-        .unwrap(),
-        &[
-            mint.to_account_info(),
-            buyer_token_account.to_account_info(),
-            buyer.to_account_info(),
-            token_program.to_account_info(),
-        ],
-        &[],
-    )?;
-    // *** END CREATE ATA ***
+    for (destination, amount) in mint_payments.iter() {
+        transfer_mint_payment(
+            treasury_mint,
+            &payer,
+            destination,
+            treasury_token_account.clone(),
+            &token_program,
+            &system_program,
+            *amount,
+        )?;
+    }
 
-    // Sequential minting is only allowed in the premint phase for now.
-    let config_line_initial_index =
-        if candy_machine.data.sequential_mint_order_enabled && mint_phase == MintPhase::Premint {
-            candy_machine.items_redeemed as usize
-        } else {
-            let data = recent_slothashes.data.borrow();
-            let most_recent = array_ref![data, 12, 8];
+    if dutch_auction_escrow_amount > 0 {
+        // Dutch auctions with rebates are always paid in SOL.
+        transfer_mint_payment(
+            None,
+            &payer,
+            &candy_machine.to_account_info(),
+            None,
+            &token_program,
+            &system_program,
+            dutch_auction_escrow_amount,
+        )?;
+    }
 
-            let index = u64::from_le_bytes(*most_recent);
-            index
-                .checked_rem(candy_machine.data.items_available)
-                .ok_or(CandyError::NumericalOverflowError)? as usize
+    let mut programmable_nft_accounts = if candy_machine.is_programmable() {
+        get_programmable_nft_accounts(
+            candy_machine,
+            &candy_pubkey,
+            &layout,
+            remaining_accounts,
+            instruction_sysvar_account_info.clone(),
+        )?
+    } else {
+        Vec::new()
+    }
+    .into_iter();
+
+    let trailing_remaining_accounts_start = get_nft_remaining_accounts_end(candy_machine, &layout)
+        + get_programmable_nft_remaining_accounts_counter(candy_machine);
+
+    let mut compressed = if candy_machine.is_compressed() {
+        Some(get_compressed_nft_accounts(
+            candy_machine,
+            remaining_accounts,
+            trailing_remaining_accounts_start,
+        )?)
+    } else {
+        None
+    };
+
+    let mut open_edition = if candy_machine.is_open_edition() {
+        Some(get_open_edition_accounts(
+            &candy_pubkey,
+            remaining_accounts,
+            trailing_remaining_accounts_start,
+            accounts.creator_authority.clone(),
+        )?)
+    } else {
+        None
+    };
+
+    // Each NFT can be refunded for an equal share of the payment held by the
+    // FreezePda.
+    let held_mint_payment_amount = get_held_mint_payment_amount(&mint_payments, &wallet_to_use_key);
+    let refund_amount = held_mint_payment_amount
+        .checked_div(quantity as u64)
+        .ok_or(CandyError::NumericalOverflowError)?;
+    let mut remaining_refund_amount = held_mint_payment_amount;
+
+    for (nft_index, nft_accounts) in nft_accounts_list.into_iter().enumerate() {
+        let mint_nft_accounts = MintNftAccounts {
+            candy_machine_creator: accounts.candy_machine_creator.clone(),
+            payer: payer.clone(),
+            recipient: recipient.clone(),
+            mint: nft_accounts.mint,
+            metadata: nft_accounts.metadata,
+            master_edition: nft_accounts.master_edition,
+            recipient_token_account: nft_accounts.recipient_token_account,
+            token_metadata_program: accounts.token_metadata_program.clone(),
+            token_program: token_program.clone(),
+            system_program: system_program.clone(),
+            ata_program: accounts.ata_program.clone(),
+            rent: accounts.rent.clone(),
+            recent_slothashes: recent_slothashes.clone(),
+            programmable: programmable_nft_accounts.next(),
+            // Compressed NFTs and open editions are minted one at a time.
+            compressed: compressed.take(),
+            open_edition: open_edition.take(),
+        };
+
+        let config_index =
+            create_nft(candy_machine, &mint_nft_accounts, creator_bump, &mint_phase)?;
+
+        if let Some(freeze_pda) = freeze_pda.as_mut() {
+            freeze_nft(
+                freeze_pda,
+                &candy_pubkey,
+                &mint_nft_accounts,
+                &mint_nft_accounts.recipient_token_account,
+                clock.unix_timestamp,
+            )?;
+
+            // The last NFT receives any remainder left over from rounding down.
+            let nft_refund_amount = if nft_index == layout.quantity - 1 {
+                remaining_refund_amount
+            } else {
+                refund_amount
+            };
+            remaining_refund_amount = remaining_refund_amount
+                .checked_sub(nft_refund_amount)
+                .ok_or(CandyError::NumericalOverflowError)?;
+            let refund_receipt_info = get_remaining_account(
+                candy_machine,
+                &layout,
+                remaining_accounts,
+                RemainingAccounts::Nft(nft_index, NftRemainingAccounts::RefundReceipt),
+            )?;
+            create_refund_receipt(
+                &refund_receipt_info,
+                &candy_pubkey,
+                &mint_nft_accounts,
+                treasury_mint,
+                nft_refund_amount,
+                config_index,
+                &purchase_counters,
+            )?;
+        }
+    }
+
+    if let Some(freeze_pda) = freeze_pda {
+        freeze_pda.exit(&crate::id())?;
+    }
+
+    if purchase_counters.merkle_allowlist_phase {
+        let mut buyer_info_account: Account<BuyerInfoAccount> =
+            Account::try_from(buyer_info_account)?;
+        let number_bought_for_merkle_allowlist = buyer_info_account
+            .number_bought_merkle_allowlist_phase
+            .checked_add(quantity as u16)
+            .ok_or(CandyError::NumericalOverflowError)?;
+        buyer_info_account.number_bought_merkle_allowlist_phase =
+            number_bought_for_merkle_allowlist;
+
+        // This re-serializes the account to persist the changes.
+        buyer_info_account.exit(&crate::id())?;
+    }
+
+    if purchase_counters.public_phase {
+        let mut buyer_info_account: Account<BuyerInfoAccount> =
+            Account::try_from(buyer_info_account)?;
+        let number_bought = buyer_info_account
+            .number_bought_public_phase
+            .checked_add(quantity as u16)
+            .ok_or(CandyError::NumericalOverflowError)?;
+        require!(
+            number_bought <= limit_per_address,
+            CandyError::BuyLimitPerAddressExceeded
+        );
+
+        buyer_info_account.number_bought_public_phase = number_bought;
+
+        // This re-serializes the account to persist the changes.
+        buyer_info_account.exit(&crate::id())?;
+    }
+
+    if let Some(mint_phase_index) = purchase_counters.mint_phase_index {
+        let mint_phase_index = mint_phase_index as usize;
+        let mut buyer_info_account: Account<BuyerInfoAccount> =
+            Account::try_from(buyer_info_account)?;
+        let number_bought_for_mint_phase = buyer_info_account.number_bought_per_mint_phase
+            [mint_phase_index]
+            .checked_add(quantity as u16)
+            .ok_or(CandyError::NumericalOverflowError)?;
+        require!(
+            number_bought_for_mint_phase <= mint_phase_limit_per_address,
+            CandyError::BuyLimitPerAddressExceeded
+        );
+
+        buyer_info_account.number_bought_per_mint_phase[mint_phase_index] =
+            number_bought_for_mint_phase;
+
+        // This re-serializes the account to persist the changes.
+        buyer_info_account.exit(&crate::id())?;
+    }
+
+    record_phase_limit_purchase(
+        buyer_info_account,
+        &mint_phase,
+        phase_limit_per_address,
+        quantity as u16,
+    )?;
+
+    if purchase_counters.total {
+        record_total_limit_purchase(
+            candy_machine,
+            buyer_info_account,
+            quantity as u16,
+            !is_recipient_omni_minter && total_limit_per_address > 0,
+        )?;
+    }
+
+    if purchase_counters.allowlist_items_sold {
+        candy_machine.allowlist_items_sold = candy_machine
+            .allowlist_items_sold
+            .checked_add(quantity as u64)
+            .ok_or(CandyError::NumericalOverflowError)?;
+    }
+
+    if dutch_auction_rebate_settings.is_some() {
+        // The rebate is owed to the payer.
+        let rebate_buyer_info_account = if layout.payer_buyer_info_account {
+            get_or_create_payer_buyer_info_account(
+                candy_machine,
+                &candy_pubkey,
+                &layout,
+                remaining_accounts,
+                &payer,
+                &system_program,
+            )?
+        } else {
+            buyer_info_account.clone()
         };
+        record_dutch_auction_rebate_purchase(
+            candy_machine,
+            &rebate_buyer_info_account,
+            price,
+            quantity as u16,
+            dutch_auction_escrow_amount,
+        )?;
+    }
+
+    Ok(())
+}
+
+/// Returns the program id of the first instruction in the transaction which is
+/// not allowed to be included alongside a mint, if there is one.
+pub(crate) fn find_unauthorized_program_id(instruction_sysvar: &[u8]) -> Result<Option<Pubkey>> {
+    let mut idx = 0;
+    let num_instructions =
+        read_u16(&mut idx, instruction_sysvar).map_err(|_| ProgramError::InvalidAccountData)?;
+
+    for index in 0..num_instructions {
+        let mut current = 2 + (index * 2) as usize;
+        let start = read_u16(&mut current, instruction_sysvar).unwrap();
+
+        current = start as usize;
+        let num_accounts = read_u16(&mut current, instruction_sysvar).unwrap();
+        current += (num_accounts as usize) * (1 + 32);
+        let program_id = read_pubkey(&mut current, instruction_sysvar).unwrap();
+
+        if !cmp_pubkeys(&program_id, &crate::id())
+            && !cmp_pubkeys(&program_id, &spl_token::id())
+            && !cmp_pubkeys(&program_id, &solana_program::system_program::ID)
+            && !cmp_pubkeys(&program_id, &A_TOKEN)
+            && !cmp_pubkeys(&program_id, &COMPUTE_BUDGET)
+        {
+            return Ok(Some(program_id));
+        }
+    }
+
+    Ok(None)
+}
+
+/// Returns whether the Merkle allowlist, the SPL token allowlist and the
+/// collection allowlist should be checked for the given mint phase. If the
+/// active mint phase is configured, only its allowlist source is checked.
+pub(crate) fn get_allowlist_phase_checks(
+    mint_phase: &MintPhase,
+    active_mint_phase_settings: Option<&MintPhaseSettings>,
+) -> (bool, bool, bool) {
+    // Allowlist checks only apply during allowlist mint phase.
+    let is_allowlist_phase = mint_phase == &MintPhase::Allowlist;
+
+    let allowlist_source = active_mint_phase_settings.map(|settings| &settings.allowlist_source);
+    let is_merkle_allowlist_phase = is_allowlist_phase
+        && matches!(
+            allowlist_source,
+            None | Some(MintPhaseAllowlistSource::MerkleAllowlist)
+        );
+    let is_spl_token_allowlist_phase = is_allowlist_phase
+        && matches!(
+            allowlist_source,
+            None | Some(MintPhaseAllowlistSource::SplTokenAllowlist)
+        );
+    let is_collection_allowlist_phase = is_allowlist_phase
+        && matches!(
+            allowlist_source,
+            None | Some(MintPhaseAllowlistSource::CollectionAllowlist)
+        );
+
+    (
+        is_merkle_allowlist_phase,
+        is_spl_token_allowlist_phase,
+        is_collection_allowlist_phase,
+    )
+}
+
+/// Returns whether the SPL token allowlist accounts and the collection
+/// allowlist accounts are passed to a mint at unix_timestamp. They are only
+/// passed during allowlist phases which check them, not during the public
+/// phase.
+pub(crate) fn get_allowlist_accounts_layout(
+    candy: &CandyMachine,
+    unix_timestamp: i64,
+) -> (bool, bool) {
+    let mint_phase = candy.get_mint_phase(unix_timestamp);
+    let active_mint_phase_settings = candy
+        .get_active_mint_phase_index(unix_timestamp)
+        .map(|mint_phase_index| &candy.data.mint_phases[mint_phase_index]);
+    let (_, is_spl_token_allowlist_phase, is_collection_allowlist_phase) =
+        get_allowlist_phase_checks(&mint_phase, active_mint_phase_settings);
+
+    (is_spl_token_allowlist_phase, is_collection_allowlist_phase)
+}
+
+/// Returns the price of a single NFT for the buyer. Buyers minting with a proof
+/// for a Merkle allowlist root which has its own price pay that price instead
+/// of the mint phase price.
+pub(crate) fn get_buyer_mint_price(
+    candy_machine: &CandyMachine,
+    mint_phase: &MintPhase,
+    now: i64,
+    should_check_merkle_allowlist_proof: bool,
+    buyer_merkle_allowlist_proof_data: &Option<BuyerMerkleAllowlistProofData>,
+) -> u64 {
+    let merkle_allowlist_root_price = match (
+        should_check_merkle_allowlist_proof,
+        buyer_merkle_allowlist_proof_data,
+    ) {
+        (true, Some(proof_data)) => {
+            candy_machine.get_merkle_allowlist_root_price(proof_data.root_index_for_proof as usize)
+        }
+        _ => None,
+    };
+
+    merkle_allowlist_root_price
+        .unwrap_or_else(|| CandyMachine::get_mint_price(candy_machine, mint_phase, now))
+}
+
+/// Checks the mint price against the price the buyer expected to pay. During a
+/// Dutch auction the price may have dropped since the transaction was created,
+/// in which case the buyer pays the lower price.
+pub(crate) fn assert_expected_mint_price(
+    candy_machine: &CandyMachine,
+    mint_phase: &MintPhase,
+    now: i64,
+    price: u64,
+    expected_price: u64,
+) -> Result<()> {
+    let is_price_valid = match candy_machine.get_dutch_auction_price(mint_phase, now) {
+        Some(_) => price <= expected_price,
+        None => price == expected_price,
+    };
+
+    if !is_price_valid {
+        msg!(
+            "Invalid mint price for mint_phase {:?}: actual mint price = {}, expected price = {}.",
+            mint_phase,
+            price,
+            expected_price
+        );
+        return Err(CandyError::InvalidMintPrice.into());
+    }
+
+    Ok(())
+}
+
+/// Splits the total price of a mint during a Dutch auction with rebates into
+/// the amount which is paid out now (the floor price) and the amount which the
+/// candy machine holds until the auction ends.
+pub(crate) fn get_dutch_auction_rebate_payments(
+    dutch_auction_rebate_settings: Option<&DutchAuctionSettings>,
+    total_price: u64,
+    quantity: u64,
+) -> Result<(u64, u64)> {
+    match dutch_auction_rebate_settings {
+        Some(settings) => {
+            let floor_price_total = settings
+                .floor_price
+                .checked_mul(quantity)
+                .ok_or(CandyError::NumericalOverflowError)?;
+            let escrow_amount = total_price
+                .checked_sub(floor_price_total)
+                .ok_or(CandyError::NumericalOverflowError)?;
+            Ok((floor_price_total, escrow_amount))
+        }
+        None => Ok((total_price, 0)),
+    }
+}
+
+/// Records a purchase during a Dutch auction with rebates in the payer's
+/// BuyerInfoAccount, so the payer can claim back the difference to the
+/// clearing price once the auction ends.
+pub(crate) fn record_dutch_auction_rebate_purchase(
+    candy_machine: &mut CandyMachine,
+    buyer_info_account: &AccountInfo,
+    price: u64,
+    quantity: u16,
+    // The amount paid above the floor price, which the candy machine holds.
+    escrow_amount: u64,
+) -> Result<()> {
+    let rebate_state = &mut candy_machine.dutch_auction_rebate_state;
+    rebate_state.clearing_price = match rebate_state.items_sold {
+        0 => price,
+        _ => rebate_state.clearing_price.min(price),
+    };
+    rebate_state.items_sold = rebate_state
+        .items_sold
+        .checked_add(quantity as u64)
+        .ok_or(CandyError::NumericalOverflowError)?;
+    rebate_state.amount_held = rebate_state
+        .amount_held
+        .checked_add(escrow_amount)
+        .ok_or(CandyError::NumericalOverflowError)?;
+
+    let amount_paid = price
+        .checked_mul(quantity as u64)
+        .ok_or(CandyError::NumericalOverflowError)?;
+    let mut buyer_info_account: Account<BuyerInfoAccount> = Account::try_from(buyer_info_account)?;
+    buyer_info_account.dutch_auction_amount_paid = buyer_info_account
+        .dutch_auction_amount_paid
+        .checked_add(amount_paid)
+        .ok_or(CandyError::NumericalOverflowError)?;
+    buyer_info_account.dutch_auction_number_bought = buyer_info_account
+        .dutch_auction_number_bought
+        .checked_add(quantity)
+        .ok_or(CandyError::NumericalOverflowError)?;
+
+    // This re-serializes the account to persist the changes.
+    buyer_info_account.exit(&crate::id())
+}
+
+/// Returns the per wallet limit of the premint or allowlist phase which
+/// applies to the buyer, or 0 if there is none.
+pub(crate) fn get_phase_limit_per_address(
+    candy_machine: &CandyMachine,
+    mint_phase: &MintPhase,
+    is_recipient_omni_minter: bool,
+) -> u16 {
+    let phase_limits = &candy_machine.data.phase_limits;
+    match (mint_phase, is_recipient_omni_minter) {
+        // Only omni minters can mint during the premint phase.
+        (MintPhase::Premint, true) => phase_limits.premint_limit_per_address,
+        (MintPhase::Allowlist, false) => phase_limits.allowlist_limit_per_address,
+        _ => 0,
+    }
+}
+
+/// Counts a purchase against the per wallet limit of the premint or allowlist
+/// phase.
+pub(crate) fn record_phase_limit_purchase(
+    buyer_info_account: &AccountInfo,
+    mint_phase: &MintPhase,
+    phase_limit_per_address: u16,
+    quantity: u16,
+) -> Result<()> {
+    if phase_limit_per_address == 0 {
+        return Ok(());
+    }
+
+    let mut buyer_info_account: Account<BuyerInfoAccount> = Account::try_from(buyer_info_account)?;
+    let number_bought = match mint_phase {
+        MintPhase::Premint => &mut buyer_info_account.number_bought_premint_phase,
+        _ => &mut buyer_info_account.number_bought_allowlist_phase,
+    };
+    *number_bought = number_bought
+        .checked_add(quantity)
+        .ok_or(CandyError::NumericalOverflowError)?;
+    require!(
+        *number_bought <= phase_limit_per_address,
+        CandyError::BuyLimitPerAddressExceeded
+    );
+
+    // This re-serializes the account to persist the changes.
+    buyer_info_account.exit(&crate::id())
+}
+
+/// Counts a purchase towards the total bought across every mint phase, which
+/// is checked against the total limit per address if the buyer is limited.
+pub(crate) fn record_total_limit_purchase(
+    candy_machine: &CandyMachine,
+    buyer_info_account: &AccountInfo,
+    quantity: u16,
+    is_limited: bool,
+) -> Result<()> {
+    let mut buyer_info_account: Account<BuyerInfoAccount> = Account::try_from(buyer_info_account)?;
+    buyer_info_account.number_bought_total = if is_limited {
+        validate_total_limit_per_address(candy_machine, &buyer_info_account, quantity)?
+    } else {
+        buyer_info_account
+            .number_bought_total
+            .checked_add(quantity)
+            .ok_or(CandyError::NumericalOverflowError)?
+    };
+
+    // This re-serializes the account to persist the changes.
+    buyer_info_account.exit(&crate::id())
+}
+
+/// Verifies the recipient's Merkle allowlist proof against the root it was
+/// created for. The root must also be live.
+pub(crate) fn verify_buyer_merkle_allowlist_proof(
+    recipient: &Pubkey,
+    candy_pubkey: &Pubkey,
+    candy_machine: &CandyMachine,
+    proof_data: &BuyerMerkleAllowlistProofData,
+    now: i64,
+) -> Result<()> {
+    let root_index_for_proof = proof_data.root_index_for_proof as usize;
+
+    let roots_list = &candy_machine.data.merkle_allowlist_root_list;
+    if roots_list.is_empty() {
+        msg!("Invalid allowlist proof provided, the current roots list is empty.");
+        return err!(CandyError::InvalidAllowlistProof);
+    } else if root_index_for_proof >= roots_list.len() {
+        msg!(
+            "Invalid root_index_for_proof provided, received: {}, roots_list length = {}.",
+            root_index_for_proof,
+            roots_list.len()
+        );
+        return err!(CandyError::InvalidAllowlistProof);
+    }
+
+    let leaf = solana_program::keccak::hashv(&[
+        &[0x00],
+        &recipient.to_bytes(),
+        &candy_pubkey.to_bytes(),
+        &proof_data.amount.to_le_bytes(),
+    ]);
+
+    let merkle_allowlist_root = &roots_list[root_index_for_proof];
+    if !merkle_allowlist_root.is_live(now) {
+        msg!(
+            "Merkle allowlist root at root_index_for_proof {} is not live, start_time = {:?}, end_time = {:?}.",
+            root_index_for_proof,
+            merkle_allowlist_root.start_time,
+            merkle_allowlist_root.end_time
+        );
+        return err!(CandyError::MerkleAllowlistRootNotLive);
+    }
+
+    let is_proof_valid = verify_merkle_proof(&proof_data.proof, merkle_allowlist_root.root, leaf.0);
+    if !is_proof_valid {
+        msg!(
+            "Invalid proof provided for root_index_for_proof: {}.",
+            root_index_for_proof
+        );
+        return err!(CandyError::InvalidAllowlistProof);
+    }
+
+    Ok(())
+}
+
+/// Checks the recipient holds enough of an SPL allowlist token to mint the
+/// given quantity. Errors are returned as a CandyError so the caller can
+/// collect the bot tax.
+pub(crate) fn validate_spl_token_allowlist_accounts(
+    spl_token_allowlist_token: &SplTokenAllowlistToken,
+    recipient: &Pubkey,
+    spl_token_allowlist_token_account: &AccountInfo,
+    allowlist_token_mint: Option<&AccountInfo>,
+    quantity: u64,
+) -> StandardResult<(), CandyError> {
+    let recipient_allowlist_token_account = assert_is_token_account(
+        spl_token_allowlist_token_account,
+        recipient,
+        &spl_token_allowlist_token.mint,
+    )
+    .map_err(|_| CandyError::NoSplAllowlistToken)?;
+
+    let required_balance = spl_token_allowlist_token
+        .min_balance
+        .max(spl_token_allowlist_token.burn_amount)
+        .saturating_mul(quantity);
+    if recipient_allowlist_token_account.amount < required_balance {
+        return Err(CandyError::NoSplAllowlistToken);
+    }
+
+    if let Some(allowlist_token_mint) = allowlist_token_mint {
+        assert_keys_equal(allowlist_token_mint.key, &spl_token_allowlist_token.mint)
+            .map_err(|_| CandyError::MintMismatch)?;
+    }
+
+    Ok(())
+}
+
+/// Checks that the recipient holds an NFT whose metadata has a verified
+/// collection matching the allowlist collection, and returns the NFT's mint.
+pub(crate) fn validate_collection_allowlist_accounts(
+    collection_allowlist_settings: &CollectionAllowlistSettings,
+    recipient: &Pubkey,
+    gating_token_account: &AccountInfo,
+    gating_metadata: &AccountInfo,
+    gating_edition: &AccountInfo,
+) -> StandardResult<Pubkey, CandyError> {
+    assert_owned_by(gating_metadata, &mpl_token_metadata::id())
+        .map_err(|_| CandyError::NoCollectionAllowlistNft)?;
+    let metadata = Metadata::from_account_info(gating_metadata)
+        .map_err(|_| CandyError::NoCollectionAllowlistNft)?;
+    let is_verified_collection_member = matches!(
+        &metadata.collection,
+        Some(collection) if collection.verified
+            && cmp_pubkeys(&collection.key, &collection_allowlist_settings.collection_mint)
+    );
+    if !is_verified_collection_member {
+        return Err(CandyError::NoCollectionAllowlistNft);
+    }
+
+    // Only NFTs have an edition, which rules out fungible tokens with
+    // collection metadata. Master and print editions share the same address.
+    let (expected_edition_key, _) = Pubkey::find_program_address(
+        &[
+            mpl_token_metadata::state::PREFIX.as_bytes(),
+            mpl_token_metadata::id().as_ref(),
+            metadata.mint.as_ref(),
+            mpl_token_metadata::state::EDITION.as_bytes(),
+        ],
+        &mpl_token_metadata::id(),
+    );
+    if !cmp_pubkeys(gating_edition.key, &expected_edition_key)
+        || !cmp_pubkeys(gating_edition.owner, &mpl_token_metadata::id())
+        || gating_edition.data_is_empty()
+    {
+        return Err(CandyError::NoCollectionAllowlistNft);
+    }
+
+    let recipient_gating_token_account =
+        assert_is_token_account(gating_token_account, recipient, &metadata.mint)
+            .map_err(|_| CandyError::NoCollectionAllowlistNft)?;
+    if recipient_gating_token_account.amount < 1 {
+        return Err(CandyError::NoCollectionAllowlistNft);
+    }
+
+    Ok(metadata.mint)
+}
+
+/// Creates the CollectionAllowlistRecord which marks the gating NFT as used.
+/// Fails if the NFT was already used for a mint.
+pub(crate) fn create_collection_allowlist_record<'info>(
+    collection_allowlist_record_info: &AccountInfo<'info>,
+    candy_pubkey: &Pubkey,
+    gating_mint: &Pubkey,
+    recipient: &Pubkey,
+    payer: &AccountInfo<'info>,
+    system_program: &AccountInfo<'info>,
+) -> Result<()> {
+    let (expected_record_key, record_bump) = Pubkey::find_program_address(
+        &[
+            CollectionAllowlistRecord::PREFIX.as_bytes(),
+            candy_pubkey.as_ref(),
+            gating_mint.as_ref(),
+        ],
+        &crate::id(),
+    );
+    assert_keys_equal(&expected_record_key, collection_allowlist_record_info.key)?;
+    if !collection_allowlist_record_info.data_is_empty() {
+        return err!(CandyError::CollectionAllowlistNftAlreadyUsed);
+    }
+
+    let signer_seeds = [
+        CollectionAllowlistRecord::PREFIX.as_bytes(),
+        candy_pubkey.as_ref(),
+        gating_mint.as_ref(),
+        &[record_bump],
+    ];
+    create_or_allocate_account_raw(
+        crate::id(),
+        collection_allowlist_record_info,
+        system_program,
+        payer,
+        CollectionAllowlistRecord::SIZE,
+        &signer_seeds,
+    )?;
+
+    let collection_allowlist_record = CollectionAllowlistRecord {
+        candy_machine: *candy_pubkey,
+        gating_mint: *gating_mint,
+        recipient: *recipient,
+    };
+    let mut data_ref: &mut [u8] = &mut collection_allowlist_record_info.try_borrow_mut_data()?;
+    collection_allowlist_record.try_serialize(&mut data_ref)?;
+
+    Ok(())
+}
+
+/// Returns the payment option the buyer selected, along with the SPL token
+/// the mint is paid in (if any). The treasury wallet must belong to the
+/// selected payment option.
+pub(crate) fn get_mint_payment_option(
+    candy_machine: &CandyMachine,
+    payment_option_index: Option<u8>,
+    treasury_wallet: &Pubkey,
+) -> Result<(Option<PaymentOption>, Option<Pubkey>)> {
+    let payment_option = candy_machine
+        .get_payment_option(payment_option_index)?
+        .cloned();
+    let (treasury_mint, expected_treasury_wallet) = match &payment_option {
+        Some(payment_option) => (payment_option.treasury_mint, payment_option.treasury_wallet),
+        None => (candy_machine.treasury_mint, candy_machine.treasury_wallet),
+    };
 
-    let config_line = get_config_line(
+    if !cmp_pubkeys(treasury_wallet, &expected_treasury_wallet) {
+        return err!(CandyError::TreasuryWalletMismatch);
+    }
+
+    Ok((payment_option, treasury_mint))
+}
+
+/// Returns the account the mint payment should be sent to when the freeze
+/// feature is active, along with the FreezePda if the NFT should be frozen.
+pub(crate) fn get_mint_payment_destination<'info>(
+    candy_machine: &CandyMachine,
+    treasury_mint: Option<Pubkey>,
+    treasury_wallet: AccountInfo<'info>,
+    freeze_pda_info: &AccountInfo<'info>,
+    freeze_ata: Option<AccountInfo<'info>>,
+    now: i64,
+) -> Result<(AccountInfo<'info>, Option<Account<'info, FreezePda>>)> {
+    if let Some(mint) = treasury_mint {
+        let freeze_ata = freeze_ata.ok_or(CandyError::IncorrectRemainingAccountsLen)?;
+        assert_is_ata(&freeze_ata, freeze_pda_info.key, &mint)?;
+        let freeze_pda: Account<FreezePda> = Account::try_from(freeze_pda_info)?;
+        if freeze_pda.thaw_eligible(now, candy_machine) {
+            Ok((treasury_wallet, None))
+        } else {
+            Ok((freeze_ata, Some(freeze_pda)))
+        }
+    } else {
+        let freeze_pda: Account<FreezePda> = Account::try_from(freeze_pda_info)?;
+        if freeze_pda.thaw_eligible(now, candy_machine) {
+            Ok((treasury_wallet, None))
+        } else {
+            Ok((freeze_pda_info.clone(), Some(freeze_pda)))
+        }
+    }
+}
+
+/// Returns the destination and amount of each mint payment. The platform fee
+/// is always paid out immediately. The rest of the price is split between the
+/// primary sale splits, unless the payment is held by the FreezePda, in which
+/// case it all goes to the given destination. Primary sale splits can't be
+/// set while freeze is active, so this only happens without splits.
+pub(crate) fn get_mint_payments<'info>(
+    candy_machine: &CandyMachine,
+    treasury_mint: Option<Pubkey>,
+    destination: AccountInfo<'info>,
+    is_payment_held_by_freeze_pda: bool,
+    platform_fee_account: Option<AccountInfo<'info>>,
+    primary_sale_split_accounts: &[AccountInfo<'info>],
+    price: u64,
+) -> Result<Vec<(AccountInfo<'info>, u64)>> {
+    let mut mint_payments = Vec::with_capacity(candy_machine.data.primary_sale_splits.len() + 1);
+
+    let price = match candy_machine.get_platform_fee_wallet() {
+        Some(platform_fee_wallet) => {
+            let platform_fee_account =
+                platform_fee_account.ok_or(CandyError::IncorrectRemainingAccountsLen)?;
+            match treasury_mint {
+                Some(mint) => {
+                    assert_is_ata(&platform_fee_account, &platform_fee_wallet, &mint)
+                        .map_err(|_| CandyError::PlatformFeeWalletMismatch)?;
+                }
+                None => {
+                    if !cmp_pubkeys(platform_fee_account.key, &platform_fee_wallet) {
+                        return err!(CandyError::PlatformFeeWalletMismatch);
+                    }
+                }
+            }
+
+            let platform_fee = (price as u128)
+                .checked_mul(candy_machine.platform_fee_basis_points as u128)
+                .and_then(|amount| amount.checked_div(MAX_PLATFORM_FEE_BASIS_POINTS as u128))
+                .ok_or(CandyError::NumericalOverflowError)? as u64;
+            mint_payments.push((platform_fee_account, platform_fee));
+
+            price
+                .checked_sub(platform_fee)
+                .ok_or(CandyError::NumericalOverflowError)?
+        }
+        None => price,
+    };
+
+    let primary_sale_splits = &candy_machine.data.primary_sale_splits;
+    if primary_sale_splits.is_empty() || is_payment_held_by_freeze_pda {
+        mint_payments.push((destination, price));
+        return Ok(mint_payments);
+    }
+
+    if primary_sale_split_accounts.len() != primary_sale_splits.len() {
+        return err!(CandyError::IncorrectRemainingAccountsLen);
+    }
+
+    let mut remaining_price = price;
+    for (index, (primary_sale_split, split_account)) in primary_sale_splits
+        .iter()
+        .zip(primary_sale_split_accounts.iter())
+        .enumerate()
+    {
+        match treasury_mint {
+            Some(mint) => {
+                assert_is_ata(split_account, &primary_sale_split.address, &mint)
+                    .map_err(|_| CandyError::PrimarySaleSplitMismatch)?;
+            }
+            None => {
+                if !cmp_pubkeys(split_account.key, &primary_sale_split.address) {
+                    return err!(CandyError::PrimarySaleSplitMismatch);
+                }
+            }
+        }
+
+        // The last split receives any remainder left over from rounding down.
+        let amount = if index == primary_sale_splits.len() - 1 {
+            remaining_price
+        } else {
+            (price as u128)
+                .checked_mul(primary_sale_split.basis_points as u128)
+                .and_then(|amount| {
+                    amount.checked_div(PRIMARY_SALE_SPLITS_TOTAL_BASIS_POINTS as u128)
+                })
+                .ok_or(CandyError::NumericalOverflowError)? as u64
+        };
+        remaining_price = remaining_price
+            .checked_sub(amount)
+            .ok_or(CandyError::NumericalOverflowError)?;
+
+        mint_payments.push((split_account.clone(), amount));
+    }
+
+    Ok(mint_payments)
+}
+
+/// Returns the total amount of the mint payments sent to the given account.
+pub(crate) fn get_held_mint_payment_amount(
+    mint_payments: &[(AccountInfo, u64)],
+    destination: &Pubkey,
+) -> u64 {
+    mint_payments
+        .iter()
+        .filter(|(account, _)| cmp_pubkeys(account.key, destination))
+        .map(|(_, amount)| *amount)
+        .sum()
+}
+
+/// Returns the primary sale split accounts, which are passed after the other
+/// remaining_accounts shared by the mint. This is empty if they are missing.
+pub(crate) fn get_primary_sale_split_accounts<'a, 'b>(
+    candy: &CandyMachine,
+    remaining_accounts: &'b [AccountInfo<'a>],
+    // The number of remaining accounts up to and including the splits.
+    end_index: usize,
+) -> &'b [AccountInfo<'a>] {
+    let start_index = end_index.saturating_sub(candy.data.primary_sale_splits.len());
+    remaining_accounts
+        .get(start_index..end_index)
+        .unwrap_or(&[])
+}
+
+/// Returns the platform fee account, which is passed right before the primary
+/// sale split accounts. This is None if the candy machine has no platform fee.
+pub(crate) fn get_platform_fee_account<'info>(
+    candy: &CandyMachine,
+    remaining_accounts: &[AccountInfo<'info>],
+    // The number of remaining accounts up to and including the splits.
+    end_index: usize,
+) -> Option<AccountInfo<'info>> {
+    candy.get_platform_fee_wallet()?;
+    end_index
+        .checked_sub(candy.data.primary_sale_splits.len() + 1)
+        .and_then(|index| remaining_accounts.get(index))
+        .cloned()
+}
+
+/// Transfers the mint payment from the payer in SOL, or in the given SPL
+/// token.
+pub(crate) fn transfer_mint_payment<'info>(
+    treasury_mint: Option<Pubkey>,
+    payer: &AccountInfo<'info>,
+    destination: &AccountInfo<'info>,
+    treasury_token_account: Option<AccountInfo<'info>>,
+    token_program: &AccountInfo<'info>,
+    system_program: &AccountInfo<'info>,
+    price: u64,
+) -> Result<()> {
+    if let Some(mint) = treasury_mint {
+        let token_account_info =
+            treasury_token_account.ok_or(CandyError::IncorrectRemainingAccountsLen)?;
+
+        let token_account = assert_is_ata(&token_account_info, payer.key, &mint)?;
+
+        if token_account.amount < price {
+            msg!(
+                "The mint price is {} SPL tokens but the payer only had {}.",
+                price,
+                token_account.amount
+            );
+            return err!(CandyError::NotEnoughTokens);
+        }
+
+        spl_token_transfer(TokenTransferParams {
+            source: token_account_info,
+            destination: destination.clone(),
+            authority: payer.clone(),
+            authority_signer_seeds: &[],
+            token_program: token_program.clone(),
+            amount: price,
+        })?;
+    } else {
+        if payer.lamports() < price {
+            msg!(
+                "The mint price is {} SOL but the payer only had {}.",
+                price,
+                payer.lamports()
+            );
+            return err!(CandyError::NotEnoughSOL);
+        }
+        invoke(
+            &system_instruction::transfer(payer.key, destination.key, price),
+            &[payer.clone(), destination.clone(), system_program.clone()],
+        )?;
+    }
+
+    Ok(())
+}
+
+/// Creates the mint, token account, metadata and master edition of a single
+/// NFT using the next available config line. Returns the index of the config
+/// line used, which is None if the candy machine uses hidden settings.
+pub(crate) fn create_nft<'info>(
+    candy_machine: &mut Account<'info, CandyMachine>,
+    accounts: &MintNftAccounts<'info>,
+    creator_bump: u8,
+    mint_phase: &MintPhase,
+) -> Result<Option<u32>> {
+    let candy_machine_creator = &accounts.candy_machine_creator;
+    let payer = &accounts.payer;
+    let recipient = &accounts.recipient;
+    let token_program = &accounts.token_program;
+
+    // *** BEGIN CREATE ATA ***
+    // Programmable NFTs are minted through the token metadata program, which
+    // creates the mint and the token account itself. Compressed NFTs have
+    // neither.
+    if accounts.programmable.is_none() && accounts.compressed.is_none() {
+        let mint = &accounts.mint;
+        let recipient_token_account = &accounts.recipient_token_account;
+        let ata_program = &accounts.ata_program;
+        let system_program = &accounts.system_program;
+        let rent = &accounts.rent;
+        let rent_struct = &Rent::from_account_info(rent)?;
+        let min_rent_lamports = rent_struct.minimum_balance(Mint::LEN).max(1);
+        invoke_signed(
+            &system_instruction::create_account(
+                &payer.key(),
+                &mint.key(),
+                min_rent_lamports,
+                Mint::LEN as u64,
+                &token_program.key(),
+            ),
+            &[
+                payer.to_account_info(),
+                mint.to_account_info(),
+                system_program.to_account_info(),
+            ],
+            &[],
+        )?;
+
+        invoke_signed(
+            &initialize_mint(
+                &token_program.key(),
+                &mint.key(),
+                &payer.key(),
+                Some(&payer.key()),
+                0,
+            )
+            .unwrap(),
+            &[
+                mint.to_account_info(),
+                rent.to_account_info(),
+                token_program.to_account_info(),
+            ],
+            &[],
+        )?;
+
+        make_ata(
+            recipient_token_account.to_account_info(),
+            recipient.to_account_info(),
+            mint.to_account_info(),
+            payer.to_account_info(),
+            ata_program.to_account_info(),
+            token_program.to_account_info(),
+            system_program.to_account_info(),
+            rent.to_account_info(),
+            &[],
+        )?;
+
+        invoke_signed(
+            &mint_to(
+                &token_program.key(),
+                &mint.key(),
+                &recipient_token_account.key(),
+                &payer.key(),
+                &[],
+                1,
+            )
+            .unwrap(),
+            &[
+                mint.to_account_info(),
+                recipient_token_account.to_account_info(),
+                payer.to_account_info(),
+                token_program.to_account_info(),
+            ],
+            &[],
+        )?;
+    }
+    // *** END CREATE ATA ***
+
+    // Open editions print the next edition number instead of using a config
+    // line.
+    if let Some(open_edition_accounts) = &accounts.open_edition {
+        candy_machine.items_redeemed = candy_machine
+            .items_redeemed
+            .checked_add(1)
+            .ok_or(CandyError::NumericalOverflowError)?;
+        create_open_edition_print(
+            &candy_machine.key(),
+            accounts,
+            open_edition_accounts,
+            candy_machine.items_redeemed,
+        )?;
+        return Ok(None);
+    }
+
+    let sequential = match candy_machine.data.mint_order {
+        MintOrder::Random => false,
+        MintOrder::SequentialPremint => mint_phase == &MintPhase::Premint,
+        MintOrder::Sequential | MintOrder::ShuffledAtReveal => true,
+    };
+    let config_line_selection = if sequential {
+        ConfigLineSelection::Sequential(candy_machine.items_redeemed as usize)
+    } else {
+        ConfigLineSelection::Random(get_mint_randomness(
+            candy_machine,
+            &accounts.recent_slothashes,
+        )?)
+    };
+
+    let (config_line, config_index) = get_config_line(
         candy_machine,
-        config_line_initial_index,
+        config_line_selection,
         candy_machine.items_redeemed,
     )?;
 
@@ -636,6 +1997,28 @@ pub fn handle_mint_nft<'info>(
     let cm_key = candy_machine.key();
     let authority_seeds = [PREFIX.as_bytes(), cm_key.as_ref(), &[creator_bump]];
 
+    if let Some(programmable_accounts) = &accounts.programmable {
+        create_programmable_nft(
+            candy_machine,
+            accounts,
+            programmable_accounts,
+            config_line,
+            &authority_seeds,
+        )?;
+        return Ok(config_index);
+    }
+
+    if let Some(compressed_accounts) = &accounts.compressed {
+        create_compressed_nft(
+            candy_machine,
+            accounts,
+            compressed_accounts,
+            config_line,
+            &authority_seeds,
+        )?;
+        return Ok(config_index);
+    }
+
     // The original creators list only includes the 1 cm creator, which gets
     // removed after minting in the update instruction below.
     let creators_for_mint_ix: Vec<mpl_token_metadata::state::Creator> =
@@ -646,37 +2029,37 @@ pub fn handle_mint_nft<'info>(
         }];
 
     let metadata_infos = vec![
-        ctx.accounts.metadata.to_account_info(),
-        ctx.accounts.mint.to_account_info(),
-        ctx.accounts.buyer.to_account_info(),
-        ctx.accounts.buyer.to_account_info(),
-        ctx.accounts.token_metadata_program.to_account_info(),
-        ctx.accounts.token_program.to_account_info(),
-        ctx.accounts.system_program.to_account_info(),
-        ctx.accounts.rent.to_account_info(),
+        accounts.metadata.to_account_info(),
+        accounts.mint.to_account_info(),
+        accounts.payer.to_account_info(),
+        accounts.payer.to_account_info(),
+        accounts.token_metadata_program.to_account_info(),
+        accounts.token_program.to_account_info(),
+        accounts.system_program.to_account_info(),
+        accounts.rent.to_account_info(),
         candy_machine_creator.to_account_info(),
     ];
 
     let master_edition_infos = vec![
-        ctx.accounts.master_edition.to_account_info(),
-        ctx.accounts.mint.to_account_info(),
-        ctx.accounts.buyer.to_account_info(),
-        ctx.accounts.buyer.to_account_info(),
-        ctx.accounts.metadata.to_account_info(),
-        ctx.accounts.token_metadata_program.to_account_info(),
-        ctx.accounts.token_program.to_account_info(),
-        ctx.accounts.system_program.to_account_info(),
-        ctx.accounts.rent.to_account_info(),
+        accounts.master_edition.to_account_info(),
+        accounts.mint.to_account_info(),
+        accounts.payer.to_account_info(),
+        accounts.payer.to_account_info(),
+        accounts.metadata.to_account_info(),
+        accounts.token_metadata_program.to_account_info(),
+        accounts.token_program.to_account_info(),
+        accounts.system_program.to_account_info(),
+        accounts.rent.to_account_info(),
         candy_machine_creator.to_account_info(),
     ];
 
     invoke_signed(
         &create_metadata_accounts_v3(
-            ctx.accounts.token_metadata_program.key(),
-            ctx.accounts.metadata.key(),
-            ctx.accounts.mint.key(),
-            ctx.accounts.buyer.key(),
-            ctx.accounts.buyer.key(),
+            accounts.token_metadata_program.key(),
+            accounts.metadata.key(),
+            accounts.mint.key(),
+            accounts.payer.key(),
+            accounts.payer.key(),
             candy_machine_creator.key(),
             config_line.name.clone(),
             candy_machine.data.symbol.clone(),
@@ -694,13 +2077,13 @@ pub fn handle_mint_nft<'info>(
     )?;
     invoke_signed(
         &create_master_edition_v3(
-            ctx.accounts.token_metadata_program.key(),
-            ctx.accounts.master_edition.key(),
-            ctx.accounts.mint.key(),
+            accounts.token_metadata_program.key(),
+            accounts.master_edition.key(),
+            accounts.mint.key(),
             candy_machine_creator.key(),
-            ctx.accounts.buyer.key(),
-            ctx.accounts.metadata.key(),
-            ctx.accounts.buyer.key(),
+            accounts.payer.key(),
+            accounts.metadata.key(),
+            accounts.payer.key(),
             Some(candy_machine.data.max_supply),
         ),
         master_edition_infos.as_slice(),
@@ -737,8 +2120,8 @@ pub fn handle_mint_nft<'info>(
     // Now update NFT creators and update_authority.
     invoke_signed(
         &update_metadata_accounts_v2(
-            ctx.accounts.token_metadata_program.key(),
-            ctx.accounts.metadata.key(),
+            accounts.token_metadata_program.key(),
+            accounts.metadata.key(),
             candy_machine_creator.key(),
             Some(candy_machine.creator_authority),
             Some(update_data),
@@ -746,144 +2129,595 @@ pub fn handle_mint_nft<'info>(
             is_mutable,
         ),
         &[
-            ctx.accounts.token_metadata_program.to_account_info(),
-            ctx.accounts.metadata.to_account_info(),
+            accounts.token_metadata_program.to_account_info(),
+            accounts.metadata.to_account_info(),
             candy_machine_creator.to_account_info(),
         ],
         &[&authority_seeds],
     )?;
 
-    if let Some(mut freeze_pda) = freeze_pda {
-        msg!("About to freeze NFT.");
-        let mint_pubkey = ctx.accounts.mint.key();
-        let nft_token_account_info = get_remaining_account(
-            candy_machine,
-            ctx.remaining_accounts,
-            RemainingAccounts::BuyerNftMintTokenAccount,
+    Ok(config_index)
+}
+
+/// Freezes a newly minted NFT and collects the freeze fee. The caller is
+/// responsible for persisting the FreezePda changes.
+pub(crate) fn freeze_nft<'info>(
+    freeze_pda: &mut Account<'info, FreezePda>,
+    candy_pubkey: &Pubkey,
+    accounts: &MintNftAccounts<'info>,
+    nft_token_account_info: &AccountInfo<'info>,
+    now: i64,
+) -> Result<()> {
+    msg!("About to freeze NFT.");
+    let payer = &accounts.payer;
+    let recipient = &accounts.recipient;
+    // Delegating the NFT token account to the FreezePda requires the
+    // recipient's signature.
+    if !recipient.is_signer {
+        return err!(CandyError::RecipientMustSign);
+    }
+    let mint_pubkey = accounts.mint.key();
+    assert_is_ata(nft_token_account_info, &recipient.key(), &mint_pubkey)?;
+    let seeds: &[&[u8]] = &[FreezePda::PREFIX.as_bytes(), candy_pubkey.as_ref()];
+    let (expected_freeze_key, freeze_bump) = Pubkey::find_program_address(seeds, &crate::id());
+    assert_keys_equal(&expected_freeze_key, &freeze_pda.key())?;
+    // redundant check
+    freeze_pda.assert_from_candy(candy_pubkey)?;
+
+    freeze_pda.frozen_count += 1;
+
+    if freeze_pda.freeze_fee > 0 {
+        invoke(
+            &system_instruction::transfer(&payer.key(), &freeze_pda.key(), freeze_pda.freeze_fee),
+            &[
+                payer.to_account_info(),
+                freeze_pda.to_account_info(),
+                accounts.system_program.to_account_info(),
+            ],
+        )?;
+    }
+
+    if freeze_pda.mint_start.is_none() {
+        freeze_pda.mint_start = Some(now);
+    }
+
+    let freeze_seeds = [
+        FreezePda::PREFIX.as_bytes(),
+        candy_pubkey.as_ref(),
+        &[freeze_bump],
+    ];
+
+    if let Some(programmable_accounts) = &accounts.programmable {
+        return freeze_programmable_nft(
+            freeze_pda,
+            accounts,
+            programmable_accounts,
+            nft_token_account_info,
+            &freeze_seeds,
         );
-        assert_is_ata(&nft_token_account_info, &buyer.key(), &mint_pubkey)?;
-        let seeds: &[&[u8]] = &[FreezePda::PREFIX.as_bytes(), candy_pubkey.as_ref()];
-        let (expected_freeze_key, freeze_bump) = Pubkey::find_program_address(seeds, &crate::id());
-        assert_keys_equal(&expected_freeze_key, &freeze_pda.key())?;
-        // redundant check
-        freeze_pda.assert_from_candy(&candy_pubkey)?;
-
-        freeze_pda.frozen_count += 1;
-
-        if freeze_pda.freeze_fee > 0 {
-            invoke(
-                &system_instruction::transfer(
-                    &ctx.accounts.buyer.key(),
-                    &freeze_pda.key(),
-                    freeze_pda.freeze_fee,
-                ),
-                &[
-                    ctx.accounts.buyer.to_account_info(),
-                    freeze_pda.to_account_info(),
-                    ctx.accounts.system_program.to_account_info(),
-                ],
-            )?;
-        }
+    }
+
+    let mut freeze_ix = freeze_delegated_account(
+        mpl_token_metadata::ID,
+        freeze_pda.key(),
+        nft_token_account_info.key(),
+        accounts.master_edition.key(),
+        accounts.mint.key(),
+    );
+    // token metadata ix is sorta bad, so this line fixes it to enable freeze without marking signer as mutable
+    freeze_ix.accounts[0] = AccountMeta::new_readonly(freeze_pda.key(), true);
+
+    invoke(
+        &approve(
+            &spl_token::ID,
+            &nft_token_account_info.key(),
+            &freeze_pda.key(),
+            &recipient.key(),
+            &[],
+            1,
+        )?,
+        &[
+            nft_token_account_info.to_account_info(),
+            freeze_pda.to_account_info(),
+            recipient.to_account_info(),
+        ],
+    )?;
+    invoke_signed(
+        &freeze_ix,
+        &[
+            freeze_pda.to_account_info(),
+            nft_token_account_info.to_account_info(),
+            accounts.master_edition.to_account_info(),
+            accounts.mint.to_account_info(),
+        ],
+        &[&freeze_seeds],
+    )?;
+
+    Ok(())
+}
+
+/// Mints a compressed NFT into the candy machine Merkle tree. The candy
+/// machine creator PDA signs as the tree delegate.
+fn create_compressed_nft<'info>(
+    candy_machine: &CandyMachine,
+    accounts: &MintNftAccounts<'info>,
+    compressed_accounts: &CompressedNftAccounts<'info>,
+    config_line: ConfigLine,
+    authority_seeds: &[&[u8]],
+) -> Result<()> {
+    // Creators aren't signers here, so they can only be added unverified.
+    let creators = candy_machine
+        .data
+        .creators
+        .iter()
+        .map(|creator| BubblegumCreator {
+            address: creator.address,
+            verified: false,
+            share: creator.share,
+        })
+        .collect();
+
+    let metadata_args = MetadataArgs {
+        name: config_line.name,
+        symbol: candy_machine.data.symbol.clone(),
+        uri: config_line.uri,
+        seller_fee_basis_points: candy_machine.data.seller_fee_basis_points,
+        primary_sale_happened: true,
+        is_mutable: candy_machine.data.is_mutable,
+        edition_nonce: None,
+        token_standard: Some(BubblegumTokenStandard::NonFungible),
+        collection: None,
+        uses: None,
+        token_program_version: TokenProgramVersion::Original,
+        creators,
+    };
+
+    mpl_bubblegum::cpi::mint_v1(
+        CpiContext::new_with_signer(
+            compressed_accounts.bubblegum_program.to_account_info(),
+            mpl_bubblegum::cpi::accounts::MintV1 {
+                tree_authority: compressed_accounts.tree_authority.to_account_info(),
+                leaf_owner: accounts.recipient.to_account_info(),
+                leaf_delegate: accounts.recipient.to_account_info(),
+                merkle_tree: compressed_accounts.merkle_tree.to_account_info(),
+                payer: accounts.payer.to_account_info(),
+                tree_delegate: accounts.candy_machine_creator.to_account_info(),
+                log_wrapper: compressed_accounts.log_wrapper.to_account_info(),
+                compression_program: compressed_accounts.compression_program.to_account_info(),
+                system_program: accounts.system_program.to_account_info(),
+            },
+            &[authority_seeds],
+        ),
+        metadata_args,
+    )
+}
+
+/// Prints the given edition of the open edition master edition to the new
+/// mint. The OpenEditionPda signs as the holder of the master edition token.
+fn create_open_edition_print<'info>(
+    candy_key: &Pubkey,
+    accounts: &MintNftAccounts<'info>,
+    open_edition_accounts: &OpenEditionAccounts<'info>,
+    edition: u64,
+) -> Result<()> {
+    let open_edition_seeds = [
+        OpenEditionPda::PREFIX.as_bytes(),
+        candy_key.as_ref(),
+        &[open_edition_accounts.open_edition_bump],
+    ];
+    msg!(
+        "Printing edition {} of {}.",
+        edition,
+        open_edition_accounts.master_edition_mint.key()
+    );
+
+    invoke_signed(
+        &mint_new_edition_from_master_edition_via_token(
+            accounts.token_metadata_program.key(),
+            accounts.metadata.key(),
+            accounts.master_edition.key(),
+            open_edition_accounts.master_edition.key(),
+            accounts.mint.key(),
+            accounts.payer.key(),
+            accounts.payer.key(),
+            open_edition_accounts.open_edition_pda.key(),
+            open_edition_accounts.open_edition_token_account.key(),
+            open_edition_accounts.creator_authority.key(),
+            open_edition_accounts.master_edition_metadata.key(),
+            open_edition_accounts.master_edition_mint.key(),
+            edition,
+        ),
+        &[
+            accounts.metadata.to_account_info(),
+            accounts.master_edition.to_account_info(),
+            open_edition_accounts.master_edition.to_account_info(),
+            accounts.mint.to_account_info(),
+            open_edition_accounts.edition_marker.to_account_info(),
+            accounts.payer.to_account_info(),
+            open_edition_accounts.open_edition_pda.to_account_info(),
+            open_edition_accounts
+                .open_edition_token_account
+                .to_account_info(),
+            open_edition_accounts.creator_authority.to_account_info(),
+            open_edition_accounts
+                .master_edition_metadata
+                .to_account_info(),
+            accounts.token_program.to_account_info(),
+            accounts.system_program.to_account_info(),
+            accounts.rent.to_account_info(),
+            accounts.token_metadata_program.to_account_info(),
+        ],
+        &[&open_edition_seeds],
+    )?;
+
+    Ok(())
+}
+
+/// Creates a programmable NFT through the token metadata program, then sets
+/// its creators and update authority like create_nft does for classic NFTs.
+fn create_programmable_nft<'info>(
+    candy_machine: &CandyMachine,
+    accounts: &MintNftAccounts<'info>,
+    programmable_accounts: &ProgrammableNftAccounts<'info>,
+    config_line: ConfigLine,
+    authority_seeds: &[&[u8]],
+) -> Result<()> {
+    let candy_machine_creator = &accounts.candy_machine_creator;
+    let print_supply = match candy_machine.data.max_supply {
+        0 => PrintSupply::Zero,
+        max_supply => PrintSupply::Limited(max_supply),
+    };
+
+    let create_ix = CreateBuilder::new()
+        .metadata(accounts.metadata.key())
+        .master_edition(accounts.master_edition.key())
+        .mint(accounts.mint.key())
+        .authority(accounts.payer.key())
+        .payer(accounts.payer.key())
+        .update_authority(candy_machine_creator.key())
+        .system_program(accounts.system_program.key())
+        .sysvar_instructions(programmable_accounts.instruction_sysvar.key())
+        .spl_token_program(accounts.token_program.key())
+        .initialize_mint(true)
+        .update_authority_as_signer(true)
+        .build(CreateArgs::V1 {
+            asset_data: AssetData {
+                name: config_line.name.clone(),
+                symbol: candy_machine.data.symbol.clone(),
+                uri: config_line.uri.clone(),
+                seller_fee_basis_points: candy_machine.data.seller_fee_basis_points,
+                // The cm creator gets removed after minting in the update
+                // instruction below.
+                creators: Some(vec![mpl_token_metadata::state::Creator {
+                    address: candy_machine_creator.key(),
+                    verified: true,
+                    share: 100,
+                }]),
+                primary_sale_happened: false,
+                is_mutable: true,
+                token_standard: TokenStandard::ProgrammableNonFungible,
+                collection: programmable_accounts.collection_mint.map(|key| Collection {
+                    verified: false,
+                    key,
+                }),
+                uses: None,
+                collection_details: None,
+                rule_set: candy_machine.get_rule_set(),
+            },
+            decimals: Some(0),
+            print_supply: Some(print_supply),
+        })
+        .map_err(|_| CandyError::InstructionBuilderFailed)?
+        .instruction();
+
+    invoke_signed(
+        &create_ix,
+        &[
+            accounts.metadata.to_account_info(),
+            accounts.master_edition.to_account_info(),
+            accounts.mint.to_account_info(),
+            accounts.payer.to_account_info(),
+            candy_machine_creator.to_account_info(),
+            accounts.system_program.to_account_info(),
+            programmable_accounts.instruction_sysvar.to_account_info(),
+            accounts.token_program.to_account_info(),
+            accounts.token_metadata_program.to_account_info(),
+        ],
+        &[authority_seeds],
+    )?;
+
+    let mut mint_builder = MintBuilder::new();
+    mint_builder
+        .token(accounts.recipient_token_account.key())
+        .token_owner(accounts.recipient.key())
+        .metadata(accounts.metadata.key())
+        .master_edition(accounts.master_edition.key())
+        .token_record(programmable_accounts.token_record.key())
+        .mint(accounts.mint.key())
+        .authority(candy_machine_creator.key())
+        .payer(accounts.payer.key())
+        .system_program(accounts.system_program.key())
+        .sysvar_instructions(programmable_accounts.instruction_sysvar.key())
+        .spl_token_program(accounts.token_program.key())
+        .spl_ata_program(accounts.ata_program.key());
+
+    let mut update_builder = UpdateBuilder::new();
+    update_builder
+        .authority(candy_machine_creator.key())
+        .mint(accounts.mint.key())
+        .metadata(accounts.metadata.key())
+        .edition(accounts.master_edition.key())
+        .payer(accounts.payer.key())
+        .system_program(accounts.system_program.key())
+        .sysvar_instructions(programmable_accounts.instruction_sysvar.key());
+
+    let mut account_infos = vec![
+        accounts.recipient_token_account.to_account_info(),
+        accounts.recipient.to_account_info(),
+        accounts.metadata.to_account_info(),
+        accounts.master_edition.to_account_info(),
+        programmable_accounts.token_record.to_account_info(),
+        accounts.mint.to_account_info(),
+        candy_machine_creator.to_account_info(),
+        accounts.payer.to_account_info(),
+        accounts.system_program.to_account_info(),
+        programmable_accounts.instruction_sysvar.to_account_info(),
+        accounts.token_program.to_account_info(),
+        accounts.ata_program.to_account_info(),
+        accounts.token_metadata_program.to_account_info(),
+    ];
+
+    if let Some((authorization_rules_program, authorization_rules)) =
+        &programmable_accounts.authorization_rules
+    {
+        mint_builder
+            .authorization_rules_program(authorization_rules_program.key())
+            .authorization_rules(authorization_rules.key());
+        update_builder
+            .authorization_rules_program(authorization_rules_program.key())
+            .authorization_rules(authorization_rules.key());
+        account_infos.push(authorization_rules_program.to_account_info());
+        account_infos.push(authorization_rules.to_account_info());
+    }
+
+    let mint_ix = mint_builder
+        .build(MintArgs::V1 {
+            amount: 1,
+            authorization_data: None,
+        })
+        .map_err(|_| CandyError::InstructionBuilderFailed)?
+        .instruction();
+    invoke_signed(&mint_ix, account_infos.as_slice(), &[authority_seeds])?;
+
+    let creators: Vec<mpl_token_metadata::state::Creator> = candy_machine
+        .data
+        .creators
+        .iter()
+        .map(|creator| mpl_token_metadata::state::Creator {
+            address: creator.address,
+            verified: false,
+            share: creator.share,
+        })
+        .collect();
+
+    let is_mutable = if !candy_machine.data.is_mutable {
+        Some(false)
+    } else {
+        None
+    };
+
+    // Now update NFT creators and update_authority.
+    let update_ix = update_builder
+        .build(UpdateArgs::V1 {
+            new_update_authority: Some(candy_machine.creator_authority),
+            data: Some(Data {
+                name: config_line.name,
+                symbol: candy_machine.data.symbol.clone(),
+                uri: config_line.uri,
+                seller_fee_basis_points: candy_machine.data.seller_fee_basis_points,
+                creators: Some(creators),
+            }),
+            primary_sale_happened: Some(true),
+            is_mutable,
+            collection: CollectionToggle::None,
+            collection_details: CollectionDetailsToggle::None,
+            uses: UsesToggle::None,
+            rule_set: RuleSetToggle::None,
+            authorization_data: None,
+        })
+        .map_err(|_| CandyError::InstructionBuilderFailed)?
+        .instruction();
+    invoke_signed(&update_ix, account_infos.as_slice(), &[authority_seeds])?;
 
-        if freeze_pda.mint_start.is_none() {
-            freeze_pda.mint_start = Some(clock.unix_timestamp);
-        }
+    Ok(())
+}
 
-        let freeze_seeds = [
-            FreezePda::PREFIX.as_bytes(),
-            candy_pubkey.as_ref(),
-            &[freeze_bump],
-        ];
-        let mut freeze_ix = freeze_delegated_account(
-            mpl_token_metadata::ID,
-            freeze_pda.key(),
-            nft_token_account_info.key(),
-            ctx.accounts.master_edition.key(),
-            ctx.accounts.mint.key(),
-        );
-        // token metadata ix is sorta bad, so this line fixes it to enable freeze without marking signer as mutable
-        freeze_ix.accounts[0] = AccountMeta::new_readonly(freeze_pda.key(), true);
+/// Freezes a programmable NFT by delegating it to the FreezePda, which then
+/// locks it. The token account of a programmable NFT is always frozen, so
+/// the lock is what prevents transfers.
+fn freeze_programmable_nft<'info>(
+    freeze_pda: &Account<'info, FreezePda>,
+    accounts: &MintNftAccounts<'info>,
+    programmable_accounts: &ProgrammableNftAccounts<'info>,
+    nft_token_account_info: &AccountInfo<'info>,
+    freeze_seeds: &[&[u8]],
+) -> Result<()> {
+    let mut delegate_builder = DelegateBuilder::new();
+    delegate_builder
+        .delegate(freeze_pda.key())
+        .metadata(accounts.metadata.key())
+        .master_edition(accounts.master_edition.key())
+        .token_record(programmable_accounts.token_record.key())
+        .mint(accounts.mint.key())
+        .token(nft_token_account_info.key())
+        .authority(accounts.recipient.key())
+        .payer(accounts.payer.key())
+        .system_program(accounts.system_program.key())
+        .sysvar_instructions(programmable_accounts.instruction_sysvar.key())
+        .spl_token_program(accounts.token_program.key());
+
+    let mut lock_builder = LockBuilder::new();
+    lock_builder
+        .authority(freeze_pda.key())
+        .token_owner(accounts.recipient.key())
+        .token(nft_token_account_info.key())
+        .mint(accounts.mint.key())
+        .metadata(accounts.metadata.key())
+        .edition(accounts.master_edition.key())
+        .token_record(programmable_accounts.token_record.key())
+        .payer(accounts.payer.key())
+        .system_program(accounts.system_program.key())
+        .sysvar_instructions(programmable_accounts.instruction_sysvar.key())
+        .spl_token_program(accounts.token_program.key());
+
+    let mut account_infos = vec![
+        freeze_pda.to_account_info(),
+        accounts.metadata.to_account_info(),
+        accounts.master_edition.to_account_info(),
+        programmable_accounts.token_record.to_account_info(),
+        accounts.mint.to_account_info(),
+        nft_token_account_info.to_account_info(),
+        accounts.recipient.to_account_info(),
+        accounts.payer.to_account_info(),
+        accounts.system_program.to_account_info(),
+        programmable_accounts.instruction_sysvar.to_account_info(),
+        accounts.token_program.to_account_info(),
+        accounts.token_metadata_program.to_account_info(),
+    ];
 
-        invoke(
-            &approve(
-                &spl_token::ID,
-                &nft_token_account_info.key(),
-                &freeze_pda.key(),
-                &buyer.key(),
-                &[],
-                1,
-            )?,
-            &[
-                nft_token_account_info.to_account_info(),
-                freeze_pda.to_account_info(),
-                buyer.to_account_info(),
-            ],
-        )?;
-        invoke_signed(
-            &freeze_ix,
-            &[
-                freeze_pda.to_account_info(),
-                nft_token_account_info.to_account_info(),
-                ctx.accounts.master_edition.to_account_info(),
-                ctx.accounts.mint.to_account_info(),
-            ],
-            &[&freeze_seeds],
-        )?;
-        freeze_pda.exit(&crate::id())?;
+    if let Some((authorization_rules_program, authorization_rules)) =
+        &programmable_accounts.authorization_rules
+    {
+        delegate_builder
+            .authorization_rules_program(authorization_rules_program.key())
+            .authorization_rules(authorization_rules.key());
+        lock_builder
+            .authorization_rules_program(authorization_rules_program.key())
+            .authorization_rules(authorization_rules.key());
+        account_infos.push(authorization_rules_program.to_account_info());
+        account_infos.push(authorization_rules.to_account_info());
     }
 
-    if is_allowlist_phase && provided_merkle_allowlist_proof {
-        let mut buyer_info_account: Account<BuyerInfoAccount> =
-            Account::try_from(buyer_info_account)?;
-        let number_bought_for_merkle_allowlist = buyer_info_account
-            .number_bought_merkle_allowlist_phase
-            .checked_add(1)
-            .unwrap();
-        buyer_info_account.number_bought_merkle_allowlist_phase =
-            number_bought_for_merkle_allowlist;
+    let delegate_ix = delegate_builder
+        .build(DelegateArgs::UtilityV1 {
+            amount: 1,
+            authorization_data: None,
+        })
+        .map_err(|_| CandyError::InstructionBuilderFailed)?
+        .instruction();
+    invoke(&delegate_ix, account_infos.as_slice())?;
 
-        // This re-serializes the account to persist the changes.
-        buyer_info_account.exit(&crate::id())?;
-    }
+    let lock_ix = lock_builder
+        .build(LockArgs::V1 {
+            authorization_data: None,
+        })
+        .map_err(|_| CandyError::InstructionBuilderFailed)?
+        .instruction();
+    invoke_signed(&lock_ix, account_infos.as_slice(), &[freeze_seeds])?;
 
-    if mint_phase == MintPhase::Public && !is_buyer_omni_minter && limit_per_address > 0 {
-        let mut buyer_info_account: Account<BuyerInfoAccount> =
-            Account::try_from(buyer_info_account)?;
-        require!(
-            buyer_info_account.number_bought_public_phase < limit_per_address,
-            CandyError::BuyLimitPerAddressExceeded
-        );
+    Ok(())
+}
 
-        let number_bought = buyer_info_account
-            .number_bought_public_phase
-            .checked_add(1)
-            .unwrap();
-        buyer_info_account.number_bought_public_phase = number_bought;
+/// Creates the RefundReceipt of a newly frozen NFT, which records the payment
+/// held by the FreezePda for it and the purchase counters it was counted in.
+pub(crate) fn create_refund_receipt<'info>(
+    refund_receipt_info: &AccountInfo<'info>,
+    candy_pubkey: &Pubkey,
+    accounts: &MintNftAccounts<'info>,
+    treasury_mint: Option<Pubkey>,
+    amount: u64,
+    config_index: Option<u32>,
+    purchase_counters: &PurchaseCounters,
+) -> Result<()> {
+    let mint_pubkey = accounts.mint.key();
+    let (expected_refund_receipt_key, refund_receipt_bump) = Pubkey::find_program_address(
+        &[
+            RefundReceipt::PREFIX.as_bytes(),
+            candy_pubkey.as_ref(),
+            mint_pubkey.as_ref(),
+        ],
+        &crate::id(),
+    );
+    assert_keys_equal(&expected_refund_receipt_key, refund_receipt_info.key)?;
 
-        // This re-serializes the account to persist the changes.
-        buyer_info_account.exit(&crate::id())?;
+    let signer_seeds = [
+        RefundReceipt::PREFIX.as_bytes(),
+        candy_pubkey.as_ref(),
+        mint_pubkey.as_ref(),
+        &[refund_receipt_bump],
+    ];
+    create_or_allocate_account_raw(
+        crate::id(),
+        refund_receipt_info,
+        &accounts.system_program,
+        &accounts.payer,
+        RefundReceipt::SIZE,
+        &signer_seeds,
+    )?;
+
+    let refund_receipt = RefundReceipt {
+        candy_machine: *candy_pubkey,
+        mint: mint_pubkey,
+        payer: accounts.payer.key(),
+        treasury_mint,
+        amount,
+        config_index,
+        recipient: accounts.recipient.key(),
+        purchase_counters: purchase_counters.clone(),
+    };
+    let mut data_ref: &mut [u8] = &mut refund_receipt_info.try_borrow_mut_data()?;
+    refund_receipt.try_serialize(&mut data_ref)?;
+
+    Ok(())
+}
+
+/// Returns the offset of the bit mask which tracks the config lines that have
+/// been minted.
+pub(crate) fn get_mint_bit_mask_start(candy_data: &CandyMachineData) -> Result<usize> {
+    Ok(get_config_bit_mask_start(candy_data)
+        + candy_data
+            .items_available
+            .checked_div(8)
+            .ok_or(CandyError::NumericalOverflowError)? as usize
+        + 4)
+}
+
+/// Marks a minted config line as available again, e.g. after its NFT was
+/// refunded.
+pub(crate) fn release_config_line(
+    candy_machine: &Account<'_, CandyMachine>,
+    index: usize,
+) -> Result<()> {
+    let items_available = candy_machine.data.items_available as usize;
+    if index >= items_available {
+        return err!(CandyError::IndexGreaterThanLength);
     }
 
+    let candy_machine_info = candy_machine.to_account_info();
+    let mut arr = candy_machine_info.data.borrow_mut();
+    let position_in_vec = get_mint_bit_mask_start(&candy_machine.data)?
+        + index
+            .checked_div(8)
+            .ok_or(CandyError::NumericalOverflowError)?;
+    let position_from_right = 7 - index
+        .checked_rem(8)
+        .ok_or(CandyError::NumericalOverflowError)?;
+    arr[position_in_vec] &= !u8::pow(2, position_from_right as u32);
+
     Ok(())
 }
 
 pub fn get_good_index(
     arr: &mut RefMut<&mut [u8]>,
-    items_available: usize,
+    candy_data: &CandyMachineData,
     index: usize,
     pos: bool,
 ) -> Result<(usize, bool)> {
+    let items_available = candy_data.items_available as usize;
     let mut index_to_use = index;
     let mut taken = 1;
     let mut found = false;
-    let bit_mask_vec_start = CONFIG_ARRAY_START
-        + 4
-        + (items_available) * CONFIG_LINE_SIZE
-        + 4
-        + items_available
-            .checked_div(8)
-            .ok_or(CandyError::NumericalOverflowError)?
-        + 4;
+    let bit_mask_vec_start = get_mint_bit_mask_start(candy_data)?;
 
     while taken > 0 && index_to_use < items_available {
         let my_position_in_vec = bit_mask_vec_start
@@ -939,67 +2773,154 @@ pub fn get_good_index(
     Ok((index_to_use, found))
 }
 
+/// How the config line of a mint is picked.
+pub enum ConfigLineSelection {
+    /// The first unminted config line at or after the index, or the last one
+    /// before it if there is none after it.
+    Sequential(usize),
+    /// The unminted config line at this random number modulo the number of
+    /// unminted config lines, so each of them is equally likely.
+    Random(u64),
+}
+
+/// Checks the account passed as recent_slothashes, which is the trusted
+/// randomness account if the candy machine has one and the SlotHashes sysvar
+/// otherwise.
+pub(crate) fn assert_randomness_account(candy_machine: &CandyMachine, key: &Pubkey) -> Result<()> {
+    match &candy_machine.data.trusted_randomness_account {
+        Some(trusted_randomness_account)
+            if !cmp_pubkeys(key, &trusted_randomness_account.address) =>
+        {
+            err!(CandyError::IncorrectTrustedRandomnessAccount)
+        }
+        None if !cmp_pubkeys(key, &SlotHashes::id()) => {
+            err!(CandyError::IncorrectSlotHashesPubkey)
+        }
+        _ => Ok(()),
+    }
+}
+
+/// Returns the random number which picks the config line of a mint. The
+/// randomness account's bytes are hashed with the candy machine and the mint
+/// number, so mints in the same slot or reading the same value still get
+/// different numbers. Nothing the buyer controls goes into the hash, but the
+/// trusted randomness account must be updated after mint transactions are
+/// signed, or buyers can predict which item they get. Its randomness isn't
+/// verified, see TrustedRandomnessAccount.
+fn get_mint_randomness(
+    candy_machine: &Account<'_, CandyMachine>,
+    randomness_account: &AccountInfo,
+) -> Result<u64> {
+    let data = randomness_account.data.borrow();
+    let randomness = match &candy_machine.data.trusted_randomness_account {
+        Some(trusted_randomness_account) => {
+            let offset = trusted_randomness_account.offset as usize;
+            data.get(offset..offset + 32)
+                .ok_or(CandyError::InvalidTrustedRandomnessAccountData)?
+        }
+        // The hash of the most recent slot, which follows the u64 length and
+        // the slot number.
+        None => data.get(16..48).ok_or(CandyError::SlotHashesEmpty)?,
+    };
+
+    let hash = solana_program::keccak::hashv(&[
+        randomness,
+        candy_machine.key().as_ref(),
+        &candy_machine.items_redeemed.to_le_bytes(),
+    ]);
+
+    Ok(u64::from_le_bytes(*array_ref![hash.0, 0, 8]))
+}
+
+/// Marks the n-th unminted config line as minted and returns its index.
+fn take_unminted_index(
+    arr: &mut RefMut<&mut [u8]>,
+    candy_data: &CandyMachineData,
+    n: usize,
+) -> Result<usize> {
+    let items_available = candy_data.items_available as usize;
+    let bit_mask_vec_start = get_mint_bit_mask_start(candy_data)?;
+    let mut remaining = n;
+
+    for first_index in (0..items_available).step_by(8) {
+        let position_in_vec = bit_mask_vec_start + first_index / 8;
+        // The last byte has bits past items_available, which never count as
+        // unminted.
+        let valid_bits = cmp::min(8, items_available - first_index) as u32;
+        let valid_mask = !u8::MAX.checked_shr(valid_bits).unwrap_or(0);
+        let unminted = !arr[position_in_vec] & valid_mask;
+
+        let unminted_count = unminted.count_ones() as usize;
+        if remaining >= unminted_count {
+            remaining -= unminted_count;
+            continue;
+        }
+
+        for position_from_left in 0..8 {
+            let mask = 0x80 >> position_from_left;
+            if unminted & mask == 0 {
+                continue;
+            }
+            if remaining == 0 {
+                arr[position_in_vec] |= mask;
+                return Ok(first_index + position_from_left);
+            }
+            remaining -= 1;
+        }
+    }
+
+    err!(CandyError::CannotFindUsableConfigLine)
+}
+
 pub fn get_config_line(
     a: &Account<'_, CandyMachine>,
-    index: usize,
+    selection: ConfigLineSelection,
     mint_number: u64,
-) -> Result<ConfigLine> {
+) -> Result<(ConfigLine, Option<u32>)> {
     if let Some(hs) = &a.data.hidden_settings {
-        return Ok(ConfigLine {
+        let config_line = ConfigLine {
             name: hs.name.clone() + "#" + &(mint_number + 1).to_string(),
             uri: hs.uri.clone(),
-        });
+        };
+        return Ok((config_line, None));
     }
     let a_info = a.to_account_info();
 
     let mut arr = a_info.data.borrow_mut();
 
-    let (mut index_to_use, good) =
-        get_good_index(&mut arr, a.data.items_available as usize, index, true)?;
-    if !good {
-        let (index_to_use_new, good_new) =
-            get_good_index(&mut arr, a.data.items_available as usize, index, false)?;
-        index_to_use = index_to_use_new;
-        if !good_new {
-            return err!(CandyError::CannotFindUsableConfigLine);
+    let index_to_use = match selection {
+        ConfigLineSelection::Sequential(index) => {
+            let (mut index_to_use, good) = get_good_index(&mut arr, &a.data, index, true)?;
+            if !good {
+                let (index_to_use_new, good_new) = get_good_index(&mut arr, &a.data, index, false)?;
+                index_to_use = index_to_use_new;
+                if !good_new {
+                    return err!(CandyError::CannotFindUsableConfigLine);
+                }
+            }
+            index_to_use
         }
-    }
+        ConfigLineSelection::Random(random) => {
+            let unminted_count = a
+                .data
+                .items_available
+                .checked_sub(a.items_redeemed)
+                .ok_or(CandyError::NumericalOverflowError)?;
+            let n = random
+                .checked_rem(unminted_count)
+                .ok_or(CandyError::CandyMachineEmpty)?;
+            take_unminted_index(&mut arr, &a.data, n as usize)?
+        }
+    };
 
-    if arr[CONFIG_ARRAY_START + 4 + index_to_use * (CONFIG_LINE_SIZE)] == 1 {
+    let config_line_start = get_config_line_start(&a.data, index_to_use);
+    if a.data.config_line_settings.is_none() && arr[config_line_start] == 1 {
         return err!(CandyError::CannotFindUsableConfigLine);
     }
 
-    let data_array = &mut arr[CONFIG_ARRAY_START + 4 + index_to_use * (CONFIG_LINE_SIZE)
-        ..CONFIG_ARRAY_START + 4 + (index_to_use + 1) * (CONFIG_LINE_SIZE)];
-
-    let mut name_vec = Vec::with_capacity(MAX_NAME_LENGTH);
-    let mut uri_vec = Vec::with_capacity(MAX_URI_LENGTH);
-
-    #[allow(clippy::needless_range_loop)]
-    for i in 4..4 + MAX_NAME_LENGTH {
-        if data_array[i] == 0 {
-            break;
-        }
-        name_vec.push(data_array[i])
-    }
-
-    #[allow(clippy::needless_range_loop)]
-    for i in 8 + MAX_NAME_LENGTH..8 + MAX_NAME_LENGTH + MAX_URI_LENGTH {
-        if data_array[i] == 0 {
-            break;
-        }
-        uri_vec.push(data_array[i])
-    }
-    let config_line: ConfigLine = ConfigLine {
-        name: match String::from_utf8(name_vec) {
-            Ok(val) => val,
-            Err(_) => return err!(CandyError::InvalidString),
-        },
-        uri: match String::from_utf8(uri_vec) {
-            Ok(val) => val,
-            Err(_) => return err!(CandyError::InvalidString),
-        },
-    };
+    let config_line = a.data.parse_config_line(
+        &arr[config_line_start..config_line_start + a.data.get_config_line_size()],
+    )?;
 
     msg!(
         "Minting config line at index {} with uri = '{}' and name = '{}'.",
@@ -1008,77 +2929,500 @@ pub fn get_config_line(
         config_line.name
     );
 
-    Ok(config_line)
+    Ok((config_line, Some(index_to_use as u32)))
 }
 
-fn get_spl_token_allowlist_remaining_accounts_counter(candy: &CandyMachine) -> usize {
-    let mut counter: usize = 0;
-    if let Some(spl_token_allowlist_settings) = &candy.data.spl_token_allowlist_settings {
-        counter += 1;
-        if spl_token_allowlist_settings.mode == SplTokenAllowlistMode::BurnEveryTime {
-            counter += 1;
+/// Returns the number of remaining_accounts passed for the SPL allowlist
+/// tokens before the token at token_index. Each token has its token account,
+/// followed by its mint if it's burned.
+pub(crate) fn get_spl_token_allowlist_token_remaining_accounts_offset(
+    spl_token_allowlist_tokens: &[SplTokenAllowlistToken],
+    token_index: usize,
+) -> usize {
+    spl_token_allowlist_tokens[..token_index]
+        .iter()
+        .map(|token| 1 + (token.burn_amount > 0) as usize)
+        .sum()
+}
+
+pub(crate) fn get_spl_token_allowlist_remaining_accounts_counter(
+    candy: &CandyMachine,
+    layout: &MintRemainingAccountsLayout,
+) -> usize {
+    if !layout.spl_token_allowlist_accounts {
+        return 0;
+    }
+    let spl_token_allowlist_tokens = candy.data.get_spl_token_allowlist_tokens();
+    get_spl_token_allowlist_token_remaining_accounts_offset(
+        &spl_token_allowlist_tokens,
+        spl_token_allowlist_tokens.len(),
+    )
+}
+
+pub(crate) fn get_collection_allowlist_remaining_accounts_counter(
+    candy: &CandyMachine,
+    layout: &MintRemainingAccountsLayout,
+) -> usize {
+    match &candy.data.collection_allowlist_settings {
+        Some(collection_allowlist_settings) if layout.collection_allowlist_accounts => {
+            3 + collection_allowlist_settings.use_once as usize
         }
+        _ => 0,
     }
+}
 
-    counter
+/// Returns the number of remaining_accounts passed for the allowlists which
+/// are checked, which come first.
+pub(crate) fn get_allowlist_remaining_accounts_counter(
+    candy: &CandyMachine,
+    layout: &MintRemainingAccountsLayout,
+) -> usize {
+    get_spl_token_allowlist_remaining_accounts_counter(candy, layout)
+        + get_collection_allowlist_remaining_accounts_counter(candy, layout)
 }
 
-fn get_treasury_remaining_accounts_counter(candy: &CandyMachine) -> usize {
-    match candy.treasury_mint {
+pub(crate) fn get_treasury_remaining_accounts_counter(treasury_mint: Option<Pubkey>) -> usize {
+    match treasury_mint {
         Some(_) => 1,
         None => 0,
     }
 }
 
-fn get_remaining_account<'a>(
+pub(crate) fn get_freeze_remaining_accounts_counter(
     candy: &CandyMachine,
-    remaining_accounts: &[AccountInfo<'a>],
+    treasury_mint: Option<Pubkey>,
+) -> usize {
+    if candy.is_feature_active(FREEZE_FEATURE_INDEX) {
+        1 + get_treasury_remaining_accounts_counter(treasury_mint)
+    } else {
+        0
+    }
+}
+
+/// Returns the number of remaining_accounts passed before the accounts of
+/// each NFT, up to and including the primary sale split accounts.
+pub(crate) fn get_nft_remaining_accounts_start(
+    candy: &CandyMachine,
+    layout: &MintRemainingAccountsLayout,
+) -> usize {
+    let treasury_mint = layout.treasury_mint;
+    let mut start = get_allowlist_remaining_accounts_counter(candy, layout)
+        + get_treasury_remaining_accounts_counter(treasury_mint)
+        + get_freeze_remaining_accounts_counter(candy, treasury_mint);
+
+    if candy.get_platform_fee_wallet().is_some() {
+        start += 1;
+    }
+
+    start + candy.data.primary_sale_splits.len()
+}
+
+/// Returns the number of remaining_accounts passed for each NFT, see
+/// NftRemainingAccounts.
+pub(crate) fn get_nft_remaining_accounts_counter(
+    candy: &CandyMachine,
+    layout: &MintRemainingAccountsLayout,
+) -> usize {
+    let mut counter: usize = 0;
+    if layout.nft_accounts_in_remaining_accounts {
+        counter += MINT_MANY_ACCOUNTS_PER_NFT;
+    }
+    if candy.is_feature_active(FREEZE_FEATURE_INDEX) {
+        counter += 1;
+    }
+    if candy.is_programmable() {
+        counter += 1;
+    }
+
+    counter
+}
+
+/// Returns the number of remaining_accounts passed up to and including the
+/// accounts of the last NFT.
+pub(crate) fn get_nft_remaining_accounts_end(
+    candy: &CandyMachine,
+    layout: &MintRemainingAccountsLayout,
+) -> usize {
+    get_nft_remaining_accounts_start(candy, layout)
+        + layout.quantity * get_nft_remaining_accounts_counter(candy, layout)
+}
+
+pub(crate) fn get_remaining_account_index(
+    candy: &CandyMachine,
+    layout: &MintRemainingAccountsLayout,
     account: RemainingAccounts,
-) -> AccountInfo<'a> {
-    let account_index: usize = match account {
-        RemainingAccounts::SplTokenAllowlistTokenAccount => 0,
-        RemainingAccounts::SplTokenAllowlistTokenMint => 1,
+) -> usize {
+    let treasury_mint = layout.treasury_mint;
+    match account {
+        RemainingAccounts::SplTokenAllowlistTokenAccount(token_index) => {
+            get_spl_token_allowlist_token_remaining_accounts_offset(
+                &candy.data.get_spl_token_allowlist_tokens(),
+                token_index,
+            )
+        }
+        RemainingAccounts::SplTokenAllowlistTokenMint(token_index) => {
+            get_spl_token_allowlist_token_remaining_accounts_offset(
+                &candy.data.get_spl_token_allowlist_tokens(),
+                token_index,
+            ) + 1
+        }
+        RemainingAccounts::CollectionAllowlistTokenAccount => {
+            get_spl_token_allowlist_remaining_accounts_counter(candy, layout)
+        }
+        RemainingAccounts::CollectionAllowlistMetadata => {
+            get_spl_token_allowlist_remaining_accounts_counter(candy, layout) + 1
+        }
+        RemainingAccounts::CollectionAllowlistEdition => {
+            get_spl_token_allowlist_remaining_accounts_counter(candy, layout) + 2
+        }
+        RemainingAccounts::CollectionAllowlistRecord => {
+            get_spl_token_allowlist_remaining_accounts_counter(candy, layout) + 3
+        }
         RemainingAccounts::TreasuryTokenAccount => {
-            get_spl_token_allowlist_remaining_accounts_counter(candy)
+            get_allowlist_remaining_accounts_counter(candy, layout)
         }
         RemainingAccounts::FreezePda => {
-            get_spl_token_allowlist_remaining_accounts_counter(candy)
-                + get_treasury_remaining_accounts_counter(candy)
+            get_allowlist_remaining_accounts_counter(candy, layout)
+                + get_treasury_remaining_accounts_counter(treasury_mint)
         }
-        RemainingAccounts::BuyerNftMintTokenAccount => {
-            get_spl_token_allowlist_remaining_accounts_counter(candy)
-                + get_treasury_remaining_accounts_counter(candy)
+        RemainingAccounts::FreezeAta => {
+            get_allowlist_remaining_accounts_counter(candy, layout)
+                + get_treasury_remaining_accounts_counter(treasury_mint)
                 + 1
         }
-        RemainingAccounts::FreezeAta => {
-            get_spl_token_allowlist_remaining_accounts_counter(candy)
-                + get_treasury_remaining_accounts_counter(candy)
-                + 2
+        RemainingAccounts::Nft(nft_index, nft_account) => {
+            let named_accounts_counter = if layout.nft_accounts_in_remaining_accounts {
+                MINT_MANY_ACCOUNTS_PER_NFT
+            } else {
+                0
+            };
+            let offset = match nft_account {
+                NftRemainingAccounts::Mint
+                | NftRemainingAccounts::Metadata
+                | NftRemainingAccounts::MasterEdition
+                | NftRemainingAccounts::RecipientTokenAccount => nft_account as usize,
+                NftRemainingAccounts::RefundReceipt => named_accounts_counter,
+                NftRemainingAccounts::TokenRecord => {
+                    named_accounts_counter + candy.is_feature_active(FREEZE_FEATURE_INDEX) as usize
+                }
+            };
+
+            get_nft_remaining_accounts_start(candy, layout)
+                + nft_index * get_nft_remaining_accounts_counter(candy, layout)
+                + offset
         }
+        RemainingAccounts::AuthorizationRulesProgram => {
+            get_nft_remaining_accounts_end(candy, layout)
+        }
+        RemainingAccounts::AuthorizationRules => get_nft_remaining_accounts_end(candy, layout) + 1,
+        RemainingAccounts::CollectionPda => {
+            get_nft_remaining_accounts_end(candy, layout)
+                + get_rule_set_remaining_accounts_counter(candy)
+        }
+        RemainingAccounts::PayerBuyerInfoAccount => {
+            get_nft_remaining_accounts_end(candy, layout)
+                + get_programmable_nft_remaining_accounts_counter(candy)
+                + get_compressed_nft_remaining_accounts_counter(candy)
+                + get_open_edition_remaining_accounts_counter(candy)
+        }
+    }
+}
+
+pub(crate) fn get_remaining_account<'a>(
+    candy: &CandyMachine,
+    layout: &MintRemainingAccountsLayout,
+    remaining_accounts: &[AccountInfo<'a>],
+    account: RemainingAccounts,
+) -> Result<AccountInfo<'a>> {
+    remaining_accounts
+        .get(get_remaining_account_index(candy, layout, account))
+        .cloned()
+        .ok_or_else(|| error!(CandyError::IncorrectRemainingAccountsLen))
+}
+
+/// Returns the mint, metadata, master_edition and recipient_token_account of
+/// the NFT at nft_index, which mint_many_nft passes in the remaining_accounts.
+fn get_nft_accounts<'info>(
+    candy: &CandyMachine,
+    layout: &MintRemainingAccountsLayout,
+    remaining_accounts: &[AccountInfo<'info>],
+    nft_index: usize,
+) -> Result<NftAccounts<'info>> {
+    let get_account = |nft_account: NftRemainingAccounts| {
+        get_remaining_account(
+            candy,
+            layout,
+            remaining_accounts,
+            RemainingAccounts::Nft(nft_index, nft_account),
+        )
     };
 
-    remaining_accounts[account_index].clone()
+    Ok(NftAccounts {
+        mint: get_account(NftRemainingAccounts::Mint)?,
+        metadata: get_account(NftRemainingAccounts::Metadata)?,
+        master_edition: get_account(NftRemainingAccounts::MasterEdition)?,
+        recipient_token_account: get_account(NftRemainingAccounts::RecipientTokenAccount)?,
+    })
+}
+
+pub(crate) fn get_rule_set_remaining_accounts_counter(candy: &CandyMachine) -> usize {
+    match candy.get_rule_set() {
+        Some(_) => 2,
+        None => 0,
+    }
 }
 
-pub fn get_expected_remaining_accounts_count(candy: &CandyMachine) -> usize {
-    let mut expected_count = 0;
+/// Returns the number of remaining_accounts passed after the accounts of
+/// each NFT if the candy machine mints programmable NFTs.
+pub(crate) fn get_programmable_nft_remaining_accounts_counter(candy: &CandyMachine) -> usize {
+    if !candy.is_programmable() {
+        return 0;
+    }
 
-    if let Some(spl_token_allowlist_settings) = &candy.data.spl_token_allowlist_settings {
-        expected_count += 1;
-        if spl_token_allowlist_settings.mode == SplTokenAllowlistMode::BurnEveryTime {
-            expected_count += 1;
-        }
+    let mut counter: usize = get_rule_set_remaining_accounts_counter(candy);
+    if candy.is_feature_active(COLLECTIONS_FEATURE_INDEX) {
+        counter += 1;
+    }
+
+    counter
+}
+
+pub(crate) fn get_compressed_nft_remaining_accounts_counter(candy: &CandyMachine) -> usize {
+    if candy.is_compressed() {
+        COMPRESSED_NFT_REMAINING_ACCOUNTS_COUNT
+    } else {
+        0
+    }
+}
+
+/// Returns the accounts used to mint a compressed NFT, which are passed after
+/// the accounts of the NFT.
+fn get_compressed_nft_accounts<'info>(
+    candy: &CandyMachine,
+    remaining_accounts: &[AccountInfo<'info>],
+    // The number of remaining accounts up to and including the accounts of
+    // the NFT.
+    start_index: usize,
+) -> Result<CompressedNftAccounts<'info>> {
+    let get_account = |account: CompressedRemainingAccounts| {
+        remaining_accounts
+            .get(start_index + account as usize)
+            .cloned()
+            .ok_or_else(|| error!(CandyError::IncorrectRemainingAccountsLen))
+    };
+
+    let merkle_tree = get_account(CompressedRemainingAccounts::MerkleTree)?;
+    match &candy.data.compressed_nft_settings {
+        Some(settings) if cmp_pubkeys(&settings.merkle_tree, merkle_tree.key) => {}
+        _ => return err!(CandyError::MerkleTreeMismatch),
+    }
+
+    let bubblegum_program = get_account(CompressedRemainingAccounts::BubblegumProgram)?;
+    assert_keys_equal(bubblegum_program.key, &mpl_bubblegum::id())?;
+
+    Ok(CompressedNftAccounts {
+        tree_authority: get_account(CompressedRemainingAccounts::TreeAuthority)?,
+        merkle_tree,
+        log_wrapper: get_account(CompressedRemainingAccounts::LogWrapper)?,
+        compression_program: get_account(CompressedRemainingAccounts::CompressionProgram)?,
+        bubblegum_program,
+    })
+}
+
+pub(crate) fn get_open_edition_remaining_accounts_counter(candy: &CandyMachine) -> usize {
+    if candy.is_open_edition() {
+        OPEN_EDITION_REMAINING_ACCOUNTS_COUNT
+    } else {
+        0
+    }
+}
+
+/// Returns the accounts used to print an open edition, which are passed after
+/// the accounts of the NFT.
+fn get_open_edition_accounts<'info>(
+    candy_pubkey: &Pubkey,
+    remaining_accounts: &[AccountInfo<'info>],
+    // The number of remaining accounts up to and including the accounts of
+    // the NFT.
+    start_index: usize,
+    creator_authority: AccountInfo<'info>,
+) -> Result<OpenEditionAccounts<'info>> {
+    let get_account = |account: OpenEditionRemainingAccounts| {
+        remaining_accounts
+            .get(start_index + account as usize)
+            .cloned()
+            .ok_or_else(|| error!(CandyError::IncorrectRemainingAccountsLen))
+    };
+
+    let open_edition_pda_info = get_account(OpenEditionRemainingAccounts::OpenEditionPda)?;
+    let (expected_open_edition_pda_key, open_edition_bump) = Pubkey::find_program_address(
+        &[OpenEditionPda::PREFIX.as_bytes(), candy_pubkey.as_ref()],
+        &crate::id(),
+    );
+    if !cmp_pubkeys(&expected_open_edition_pda_key, open_edition_pda_info.key) {
+        return err!(CandyError::OpenEditionMismatch);
     }
+    let open_edition_pda: Account<OpenEditionPda> = Account::try_from(&open_edition_pda_info)?;
 
-    if candy.treasury_mint.is_some() {
-        expected_count += 1;
+    let master_edition_mint = get_account(OpenEditionRemainingAccounts::MasterEditionMint)?;
+    if !cmp_pubkeys(&open_edition_pda.mint, master_edition_mint.key) {
+        return err!(CandyError::OpenEditionMismatch);
     }
 
-    if is_feature_active(&candy.data.uuid, FREEZE_FEATURE_INDEX) {
-        expected_count += 2;
-        if candy.treasury_mint.is_some() {
-            expected_count += 1;
+    Ok(OpenEditionAccounts {
+        open_edition_pda: open_edition_pda_info,
+        open_edition_bump,
+        master_edition_mint,
+        master_edition_metadata: get_account(OpenEditionRemainingAccounts::MasterEditionMetadata)?,
+        master_edition: get_account(OpenEditionRemainingAccounts::MasterEdition)?,
+        open_edition_token_account: get_account(
+            OpenEditionRemainingAccounts::OpenEditionTokenAccount,
+        )?,
+        edition_marker: get_account(OpenEditionRemainingAccounts::EditionMarker)?,
+        creator_authority,
+    })
+}
+
+/// Returns the accounts used to create each programmable NFT. The token record
+/// is passed with the accounts of each NFT, the rule set and the CollectionPda
+/// after them.
+fn get_programmable_nft_accounts<'info>(
+    candy: &CandyMachine,
+    candy_pubkey: &Pubkey,
+    layout: &MintRemainingAccountsLayout,
+    remaining_accounts: &[AccountInfo<'info>],
+    instruction_sysvar: AccountInfo<'info>,
+) -> Result<Vec<ProgrammableNftAccounts<'info>>> {
+    let get_account = |account: RemainingAccounts| {
+        get_remaining_account(candy, layout, remaining_accounts, account)
+    };
+
+    let authorization_rules = match candy.get_rule_set() {
+        Some(_) => {
+            let authorization_rules = get_account(RemainingAccounts::AuthorizationRules)?;
+            assert_candy_machine_rule_set(candy, &authorization_rules)?;
+            Some((
+                get_account(RemainingAccounts::AuthorizationRulesProgram)?,
+                authorization_rules,
+            ))
+        }
+        None => None,
+    };
+
+    let collection_mint = if candy.is_feature_active(COLLECTIONS_FEATURE_INDEX) {
+        let collection_pda_info = get_account(RemainingAccounts::CollectionPda)?;
+        let (expected_collection_pda_key, _) = Pubkey::find_program_address(
+            &[CollectionPda::PREFIX.as_bytes(), candy_pubkey.as_ref()],
+            &crate::id(),
+        );
+        if !cmp_pubkeys(&expected_collection_pda_key, collection_pda_info.key) {
+            return err!(CandyError::MismatchedCollectionPda);
         }
+        let collection_pda: Account<CollectionPda> = Account::try_from(&collection_pda_info)?;
+        Some(collection_pda.mint)
+    } else {
+        None
+    };
+
+    (0..layout.quantity)
+        .map(|nft_index| {
+            Ok(ProgrammableNftAccounts {
+                token_record: get_account(RemainingAccounts::Nft(
+                    nft_index,
+                    NftRemainingAccounts::TokenRecord,
+                ))?,
+                instruction_sysvar: instruction_sysvar.clone(),
+                authorization_rules: authorization_rules.clone(),
+                collection_mint,
+            })
+        })
+        .collect()
+}
+
+/// Checks the rule set account passed to a programmable NFT mint is the
+/// candy machine rule set.
+pub(crate) fn assert_candy_machine_rule_set(
+    candy: &CandyMachine,
+    authorization_rules: &AccountInfo,
+) -> Result<()> {
+    match candy.get_rule_set() {
+        Some(rule_set) if cmp_pubkeys(&rule_set, authorization_rules.key) => Ok(()),
+        _ => err!(CandyError::RuleSetMismatch),
+    }
+}
+
+pub(crate) fn get_expected_remaining_accounts_count(
+    candy: &CandyMachine,
+    layout: &MintRemainingAccountsLayout,
+) -> usize {
+    get_nft_remaining_accounts_end(candy, layout)
+        + get_programmable_nft_remaining_accounts_counter(candy)
+        + get_compressed_nft_remaining_accounts_counter(candy)
+        + get_open_edition_remaining_accounts_counter(candy)
+        + layout.payer_buyer_info_account as usize
+}
+
+/// Dutch auction rebates are owed to the payer, which is only tracked by the
+/// BuyerInfoAccount of the mint if the payer is also the recipient.
+pub(crate) fn is_payer_buyer_info_account_needed(
+    candy: &CandyMachine,
+    payer: &Pubkey,
+    recipient: &Pubkey,
+) -> bool {
+    let is_rebate_enabled = candy
+        .data
+        .dutch_auction_settings
+        .as_ref()
+        .map_or(false, |settings| settings.rebate_enabled);
+    is_rebate_enabled && !cmp_pubkeys(payer, recipient)
+}
+
+/// Returns the payer's BuyerInfoAccount, creating it if it doesn't exist.
+fn get_or_create_payer_buyer_info_account<'info>(
+    candy: &CandyMachine,
+    candy_pubkey: &Pubkey,
+    layout: &MintRemainingAccountsLayout,
+    remaining_accounts: &[AccountInfo<'info>],
+    payer: &AccountInfo<'info>,
+    system_program: &AccountInfo<'info>,
+) -> Result<AccountInfo<'info>> {
+    let payer_buyer_info_account = get_remaining_account(
+        candy,
+        layout,
+        remaining_accounts,
+        RemainingAccounts::PayerBuyerInfoAccount,
+    )?;
+    let (expected_buyer_info_account_key, buyer_info_account_bump) = Pubkey::find_program_address(
+        &[
+            BUYER_INFO_ACCOUNT_PREFIX.as_bytes(),
+            candy_pubkey.as_ref(),
+            payer.key.as_ref(),
+        ],
+        &crate::id(),
+    );
+    assert_keys_equal(
+        &expected_buyer_info_account_key,
+        payer_buyer_info_account.key,
+    )?;
+
+    if payer_buyer_info_account.data_is_empty() {
+        let signer_seeds = [
+            BUYER_INFO_ACCOUNT_PREFIX.as_bytes(),
+            candy_pubkey.as_ref(),
+            payer.key.as_ref(),
+            &[buyer_info_account_bump],
+        ];
+        create_or_allocate_account_raw(
+            crate::id(),
+            &payer_buyer_info_account,
+            system_program,
+            payer,
+            BUYER_INFO_ACCOUNT_SPACE,
+            &signer_seeds,
+        )?;
+
+        write_anchor_account_discriminator::<BuyerInfoAccount>(&payer_buyer_info_account)?;
     }
-    expected_count
+
+    Ok(payer_buyer_info_account)
 }
@@ -0,0 +1,134 @@
+use anchor_lang::prelude::*;
+use anchor_spl::associated_token::AssociatedToken;
+use anchor_spl::token::Token;
+use solana_program::sysvar;
+
+use crate::constants::{MAX_MINT_MANY_QUANTITY, PREFIX};
+use crate::{
+    processor::mint::{process_mint, MintCommonAccounts, MintParams},
+    BuyerMerkleAllowlistProofData, CandyError, CandyMachine, BUYER_INFO_ACCOUNT_PREFIX,
+};
+
+/// Mint multiple NFTs pseudo-randomly from the config array. The
+/// remaining_accounts follow the layout of MintNFT, except the mint (signer),
+/// metadata, master_edition and recipient_token_account of each NFT are
+/// passed with the accounts of that NFT, see NftRemainingAccounts. If the
+/// collections feature is active, the mint must be followed by a
+/// set_collection_during_mint instruction for each NFT, in the same order.
+#[derive(Accounts)]
+#[instruction(creator_bump: u8)]
+pub struct MintManyNFT<'info> {
+    #[account(
+        mut,
+        has_one = creator_authority,
+    )]
+    candy_machine: Box<Account<'info, CandyMachine>>,
+    /// CHECK: account constraints checked in account trait
+    #[account(
+        seeds=[
+            PREFIX.as_bytes(),
+            candy_machine.key().as_ref()
+        ],
+        bump = creator_bump
+    )]
+    candy_machine_creator: UncheckedAccount<'info>,
+    payer: Signer<'info>,
+    /// CHECK: recipient can be any account, it only receives the NFTs
+    recipient: UncheckedAccount<'info>,
+    /// CHECK: treasury_wallet is checked against the selected payment option
+    #[account(mut)]
+    treasury_wallet: UncheckedAccount<'info>,
+    /// CHECK: account checked in CPI
+    creator_authority: UncheckedAccount<'info>,
+    /// CHECK: account checked in CPI
+    #[account(address = mpl_token_metadata::id())]
+    token_metadata_program: UncheckedAccount<'info>,
+    token_program: Program<'info, Token>,
+    system_program: Program<'info, System>,
+    rent: Sysvar<'info, Rent>,
+    /// CHECK: checked in program. The SlotHashes sysvar, or the trusted
+    /// randomness account if the candy machine has one.
+    recent_slothashes: UncheckedAccount<'info>,
+    /// CHECK: account constraints checked in account trait
+    #[account(address = sysvar::instructions::id())]
+    instruction_sysvar_account: UncheckedAccount<'info>,
+    /// CHECK: Validated in the instruction handler.
+    bot_signer_authority: UncheckedAccount<'info>,
+    /// CHECK: This account is validated in the instruction handler.
+    #[account(
+        mut,
+        seeds = [
+            BUYER_INFO_ACCOUNT_PREFIX.as_bytes(),
+            candy_machine.key().as_ref(),
+            recipient.key().as_ref()
+        ],
+        bump
+    )]
+    buyer_info_account: UncheckedAccount<'info>,
+    ata_program: Program<'info, AssociatedToken>,
+    // Some additional remaining_accounts may also be included. See the
+    // RemainingAccounts enum of MintNFT for details.
+}
+
+pub fn handle_mint_many_nft<'info>(
+    ctx: Context<'_, '_, '_, 'info, MintManyNFT<'info>>,
+    creator_bump: u8,
+    buyer_info_account_bump: u8,
+    buyer_merkle_allowlist_proof_data: Option<BuyerMerkleAllowlistProofData>,
+    quantity: u8,
+    // Price of a single NFT. Sole purpose of passing this in is to make this ix easier to parse.
+    expected_price: u64,
+    // Index into the candy machine's payment_options, or None to pay with
+    // the treasury mint.
+    payment_option_index: Option<u8>,
+) -> Result<()> {
+    if quantity == 0 || quantity as usize > MAX_MINT_MANY_QUANTITY {
+        msg!(
+            "Invalid quantity {}, must be between 1 and {}.",
+            quantity,
+            MAX_MINT_MANY_QUANTITY
+        );
+        return err!(CandyError::InvalidMintQuantity);
+    }
+
+    if ctx.accounts.candy_machine.is_compressed() {
+        return err!(CandyError::NotSupportedForCompressedNfts);
+    }
+
+    if ctx.accounts.candy_machine.is_open_edition() {
+        return err!(CandyError::NotSupportedForOpenEditions);
+    }
+
+    let accounts = MintCommonAccounts {
+        candy_machine: &mut *ctx.accounts.candy_machine,
+        candy_machine_creator: ctx.accounts.candy_machine_creator.to_account_info(),
+        payer: ctx.accounts.payer.to_account_info(),
+        recipient: ctx.accounts.recipient.to_account_info(),
+        treasury_wallet: ctx.accounts.treasury_wallet.to_account_info(),
+        creator_authority: ctx.accounts.creator_authority.to_account_info(),
+        token_metadata_program: ctx.accounts.token_metadata_program.to_account_info(),
+        token_program: ctx.accounts.token_program.to_account_info(),
+        system_program: ctx.accounts.system_program.to_account_info(),
+        rent: ctx.accounts.rent.to_account_info(),
+        recent_slothashes: ctx.accounts.recent_slothashes.to_account_info(),
+        instruction_sysvar_account: ctx.accounts.instruction_sysvar_account.to_account_info(),
+        bot_signer_authority: ctx.accounts.bot_signer_authority.to_account_info(),
+        buyer_info_account: ctx.accounts.buyer_info_account.to_account_info(),
+        ata_program: ctx.accounts.ata_program.to_account_info(),
+    };
+
+    process_mint(
+        ctx.program_id,
+        accounts,
+        ctx.remaining_accounts,
+        None,
+        MintParams {
+            creator_bump,
+            buyer_info_account_bump,
+            buyer_merkle_allowlist_proof_data,
+            quantity,
+            expected_price,
+            payment_option_index,
+        },
+    )
+}
@@ -1,17 +1,35 @@
 pub mod add_config_lines;
 pub mod collection;
+pub mod compact_candy_machine;
+pub mod dutch_auction;
 pub mod freeze;
 pub mod initialize;
 pub mod merkle_allowlist;
+pub mod migrate_candy_machine;
 pub mod mint;
+pub mod mint_many;
+pub mod open_edition;
+pub mod remove_config_lines;
+pub mod replace_config_lines;
+pub mod resize_candy_machine;
+pub mod reveal_shuffle_seed;
 pub mod update;
 pub mod withdraw;
 
 pub use add_config_lines::*;
 pub use collection::*;
+pub use compact_candy_machine::*;
+pub use dutch_auction::*;
 pub use freeze::*;
 pub use initialize::*;
 pub use merkle_allowlist::*;
+pub use migrate_candy_machine::*;
 pub use mint::*;
+pub use mint_many::*;
+pub use open_edition::*;
+pub use remove_config_lines::*;
+pub use replace_config_lines::*;
+pub use resize_candy_machine::*;
+pub use reveal_shuffle_seed::*;
 pub use update::*;
 pub use withdraw::*;
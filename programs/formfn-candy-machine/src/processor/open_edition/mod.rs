@@ -0,0 +1,5 @@
+pub mod remove_open_edition;
+pub mod set_open_edition;
+
+pub use remove_open_edition::*;
+pub use set_open_edition::*;
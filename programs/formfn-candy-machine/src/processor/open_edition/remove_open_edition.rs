@@ -0,0 +1,80 @@
+use anchor_lang::prelude::*;
+use anchor_spl::token::{close_account, CloseAccount, Mint, Token, TokenAccount};
+
+use crate::{spl_token_transfer, CandyError, CandyMachine, OpenEditionPda, TokenTransferParams};
+
+/// Remove the open edition master edition from the candy machine. The master
+/// edition token is returned to the creator_authority.
+#[derive(Accounts)]
+pub struct RemoveOpenEdition<'info> {
+    #[account(has_one = formfn_authority, has_one = creator_authority)]
+    candy_machine: Account<'info, CandyMachine>,
+    #[account(mut)]
+    formfn_authority: Signer<'info>,
+    /// CHECK: account is checked against the CandyMachine constraints above.
+    creator_authority: UncheckedAccount<'info>,
+    #[account(
+        mut,
+        seeds = [
+            OpenEditionPda::PREFIX.as_bytes(),
+            candy_machine.key().as_ref()
+        ],
+        bump,
+        has_one = candy_machine,
+        has_one = mint,
+        close = formfn_authority
+    )]
+    open_edition_pda: Account<'info, OpenEditionPda>,
+    #[account(
+        mut,
+        associated_token::mint = mint,
+        associated_token::authority = open_edition_pda
+    )]
+    open_edition_token_account: Account<'info, TokenAccount>,
+    #[account(mut, token::mint = mint, token::authority = creator_authority)]
+    creator_authority_token_account: Account<'info, TokenAccount>,
+    mint: Account<'info, Mint>,
+    token_program: Program<'info, Token>,
+}
+
+pub fn handle_remove_open_edition(ctx: Context<RemoveOpenEdition>) -> Result<()> {
+    let candy_machine = &ctx.accounts.candy_machine;
+    candy_machine.assert_current_version()?;
+    let now = Clock::get()?.unix_timestamp;
+    // The master edition can be removed before minting starts or once the
+    // sale is over.
+    if !candy_machine.is_sale_over(now) {
+        candy_machine.assert_not_minted(error!(CandyError::NoChangingOpenEditionDuringMint))?;
+    }
+
+    let candy_key = candy_machine.key();
+    let open_edition_seeds = [
+        OpenEditionPda::PREFIX.as_bytes(),
+        candy_key.as_ref(),
+        &[*ctx.bumps.get("open_edition_pda").unwrap()],
+    ];
+
+    spl_token_transfer(TokenTransferParams {
+        source: ctx.accounts.open_edition_token_account.to_account_info(),
+        destination: ctx
+            .accounts
+            .creator_authority_token_account
+            .to_account_info(),
+        authority: ctx.accounts.open_edition_pda.to_account_info(),
+        authority_signer_seeds: &open_edition_seeds,
+        token_program: ctx.accounts.token_program.to_account_info(),
+        amount: ctx.accounts.open_edition_token_account.amount,
+    })?;
+
+    close_account(CpiContext::new_with_signer(
+        ctx.accounts.token_program.to_account_info(),
+        CloseAccount {
+            account: ctx.accounts.open_edition_token_account.to_account_info(),
+            destination: ctx.accounts.formfn_authority.to_account_info(),
+            authority: ctx.accounts.open_edition_pda.to_account_info(),
+        },
+        &[&open_edition_seeds],
+    ))?;
+
+    Ok(())
+}
@@ -0,0 +1,132 @@
+use anchor_lang::prelude::*;
+use anchor_spl::{
+    associated_token::AssociatedToken,
+    token::{Mint, Token, TokenAccount},
+};
+use mpl_token_metadata::{
+    assertions::collection::assert_master_edition,
+    instruction::update_metadata_accounts_v2,
+    state::{MasterEditionV2, Metadata, TokenMetadataAccount},
+};
+use solana_program::program::invoke;
+
+use crate::{
+    cmp_pubkeys, spl_token_transfer, CandyError, CandyMachine, OpenEditionPda, TokenTransferParams,
+};
+
+/// Set the master edition an open edition candy machine prints from. The
+/// master edition token is moved to the OpenEditionPda.
+#[derive(Accounts)]
+pub struct SetOpenEdition<'info> {
+    #[account(has_one = formfn_authority, has_one = creator_authority)]
+    candy_machine: Account<'info, CandyMachine>,
+    #[account(mut)]
+    formfn_authority: Signer<'info>,
+    /// CHECK: account is checked against the CandyMachine constraints above.
+    creator_authority: UncheckedAccount<'info>,
+    #[account(
+        init,
+        seeds = [
+            OpenEditionPda::PREFIX.as_bytes(),
+            candy_machine.key().as_ref()
+        ],
+        bump,
+        space = OpenEditionPda::SIZE,
+        payer = formfn_authority
+    )]
+    open_edition_pda: Account<'info, OpenEditionPda>,
+    #[account(
+        init,
+        associated_token::mint = mint,
+        associated_token::authority = open_edition_pda,
+        payer = formfn_authority
+    )]
+    open_edition_token_account: Account<'info, TokenAccount>,
+    #[account(mut, token::mint = mint, token::authority = formfn_authority)]
+    formfn_authority_token_account: Account<'info, TokenAccount>,
+    /// CHECK: account checked in handler and CPI
+    #[account(mut)]
+    metadata: UncheckedAccount<'info>,
+    mint: Account<'info, Mint>,
+    /// CHECK: account checked in handler
+    edition: UncheckedAccount<'info>,
+    /// CHECK: account checked in CPI
+    #[account(address = mpl_token_metadata::id())]
+    token_metadata_program: UncheckedAccount<'info>,
+    token_program: Program<'info, Token>,
+    associated_token_program: Program<'info, AssociatedToken>,
+    system_program: Program<'info, System>,
+    rent: Sysvar<'info, Rent>,
+}
+
+pub fn handle_set_open_edition(ctx: Context<SetOpenEdition>) -> Result<()> {
+    let candy_machine = &ctx.accounts.candy_machine;
+    candy_machine.assert_current_version()?;
+    if !candy_machine.is_open_edition() {
+        return err!(CandyError::OpenEditionNotEnabled);
+    }
+    candy_machine.assert_not_minted(error!(CandyError::NoChangingOpenEditionDuringMint))?;
+
+    let metadata: Metadata = Metadata::from_account_info(&ctx.accounts.metadata.to_account_info())?;
+    if !cmp_pubkeys(
+        &metadata.update_authority,
+        &ctx.accounts.formfn_authority.key(),
+    ) {
+        return err!(CandyError::IncorrectOpenEditionAuthority);
+    }
+    if !cmp_pubkeys(&metadata.mint, &ctx.accounts.mint.key()) {
+        return err!(CandyError::MintMismatch);
+    }
+    let edition_info = ctx.accounts.edition.to_account_info();
+    assert_master_edition(&metadata, &edition_info)?;
+
+    // Edition numbers follow items_redeemed, so the master edition can't have
+    // any prints yet and must allow every item to be printed.
+    let master_edition = MasterEditionV2::from_account_info(&edition_info)?;
+    let has_enough_supply = master_edition.max_supply.map_or(true, |max_supply| {
+        max_supply >= candy_machine.data.items_available
+    });
+    if master_edition.supply > 0 || !has_enough_supply {
+        return err!(CandyError::InvalidOpenEditionMasterEdition);
+    }
+
+    spl_token_transfer(TokenTransferParams {
+        source: ctx
+            .accounts
+            .formfn_authority_token_account
+            .to_account_info(),
+        destination: ctx.accounts.open_edition_token_account.to_account_info(),
+        authority: ctx.accounts.formfn_authority.to_account_info(),
+        authority_signer_seeds: &[],
+        token_program: ctx.accounts.token_program.to_account_info(),
+        amount: 1,
+    })?;
+
+    // Print editions are created with the update_authority of the master
+    // edition, so like set_collection it is updated to the creator_authority.
+    msg!(
+        "Updating master edition update_authority to creator_authority {}.",
+        ctx.accounts.creator_authority.key()
+    );
+    invoke(
+        &update_metadata_accounts_v2(
+            ctx.accounts.token_metadata_program.key(),
+            ctx.accounts.metadata.key(),
+            ctx.accounts.formfn_authority.key(),
+            Some(ctx.accounts.creator_authority.key()),
+            None,
+            None,
+            None,
+        ),
+        &[
+            ctx.accounts.token_metadata_program.to_account_info(),
+            ctx.accounts.metadata.to_account_info(),
+            ctx.accounts.formfn_authority.to_account_info(),
+        ],
+    )?;
+
+    let open_edition_pda = &mut ctx.accounts.open_edition_pda;
+    open_edition_pda.mint = ctx.accounts.mint.key();
+    open_edition_pda.candy_machine = candy_machine.key();
+    Ok(())
+}
@@ -0,0 +1,57 @@
+use anchor_lang::prelude::*;
+
+use crate::{
+    processor::add_config_lines::{
+        assert_config_lines_fit, get_config_count, get_config_line_start, set_config_count,
+        set_config_line_loaded,
+    },
+    CandyError, CandyMachine,
+};
+
+/// Remove config lines from the candy machine before minting starts.
+#[derive(Accounts)]
+pub struct RemoveConfigLines<'info> {
+    #[account(mut, has_one = formfn_authority)]
+    candy_machine: Account<'info, CandyMachine>,
+    formfn_authority: Signer<'info>,
+}
+
+pub fn handle_remove_config_lines(
+    ctx: Context<RemoveConfigLines>,
+    index: u32,
+    count: u32,
+) -> Result<()> {
+    let candy_machine = &ctx.accounts.candy_machine;
+    candy_machine.assert_current_version()?;
+    candy_machine.assert_not_minted(error!(CandyError::NoChangingConfigLinesDuringMint))?;
+    candy_machine.assert_not_compacted(&candy_machine.to_account_info())?;
+    if candy_machine.is_open_edition() {
+        return err!(CandyError::NotSupportedForOpenEditions);
+    }
+    if candy_machine.data.hidden_settings.is_some() {
+        return err!(CandyError::HiddenSettingsConfigsDoNotHaveConfigLines);
+    }
+    assert_config_lines_fit(candy_machine, index, count as usize)?;
+
+    let account = candy_machine.to_account_info();
+    let mut data = account.data.borrow_mut();
+
+    // Zero the removed lines so they read as empty, like lines never added.
+    let start = get_config_line_start(&candy_machine.data, index as usize);
+    let end = get_config_line_start(&candy_machine.data, (index + count) as usize);
+    data[start..end].fill(0);
+
+    let mut new_count = get_config_count(&data)?;
+    for position in index as usize..(index + count) as usize {
+        let was_loaded = set_config_line_loaded(&mut data, &candy_machine.data, position, false)?;
+        if was_loaded {
+            new_count = new_count
+                .checked_sub(1)
+                .ok_or(CandyError::NumericalOverflowError)?;
+        }
+    }
+
+    set_config_count(&mut data, new_count);
+
+    Ok(())
+}
@@ -0,0 +1,47 @@
+use anchor_lang::prelude::*;
+
+use crate::{
+    processor::add_config_lines::{
+        assert_config_lines_fit, set_config_line_loaded, write_config_lines,
+    },
+    CandyError, CandyMachine, ConfigLine,
+};
+
+/// Replace config lines which were already added, before minting starts.
+#[derive(Accounts)]
+pub struct ReplaceConfigLines<'info> {
+    #[account(mut, has_one = formfn_authority)]
+    candy_machine: Account<'info, CandyMachine>,
+    formfn_authority: Signer<'info>,
+}
+
+pub fn handle_replace_config_lines(
+    ctx: Context<ReplaceConfigLines>,
+    index: u32,
+    config_lines: Vec<ConfigLine>,
+) -> Result<()> {
+    let candy_machine = &ctx.accounts.candy_machine;
+    candy_machine.assert_current_version()?;
+    candy_machine.assert_not_minted(error!(CandyError::NoChangingConfigLinesDuringMint))?;
+    candy_machine.assert_not_compacted(&candy_machine.to_account_info())?;
+    if candy_machine.is_open_edition() {
+        return err!(CandyError::NotSupportedForOpenEditions);
+    }
+    if candy_machine.data.hidden_settings.is_some() {
+        return err!(CandyError::HiddenSettingsConfigsDoNotHaveConfigLines);
+    }
+    assert_config_lines_fit(candy_machine, index, config_lines.len())?;
+
+    let account = candy_machine.to_account_info();
+    let mut data = account.data.borrow_mut();
+
+    // Setting a loaded bit again leaves it and the config count unchanged.
+    for i in 0..config_lines.len() {
+        let position = index as usize + i;
+        if !set_config_line_loaded(&mut data, &candy_machine.data, position, true)? {
+            return err!(CandyError::ConfigLineNotLoaded);
+        }
+    }
+
+    write_config_lines(&mut data, &candy_machine.data, index, &config_lines)
+}
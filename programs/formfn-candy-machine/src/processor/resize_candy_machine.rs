@@ -0,0 +1,161 @@
+use anchor_lang::prelude::*;
+use solana_program::{
+    entrypoint::MAX_PERMITTED_DATA_INCREASE, program::invoke, system_instruction,
+};
+
+use crate::{
+    processor::{
+        add_config_lines::{
+            get_config_bit_mask_start, get_config_line_start, set_config_bit_mask_len,
+            set_config_count,
+        },
+        initialize::get_space_for_candy,
+        mint::get_mint_bit_mask_start,
+    },
+    transfer_lamports_from_program_account, CandyError, CandyMachine,
+};
+
+/// Change items_available of a candy machine with config lines before the
+/// first mint. The account is reallocated and the bitmask of loaded config
+/// lines and the mint bitmask are moved to their new offsets. An account can
+/// only grow by MAX_PERMITTED_DATA_INCREASE bytes per instruction, so large
+/// increases must be split across several calls.
+#[derive(Accounts)]
+pub struct ResizeCandyMachine<'info> {
+    #[account(mut, has_one = formfn_authority)]
+    candy_machine: Account<'info, CandyMachine>,
+    #[account(mut)]
+    formfn_authority: Signer<'info>,
+    system_program: Program<'info, System>,
+}
+
+pub fn handle_resize_candy_machine(
+    ctx: Context<ResizeCandyMachine>,
+    items_available: u64,
+) -> Result<()> {
+    let candy_machine = &mut ctx.accounts.candy_machine;
+    let candy_machine_info = candy_machine.to_account_info();
+    candy_machine.assert_current_version()?;
+    candy_machine.assert_not_minted(error!(CandyError::NoResizingAfterMint))?;
+    candy_machine.assert_not_compacted(&candy_machine_info)?;
+    if candy_machine.is_open_edition() {
+        return err!(CandyError::NotSupportedForOpenEditions);
+    }
+    // Hidden settings candy machines change items_available through
+    // update_candy_machine.
+    if candy_machine.data.hidden_settings.is_some() {
+        return err!(CandyError::HiddenSettingsConfigsDoNotHaveConfigLines);
+    }
+    if items_available == 0 {
+        return err!(CandyError::InvalidItemsAvailable);
+    }
+
+    let old_items = candy_machine.data.items_available as usize;
+    let new_items = items_available as usize;
+    let old_len = candy_machine_info.data_len();
+    let mut resized_data = candy_machine.data.clone();
+    resized_data.items_available = items_available;
+    let new_len = get_space_for_candy(resized_data.clone())?;
+
+    if new_len > old_len {
+        if new_len - old_len > MAX_PERMITTED_DATA_INCREASE {
+            return err!(CandyError::ResizeExceedsMaxDataIncrease);
+        }
+        candy_machine_info.realloc(new_len, false)?;
+    }
+
+    {
+        let mut data = candy_machine_info.data.borrow_mut();
+        let old_bit_mask_start = get_config_bit_mask_start(&candy_machine.data);
+        let new_bit_mask_start = get_config_bit_mask_start(&resized_data);
+        let old_mint_bit_mask_start = get_mint_bit_mask_start(&candy_machine.data)?;
+        let new_mint_bit_mask_start = get_mint_bit_mask_start(&resized_data)?;
+        let bit_mask_len = get_bit_mask_len(old_items.min(new_items));
+
+        // Move the mint bitmask first when growing and last when shrinking so
+        // neither bitmask overwrites the other before it is moved.
+        if new_items > old_items {
+            data.copy_within(
+                old_mint_bit_mask_start..old_mint_bit_mask_start + bit_mask_len,
+                new_mint_bit_mask_start,
+            );
+            data.copy_within(
+                old_bit_mask_start..old_bit_mask_start + bit_mask_len,
+                new_bit_mask_start,
+            );
+        } else {
+            data.copy_within(
+                old_bit_mask_start..old_bit_mask_start + bit_mask_len,
+                new_bit_mask_start,
+            );
+            data.copy_within(
+                old_mint_bit_mask_start..old_mint_bit_mask_start + bit_mask_len,
+                new_mint_bit_mask_start,
+            );
+        }
+
+        // New config lines start out empty, like lines never added.
+        let old_lines_end = get_config_line_start(&candy_machine.data, old_items);
+        if new_bit_mask_start > old_lines_end {
+            data[old_lines_end..new_bit_mask_start].fill(0);
+        }
+        set_config_bit_mask_len(&mut data, &resized_data)?;
+        data[new_bit_mask_start + bit_mask_len..new_mint_bit_mask_start].fill(0);
+        data[new_mint_bit_mask_start + bit_mask_len..new_len].fill(0);
+
+        // Drop the bits of config lines past the new items_available.
+        let last_byte_mask = !(u8::MAX >> (new_items % 8));
+        data[new_bit_mask_start + new_items / 8] &= last_byte_mask;
+        data[new_mint_bit_mask_start + new_items / 8] &= last_byte_mask;
+
+        let new_count = data[new_bit_mask_start..new_bit_mask_start + get_bit_mask_len(new_items)]
+            .iter()
+            .map(|byte| byte.count_ones() as usize)
+            .sum();
+        set_config_count(&mut data, new_count);
+    }
+
+    if new_len < old_len {
+        candy_machine_info.realloc(new_len, false)?;
+    }
+
+    let rent = Rent::get()?;
+    if new_len > old_len {
+        // The formfn_authority pays for the extra account space.
+        let rent_exempt_lamports = rent.minimum_balance(new_len);
+        let lamports = candy_machine_info.lamports();
+        if lamports < rent_exempt_lamports {
+            invoke(
+                &system_instruction::transfer(
+                    &ctx.accounts.formfn_authority.key(),
+                    &candy_machine_info.key(),
+                    rent_exempt_lamports - lamports,
+                ),
+                &[
+                    ctx.accounts.formfn_authority.to_account_info(),
+                    candy_machine_info.clone(),
+                    ctx.accounts.system_program.to_account_info(),
+                ],
+            )?;
+        }
+    } else {
+        // Only the rent of the removed bytes is refunded, as in
+        // compact_candy_machine.
+        let refund = rent
+            .minimum_balance(old_len)
+            .saturating_sub(rent.minimum_balance(new_len));
+        transfer_lamports_from_program_account(
+            &candy_machine_info,
+            &ctx.accounts.formfn_authority.to_account_info(),
+            refund,
+        )?;
+    }
+
+    candy_machine.data.items_available = items_available;
+
+    Ok(())
+}
+
+fn get_bit_mask_len(items_available: usize) -> usize {
+    items_available / 8 + 1
+}
@@ -0,0 +1,43 @@
+use anchor_lang::prelude::*;
+
+use crate::{CandyError, CandyMachine};
+
+/// Reveal the seed which defines the final order of a candy machine with a
+/// shuffled mint order, once it has sold out or its sale has ended. This only
+/// publishes the seed, the NFTs keep their hidden settings until they're
+/// revealed off-chain in the order given by get_shuffled_order.
+#[derive(Accounts)]
+pub struct RevealShuffleSeed<'info> {
+    #[account(mut, has_one = formfn_authority)]
+    candy_machine: Account<'info, CandyMachine>,
+    formfn_authority: Signer<'info>,
+}
+
+pub fn handle_reveal_shuffle_seed(ctx: Context<RevealShuffleSeed>, seed: [u8; 32]) -> Result<()> {
+    let candy_machine = &mut ctx.accounts.candy_machine;
+    candy_machine.assert_current_version()?;
+
+    // Revealing the seed earlier would let buyers pick which item they get.
+    let now = Clock::get()?.unix_timestamp;
+    if candy_machine.items_redeemed < candy_machine.data.items_available
+        && now < candy_machine.data.public_sale_end_time
+    {
+        return err!(CandyError::CandyMachineNotSoldOut);
+    }
+
+    let shuffle_settings = candy_machine
+        .data
+        .shuffle_settings
+        .as_mut()
+        .ok_or(CandyError::MintOrderNotShuffled)?;
+    if shuffle_settings.seed.is_some() {
+        return err!(CandyError::ShuffleSeedAlreadyRevealed);
+    }
+    if solana_program::keccak::hash(&seed).0 != shuffle_settings.seed_commitment {
+        return err!(CandyError::InvalidShuffleSeed);
+    }
+
+    shuffle_settings.seed = Some(seed);
+
+    Ok(())
+}
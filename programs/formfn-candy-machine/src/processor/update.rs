@@ -1,9 +1,14 @@
 use anchor_lang::prelude::*;
 
-use crate::constants::FREEZE_FEATURE_INDEX;
+use crate::constants::{
+    COLLECTIONS_FEATURE_INDEX, FREEZE_FEATURE_INDEX, MAX_PLATFORM_FEE_BASIS_POINTS,
+};
 use crate::{
-    is_feature_active, validate_candy_machine_allowlist_state, validate_mint_phase_times,
-    CandyError, CandyMachine, CandyMachineData,
+    validate_bonding_curve_settings, validate_candy_machine_allowlist_state,
+    validate_compressed_nft_settings, validate_config_line_settings,
+    validate_dutch_auction_settings, validate_mint_phase_times, validate_open_edition_settings,
+    validate_payment_options, validate_primary_sale_splits, validate_shuffle_settings, CandyError,
+    CandyMachine, CandyMachineData, ShuffleSettings,
 };
 
 /// Update the candy machine state.
@@ -21,14 +26,39 @@ pub struct UpdateCandyMachine<'info> {
     // treasury mint
 }
 
+/// Update the platform fee taken on each mint.
+#[derive(Accounts)]
+pub struct UpdatePlatformFee<'info> {
+    #[account(
+        mut,
+        has_one = formfn_authority
+    )]
+    candy_machine: Account<'info, CandyMachine>,
+    formfn_authority: Signer<'info>,
+}
+
+/// Move the feature flags of a candy machine created before the features
+/// bitfield out of its uuid. Accounts of that age have to be migrated with
+/// migrate_candy_machine first, which adds the features fields.
+#[derive(Accounts)]
+pub struct MigrateFeatureFlags<'info> {
+    #[account(
+        mut,
+        has_one = formfn_authority
+    )]
+    candy_machine: Account<'info, CandyMachine>,
+    formfn_authority: Signer<'info>,
+}
+
 pub fn handle_update_authority(
     ctx: Context<UpdateCandyMachine>,
     new_authority: Option<Pubkey>,
 ) -> Result<()> {
     let candy_machine = &mut ctx.accounts.candy_machine;
+    candy_machine.assert_current_version()?;
 
     if let Some(new_auth) = new_authority {
-        if is_feature_active(&candy_machine.data.uuid, FREEZE_FEATURE_INDEX)
+        if candy_machine.is_feature_active(FREEZE_FEATURE_INDEX)
             && candy_machine.formfn_authority != new_auth
         {
             return err!(CandyError::NoChangingAuthorityWithFreeze);
@@ -39,12 +69,33 @@ pub fn handle_update_authority(
     Ok(())
 }
 
+pub fn handle_update_platform_fee(
+    ctx: Context<UpdatePlatformFee>,
+    platform_fee_basis_points: u16,
+    platform_fee_wallet: Option<Pubkey>,
+) -> Result<()> {
+    let candy_machine = &mut ctx.accounts.candy_machine;
+    candy_machine.assert_current_version()?;
+
+    if platform_fee_basis_points > MAX_PLATFORM_FEE_BASIS_POINTS
+        || (platform_fee_basis_points > 0 && platform_fee_wallet.is_none())
+    {
+        return err!(CandyError::InvalidPlatformFee);
+    }
+
+    candy_machine.platform_fee_basis_points = platform_fee_basis_points;
+    candy_machine.platform_fee_wallet = platform_fee_wallet;
+
+    Ok(())
+}
+
 // updates without modifying UUID
 pub fn handle_update_candy_machine(
     ctx: Context<UpdateCandyMachine>,
     data: CandyMachineData,
 ) -> Result<()> {
     let candy_machine = &mut ctx.accounts.candy_machine;
+    candy_machine.assert_current_version()?;
 
     // Note: there is currently no validation to ensure an update doesn't change
     // any sale time settings after sales have already begun.
@@ -52,6 +103,66 @@ pub fn handle_update_candy_machine(
 
     validate_candy_machine_allowlist_state(&data)?;
 
+    validate_primary_sale_splits(&data)?;
+
+    validate_bonding_curve_settings(&data)?;
+
+    validate_payment_options(&data)?;
+
+    validate_compressed_nft_settings(&data)?;
+
+    validate_open_edition_settings(&data)?;
+
+    validate_config_line_settings(&data)?;
+
+    validate_shuffle_settings(&data)?;
+
+    // Open edition candy machines are allocated without config lines.
+    if data.open_edition_enabled != candy_machine.data.open_edition_enabled {
+        return err!(CandyError::CannotChangeOpenEditionMode);
+    }
+
+    // The config line settings determine the layout of the config lines.
+    if data.config_line_settings != candy_machine.data.config_line_settings {
+        return err!(CandyError::CannotChangeConfigLineSettings);
+    }
+
+    // Which config line the next mint gets depends on the mint order.
+    if data.mint_order != candy_machine.data.mint_order {
+        candy_machine.assert_not_minted(error!(CandyError::CannotChangeMintOrderAfterMint))?;
+    }
+
+    // Buyers can't be switched onto another source of randomness mid-sale.
+    if data.trusted_randomness_account != candy_machine.data.trusted_randomness_account {
+        candy_machine.assert_not_minted(error!(
+            CandyError::CannotChangeTrustedRandomnessAccountAfterMint
+        ))?;
+    }
+
+    // The seed commitment can't change once buyers rely on it, and the seed is
+    // only set by reveal_shuffle_seed.
+    if data.shuffle_settings != candy_machine.data.shuffle_settings {
+        candy_machine
+            .assert_not_minted(error!(CandyError::CannotChangeShuffleSettingsAfterMint))?;
+        if let Some(ShuffleSettings { seed: Some(_), .. }) = &data.shuffle_settings {
+            return err!(CandyError::InvalidShuffleSettings);
+        }
+    }
+
+    // Buyers' mint counts are kept per mint phase index, so a phase has to
+    // stay at its index once minting has started. Its times, price and limit
+    // can still change.
+    let mint_phases_changed = data.mint_phases.len() != candy_machine.data.mint_phases.len()
+        || data
+            .mint_phases
+            .iter()
+            .zip(candy_machine.data.mint_phases.iter())
+            .any(|(new_phase, old_phase)| new_phase.allowlist_source != old_phase.allowlist_source);
+    if mint_phases_changed {
+        candy_machine.assert_not_minted(error!(CandyError::CannotChangeMintPhasesAfterMint))?;
+    }
+
+    // Candy machines with config lines are resized with resize_candy_machine.
     if data.items_available != candy_machine.data.items_available && data.hidden_settings.is_none()
     {
         return err!(CandyError::CannotChangeNumberOfLines);
@@ -62,6 +173,35 @@ pub fn handle_update_candy_machine(
         .get(0)
         .map(|account_info| account_info.key());
 
+    validate_dutch_auction_settings(&data, treasury_mint)?;
+
+    if data.dutch_auction_settings != candy_machine.data.dutch_auction_settings {
+        candy_machine.assert_not_minted(error!(CandyError::CannotChangeDutchAuctionAfterMint))?;
+    }
+
+    if data.bonding_curve_settings != candy_machine.data.bonding_curve_settings {
+        candy_machine.assert_not_minted(error!(CandyError::CannotChangeBondingCurveAfterMint))?;
+    }
+
+    // The collection authority of programmable NFTs is set up differently, see
+    // set_collection.
+    if data.programmable_nft_settings != candy_machine.data.programmable_nft_settings {
+        candy_machine.assert_not_minted(error!(CandyError::CannotChangeProgrammableNftSettings))?;
+        if candy_machine.is_feature_active(COLLECTIONS_FEATURE_INDEX) {
+            return err!(CandyError::CannotChangeProgrammableNftSettings);
+        }
+    }
+
+    // Compressed NFTs can't be frozen or verified as part of a collection.
+    if data.compressed_nft_settings != candy_machine.data.compressed_nft_settings {
+        candy_machine.assert_not_minted(error!(CandyError::CannotChangeCompressedNftSettings))?;
+        if candy_machine.is_feature_active(FREEZE_FEATURE_INDEX)
+            || candy_machine.is_feature_active(COLLECTIONS_FEATURE_INDEX)
+        {
+            return err!(CandyError::CannotChangeCompressedNftSettings);
+        }
+    }
+
     if candy_machine.data.items_available > 0
         && candy_machine.data.hidden_settings.is_none()
         && data.hidden_settings.is_some()
@@ -70,11 +210,17 @@ pub fn handle_update_candy_machine(
     }
 
     let old_uuid = candy_machine.data.uuid.clone();
-    if is_feature_active(&old_uuid, FREEZE_FEATURE_INDEX)
-        && candy_machine.treasury_mint != treasury_mint
+    if candy_machine.is_feature_active(FREEZE_FEATURE_INDEX)
+        && (candy_machine.treasury_mint != treasury_mint
+            || candy_machine.data.payment_options != data.payment_options)
     {
         return err!(CandyError::NoChangingTokenWithFreeze);
     }
+    // unlock_funds pays out the frozen funds without the primary sale splits.
+    if candy_machine.is_feature_active(FREEZE_FEATURE_INDEX) && !data.primary_sale_splits.is_empty()
+    {
+        return err!(CandyError::NoPrimarySaleSplitsWithFreeze);
+    }
 
     candy_machine.treasury_wallet = ctx.accounts.treasury_wallet.key();
     candy_machine.data = data;
@@ -83,3 +229,12 @@ pub fn handle_update_candy_machine(
 
     Ok(())
 }
+
+pub fn handle_migrate_feature_flags(ctx: Context<MigrateFeatureFlags>) -> Result<()> {
+    let candy_machine = &mut ctx.accounts.candy_machine;
+    candy_machine.assert_current_version()?;
+
+    candy_machine.migrate_features();
+
+    Ok(())
+}
@@ -2,7 +2,7 @@ use anchor_lang::prelude::*;
 use anchor_lang::AccountsClose;
 
 use crate::constants::{FREEZE_FEATURE_INDEX, FREEZE_LOCK_FEATURE_INDEX};
-use crate::{cmp_pubkeys, is_feature_active, CandyError, CandyMachine, CollectionPda};
+use crate::{cmp_pubkeys, CandyError, CandyMachine, CollectionPda};
 
 /// Withdraw SOL from candy machine account.
 #[derive(Accounts)]
@@ -20,12 +20,22 @@ pub fn handle_withdraw_funds<'info>(
 ) -> Result<()> {
     let authority = &ctx.accounts.formfn_authority;
     let candy_machine = &ctx.accounts.candy_machine;
-    if is_feature_active(&candy_machine.data.uuid, FREEZE_FEATURE_INDEX) {
+    candy_machine.assert_current_version()?;
+    if candy_machine.is_feature_active(FREEZE_FEATURE_INDEX) {
         return err!(CandyError::NoWithdrawWithFreeze);
     }
-    if is_feature_active(&candy_machine.data.uuid, FREEZE_LOCK_FEATURE_INDEX) {
+    if candy_machine.is_feature_active(FREEZE_LOCK_FEATURE_INDEX) {
         return err!(CandyError::NoWithdrawWithFrozenFunds);
     }
+    // Closing the candy machine would take the Dutch auction proceeds which are
+    // owed to the treasury wallet and the rebates which are owed to buyers.
+    let rebate_state = &candy_machine.dutch_auction_rebate_state;
+    if rebate_state.items_sold > 0 && !rebate_state.proceeds_released {
+        return err!(CandyError::NoWithdrawWithDutchAuctionProceeds);
+    }
+    if rebate_state.amount_held > 0 {
+        return err!(CandyError::NoWithdrawWithUnclaimedDutchAuctionRebates);
+    }
 
     if !ctx.remaining_accounts.is_empty() {
         let candy_key = candy_machine.key();
@@ -1,5 +1,11 @@
-use crate::constants::FREEZE_FEE;
-use crate::CandyError;
+use crate::constants::{
+    CONFIG_ARRAY_START, CONFIG_LINE_SIZE, FEATURES_VERSION, FREEZE_FEE, MAX_MINT_PHASES,
+    MERKLE_ROOT_SIZE,
+};
+use crate::layout::CANDY_MACHINE_VERSION;
+use crate::{
+    get_legacy_features, is_feature_active, remove_feature_flag, set_feature_flag, CandyError,
+};
 use anchor_lang::prelude::*;
 use mpl_token_metadata::state::{MAX_NAME_LENGTH, MAX_URI_LENGTH};
 
@@ -7,11 +13,29 @@ use mpl_token_metadata::state::{MAX_NAME_LENGTH, MAX_URI_LENGTH};
 #[account]
 #[derive(Default, Debug)]
 pub struct CandyMachine {
+    // Layout version of the account, see layout.rs. This must stay the first
+    // field so the version can be read before the account is deserialized.
+    pub version: u8,
     pub formfn_authority: Pubkey,
     pub creator_authority: Pubkey,
     pub treasury_wallet: Pubkey,
     pub treasury_mint: Option<Pubkey>,
     pub items_redeemed: u64,
+    // Number of items sold during the allowlist phase, which
+    // mint_caps.allowlist_items_cap limits.
+    pub allowlist_items_sold: u64,
+    // Platform fee taken from each mint payment, controlled by the
+    // formfn_authority. Basis points of the mint price (0-10000).
+    pub platform_fee_basis_points: u16,
+    pub platform_fee_wallet: Option<Pubkey>,
+    // Running totals of a Dutch auction with rebates enabled.
+    pub dutch_auction_rebate_state: DutchAuctionRebateState,
+    // Layout version of features. Version 0 accounts still keep their feature
+    // flags as '0'/'1' characters in data.uuid, see migrate_features.
+    pub features_version: u8,
+    // Bitfield of the features enabled on the candy machine, indexed by the
+    // *_FEATURE_INDEX constants.
+    pub features: u64,
     pub data: CandyMachineData,
     // After this is additional account space which contains the config lines
     // and related data, which is deserialized manually as a byte array.
@@ -40,15 +64,249 @@ pub struct CandyMachineData {
     pub bot_protection_enabled: bool,
     // Denotes the limit per address, 0 if unlimited.
     pub limit_per_address: u16,
-    // If true, minting in the pre-mint phase is in sequential order.
-    pub sequential_mint_order_enabled: bool,
-    // Vector of merkle tree root hashes for address based allowlist.
-    pub merkle_allowlist_root_list: Vec<[u8; 32]>,
+    // The order config lines are minted in.
+    pub mint_order: MintOrder,
+    // Vector of merkle tree roots for address based allowlist.
+    pub merkle_allowlist_root_list: Vec<MerkleAllowlistRoot>,
     // SPL token allowlist settings.
     pub spl_token_allowlist_settings: Option<SplTokenAllowlistSettings>,
+    // Ordered list of configurable mint phases. If this is non-empty it replaces
+    // the fixed allowlist/public sale start times and prices above, and the
+    // sale still ends at public_sale_end_time.
+    pub mint_phases: Vec<MintPhaseSettings>,
+    // Wallets which split the primary sale proceeds, separate from the royalty
+    // creators above. If this is empty the full price goes to treasury_wallet.
+    pub primary_sale_splits: Vec<PrimarySaleSplit>,
+    // If set, the public sale price declines over time instead of being fixed.
+    pub dutch_auction_settings: Option<DutchAuctionSettings>,
+    // If set, the public sale price rises as items are redeemed instead of
+    // being fixed. This can't be combined with a Dutch auction.
+    pub bonding_curve_settings: Option<BondingCurveSettings>,
+    // Additional currencies buyers can pay with, besides the treasury mint.
+    pub payment_options: Vec<PaymentOption>,
+    // If set, the candy machine mints programmable NFTs instead of classic NFTs.
+    pub programmable_nft_settings: Option<ProgrammableNftSettings>,
+    // If set, the candy machine mints compressed NFTs into a Merkle tree.
+    pub compressed_nft_settings: Option<CompressedNftSettings>,
+    // If true, the candy machine mints numbered print editions of the master
+    // edition held by its OpenEditionPda instead of using config lines.
+    // items_available caps the number of editions (u64::MAX for no cap) and
+    // the sale ends at public_sale_end_time.
+    pub open_edition_enabled: bool,
+    // If set, config lines only store the name and uri after the shared
+    // prefixes, which shrinks the account for large collections.
+    pub config_line_settings: Option<ConfigLineSettings>,
+    // If set, mints read their randomness from this account instead of
+    // the SlotHashes sysvar.
+    pub trusted_randomness_account: Option<TrustedRandomnessAccount>,
+    // Set if mint_order is MintOrder::ShuffledAtReveal.
+    pub shuffle_settings: Option<ShuffleSettings>,
+    // Per wallet limits for the premint and allowlist phases. The public
+    // phase is limited by limit_per_address.
+    pub phase_limits: PhaseLimits,
+    // Limits which apply across mint phases.
+    pub mint_caps: MintCaps,
+    // If set, the allowlist phase is gated on holding an NFT from a verified
+    // collection.
+    pub collection_allowlist_settings: Option<CollectionAllowlistSettings>,
+    // SPL tokens the recipient must all hold during the allowlist phase, each
+    // with its own burn amount and minimum balance. This replaces
+    // spl_token_allowlist_settings, which can't be set at the same time.
+    pub spl_token_allowlist_tokens: Vec<SplTokenAllowlistToken>,
+}
+
+impl CandyMachineData {
+    pub fn is_spl_token_allowlist_enabled(&self) -> bool {
+        self.spl_token_allowlist_settings.is_some() || !self.spl_token_allowlist_tokens.is_empty()
+    }
+
+    /// Returns the SPL allowlist tokens, treating spl_token_allowlist_settings
+    /// as a single token of which one is burned or held per NFT.
+    pub fn get_spl_token_allowlist_tokens(&self) -> Vec<SplTokenAllowlistToken> {
+        match &self.spl_token_allowlist_settings {
+            Some(spl_token_allowlist_settings) => vec![SplTokenAllowlistToken {
+                mint: spl_token_allowlist_settings.mint,
+                burn_amount: match spl_token_allowlist_settings.mode {
+                    SplTokenAllowlistMode::BurnEveryTime => 1,
+                    SplTokenAllowlistMode::NeverBurn => 0,
+                },
+                min_balance: 1,
+            }],
+            None => self.spl_token_allowlist_tokens.clone(),
+        }
+    }
+
+    /// Returns the number of bytes each config line takes in the account.
+    pub fn get_config_line_size(&self) -> usize {
+        match &self.config_line_settings {
+            Some(settings) => settings.get_config_line_size(),
+            None => CONFIG_LINE_SIZE,
+        }
+    }
+
+    /// Parses a config line stored in the account and adds back the shared
+    /// prefixes of the config line settings.
+    pub fn parse_config_line(&self, config_line: &[u8]) -> Result<ConfigLine> {
+        let config_line = match &self.config_line_settings {
+            Some(settings) => {
+                let name_length = settings.name_length as usize;
+                let uri_length = settings.uri_length as usize;
+                ConfigLine {
+                    name: settings.prefix_name.clone()
+                        + &parse_padded_string(&config_line[..name_length])?,
+                    uri: settings.prefix_uri.clone()
+                        + &parse_padded_string(
+                            &config_line[name_length..name_length + uri_length],
+                        )?,
+                }
+            }
+            None => ConfigLine {
+                name: parse_padded_string(&config_line[4..4 + MAX_NAME_LENGTH])?,
+                uri: parse_padded_string(
+                    &config_line[8 + MAX_NAME_LENGTH..8 + MAX_NAME_LENGTH + MAX_URI_LENGTH],
+                )?,
+            },
+        };
+
+        Ok(config_line)
+    }
+}
+
+/// Reads a string which is padded with zeroes to its max length.
+fn parse_padded_string(bytes: &[u8]) -> Result<String> {
+    let end = bytes
+        .iter()
+        .position(|byte| *byte == 0)
+        .unwrap_or(bytes.len());
+    match String::from_utf8(bytes[..end].to_vec()) {
+        Ok(value) => Ok(value),
+        Err(_) => err!(CandyError::InvalidString),
+    }
 }
 
 impl CandyMachine {
+    /// Returns the payment option the buyer selected, or None if the buyer
+    /// pays with the candy machine's treasury mint.
+    pub fn get_payment_option(
+        &self,
+        payment_option_index: Option<u8>,
+    ) -> Result<Option<&PaymentOption>> {
+        match payment_option_index {
+            Some(index) => match self.data.payment_options.get(index as usize) {
+                Some(payment_option) => Ok(Some(payment_option)),
+                None => err!(CandyError::InvalidPaymentOptionIndex),
+            },
+            None => Ok(None),
+        }
+    }
+
+    /// Returns every SPL token the candy machine accepts as payment, starting
+    /// with the treasury mint.
+    pub fn get_payment_mints(&self) -> Vec<Pubkey> {
+        self.treasury_mint
+            .into_iter()
+            .chain(
+                self.data
+                    .payment_options
+                    .iter()
+                    .filter_map(|payment_option| payment_option.treasury_mint),
+            )
+            .collect()
+    }
+
+    /// Returns true if the candy machine mints programmable NFTs.
+    pub fn is_programmable(&self) -> bool {
+        self.data.programmable_nft_settings.is_some()
+    }
+
+    /// Returns the rule set of the minted programmable NFTs, if any.
+    pub fn get_rule_set(&self) -> Option<Pubkey> {
+        self.data
+            .programmable_nft_settings
+            .as_ref()
+            .and_then(|settings| settings.rule_set)
+    }
+
+    /// Returns true if the candy machine mints compressed NFTs.
+    pub fn is_compressed(&self) -> bool {
+        self.data.compressed_nft_settings.is_some()
+    }
+
+    /// Returns true if the seed of a shuffled mint order has been revealed,
+    /// which fixes the item of every minted NFT.
+    pub fn is_shuffle_seed_revealed(&self) -> bool {
+        self.data
+            .shuffle_settings
+            .as_ref()
+            .map_or(false, |shuffle_settings| shuffle_settings.seed.is_some())
+    }
+
+    /// Returns true if the candy machine mints print editions of a single
+    /// master edition.
+    pub fn is_open_edition(&self) -> bool {
+        self.data.open_edition_enabled
+    }
+
+    /// Returns true if the candy machine account stores config lines and a
+    /// mint bitmask after its settings.
+    pub fn has_config_lines(&self) -> bool {
+        self.data.hidden_settings.is_none() && !self.is_open_edition()
+    }
+
+    /// Returns true if the config lines were removed by compact_candy_machine.
+    pub fn is_compacted(&self, candy_machine_info: &AccountInfo) -> bool {
+        self.has_config_lines() && candy_machine_info.data_len() <= CONFIG_ARRAY_START
+    }
+
+    pub fn assert_not_compacted(&self, candy_machine_info: &AccountInfo) -> Result<()> {
+        if self.is_compacted(candy_machine_info) {
+            return err!(CandyError::CandyMachineCompacted);
+        }
+
+        Ok(())
+    }
+
+    /// Returns the enabled features, decoding the legacy uuid flags of
+    /// accounts which haven't been migrated yet.
+    pub fn get_features(&self) -> u64 {
+        if self.features_version < FEATURES_VERSION {
+            get_legacy_features(&self.data.uuid)
+        } else {
+            self.features
+        }
+    }
+
+    pub fn is_feature_active(&self, feature_index: usize) -> bool {
+        is_feature_active(self.get_features(), feature_index)
+    }
+
+    /// Moves the legacy uuid feature flags onto the features bitfield. The
+    /// uuid is left as is but no longer read.
+    pub fn migrate_features(&mut self) {
+        if self.features_version < FEATURES_VERSION {
+            self.features = self.get_features();
+            self.features_version = FEATURES_VERSION;
+        }
+    }
+
+    pub fn set_feature(&mut self, feature_index: usize) {
+        self.migrate_features();
+        set_feature_flag(&mut self.features, feature_index);
+    }
+
+    pub fn remove_feature(&mut self, feature_index: usize) {
+        self.migrate_features();
+        remove_feature_flag(&mut self.features, feature_index);
+    }
+
+    pub fn assert_current_version(&self) -> Result<()> {
+        if self.version != CANDY_MACHINE_VERSION {
+            return err!(CandyError::CandyMachineNeedsMigration);
+        }
+
+        Ok(())
+    }
+
     pub fn assert_not_minted(&self, candy_error: Error) -> Result<()> {
         if self.items_redeemed > 0 {
             Err(candy_error)
@@ -57,6 +315,35 @@ impl CandyMachine {
         }
     }
 
+    /// Returns the wallet which receives the platform fee, if a platform fee
+    /// should be taken on each mint.
+    pub fn get_platform_fee_wallet(&self) -> Option<Pubkey> {
+        match self.platform_fee_basis_points {
+            0 => None,
+            _ => self.platform_fee_wallet,
+        }
+    }
+
+    /// Returns the index of the configured mint phase which is live at `now`,
+    /// if the candy machine uses configurable mint phases.
+    pub fn get_active_mint_phase_index(&self, now: i64) -> Option<usize> {
+        if now >= self.data.public_sale_end_time {
+            return None;
+        }
+
+        // Phases are validated to be sorted by start time, so the last phase
+        // which has started is the active one.
+        self.data
+            .mint_phases
+            .iter()
+            .rposition(|mint_phase| now >= mint_phase.start_time)
+    }
+
+    pub fn get_active_mint_phase_settings(&self, now: i64) -> Option<&MintPhaseSettings> {
+        self.get_active_mint_phase_index(now)
+            .map(|index| &self.data.mint_phases[index])
+    }
+
     pub fn get_mint_phase(&self, now: i64) -> MintPhase {
         let allowlist_sale_start_time = self.data.allowlist_sale_start_time;
         let public_sale_start_time = self.data.public_sale_start_time;
@@ -66,6 +353,13 @@ impl CandyMachine {
             return MintPhase::Expired;
         }
 
+        if !self.data.mint_phases.is_empty() {
+            return match self.get_active_mint_phase_settings(now) {
+                Some(mint_phase_settings) => mint_phase_settings.get_mint_phase(),
+                None => MintPhase::Premint,
+            };
+        }
+
         if now >= public_sale_start_time {
             return MintPhase::Public;
         }
@@ -82,11 +376,120 @@ impl CandyMachine {
         }
     }
 
-    pub fn get_mint_price(&self, mint_phase: &MintPhase) -> u64 {
+    /// Returns the price override of the Merkle allowlist root at root_index, if any.
+    pub fn get_merkle_allowlist_root_price(&self, root_index: usize) -> Option<u64> {
+        self.data
+            .merkle_allowlist_root_list
+            .get(root_index)
+            .and_then(|merkle_allowlist_root| merkle_allowlist_root.price)
+    }
+
+    /// Returns the start time of the public sale which is live at `now`. If
+    /// the candy machine uses configurable mint phases, this is the start
+    /// time of the active mint phase.
+    pub fn get_public_sale_start_time(&self, now: i64) -> i64 {
+        self.get_active_mint_phase_settings(now)
+            .map_or(self.data.public_sale_start_time, |mint_phase_settings| {
+                mint_phase_settings.start_time
+            })
+    }
+
+    /// Returns the Dutch auction price at `now`, if the candy machine has a
+    /// Dutch auction and the public sale is live.
+    pub fn get_dutch_auction_price(&self, mint_phase: &MintPhase, now: i64) -> Option<u64> {
+        if mint_phase != &MintPhase::Public {
+            return None;
+        }
+
+        self.data
+            .dutch_auction_settings
+            .as_ref()
+            .map(|settings| settings.get_price(self.get_public_sale_start_time(now), now))
+    }
+
+    /// Returns the Dutch auction settings if the public sale is live and the
+    /// Dutch auction has rebates enabled.
+    pub fn get_dutch_auction_rebate_settings(
+        &self,
+        mint_phase: &MintPhase,
+    ) -> Option<&DutchAuctionSettings> {
+        if mint_phase != &MintPhase::Public {
+            return None;
+        }
+
+        self.data
+            .dutch_auction_settings
+            .as_ref()
+            .filter(|settings| settings.rebate_enabled)
+    }
+
+    /// The sale is over once the public sale ends or the candy machine sells
+    /// out.
+    pub fn is_sale_over(&self, now: i64) -> bool {
+        now >= self.data.public_sale_end_time || self.items_redeemed >= self.data.items_available
+    }
+
+    /// The Dutch auction ends when the sale ends or the candy machine sells out.
+    pub fn has_dutch_auction_ended(&self, now: i64) -> bool {
+        self.is_sale_over(now)
+    }
+
+    /// Returns the bonding curve price of the item at items_redeemed, if the
+    /// candy machine has a bonding curve and the public sale is live.
+    pub fn get_bonding_curve_price(
+        &self,
+        mint_phase: &MintPhase,
+        items_redeemed: u64,
+    ) -> Option<u64> {
+        if mint_phase != &MintPhase::Public {
+            return None;
+        }
+
+        self.data
+            .bonding_curve_settings
+            .as_ref()
+            .map(|settings| settings.get_price(items_redeemed))
+    }
+
+    /// Returns the total bonding curve price of the next `quantity` items, if
+    /// the candy machine has a bonding curve and the public sale is live.
+    pub fn get_bonding_curve_total_price(
+        &self,
+        mint_phase: &MintPhase,
+        quantity: u64,
+    ) -> Option<u64> {
+        (0..quantity).try_fold(0u64, |total_price, offset| {
+            self.get_bonding_curve_price(mint_phase, self.items_redeemed.saturating_add(offset))
+                .map(|price| total_price.saturating_add(price))
+        })
+    }
+
+    /// Returns the price of the item after the next one. This only differs
+    /// from the current mint price if the candy machine has a bonding curve.
+    pub fn get_next_mint_price(&self, mint_phase: &MintPhase, now: i64) -> u64 {
+        self.get_bonding_curve_price(mint_phase, self.items_redeemed.saturating_add(1))
+            .unwrap_or_else(|| self.get_mint_price(mint_phase, now))
+    }
+
+    pub fn get_mint_price(&self, mint_phase: &MintPhase, now: i64) -> u64 {
         let price = self.data.price;
         let premint_price = self.data.premint_price;
         let allowlist_price = self.data.allowlist_price;
 
+        if let Some(dutch_auction_price) = self.get_dutch_auction_price(mint_phase, now) {
+            return dutch_auction_price;
+        }
+
+        if let Some(bonding_curve_price) =
+            self.get_bonding_curve_price(mint_phase, self.items_redeemed)
+        {
+            return bonding_curve_price;
+        }
+
+        if let Some(mint_phase_settings) = self.get_active_mint_phase_settings(now) {
+            return mint_phase_settings.price;
+        }
+
         match mint_phase {
             MintPhase::Premint => premint_price.unwrap_or(price),
             MintPhase::Allowlist => allowlist_price.unwrap_or(price),
@@ -126,6 +529,114 @@ pub const HIDDEN_SETTINGS_SPACE: usize = 1 + // Option
 4 + MAX_URI_LENGTH + // uri length,
 32; // hash
 
+/// Shared prefixes of the config line names and uris. Config lines then only
+/// store their suffixes, zero padded to name_length and uri_length bytes
+/// without a length prefix.
+#[derive(AnchorSerialize, AnchorDeserialize, Clone, Default, Debug, PartialEq, Eq)]
+pub struct ConfigLineSettings {
+    pub prefix_name: String,
+    // Max length of the name suffix stored in each config line.
+    pub name_length: u32,
+    pub prefix_uri: String,
+    // Max length of the uri suffix stored in each config line.
+    pub uri_length: u32,
+}
+
+pub const CONFIG_LINE_SETTINGS_SPACE: usize = 1 + // Option
+4 + MAX_NAME_LENGTH + // prefix_name
+4 + // name_length
+4 + MAX_URI_LENGTH + // prefix_uri
+4; // uri_length
+
+impl ConfigLineSettings {
+    pub fn get_config_line_size(&self) -> usize {
+        self.name_length as usize + self.uri_length as usize
+    }
+}
+
+/// An account whose data holds randomness written by someone the candy machine's
+/// authority trusts, e.g. an off-chain oracle. It's passed in place of the
+/// SlotHashes sysvar when minting, and its 32 random bytes starting at offset
+/// pick the config line. The program only checks the account's address: it
+/// doesn't check its owner, whether its value is fresh or any proof (it is not
+/// a VRF), so the randomness is only as unpredictable as whoever writes it.
+#[derive(AnchorSerialize, AnchorDeserialize, Clone, Default, Debug, PartialEq, Eq)]
+pub struct TrustedRandomnessAccount {
+    pub address: Pubkey,
+    pub offset: u32,
+}
+
+pub const TRUSTED_RANDOMNESS_ACCOUNT_SPACE: usize = 1 + // Option
+32 + // address
+4; // offset
+
+/// The order config lines are minted in. Each variant is stored as a single
+/// byte, where 0 and 1 match the bool which used to be stored in its place.
+#[derive(AnchorSerialize, AnchorDeserialize, Clone, Copy, Debug, PartialEq, Eq)]
+pub enum MintOrder {
+    Random,
+    // Sequential in the premint phase and random after it.
+    SequentialPremint,
+    Sequential,
+    // Sequential in every phase, and a seed committed to in the
+    // shuffle_settings and revealed after the sale defines the final order,
+    // see get_shuffled_order. Only supported with hidden settings. The program
+    // never applies the final order: NFTs are minted sequentially with the
+    // hidden settings, and the final order is only applied off-chain when
+    // their metadata is updated by the reveal.
+    ShuffledAtReveal,
+}
+
+impl Default for MintOrder {
+    fn default() -> Self {
+        MintOrder::Random
+    }
+}
+
+/// Commitment to the seed of a shuffled mint order.
+#[derive(AnchorSerialize, AnchorDeserialize, Clone, Default, Debug, PartialEq, Eq)]
+pub struct ShuffleSettings {
+    // Keccak hash of the seed.
+    pub seed_commitment: [u8; 32],
+    // Set by reveal_shuffle_seed once the candy machine sells out or its sale
+    // ends.
+    pub seed: Option<[u8; 32]>,
+}
+
+pub const SHUFFLE_SETTINGS_SPACE: usize = 1 + // Option
+32 + // seed_commitment
+1 + 32; // seed
+
+/// Per wallet limits for the premint and allowlist phases, where 0 means
+/// unlimited.
+#[derive(AnchorSerialize, AnchorDeserialize, Clone, Copy, Default, Debug, PartialEq, Eq)]
+pub struct PhaseLimits {
+    // Only omni minters can mint during the premint phase, so this limits
+    // each omni mint wallet.
+    pub premint_limit_per_address: u16,
+    // Limits Merkle allowlist and SPL token allowlist mints together. Merkle
+    // allowlist buyers are still limited to the amount in their proof.
+    pub allowlist_limit_per_address: u16,
+}
+
+pub const PHASE_LIMITS_SPACE: usize = 2 + // premint_limit_per_address
+2; // allowlist_limit_per_address
+
+/// Limits which apply across mint phases, where 0 means unlimited. Omni
+/// minters are exempt from both.
+#[derive(AnchorSerialize, AnchorDeserialize, Clone, Copy, Default, Debug, PartialEq, Eq)]
+pub struct MintCaps {
+    // Per wallet limit on the total bought in every mint phase, checked on top
+    // of the limit of each phase.
+    pub total_limit_per_address: u16,
+    // Maximum number of items sold during the allowlist phase, so items sold
+    // during premint don't count towards it.
+    pub allowlist_items_cap: u64,
+}
+
+pub const MINT_CAPS_SPACE: usize = 2 + // total_limit_per_address
+8; // allowlist_items_cap
+
 pub const BUYER_INFO_ACCOUNT_PREFIX: &str = "buyer_info_account";
 
 #[account]
@@ -135,12 +646,114 @@ pub struct BuyerInfoAccount {
     pub number_bought_merkle_allowlist_phase: u16,
     /// Number bought during the public phase.
     pub number_bought_public_phase: u16,
+    /// Number bought during each configured mint phase, by mint phase index.
+    pub number_bought_per_mint_phase: [u16; MAX_MINT_PHASES],
+    /// Total paid during a Dutch auction with rebates enabled, which has not
+    /// been rebated yet.
+    pub dutch_auction_amount_paid: u64,
+    /// Number bought during a Dutch auction with rebates enabled, which has
+    /// not been rebated yet.
+    pub dutch_auction_number_bought: u16,
+    /// Number bought during the premint phase.
+    pub number_bought_premint_phase: u16,
+    /// Number bought during the allowlist phase, with either allowlist.
+    pub number_bought_allowlist_phase: u16,
+    /// Number bought in every mint phase, counted since the total limit per
+    /// address was added.
+    pub number_bought_total: u16,
 }
 
+// Note: The counters after number_bought_public_phase were all carved out of
+// the original 64 bytes of padding, so existing accounts deserialize them as
+// zero and keep their size.
 pub const BUYER_INFO_ACCOUNT_SPACE: usize = 8 + // Discriminator
 2 + // number_bought_merkle_allowlist_phase
 2 + // number_bought_public_phase
-64; // padding
+2 * MAX_MINT_PHASES + // number_bought_per_mint_phase
+8 + // dutch_auction_amount_paid
+2 + // dutch_auction_number_bought
+2 + // number_bought_premint_phase
+2 + // number_bought_allowlist_phase
+2 + // number_bought_total
+64 - 2 * MAX_MINT_PHASES
+    - 8
+    - 2
+    - 2
+    - 2
+    - 2; // padding
+
+impl BuyerInfoAccount {
+    /// Uncounts a refunded NFT from the counters its mint incremented.
+    pub fn release_purchase(&mut self, purchase_counters: &PurchaseCounters) -> Result<()> {
+        let mut counters = vec![];
+        if purchase_counters.merkle_allowlist_phase {
+            counters.push(&mut self.number_bought_merkle_allowlist_phase);
+        }
+        if purchase_counters.public_phase {
+            counters.push(&mut self.number_bought_public_phase);
+        }
+        if let Some(mint_phase_index) = purchase_counters.mint_phase_index {
+            counters.push(&mut self.number_bought_per_mint_phase[mint_phase_index as usize]);
+        }
+        if purchase_counters.premint_phase {
+            counters.push(&mut self.number_bought_premint_phase);
+        }
+        if purchase_counters.allowlist_phase {
+            counters.push(&mut self.number_bought_allowlist_phase);
+        }
+        if purchase_counters.total {
+            counters.push(&mut self.number_bought_total);
+        }
+
+        for counter in counters {
+            *counter = counter
+                .checked_sub(1)
+                .ok_or(CandyError::NumericalOverflowError)?;
+        }
+        Ok(())
+    }
+}
+
+/// The purchase counters the mint of an NFT incremented, so refunding the NFT
+/// can decrement them again.
+#[derive(AnchorSerialize, AnchorDeserialize, Clone, Default, Debug, PartialEq, Eq)]
+pub struct PurchaseCounters {
+    /// Counted in the BuyerInfoAccount's number_bought_merkle_allowlist_phase.
+    pub merkle_allowlist_phase: bool,
+    /// Counted in the BuyerInfoAccount's number_bought_public_phase.
+    pub public_phase: bool,
+    /// Counted in the BuyerInfoAccount's number_bought_per_mint_phase at this
+    /// index.
+    pub mint_phase_index: Option<u8>,
+    /// Counted in the BuyerInfoAccount's number_bought_premint_phase.
+    pub premint_phase: bool,
+    /// Counted in the BuyerInfoAccount's number_bought_allowlist_phase.
+    pub allowlist_phase: bool,
+    /// Counted in the BuyerInfoAccount's number_bought_total.
+    pub total: bool,
+    /// Counted in the candy machine's allowlist_items_sold.
+    pub allowlist_items_sold: bool,
+}
+
+impl PurchaseCounters {
+    /// Returns whether any of the counters are in the BuyerInfoAccount.
+    pub fn is_buyer_info_account_counted(&self) -> bool {
+        self.merkle_allowlist_phase
+            || self.public_phase
+            || self.mint_phase_index.is_some()
+            || self.premint_phase
+            || self.allowlist_phase
+            || self.total
+    }
+}
+
+pub const PURCHASE_COUNTERS_SPACE: usize = 1 + // merkle_allowlist_phase
+1 + // public_phase
+1 + 1 + // mint_phase_index
+1 + // premint_phase
+1 + // allowlist_phase
+1 + // total
+1; // allowlist_items_sold
 
 #[derive(Clone, Debug, PartialEq, Eq)]
 pub enum MintPhase {
@@ -150,6 +763,242 @@ pub enum MintPhase {
     Expired,
 }
 
+/// Settings for a single configurable mint phase. A phase runs from its
+/// start_time until the start_time of the next phase (or the sale end time).
+#[derive(AnchorSerialize, AnchorDeserialize, Clone, Debug, PartialEq, Eq)]
+pub struct MintPhaseSettings {
+    pub start_time: i64,
+    pub price: u64,
+    pub allowlist_source: MintPhaseAllowlistSource,
+    // Denotes the limit per address for this phase, 0 if unlimited.
+    pub limit_per_address: u16,
+}
+
+pub const MINT_PHASE_SETTINGS_SPACE: usize = 8 + // start_time
+8 + // price
+1 + // allowlist_source
+2; // limit_per_address
+
+impl MintPhaseSettings {
+    pub fn get_mint_phase(&self) -> MintPhase {
+        match self.allowlist_source {
+            MintPhaseAllowlistSource::Public => MintPhase::Public,
+            MintPhaseAllowlistSource::MerkleAllowlist
+            | MintPhaseAllowlistSource::SplTokenAllowlist
+            | MintPhaseAllowlistSource::CollectionAllowlist => MintPhase::Allowlist,
+        }
+    }
+}
+
+/// Which allowlist (if any) gates minting during a configured mint phase.
+#[derive(AnchorSerialize, AnchorDeserialize, Clone, Eq, PartialEq, Debug)]
+pub enum MintPhaseAllowlistSource {
+    Public,
+    MerkleAllowlist,
+    SplTokenAllowlist,
+    CollectionAllowlist,
+}
+
+/// A Merkle allowlist root hash with optional settings which bind the root to
+/// its own window (and price) within the allowlist phase.
+#[derive(AnchorSerialize, AnchorDeserialize, Clone, Default, Debug, PartialEq, Eq)]
+pub struct MerkleAllowlistRoot {
+    pub root: [u8; 32],
+    pub start_time: Option<i64>,
+    pub end_time: Option<i64>,
+    // Overrides the mint price for buyers minting with a proof for this root.
+    pub price: Option<u64>,
+}
+
+pub const MERKLE_ALLOWLIST_ROOT_SPACE: usize = MERKLE_ROOT_SIZE + // root
+9 + // start_time
+9 + // end_time
+9; // price
+
+impl MerkleAllowlistRoot {
+    pub fn is_live(&self, now: i64) -> bool {
+        let has_started = self.start_time.map_or(true, |start_time| now >= start_time);
+        let has_ended = self.end_time.map_or(false, |end_time| now >= end_time);
+        has_started && !has_ended
+    }
+}
+
+impl From<[u8; 32]> for MerkleAllowlistRoot {
+    fn from(root: [u8; 32]) -> Self {
+        MerkleAllowlistRoot {
+            root,
+            start_time: None,
+            end_time: None,
+            price: None,
+        }
+    }
+}
+
+/// A wallet which receives a share of the primary sale proceeds.
+#[derive(AnchorSerialize, AnchorDeserialize, Clone, Debug, PartialEq, Eq)]
+pub struct PrimarySaleSplit {
+    pub address: Pubkey,
+    // In basis points, all splits must add up to 10000.
+    pub basis_points: u16,
+}
+
+pub const PRIMARY_SALE_SPLIT_SPACE: usize = 32 + // address
+2; // basis_points
+
+/// An additional currency buyers can pay with. The price is the same in every
+/// mint phase, so payment options can't be combined with phase or merkle
+/// allowlist root prices.
+#[derive(AnchorSerialize, AnchorDeserialize, Clone, Debug, PartialEq, Eq)]
+pub struct PaymentOption {
+    // None to pay in SOL.
+    pub treasury_mint: Option<Pubkey>,
+    // The wallet, or token account for an SPL token, which receives payments.
+    pub treasury_wallet: Pubkey,
+    pub price: u64,
+}
+
+pub const PAYMENT_OPTION_SPACE: usize = 33 + // treasury_mint
+32 + // treasury_wallet
+8; // price
+
+/// Settings for minting programmable NFTs, which enforce royalties through the
+/// token metadata program. Token-2022 mints are not supported, every NFT is
+/// minted through the SPL Token program.
+#[derive(AnchorSerialize, AnchorDeserialize, Clone, Debug, PartialEq, Eq)]
+pub struct ProgrammableNftSettings {
+    // The token auth rules rule set which governs transfers, or None to
+    // use no rule set.
+    pub rule_set: Option<Pubkey>,
+}
+
+pub const PROGRAMMABLE_NFT_SETTINGS_SPACE: usize = 1 + // Option
+33; // rule_set
+
+/// Settings for minting compressed NFTs with Bubblegum. Each NFT is a leaf of
+/// the Merkle tree, so no mint, token account, metadata or master edition is
+/// created.
+#[derive(AnchorSerialize, AnchorDeserialize, Clone, Debug, PartialEq, Eq)]
+pub struct CompressedNftSettings {
+    // The Bubblegum tree to mint into. The candy machine creator PDA must be
+    // the tree creator or its tree delegate.
+    pub merkle_tree: Pubkey,
+}
+
+pub const COMPRESSED_NFT_SETTINGS_SPACE: usize = 1 + // Option
+32; // merkle_tree
+
+/// A declining price schedule for the public sale. The price starts at
+/// start_price when the public sale starts and drops by price_decrement every
+/// decrement_interval seconds, until it reaches floor_price.
+#[derive(AnchorSerialize, AnchorDeserialize, Clone, Debug, PartialEq, Eq)]
+pub struct DutchAuctionSettings {
+    pub start_price: u64,
+    pub floor_price: u64,
+    pub price_decrement: u64,
+    // In seconds.
+    pub decrement_interval: i64,
+    // If true, the amount paid above the floor price is held by the candy
+    // machine, and once the auction ends each buyer can claim back the
+    // difference between what they paid and the final clearing price.
+    pub rebate_enabled: bool,
+}
+
+pub const DUTCH_AUCTION_SETTINGS_SPACE: usize = 1 + // Option
+8 + // start_price
+8 + // floor_price
+8 + // price_decrement
+8 + // decrement_interval
+1; // rebate_enabled
+
+impl DutchAuctionSettings {
+    pub fn get_price(&self, start_time: i64, now: i64) -> u64 {
+        let elapsed = now.saturating_sub(start_time).max(0);
+        let decrements = (elapsed / self.decrement_interval) as u64;
+        self.start_price
+            .saturating_sub(decrements.saturating_mul(self.price_decrement))
+            .max(self.floor_price)
+    }
+}
+
+/// A price curve for the public sale which rises as items are redeemed.
+#[derive(AnchorSerialize, AnchorDeserialize, Clone, Debug, PartialEq, Eq)]
+pub struct BondingCurveSettings {
+    pub mode: BondingCurveMode,
+    // The price of the first item.
+    pub start_price: u64,
+    pub price_increment: u64,
+    // Only used by the Step mode.
+    pub items_per_step: u64,
+}
+
+pub const BONDING_CURVE_SETTINGS_SPACE: usize = 1 + // Option
+1 + // mode
+8 + // start_price
+8 + // price_increment
+8; // items_per_step
+
+impl BondingCurveSettings {
+    pub fn get_price(&self, items_redeemed: u64) -> u64 {
+        let increments = match self.mode {
+            BondingCurveMode::Linear => items_redeemed,
+            BondingCurveMode::Step => items_redeemed / self.items_per_step,
+        };
+
+        self.start_price
+            .saturating_add(increments.saturating_mul(self.price_increment))
+    }
+}
+
+#[derive(AnchorSerialize, AnchorDeserialize, Clone, Eq, PartialEq, Debug)]
+pub enum BondingCurveMode {
+    // The price increases by price_increment with every item redeemed.
+    Linear,
+    // The price increases by price_increment every items_per_step items.
+    Step,
+}
+
+/// Running totals of a Dutch auction with rebates enabled.
+#[derive(AnchorSerialize, AnchorDeserialize, Clone, Default, Debug, PartialEq, Eq)]
+pub struct DutchAuctionRebateState {
+    // The lowest price paid during the auction, which is what every buyer
+    // ends up paying once they claim their rebate.
+    pub clearing_price: u64,
+    pub items_sold: u64,
+    // Whether the proceeds held above the floor price have been released to
+    // the treasury wallet.
+    pub proceeds_released: bool,
+    // The amount paid above the floor price which the candy machine still
+    // holds, which is owed to buyers as rebates and to the treasury wallet as
+    // proceeds.
+    pub amount_held: u64,
+}
+
+pub const DUTCH_AUCTION_REBATE_STATE_SPACE: usize = 8 + // clearing_price
+8 + // items_sold
+1 + // proceeds_released
+8; // amount_held
+
+impl DutchAuctionRebateState {
+    /// Returns the proceeds held above the floor price which belong to the
+    /// treasury wallet once the auction ends.
+    pub fn get_proceeds(&self, floor_price: u64) -> Result<u64> {
+        self.clearing_price
+            .checked_sub(floor_price)
+            .and_then(|amount| amount.checked_mul(self.items_sold))
+            .ok_or_else(|| CandyError::NumericalOverflowError.into())
+    }
+
+    /// Records an amount paid out of the amount held, as a rebate or as the
+    /// proceeds.
+    pub fn release_amount_held(&mut self, amount: u64) -> Result<()> {
+        self.amount_held = self
+            .amount_held
+            .checked_sub(amount)
+            .ok_or(CandyError::NumericalOverflowError)?;
+        Ok(())
+    }
+}
+
 #[derive(AnchorSerialize, AnchorDeserialize, Clone, Default, Debug)]
 pub struct BuyerMerkleAllowlistProofData {
     pub amount: u16,
@@ -173,6 +1022,51 @@ pub enum SplTokenAllowlistMode {
     NeverBurn,
 }
 
+/// An SPL token the recipient must hold to mint during the allowlist phase.
+#[derive(AnchorSerialize, AnchorDeserialize, Clone, Debug, PartialEq, Eq)]
+pub struct SplTokenAllowlistToken {
+    pub mint: Pubkey,
+    // Tokens burned for each NFT minted, 0 to only check the balance.
+    pub burn_amount: u64,
+    // Balance the recipient must hold for each NFT minted, which is at least
+    // the burn amount.
+    pub min_balance: u64,
+}
+
+pub const SPL_TOKEN_ALLOWLIST_TOKEN_SPACE: usize = 32 + // mint
+8 + // burn_amount
+8; // min_balance
+
+/// Gates the allowlist phase on holding any NFT whose metadata has a verified
+/// collection matching collection_mint.
+#[derive(AnchorSerialize, AnchorDeserialize, Clone, Debug, PartialEq, Eq)]
+pub struct CollectionAllowlistSettings {
+    pub collection_mint: Pubkey,
+    // If true, each gating NFT can only be used for one mint, which is tracked
+    // by a CollectionAllowlistRecord keyed by the gating NFT's mint.
+    pub use_once: bool,
+}
+
+pub const COLLECTION_ALLOWLIST_SETTINGS_SPACE: usize = 1 + // Option
+32 + // collection_mint
+1; // use_once
+
+/// Marks an NFT from the allowlist collection as used, if the collection
+/// allowlist only allows each NFT to be used for one mint.
+#[account]
+#[derive(Default, Debug, PartialEq, Eq)]
+pub struct CollectionAllowlistRecord {
+    pub candy_machine: Pubkey, // 32
+    pub gating_mint: Pubkey,   // 32
+    pub recipient: Pubkey,     // 32
+}
+
+impl CollectionAllowlistRecord {
+    pub const SIZE: usize = 8 + 32 + 32 + 32;
+
+    pub const PREFIX: &'static str = "collection_allowlist_record";
+}
+
 /// Collection PDA account
 #[account]
 #[derive(Default, Debug)]
@@ -185,6 +1079,21 @@ impl CollectionPda {
     pub const PREFIX: &'static str = "collection";
 }
 
+/// Open edition PDA account, which holds the master edition token that print
+/// editions are minted from.
+#[account]
+#[derive(Default, Debug, PartialEq, Eq)]
+pub struct OpenEditionPda {
+    pub mint: Pubkey,          // 32
+    pub candy_machine: Pubkey, // 32
+}
+
+impl OpenEditionPda {
+    pub const SIZE: usize = 8 + 32 + 32;
+
+    pub const PREFIX: &'static str = "open_edition";
+}
+
 /// Collection PDA account
 #[account]
 #[derive(Default, Debug, PartialEq, Eq)]
@@ -230,3 +1139,28 @@ impl FreezePda {
         Ok(())
     }
 }
+
+/// Records the payment held by the FreezePda for an NFT minted while the
+/// freeze feature is active, so the NFT can be refunded before it is thawed.
+/// The platform fee is paid out when the NFT is minted, so amount excludes it
+/// and it isn't refunded.
+#[account]
+#[derive(Default, Debug, PartialEq, Eq)]
+pub struct RefundReceipt {
+    pub candy_machine: Pubkey,         // 32
+    pub mint: Pubkey,                  // 32
+    pub payer: Pubkey,                 // 32
+    pub treasury_mint: Option<Pubkey>, // 1 + 32
+    pub amount: u64,                   // 8
+    // None if the candy machine uses hidden settings.
+    pub config_index: Option<u32>, // 1 + 4
+    // The owner of the BuyerInfoAccount the NFT was counted in.
+    pub recipient: Pubkey,                   // 32
+    pub purchase_counters: PurchaseCounters, // PURCHASE_COUNTERS_SPACE
+}
+
+impl RefundReceipt {
+    pub const SIZE: usize = 8 + 32 + 32 + 32 + 1 + 32 + 8 + 1 + 4 + 32 + PURCHASE_COUNTERS_SPACE;
+
+    pub const PREFIX: &'static str = "refund_receipt";
+}
@@ -1,8 +1,11 @@
-use std::str::from_utf8_unchecked;
-
 use std::result::Result as StandardResult;
 
 use anchor_lang::prelude::*;
+use arrayref::array_ref;
+use mpl_token_metadata::{
+    instruction::{builders::VerifyBuilder, InstructionBuilder, VerificationArgs},
+    state::{TokenStandard, MAX_NAME_LENGTH, MAX_URI_LENGTH, TOKEN_STANDARD_INDEX},
+};
 use solana_program::{
     account_info::AccountInfo,
     program::{invoke, invoke_signed},
@@ -19,7 +22,14 @@ use crate::constants::ANTI_BOT_DEV_AUTHORITY;
 #[cfg(not(feature = "use-test-anti-bot-authority"))]
 use crate::constants::ANTI_BOT_MAINNET_AUTHORITY;
 
-use crate::{BuyerMerkleAllowlistProofData, CandyError, CandyMachine, CandyMachineData, MintPhase};
+use crate::{
+    constants::{
+        LEGACY_FEATURE_FLAGS_LEN, MAX_MINT_PHASES, MAX_PAYMENT_OPTIONS, MAX_PRIMARY_SALE_SPLITS,
+        MAX_SPL_TOKEN_ALLOWLIST_TOKENS, PRIMARY_SALE_SPLITS_TOTAL_BASIS_POINTS,
+    },
+    BondingCurveMode, BuyerInfoAccount, BuyerMerkleAllowlistProofData, CandyError, CandyMachine,
+    CandyMachineData, MintOrder, MintPhase, MintPhaseAllowlistSource, MintPhaseSettings,
+};
 
 pub fn assert_initialized<T: Pack + IsInitialized>(account_info: &AccountInfo) -> Result<T> {
     let account: T = T::unpack_unchecked(&account_info.data.borrow())?;
@@ -34,16 +44,14 @@ pub fn cmp_pubkeys(a: &Pubkey, b: &Pubkey) -> bool {
     sol_memcmp(a.as_ref(), b.as_ref(), PUBKEY_BYTES) == 0
 }
 
-pub fn is_omni_minter<'info>(
-    buyer: &Signer<'info>,
-    candy_machine: &Account<'info, CandyMachine>,
-) -> bool {
-    candy_machine.data.omni_mint_wallets.contains(&buyer.key())
+pub fn is_omni_minter(buyer: &Pubkey, candy_machine: &CandyMachine) -> bool {
+    candy_machine.data.omni_mint_wallets.contains(buyer)
 }
 
 pub fn validate_mint_phase<'info>(
-    buyer: &Signer<'info>,
+    buyer: &Pubkey,
     mint_phase: &MintPhase,
+    active_mint_phase_settings: Option<&MintPhaseSettings>,
     candy_machine: &Account<'info, CandyMachine>,
     buyer_merkle_allowlist_proof_data: &Option<BuyerMerkleAllowlistProofData>,
 ) -> StandardResult<(), CandyError> {
@@ -54,7 +62,11 @@ pub fn validate_mint_phase<'info>(
     match mint_phase {
         MintPhase::Expired => Err(CandyError::CandyMachinePublicSaleEnded),
         MintPhase::Premint => {
-            let error = if candy_machine.data.allowlist_sale_start_time.is_some() {
+            let has_allowlist_phase = match candy_machine.data.mint_phases.first() {
+                Some(first_mint_phase) => first_mint_phase.get_mint_phase() == MintPhase::Allowlist,
+                None => candy_machine.data.allowlist_sale_start_time.is_some(),
+            };
+            let error = if has_allowlist_phase {
                 CandyError::CandyMachineAllowlistSaleNotLive
             } else {
                 CandyError::CandyMachinePublicSaleNotLive
@@ -62,8 +74,23 @@ pub fn validate_mint_phase<'info>(
             Err(error)
         }
         MintPhase::Allowlist => {
-            let allowlist_settings_present = buyer_merkle_allowlist_proof_data.is_some()
-                || candy_machine.data.spl_token_allowlist_settings.is_some();
+            let merkle_allowlist_present = buyer_merkle_allowlist_proof_data.is_some();
+            let spl_token_allowlist_present = candy_machine.data.is_spl_token_allowlist_enabled();
+            let collection_allowlist_present =
+                candy_machine.data.collection_allowlist_settings.is_some();
+
+            let allowlist_settings_present = match active_mint_phase_settings
+                .map(|settings| &settings.allowlist_source)
+            {
+                Some(MintPhaseAllowlistSource::MerkleAllowlist) => merkle_allowlist_present,
+                Some(MintPhaseAllowlistSource::SplTokenAllowlist) => spl_token_allowlist_present,
+                Some(MintPhaseAllowlistSource::CollectionAllowlist) => collection_allowlist_present,
+                _ => {
+                    merkle_allowlist_present
+                        || spl_token_allowlist_present
+                        || collection_allowlist_present
+                }
+            };
 
             if !allowlist_settings_present {
                 Err(CandyError::CandyMachineAllowlistSaleNotLive)
@@ -75,10 +102,80 @@ pub fn validate_mint_phase<'info>(
     }
 }
 
+/// Checks that minting quantity more items during the allowlist phase keeps
+/// the items sold during the allowlist phase within the allowlist items cap.
+pub fn validate_allowlist_items_cap(
+    candy_machine: &CandyMachine,
+    mint_phase: &MintPhase,
+    is_recipient_omni_minter: bool,
+    quantity: u64,
+) -> Result<()> {
+    let allowlist_items_cap = candy_machine.data.mint_caps.allowlist_items_cap;
+    if mint_phase != &MintPhase::Allowlist || is_recipient_omni_minter || allowlist_items_cap == 0 {
+        return Ok(());
+    }
+
+    let allowlist_items_sold = candy_machine
+        .allowlist_items_sold
+        .checked_add(quantity)
+        .ok_or(CandyError::NumericalOverflowError)?;
+    require!(
+        allowlist_items_sold <= allowlist_items_cap,
+        CandyError::AllowlistItemsCapReached
+    );
+    Ok(())
+}
+
+/// Gasless mints, where a third party pays and the recipient doesn't sign,
+/// are only supported when the mint doesn't need the recipient's authority.
+/// Freezing the NFT delegates the recipient's token account and the SPL token
+/// allowlist burns the recipient's allowlist tokens, so both require the
+/// recipient to sign.
+pub fn validate_recipient_signature(
+    candy_machine: &CandyMachine,
+    recipient: &AccountInfo,
+    freezes_nft: bool,
+    burns_spl_token_allowlist_tokens: bool,
+) -> Result<()> {
+    if recipient.is_signer {
+        return Ok(());
+    }
+
+    let burns_allowlist_tokens = burns_spl_token_allowlist_tokens
+        && candy_machine
+            .data
+            .get_spl_token_allowlist_tokens()
+            .iter()
+            .any(|spl_token_allowlist_token| spl_token_allowlist_token.burn_amount > 0);
+    if freezes_nft || burns_allowlist_tokens {
+        return err!(CandyError::RecipientMustSign);
+    }
+    Ok(())
+}
+
+/// Returns the number the buyer has bought in every mint phase including this
+/// purchase, if it's within the total limit per address.
+pub fn validate_total_limit_per_address(
+    candy_machine: &CandyMachine,
+    buyer_info_account: &BuyerInfoAccount,
+    quantity: u16,
+) -> Result<u16> {
+    let number_bought_total = buyer_info_account
+        .number_bought_total
+        .checked_add(quantity)
+        .ok_or(CandyError::NumericalOverflowError)?;
+    require!(
+        number_bought_total <= candy_machine.data.mint_caps.total_limit_per_address,
+        CandyError::BuyLimitPerAddressExceeded
+    );
+    Ok(number_bought_total)
+}
+
 pub fn validate_mint_phase_times(candy_machine_data: &CandyMachineData) -> Result<()> {
     let allowlist_sale_start_time = candy_machine_data.allowlist_sale_start_time;
     let public_sale_start_time = candy_machine_data.public_sale_start_time;
     let public_sale_end_time = candy_machine_data.public_sale_end_time;
+    let mint_phases = &candy_machine_data.mint_phases;
 
     if public_sale_start_time >= public_sale_end_time {
         return Err(CandyError::CandyMachineInvalidMintPhases.into());
@@ -90,9 +187,202 @@ pub fn validate_mint_phase_times(candy_machine_data: &CandyMachineData) -> Resul
         }
     }
 
+    if mint_phases.len() > MAX_MINT_PHASES {
+        return Err(CandyError::TooManyMintPhases.into());
+    }
+
+    // Configured mint phases must be in strictly increasing start time order
+    // and all start before the sale ends.
+    let mut previous_start_time: Option<i64> = None;
+    for mint_phase in mint_phases.iter() {
+        if mint_phase.start_time >= public_sale_end_time {
+            return Err(CandyError::CandyMachineInvalidMintPhases.into());
+        }
+
+        if let Some(previous_start_time) = previous_start_time {
+            if mint_phase.start_time <= previous_start_time {
+                return Err(CandyError::CandyMachineInvalidMintPhases.into());
+            }
+        }
+
+        previous_start_time = Some(mint_phase.start_time);
+    }
+
+    Ok(())
+}
+
+pub fn validate_primary_sale_splits(candy_machine_data: &CandyMachineData) -> Result<()> {
+    let primary_sale_splits = &candy_machine_data.primary_sale_splits;
+
+    if primary_sale_splits.len() > MAX_PRIMARY_SALE_SPLITS {
+        return Err(CandyError::TooManyPrimarySaleSplits.into());
+    }
+
+    if primary_sale_splits.is_empty() {
+        return Ok(());
+    }
+
+    let mut total_basis_points: u16 = 0;
+    for primary_sale_split in primary_sale_splits.iter() {
+        if primary_sale_split.basis_points == 0 {
+            return Err(CandyError::InvalidPrimarySaleSplits.into());
+        }
+
+        total_basis_points = total_basis_points
+            .checked_add(primary_sale_split.basis_points)
+            .ok_or(CandyError::InvalidPrimarySaleSplits)?;
+    }
+
+    if total_basis_points != PRIMARY_SALE_SPLITS_TOTAL_BASIS_POINTS {
+        return Err(CandyError::InvalidPrimarySaleSplits.into());
+    }
+
+    Ok(())
+}
+
+pub fn validate_payment_options(candy_machine_data: &CandyMachineData) -> Result<()> {
+    let payment_options = &candy_machine_data.payment_options;
+
+    if payment_options.len() > MAX_PAYMENT_OPTIONS {
+        return Err(CandyError::TooManyPaymentOptions.into());
+    }
+
+    // Dynamic prices are in the treasury mint, so can't be converted to the
+    // fixed price of each payment option.
+    if !payment_options.is_empty()
+        && (candy_machine_data.dutch_auction_settings.is_some()
+            || candy_machine_data.bonding_curve_settings.is_some())
+    {
+        return Err(CandyError::InvalidPaymentOptions.into());
+    }
+
+    // The price of a payment option is the same in every mint phase, so it
+    // can't honor a discounted phase or merkle allowlist root price either.
+    let overrides_price = |price_override: Option<u64>| {
+        price_override.map_or(false, |price_override| {
+            price_override != candy_machine_data.price
+        })
+    };
+    let has_price_overrides = overrides_price(candy_machine_data.premint_price)
+        || overrides_price(candy_machine_data.allowlist_price)
+        || candy_machine_data
+            .mint_phases
+            .iter()
+            .any(|mint_phase| overrides_price(Some(mint_phase.price)))
+        || candy_machine_data
+            .merkle_allowlist_root_list
+            .iter()
+            .any(|merkle_allowlist_root| overrides_price(merkle_allowlist_root.price));
+    if !payment_options.is_empty() && has_price_overrides {
+        return Err(CandyError::InvalidPaymentOptions.into());
+    }
+
+    Ok(())
+}
+
+pub fn validate_dutch_auction_settings(
+    candy_machine_data: &CandyMachineData,
+    treasury_mint: Option<Pubkey>,
+) -> Result<()> {
+    if let Some(dutch_auction_settings) = &candy_machine_data.dutch_auction_settings {
+        if dutch_auction_settings.start_price < dutch_auction_settings.floor_price
+            || dutch_auction_settings.price_decrement == 0
+            || dutch_auction_settings.decrement_interval <= 0
+        {
+            return Err(CandyError::InvalidDutchAuctionSettings.into());
+        }
+
+        // Rebates are held in the candy machine account, so they are only
+        // supported for SOL mints.
+        if dutch_auction_settings.rebate_enabled && treasury_mint.is_some() {
+            return Err(CandyError::InvalidDutchAuctionSettings.into());
+        }
+    }
+
+    Ok(())
+}
+
+pub fn validate_bonding_curve_settings(candy_machine_data: &CandyMachineData) -> Result<()> {
+    if let Some(bonding_curve_settings) = &candy_machine_data.bonding_curve_settings {
+        if candy_machine_data.dutch_auction_settings.is_some() {
+            return Err(CandyError::InvalidBondingCurveSettings.into());
+        }
+
+        if bonding_curve_settings.mode == BondingCurveMode::Step
+            && bonding_curve_settings.items_per_step == 0
+        {
+            return Err(CandyError::InvalidBondingCurveSettings.into());
+        }
+    }
+
+    Ok(())
+}
+
+pub fn validate_compressed_nft_settings(candy_machine_data: &CandyMachineData) -> Result<()> {
+    if candy_machine_data.compressed_nft_settings.is_some()
+        && candy_machine_data.programmable_nft_settings.is_some()
+    {
+        return Err(CandyError::InvalidCompressedNftSettings.into());
+    }
+
     Ok(())
 }
 
+pub fn validate_open_edition_settings(candy_machine_data: &CandyMachineData) -> Result<()> {
+    if candy_machine_data.open_edition_enabled
+        && (candy_machine_data.hidden_settings.is_some()
+            || candy_machine_data.programmable_nft_settings.is_some()
+            || candy_machine_data.compressed_nft_settings.is_some())
+    {
+        return Err(CandyError::InvalidOpenEditionSettings.into());
+    }
+
+    Ok(())
+}
+
+pub fn validate_config_line_settings(candy_machine_data: &CandyMachineData) -> Result<()> {
+    if let Some(settings) = &candy_machine_data.config_line_settings {
+        if candy_machine_data.hidden_settings.is_some()
+            || candy_machine_data.open_edition_enabled
+            || settings.prefix_name.len() + settings.name_length as usize > MAX_NAME_LENGTH
+            || settings.prefix_uri.len() + settings.uri_length as usize > MAX_URI_LENGTH
+        {
+            return err!(CandyError::InvalidConfigLineSettings);
+        }
+    }
+
+    Ok(())
+}
+
+pub fn validate_shuffle_settings(candy_machine_data: &CandyMachineData) -> Result<()> {
+    let shuffled = candy_machine_data.mint_order == MintOrder::ShuffledAtReveal;
+    let valid = match &candy_machine_data.shuffle_settings {
+        Some(_) => shuffled && candy_machine_data.hidden_settings.is_some(),
+        None => !shuffled,
+    };
+    if !valid {
+        return err!(CandyError::InvalidShuffleSettings);
+    }
+
+    Ok(())
+}
+
+/// Returns the final order of a candy machine with a shuffled mint order,
+/// where the NFT minted at each position gets the item at that index. This is
+/// a Fisher-Yates shuffle driven by hashes of the revealed seed, so anyone can
+/// check the order against the seed commitment. Only used off-chain to reveal
+/// the NFTs, the program doesn't apply it.
+pub fn get_shuffled_order(seed: &[u8; 32], items_available: u64) -> Vec<u64> {
+    let mut order: Vec<u64> = (0..items_available).collect();
+    for i in (1..items_available).rev() {
+        let hash = solana_program::keccak::hashv(&[seed, &i.to_le_bytes()]);
+        let j = u64::from_le_bytes(*array_ref![hash.0, 0, 8]) % (i + 1);
+        order.swap(i as usize, j as usize);
+    }
+
+    order
+}
+
 pub fn assert_owned_by(account: &AccountInfo, owner: &Pubkey) -> Result<()> {
     if !cmp_pubkeys(account.owner, owner) {
         Err(CandyError::IncorrectOwner.into())
@@ -147,6 +437,28 @@ pub fn spl_token_transfer(params: TokenTransferParams<'_, '_>) -> Result<()> {
     result.map_err(|_| CandyError::TokenTransferFailed.into())
 }
 
+/// Accounts owned by this program, e.g. the candy machine or the FreezePda,
+/// can have lamports moved out of them directly.
+pub fn transfer_lamports_from_program_account(
+    source: &AccountInfo,
+    destination: &AccountInfo,
+    amount: u64,
+) -> Result<()> {
+    let source_lamports = source
+        .lamports()
+        .checked_sub(amount)
+        .ok_or(CandyError::NumericalOverflowError)?;
+    let destination_lamports = destination
+        .lamports()
+        .checked_add(amount)
+        .ok_or(CandyError::NumericalOverflowError)?;
+
+    **source.try_borrow_mut_lamports()? = source_lamports;
+    **destination.try_borrow_mut_lamports()? = destination_lamports;
+
+    Ok(())
+}
+
 pub fn assert_is_ata(
     ata: &AccountInfo,
     wallet: &Pubkey,
@@ -177,8 +489,8 @@ pub fn assert_keys_equal(key1: &Pubkey, key2: &Pubkey) -> Result<()> {
     }
 }
 
-pub fn is_feature_active(uuid: &str, feature_index: usize) -> bool {
-    uuid.as_bytes()[feature_index] == b"1"[0]
+pub fn is_feature_active(features: u64, feature_index: usize) -> bool {
+    (features & (1 << feature_index)) != 0
 }
 
 /// TokenBurnParams
@@ -229,48 +541,107 @@ pub fn spl_token_burn(params: TokenBurnParams<'_, '_>) -> Result<()> {
     result.map_err(|_| CandyError::TokenBurnFailed.into())
 }
 
-// string is 6 bytes long, can be any valid utf8 char coming in.
-// feature_index is between 0 and 5, inclusive. We set it to an array of utf8 "0"s first
-pub fn set_feature_flag(uuid: &mut String, feature_index: usize) {
-    let mut bytes: [u8; 6] = [b'0'; 6];
-    uuid.bytes().enumerate().for_each(|(i, byte)| {
-        if i == feature_index || byte == b'1' {
-            bytes[i] = b'1';
-        }
-    });
-
-    // unsafe is fine because we know for a fact that the array will only
-    // contain valid UTF8 bytes since we fully ignore user inputted UUID and set
-    // it to an array of only valid bytes (b'0') and then only modify the bytes in
-    // that valid utf8 byte array to other valid utf8 characters (b'1')
-    // This saves a bit of compute from the overhead of using the from_utf8 or
-    // other similar methods that need to ensure that the bytes are valid
-    unsafe {
-        uuid.replace_range(.., from_utf8_unchecked(&bytes));
-    }
-}
-
-// string is 6 bytes long, can be any valid utf8 char coming in.
-// feature_index is between 0 and 5, inclusive. We set it to an array of utf8 "0"s first
-pub fn remove_feature_flag(uuid: &mut String, feature_index: usize) {
-    let mut bytes: [u8; 6] = [b'0'; 6];
-    uuid.bytes().enumerate().for_each(|(i, byte)| {
-        if i == feature_index {
-            bytes[i] = b'0';
-        } else if byte == b'1' {
-            bytes[i] = b'1';
-        }
-    });
+/// VerifyProgrammableCollectionParams
+pub struct VerifyProgrammableCollectionParams<'a: 'b, 'b> {
+    /// CHECK: account checked in CPI
+    pub metadata: AccountInfo<'a>,
+    /// The collection delegate, i.e. the CollectionPda.
+    /// CHECK: account checked in CPI
+    pub authority: AccountInfo<'a>,
+    /// authority_signer_seeds
+    pub authority_signer_seeds: &'b [&'b [u8]],
+    /// The collection metadata delegate record of the authority.
+    /// CHECK: account checked in CPI
+    pub delegate_record: AccountInfo<'a>,
+    /// CHECK: account checked in CPI
+    pub collection_mint: AccountInfo<'a>,
+    /// CHECK: account checked in CPI
+    pub collection_metadata: AccountInfo<'a>,
+    /// CHECK: account checked in CPI
+    pub collection_master_edition: AccountInfo<'a>,
+    /// CHECK: account checked in CPI
+    pub system_program: AccountInfo<'a>,
+    /// CHECK: account checked in CPI
+    pub instruction_sysvar: AccountInfo<'a>,
+    /// CHECK: account checked in CPI
+    pub token_metadata_program: AccountInfo<'a>,
+}
 
-    // unsafe is fine because we know for a fact that the array will only
-    // contain valid UTF8 bytes since we fully ignore user inputted UUID and set
-    // it to an array of only valid bytes (b'0') and then only modify the bytes in
-    // that valid utf8 byte array to other valid utf8 characters (b'1')
-    // This saves a bit of compute from the overhead of using the from_utf8 or
-    // other similar methods that need to ensure that the bytes are valid
-    unsafe {
-        uuid.replace_range(.., from_utf8_unchecked(&bytes));
-    }
+/// Verifies the collection of a programmable NFT, which was set when the NFT
+/// was created. Programmable NFTs don't support set_and_verify_collection.
+pub fn verify_programmable_collection(
+    params: VerifyProgrammableCollectionParams<'_, '_>,
+) -> Result<()> {
+    let VerifyProgrammableCollectionParams {
+        metadata,
+        authority,
+        authority_signer_seeds,
+        delegate_record,
+        collection_mint,
+        collection_metadata,
+        collection_master_edition,
+        system_program,
+        instruction_sysvar,
+        token_metadata_program,
+    } = params;
+
+    let verify_ix = VerifyBuilder::new()
+        .authority(authority.key())
+        .delegate_record(delegate_record.key())
+        .metadata(metadata.key())
+        .collection_mint(collection_mint.key())
+        .collection_metadata(collection_metadata.key())
+        .collection_master_edition(collection_master_edition.key())
+        .system_program(system_program.key())
+        .sysvar_instructions(instruction_sysvar.key())
+        .build(VerificationArgs::CollectionV1)
+        .map_err(|_| CandyError::InstructionBuilderFailed)?
+        .instruction();
+
+    invoke_signed(
+        &verify_ix,
+        &[
+            authority,
+            delegate_record,
+            metadata,
+            collection_mint,
+            collection_metadata,
+            collection_master_edition,
+            system_program,
+            instruction_sysvar,
+            token_metadata_program,
+        ],
+        &[authority_signer_seeds],
+    )?;
+
+    Ok(())
+}
+
+/// Returns true if the master edition belongs to a programmable NFT.
+pub fn is_programmable_edition(edition: &AccountInfo) -> bool {
+    let edition_data = edition.data.borrow();
+    edition_data.len() > TOKEN_STANDARD_INDEX
+        && edition_data[TOKEN_STANDARD_INDEX] == TokenStandard::ProgrammableNonFungible as u8
+}
+
+pub fn set_feature_flag(features: &mut u64, feature_index: usize) {
+    *features |= 1 << feature_index;
+}
+
+pub fn remove_feature_flag(features: &mut u64, feature_index: usize) {
+    *features &= !(1 << feature_index);
+}
+
+/// Decodes the feature flags of accounts created before the features
+/// bitfield, which were stored as '0'/'1' characters in the uuid.
+pub fn get_legacy_features(uuid: &str) -> u64 {
+    uuid.bytes()
+        .take(LEGACY_FEATURE_FLAGS_LEN)
+        .enumerate()
+        .filter(|(_, byte)| *byte == b'1')
+        .fold(0, |features, (feature_index, _)| {
+            features | (1 << feature_index)
+        })
 }
 
 pub fn punish_bots<'a>(
@@ -400,9 +771,66 @@ pub fn verify_merkle_proof(proof: &[[u8; 32]], root: [u8; 32], leaf: [u8; 32]) -
     computed_hash == root
 }
 
-// We disallow enabling both allowlist types for a single candy machine.
+// More than one allowlist type can be enabled for a single candy machine if
+// each allowlist phase names the allowlist it uses, which requires mint phases.
+// The allowlist phase of a candy machine without mint phases checks every
+// enabled allowlist, see get_allowlist_phase_checks, so with more than one a
+// buyer would have to be on all of them rather than any of them.
 pub fn validate_candy_machine_allowlist_state(data: &CandyMachineData) -> Result<()> {
-    if data.spl_token_allowlist_settings.is_some() && !data.merkle_allowlist_root_list.is_empty() {
+    validate_spl_token_allowlist_tokens(data)?;
+
+    let enabled_allowlist_types = [
+        data.is_spl_token_allowlist_enabled(),
+        !data.merkle_allowlist_root_list.is_empty(),
+        data.collection_allowlist_settings.is_some(),
+    ];
+    if enabled_allowlist_types
+        .iter()
+        .filter(|enabled| **enabled)
+        .count()
+        > 1
+        && data.mint_phases.is_empty()
+        && data.allowlist_sale_start_time.is_some()
+    {
+        return Err(CandyError::InvalidAllowlistSettings.into());
+    }
+
+    // Mint phases must only reference allowlist types which are enabled. Merkle
+    // allowlist roots are appended after the candy machine is created.
+    for mint_phase in data.mint_phases.iter() {
+        let is_allowlist_source_valid = match mint_phase.allowlist_source {
+            MintPhaseAllowlistSource::Public => true,
+            MintPhaseAllowlistSource::MerkleAllowlist => true,
+            MintPhaseAllowlistSource::SplTokenAllowlist => data.is_spl_token_allowlist_enabled(),
+            MintPhaseAllowlistSource::CollectionAllowlist => {
+                data.collection_allowlist_settings.is_some()
+            }
+        };
+
+        if !is_allowlist_source_valid {
+            return Err(CandyError::InvalidAllowlistSettings.into());
+        }
+    }
+
+    Ok(())
+}
+
+fn validate_spl_token_allowlist_tokens(data: &CandyMachineData) -> Result<()> {
+    let spl_token_allowlist_tokens = &data.spl_token_allowlist_tokens;
+
+    if spl_token_allowlist_tokens.len() > MAX_SPL_TOKEN_ALLOWLIST_TOKENS {
+        return Err(CandyError::TooManySplTokenAllowlistTokens.into());
+    }
+
+    if !spl_token_allowlist_tokens.is_empty() && data.spl_token_allowlist_settings.is_some() {
+        return Err(CandyError::InvalidAllowlistSettings.into());
+    }
+
+    // A token which is neither burned nor held doesn't gate anything.
+    if spl_token_allowlist_tokens
+        .iter()
+        .any(|token| token.burn_amount == 0 && token.min_balance == 0)
+    {
         return Err(CandyError::InvalidAllowlistSettings.into());
     }
 
@@ -416,27 +844,30 @@ pub mod tests {
 
     #[test]
     fn feature_flag_working() {
-        let mut uuid = String::from("ABCDEF");
-        println!(
-            "Should be 65: {}",
-            uuid.as_bytes()[COLLECTIONS_FEATURE_INDEX]
-        );
-
-        uuid = String::from("01H333");
-        println!("Should be 01H333: {}", uuid);
-        set_feature_flag(&mut uuid, COLLECTIONS_FEATURE_INDEX + 1);
-        assert!(is_feature_active(&uuid, COLLECTIONS_FEATURE_INDEX + 1));
-        println!("Should be 010000: {}", uuid);
-        remove_feature_flag(&mut uuid, COLLECTIONS_FEATURE_INDEX + 1);
-        assert!(!is_feature_active(&uuid, COLLECTIONS_FEATURE_INDEX + 1));
-        println!("Should be 000000: {}", uuid);
-
-        set_feature_flag(&mut uuid, COLLECTIONS_FEATURE_INDEX);
-        assert!(is_feature_active(&uuid, COLLECTIONS_FEATURE_INDEX));
-        println!("Should be 100000: {}", uuid);
-        remove_feature_flag(&mut uuid, COLLECTIONS_FEATURE_INDEX);
-        assert!(!is_feature_active(&uuid, COLLECTIONS_FEATURE_INDEX));
-        println!("Should be 000000: {}", uuid);
+        let mut features = 0;
+        set_feature_flag(&mut features, COLLECTIONS_FEATURE_INDEX + 1);
+        assert!(is_feature_active(features, COLLECTIONS_FEATURE_INDEX + 1));
+        assert_eq!(features, 0b10);
+        remove_feature_flag(&mut features, COLLECTIONS_FEATURE_INDEX + 1);
+        assert!(!is_feature_active(features, COLLECTIONS_FEATURE_INDEX + 1));
+        assert_eq!(features, 0);
+
+        set_feature_flag(&mut features, COLLECTIONS_FEATURE_INDEX);
+        set_feature_flag(&mut features, 63);
+        assert!(is_feature_active(features, COLLECTIONS_FEATURE_INDEX));
+        assert!(is_feature_active(features, 63));
+        remove_feature_flag(&mut features, COLLECTIONS_FEATURE_INDEX);
+        assert!(!is_feature_active(features, COLLECTIONS_FEATURE_INDEX));
+        assert_eq!(features, 1 << 63);
+    }
+
+    #[test]
+    fn legacy_feature_flags_decoded() {
+        assert_eq!(get_legacy_features("000000"), 0);
+        assert_eq!(get_legacy_features("011000"), 0b110);
+        // Only the first 6 characters are flags, anything but '1' is off.
+        assert_eq!(get_legacy_features("01H3331"), 0b10);
+        assert_eq!(get_legacy_features("1"), 0b1);
     }
 
     #[test]
@@ -2,16 +2,19 @@
 #![allow(dead_code)]
 
 use formfn_candy_machine::{
-    BuyerMerkleAllowlistProofData, CandyError, MintPhase, SplTokenAllowlistMode::BurnEveryTime,
+    BuyerMerkleAllowlistProofData, CandyError, MintCaps, MintPhase, PhaseLimits,
+    SplTokenAllowlistMode::{BurnEveryTime, NeverBurn},
+    BUYER_INFO_ACCOUNT_SPACE,
 };
 use solana_program_test::*;
-use solana_sdk::{signature::Keypair, signer::Signer};
+use solana_sdk::{account::AccountSharedData, signature::Keypair, signer::Signer};
 
 use crate::{
-    core::helpers::airdrop,
+    core::helpers::{airdrop, clone_keypair, get_account},
     utils::{
-        candy_machine_program_test, get_allowlist_config_data, helpers::sol, CandyConfigBuilder,
-        CandyManagerBuilder, SplTokenAllowlistConfig, DEFAULT_PRICE,
+        candy_machine_program_test, get_allowlist_config_data,
+        helpers::{find_buyer_info_account_pda, sol},
+        CandyConfigBuilder, CandyManagerBuilder, SplTokenAllowlistConfig, DEFAULT_PRICE,
     },
 };
 
@@ -204,3 +207,319 @@ async fn buy_limit_with_merkle_allowlist() {
         .mint_and_assert_failure(context, None, CandyError::BuyLimitPerAddressExceeded)
         .await;
 }
+
+#[tokio::test]
+async fn allowlist_buy_limit_with_spl_token_allowlist_never_burn() {
+    let mut context = candy_machine_program_test().start_with_context().await;
+    let context = &mut context;
+
+    let allowlist_limit_per_address = 2;
+    let spl_token_allowlist_settings = SplTokenAllowlistConfig::new(NeverBurn);
+
+    let mut candy_manager = CandyManagerBuilder::new()
+        .set_collection(true)
+        .set_spl_token_allowlist_config(spl_token_allowlist_settings.clone())
+        .build(context)
+        .await;
+
+    let candy_data = CandyConfigBuilder::new(&candy_manager)
+        .enable_mint_phase(MintPhase::Allowlist)
+        .set_spl_token_allowlist_settings(SplTokenAllowlistConfig::to_candy_format(
+            spl_token_allowlist_settings,
+            &candy_manager.spl_token_allowlist_info.mint,
+        ))
+        .set_phase_limits(PhaseLimits {
+            premint_limit_per_address: 0,
+            allowlist_limit_per_address,
+        })
+        .build();
+    let price = candy_data.price;
+
+    candy_manager.create(context, candy_data).await.unwrap();
+    candy_manager.fill_config_lines(context).await.unwrap();
+    candy_manager.set_collection(context).await.unwrap();
+
+    // The allowlist token is kept, so only the limit stops further mints.
+    for _ in 0..allowlist_limit_per_address {
+        candy_manager
+            .mint_and_assert_successful(context, Some(price), false, None)
+            .await
+            .unwrap();
+    }
+
+    candy_manager
+        .mint_and_assert_failure(context, None, CandyError::BuyLimitPerAddressExceeded)
+        .await;
+
+    let buyer_info_account = candy_manager.get_buyer_info_account(context).await;
+    assert_eq!(
+        buyer_info_account.number_bought_allowlist_phase,
+        allowlist_limit_per_address
+    );
+}
+
+#[tokio::test]
+async fn premint_buy_limit_for_omni_minters() {
+    let mut context = candy_machine_program_test().start_with_context().await;
+    let context = &mut context;
+    let mut candy_manager = CandyManagerBuilder::default(context).await;
+
+    candy_manager.set_new_minter_keypair(clone_keypair(&candy_manager.creator_authority));
+
+    let premint_limit_per_address = 2;
+    let candy_data = CandyConfigBuilder::new(&candy_manager)
+        .enable_mint_phase(MintPhase::Premint)
+        .set_phase_limits(PhaseLimits {
+            premint_limit_per_address,
+            allowlist_limit_per_address: 0,
+        })
+        .build();
+
+    candy_manager.create(context, candy_data).await.unwrap();
+    candy_manager.fill_config_lines(context).await.unwrap();
+
+    for _ in 0..premint_limit_per_address {
+        candy_manager
+            .mint_and_assert_successful(context, Some(sol(1)), false, None)
+            .await
+            .unwrap();
+    }
+
+    candy_manager
+        .mint_and_assert_failure(context, None, CandyError::BuyLimitPerAddressExceeded)
+        .await;
+
+    // The premint limit doesn't carry over to later phases.
+    let candy_data = CandyConfigBuilder::new(&candy_manager)
+        .enable_mint_phase(MintPhase::Public)
+        .set_phase_limits(PhaseLimits {
+            premint_limit_per_address,
+            allowlist_limit_per_address: 0,
+        })
+        .build();
+    candy_manager
+        .update(context, None, candy_data)
+        .await
+        .unwrap();
+
+    candy_manager
+        .mint_and_assert_successful(context, Some(sol(1)), false, None)
+        .await
+        .unwrap();
+
+    // Omni minters are exempt from the total limit, but still count towards
+    // the total.
+    let buyer_info_account = candy_manager.get_buyer_info_account(context).await;
+    assert_eq!(
+        buyer_info_account.number_bought_total,
+        premint_limit_per_address + 1
+    );
+}
+
+#[tokio::test]
+async fn buyer_info_account_keeps_its_original_size() {
+    let mut context = candy_machine_program_test().start_with_context().await;
+    let context = &mut context;
+    let mut candy_manager = CandyManagerBuilder::default(context).await;
+
+    let limit_per_address = 3;
+    let candy_data = CandyConfigBuilder::new(&candy_manager)
+        .set_limit_per_address(limit_per_address)
+        .build();
+    candy_manager.create(context, candy_data).await.unwrap();
+    candy_manager.fill_config_lines(context).await.unwrap();
+
+    candy_manager
+        .mint_and_assert_successful(context, Some(sol(1)), false, None)
+        .await
+        .unwrap();
+
+    // The counters added since fit in the padding of the original 76 byte
+    // accounts, so accounts created before them read them as zero.
+    let original_space = 76;
+    assert_eq!(BUYER_INFO_ACCOUNT_SPACE, original_space);
+    let (buyer_info_account_pda, _) = find_buyer_info_account_pda(
+        &candy_manager.candy_machine.pubkey(),
+        &candy_manager.minter.pubkey(),
+    );
+    let mut account = get_account(context, &buyer_info_account_pda).await;
+    assert_eq!(account.data.len(), original_space);
+    // Clear everything after the discriminator and the original counters.
+    account.data[12..].fill(0);
+    context.set_account(&buyer_info_account_pda, &AccountSharedData::from(account));
+
+    candy_manager
+        .mint_and_assert_successful(context, Some(sol(1)), false, None)
+        .await
+        .unwrap();
+
+    let account = get_account(context, &buyer_info_account_pda).await;
+    assert_eq!(account.data.len(), original_space);
+    let buyer_info_account = candy_manager.get_buyer_info_account(context).await;
+    assert_eq!(buyer_info_account.number_bought_public_phase, 2);
+    assert_eq!(buyer_info_account.number_bought_total, 1);
+}
+
+#[tokio::test]
+async fn total_buy_limit_across_mint_phases() {
+    let mut context = candy_machine_program_test().start_with_context().await;
+    let context = &mut context;
+
+    let total_limit_per_address = 3;
+    let spl_token_allowlist_settings = SplTokenAllowlistConfig::new(NeverBurn);
+
+    let mut candy_manager = CandyManagerBuilder::new()
+        .set_collection(true)
+        .set_spl_token_allowlist_config(spl_token_allowlist_settings.clone())
+        .build(context)
+        .await;
+
+    let mint_caps = MintCaps {
+        total_limit_per_address,
+        allowlist_items_cap: 0,
+    };
+    let candy_data = CandyConfigBuilder::new(&candy_manager)
+        .enable_mint_phase(MintPhase::Allowlist)
+        .set_spl_token_allowlist_settings(SplTokenAllowlistConfig::to_candy_format(
+            spl_token_allowlist_settings.clone(),
+            &candy_manager.spl_token_allowlist_info.mint,
+        ))
+        .set_mint_caps(mint_caps)
+        .build();
+    let price = candy_data.price;
+
+    candy_manager.create(context, candy_data).await.unwrap();
+    candy_manager.fill_config_lines(context).await.unwrap();
+    candy_manager.set_collection(context).await.unwrap();
+
+    for _ in 0..2 {
+        candy_manager
+            .mint_and_assert_successful(context, Some(price), false, None)
+            .await
+            .unwrap();
+    }
+
+    // The public phase has no limit of its own, but the allowlist mints count
+    // towards the total.
+    let candy_data = CandyConfigBuilder::new(&candy_manager)
+        .enable_mint_phase(MintPhase::Public)
+        .set_spl_token_allowlist_settings(SplTokenAllowlistConfig::to_candy_format(
+            spl_token_allowlist_settings,
+            &candy_manager.spl_token_allowlist_info.mint,
+        ))
+        .set_mint_caps(mint_caps)
+        .build();
+    candy_manager
+        .update(context, None, candy_data)
+        .await
+        .unwrap();
+
+    candy_manager
+        .mint_and_assert_successful(context, Some(price), false, None)
+        .await
+        .unwrap();
+
+    candy_manager
+        .mint_and_assert_failure(context, None, CandyError::BuyLimitPerAddressExceeded)
+        .await;
+
+    let buyer_info_account = candy_manager.get_buyer_info_account(context).await;
+    assert_eq!(
+        buyer_info_account.number_bought_total,
+        total_limit_per_address
+    );
+}
+
+#[tokio::test]
+async fn allowlist_items_cap_leaves_items_for_public_phase() {
+    let mut context = candy_machine_program_test().start_with_context().await;
+    let context = &mut context;
+
+    let allowlist_items_cap = 2;
+    let spl_token_allowlist_settings = SplTokenAllowlistConfig::new(NeverBurn);
+
+    let mut candy_manager = CandyManagerBuilder::new()
+        .set_collection(true)
+        .set_spl_token_allowlist_config(spl_token_allowlist_settings.clone())
+        .build(context)
+        .await;
+
+    let mint_caps = MintCaps {
+        total_limit_per_address: 0,
+        allowlist_items_cap,
+    };
+    let candy_data = CandyConfigBuilder::new(&candy_manager)
+        .enable_mint_phase(MintPhase::Premint)
+        .set_spl_token_allowlist_settings(SplTokenAllowlistConfig::to_candy_format(
+            spl_token_allowlist_settings.clone(),
+            &candy_manager.spl_token_allowlist_info.mint,
+        ))
+        .set_items_available(5)
+        .set_mint_caps(mint_caps)
+        .build();
+    let price = candy_data.price;
+
+    candy_manager.create(context, candy_data).await.unwrap();
+    candy_manager.fill_config_lines(context).await.unwrap();
+    candy_manager.set_collection(context).await.unwrap();
+
+    // Items sold during premint don't count towards the allowlist items cap.
+    let minter = clone_keypair(&candy_manager.minter);
+    candy_manager.set_new_minter_keypair(clone_keypair(&candy_manager.creator_authority));
+    candy_manager
+        .mint_and_assert_successful(context, None, false, None)
+        .await
+        .unwrap();
+    candy_manager.set_new_minter_keypair(minter);
+
+    let candy_data = CandyConfigBuilder::new(&candy_manager)
+        .enable_mint_phase(MintPhase::Allowlist)
+        .set_spl_token_allowlist_settings(SplTokenAllowlistConfig::to_candy_format(
+            spl_token_allowlist_settings.clone(),
+            &candy_manager.spl_token_allowlist_info.mint,
+        ))
+        .set_items_available(5)
+        .set_mint_caps(mint_caps)
+        .build();
+    candy_manager
+        .update(context, None, candy_data)
+        .await
+        .unwrap();
+
+    for _ in 0..allowlist_items_cap {
+        candy_manager
+            .mint_and_assert_successful(context, Some(price), false, None)
+            .await
+            .unwrap();
+    }
+
+    candy_manager
+        .mint_and_assert_failure(context, None, CandyError::AllowlistItemsCapReached)
+        .await;
+    assert_eq!(
+        candy_manager.get_candy(context).await.allowlist_items_sold,
+        allowlist_items_cap
+    );
+
+    let candy_data = CandyConfigBuilder::new(&candy_manager)
+        .enable_mint_phase(MintPhase::Public)
+        .set_spl_token_allowlist_settings(SplTokenAllowlistConfig::to_candy_format(
+            spl_token_allowlist_settings,
+            &candy_manager.spl_token_allowlist_info.mint,
+        ))
+        .set_items_available(5)
+        .set_mint_caps(mint_caps)
+        .build();
+    candy_manager
+        .update(context, None, candy_data)
+        .await
+        .unwrap();
+
+    for _ in 0..2 {
+        candy_manager
+            .mint_and_assert_successful(context, Some(price), false, None)
+            .await
+            .unwrap();
+    }
+    assert_eq!(candy_manager.get_candy(context).await.items_redeemed, 5);
+}
@@ -0,0 +1,243 @@
+#![cfg(feature = "test-bpf")]
+#![allow(dead_code)]
+
+use anchor_lang::AccountDeserialize;
+use chrono::Duration;
+use formfn_candy_machine::{
+    CandyError, CollectionAllowlistRecord, CollectionAllowlistSettings, MintPhase,
+    MintPhaseAllowlistSource,
+};
+use solana_program::pubkey::Pubkey;
+use solana_program_test::*;
+use solana_sdk::signer::Signer;
+
+use crate::{
+    core::{
+        helpers::{clone_keypair, get_account},
+        master_edition_manager::MasterEditionManager,
+    },
+    utils::{
+        candy_machine_program_test,
+        helpers::{
+            assert_tx_failed_with_error_code, find_collection_allowlist_record_pda,
+            get_current_unix_timestamp,
+        },
+        CandyConfigBuilder, CandyManager, CandyManagerBuilder, DEFAULT_PRICE,
+    },
+};
+
+mod core;
+mod utils;
+
+/// Mints an NFT from a candy machine with a collection, which makes the minter
+/// a holder of a verified member of that collection.
+async fn mint_gating_nft(context: &mut ProgramTestContext) -> (CandyManager, MasterEditionManager) {
+    let mut gating_candy_manager = CandyManagerBuilder::new()
+        .set_collection(true)
+        .build(context)
+        .await;
+    let candy_data = CandyConfigBuilder::new(&gating_candy_manager)
+        .enable_mint_phase(MintPhase::Public)
+        .build();
+    gating_candy_manager
+        .create(context, candy_data)
+        .await
+        .unwrap();
+    gating_candy_manager
+        .fill_config_lines(context)
+        .await
+        .unwrap();
+    gating_candy_manager.set_collection(context).await.unwrap();
+    let gating_nft = gating_candy_manager
+        .mint_nft(context, None, None)
+        .await
+        .unwrap();
+    (gating_candy_manager, gating_nft)
+}
+
+fn get_gating_accounts(
+    candy_manager: &CandyManager,
+    gating_nft: &MasterEditionManager,
+) -> Vec<Pubkey> {
+    vec![
+        gating_nft.token_account,
+        gating_nft.metadata_pubkey,
+        gating_nft.edition_pubkey,
+        find_collection_allowlist_record_pda(
+            &candy_manager.candy_machine.pubkey(),
+            &gating_nft.mint.pubkey(),
+        )
+        .0,
+    ]
+}
+
+#[tokio::test]
+async fn mint_using_collection_allowlist() {
+    let mut context = candy_machine_program_test().start_with_context().await;
+    let context = &mut context;
+    let (gating_candy_manager, gating_nft) = mint_gating_nft(context).await;
+
+    let mut candy_manager = CandyManagerBuilder::new()
+        .set_minter(clone_keypair(&gating_candy_manager.minter))
+        .build(context)
+        .await;
+    let candy_data = CandyConfigBuilder::new(&candy_manager)
+        .enable_mint_phase(MintPhase::Allowlist)
+        .set_allowlist_price(1)
+        .set_collection_allowlist_settings(Some(CollectionAllowlistSettings {
+            collection_mint: gating_candy_manager.collection_info.mint.pubkey(),
+            use_once: true,
+        }))
+        .build();
+    candy_manager.create(context, candy_data).await.unwrap();
+    candy_manager.fill_config_lines(context).await.unwrap();
+
+    // The collection NFT itself is not a verified member of its collection.
+    candy_manager.collection_allowlist_accounts = vec![
+        gating_candy_manager.collection_info.token_account,
+        gating_candy_manager.collection_info.metadata,
+        gating_candy_manager.collection_info.master_edition,
+        find_collection_allowlist_record_pda(
+            &candy_manager.candy_machine.pubkey(),
+            &gating_candy_manager.collection_info.mint.pubkey(),
+        )
+        .0,
+    ];
+    candy_manager
+        .mint_and_assert_bot_tax(context, None, None)
+        .await
+        .unwrap();
+
+    candy_manager.collection_allowlist_accounts = get_gating_accounts(&candy_manager, &gating_nft);
+    candy_manager
+        .mint_and_assert_successful(context, Some(1), false, None)
+        .await
+        .unwrap();
+
+    let record_pubkey = candy_manager.collection_allowlist_accounts[3];
+    let record_account = get_account(context, &record_pubkey).await;
+    let record =
+        CollectionAllowlistRecord::try_deserialize(&mut record_account.data.as_ref()).unwrap();
+    assert_eq!(record.candy_machine, candy_manager.candy_machine.pubkey());
+    assert_eq!(record.gating_mint, gating_nft.mint.pubkey());
+    assert_eq!(record.recipient, candy_manager.minter.pubkey());
+
+    // Each gating NFT can only be used once.
+    let tx_result = candy_manager.mint_nft(context, None, None).await;
+    assert_tx_failed_with_error_code(tx_result, CandyError::CollectionAllowlistNftAlreadyUsed);
+}
+
+#[tokio::test]
+async fn collection_allowlist_nft_can_be_reused() {
+    let mut context = candy_machine_program_test().start_with_context().await;
+    let context = &mut context;
+    let (gating_candy_manager, gating_nft) = mint_gating_nft(context).await;
+
+    let mut candy_manager = CandyManagerBuilder::new()
+        .set_minter(clone_keypair(&gating_candy_manager.minter))
+        .build(context)
+        .await;
+    let candy_data = CandyConfigBuilder::new(&candy_manager)
+        .enable_mint_phase(MintPhase::Allowlist)
+        .set_allowlist_price(1)
+        .set_collection_allowlist_settings(Some(CollectionAllowlistSettings {
+            collection_mint: gating_candy_manager.collection_info.mint.pubkey(),
+            use_once: false,
+        }))
+        .build();
+    candy_manager.create(context, candy_data).await.unwrap();
+    candy_manager.fill_config_lines(context).await.unwrap();
+
+    // Without use_once no record is passed or created.
+    let mut gating_accounts = get_gating_accounts(&candy_manager, &gating_nft);
+    gating_accounts.pop();
+    candy_manager.collection_allowlist_accounts = gating_accounts;
+    for _ in 0..2 {
+        candy_manager
+            .mint_and_assert_successful(context, Some(1), false, None)
+            .await
+            .unwrap();
+    }
+    assert_eq!(candy_manager.get_candy(context).await.items_redeemed, 2);
+}
+
+#[tokio::test]
+async fn collection_allowlist_with_merkle_allowlist_roots_needs_mint_phases() {
+    let mut context = candy_machine_program_test().start_with_context().await;
+    let context = &mut context;
+    let mut candy_manager = CandyManagerBuilder::default(context).await;
+    let collection_allowlist_settings = Some(CollectionAllowlistSettings {
+        collection_mint: Pubkey::new_unique(),
+        use_once: false,
+    });
+
+    // Without mint phases the allowlist phase can't tell which allowlist to use.
+    let candy_data = CandyConfigBuilder::new(&candy_manager)
+        .enable_mint_phase(MintPhase::Allowlist)
+        .set_collection_allowlist_settings(collection_allowlist_settings.clone())
+        .build();
+    candy_manager.create(context, candy_data).await.unwrap();
+
+    let tx_result = candy_manager
+        .append_merkle_allowlist_roots(context, vec![[1; 32]])
+        .await;
+    assert_tx_failed_with_error_code(tx_result, CandyError::InvalidAllowlistSettings);
+
+    let now = get_current_unix_timestamp();
+    let candy_data = CandyConfigBuilder::new(&candy_manager)
+        .add_mint_phase(
+            now - Duration::minutes(1).num_seconds(),
+            DEFAULT_PRICE,
+            MintPhaseAllowlistSource::MerkleAllowlist,
+            0,
+        )
+        .add_mint_phase(
+            now + Duration::minutes(10).num_seconds(),
+            DEFAULT_PRICE,
+            MintPhaseAllowlistSource::CollectionAllowlist,
+            0,
+        )
+        .set_collection_allowlist_settings(collection_allowlist_settings)
+        .build();
+    candy_manager
+        .update(context, None, candy_data)
+        .await
+        .unwrap();
+
+    candy_manager
+        .append_merkle_allowlist_roots(context, vec![[1; 32]])
+        .await
+        .unwrap();
+    let candy = candy_manager.get_candy(context).await;
+    assert_eq!(candy.data.merkle_allowlist_root_list.len(), 1);
+}
+
+#[tokio::test]
+async fn collection_allowlist_accounts_not_needed_in_public_phase() {
+    let mut context = candy_machine_program_test().start_with_context().await;
+    let context = &mut context;
+    let mut candy_manager = CandyManagerBuilder::default(context).await;
+    let candy_data = CandyConfigBuilder::new(&candy_manager)
+        .enable_mint_phase(MintPhase::Public)
+        .set_items_available(2)
+        .set_collection_allowlist_settings(Some(CollectionAllowlistSettings {
+            collection_mint: Pubkey::new_unique(),
+            use_once: true,
+        }))
+        .build();
+    candy_manager.create(context, candy_data).await.unwrap();
+    candy_manager.fill_config_lines(context).await.unwrap();
+
+    // The gating accounts are only passed during the allowlist phase, so the
+    // accounts of each NFT directly follow the named accounts.
+    candy_manager.collection_allowlist_accounts = (0..4).map(|_| Pubkey::new_unique()).collect();
+    assert!(candy_manager
+        .get_allowlist_accounts(context)
+        .await
+        .is_empty());
+    candy_manager
+        .mint_many_and_assert_successful(context, 2, None)
+        .await
+        .unwrap();
+    assert_eq!(candy_manager.get_candy(context).await.items_redeemed, 2);
+}
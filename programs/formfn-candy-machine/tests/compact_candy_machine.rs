@@ -0,0 +1,54 @@
+#![cfg(feature = "test-bpf")]
+#![allow(dead_code)]
+
+use formfn_candy_machine::{constants::CONFIG_ARRAY_START, CandyError};
+use solana_program_test::*;
+use solana_sdk::signer::Signer;
+
+use crate::core::helpers::{get_account, get_balance};
+use crate::utils::{
+    candy_machine_program_test, helpers::assert_tx_failed_with_error_code, CandyConfigBuilder,
+    CandyManagerBuilder,
+};
+
+mod core;
+mod utils;
+
+#[tokio::test]
+async fn compact_sold_out_candy_machine() {
+    let mut context = candy_machine_program_test().start_with_context().await;
+    let context = &mut context;
+    let mut candy_manager = CandyManagerBuilder::default(context).await;
+    let candy_data = CandyConfigBuilder::new(&candy_manager)
+        .set_items_available(2)
+        .build();
+    candy_manager.create(context, candy_data).await.unwrap();
+    candy_manager.fill_config_lines(context).await.unwrap();
+    candy_manager.mint_nft(context, None, None).await.unwrap();
+
+    let tx_result = candy_manager.compact_candy_machine(context).await;
+    assert_tx_failed_with_error_code(tx_result, CandyError::CannotCompactCandyMachine);
+
+    candy_manager.mint_nft(context, None, None).await.unwrap();
+
+    let candy_pubkey = candy_manager.candy_machine.pubkey();
+    let candy_balance_before = get_balance(context, &candy_pubkey).await;
+    let authority_pubkey = candy_manager.formfn_authority.pubkey();
+    let authority_balance_before = get_balance(context, &authority_pubkey).await;
+
+    candy_manager.compact_candy_machine(context).await.unwrap();
+
+    let account = get_account(context, &candy_pubkey).await;
+    assert_eq!(account.data.len(), CONFIG_ARRAY_START);
+    assert!(account.lamports < candy_balance_before);
+    // The authority receives the refund, which is more than the transaction fee.
+    assert!(get_balance(context, &authority_pubkey).await > authority_balance_before);
+
+    // The settings are still readable.
+    let candy = candy_manager.get_candy(context).await;
+    assert_eq!(candy.items_redeemed, 2);
+    assert_eq!(candy.data.items_available, 2);
+
+    let tx_result = candy_manager.compact_candy_machine(context).await;
+    assert_tx_failed_with_error_code(tx_result, CandyError::NothingToCompact);
+}
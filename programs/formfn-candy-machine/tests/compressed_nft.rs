@@ -0,0 +1,161 @@
+#![cfg(feature = "test-bpf")]
+#![allow(dead_code)]
+
+use anchor_lang::AccountDeserialize;
+use formfn_candy_machine::{CandyError, CompressedNftSettings, ProgrammableNftSettings};
+use mpl_bubblegum::state::TreeConfig;
+use solana_program::pubkey::Pubkey;
+use solana_program_test::*;
+use solana_sdk::signer::Signer;
+
+use crate::core::{
+    helpers::{assert_account_empty, get_account, new_funded_keypair, prepare_nft},
+    MasterEditionManager,
+};
+use crate::utils::{
+    candy_machine_program_test, compressed_nft_program_test, create_compressed_nft_tree,
+    helpers::{assert_tx_failed_with_error_code, find_candy_creator, find_tree_authority, sol},
+    mint_nft, CandyConfigBuilder, CandyManager, CandyManagerBuilder, FreezeConfig,
+    SolanaProgramTestResult, SPL_ACCOUNT_COMPRESSION_PROGRAM_ID, SPL_NOOP_PROGRAM_ID,
+};
+
+mod core;
+mod utils;
+
+#[tokio::test]
+async fn invalid_compressed_nft_settings() {
+    let mut context = candy_machine_program_test().start_with_context().await;
+    let context = &mut context;
+    let mut candy_manager = CandyManagerBuilder::default(context).await;
+
+    let candy_data = CandyConfigBuilder::new(&candy_manager)
+        .set_compressed_nft_settings(Some(CompressedNftSettings {
+            merkle_tree: Pubkey::new_unique(),
+        }))
+        .set_programmable_nft_settings(Some(ProgrammableNftSettings { rule_set: None }))
+        .build();
+    let tx_result = candy_manager.create(context, candy_data).await;
+    assert_tx_failed_with_error_code(tx_result, CandyError::InvalidCompressedNftSettings);
+}
+
+#[tokio::test]
+async fn compressed_nft_freeze_not_supported() {
+    let mut context = candy_machine_program_test().start_with_context().await;
+    let context = &mut context;
+    let mut candy_manager = CandyManagerBuilder::new()
+        .set_freeze(FreezeConfig::new(true, 60 * 60))
+        .build(context)
+        .await;
+
+    let candy_data = CandyConfigBuilder::new(&candy_manager)
+        .set_compressed_nft_settings(Some(CompressedNftSettings {
+            merkle_tree: Pubkey::new_unique(),
+        }))
+        .build();
+    candy_manager.create(context, candy_data).await.unwrap();
+
+    let tx_result = candy_manager.set_freeze(context).await;
+    assert_tx_failed_with_error_code(tx_result, CandyError::NotSupportedForCompressedNfts);
+}
+
+#[tokio::test]
+async fn mint_compressed_nft() {
+    let mut context = compressed_nft_program_test().start_with_context().await;
+    let context = &mut context;
+    let mut candy_manager = CandyManagerBuilder::default(context).await;
+
+    // The candy machine creator PDA mints into the tree as its tree delegate.
+    let tree_creator = new_funded_keypair(context, sol(1)).await;
+    let (candy_machine_creator, _) = find_candy_creator(&candy_manager.candy_machine.pubkey());
+    let merkle_tree = create_compressed_nft_tree(context, &tree_creator, &candy_machine_creator)
+        .await
+        .unwrap();
+
+    let candy_data = CandyConfigBuilder::new(&candy_manager)
+        .set_compressed_nft_settings(Some(CompressedNftSettings { merkle_tree }))
+        .build();
+    candy_manager.create(context, candy_data).await.unwrap();
+    candy_manager.fill_config_lines(context).await.unwrap();
+
+    let nft_info = candy_manager.mint_nft(context, None, None).await.unwrap();
+
+    assert_eq!(candy_manager.get_candy(context).await.items_redeemed, 1);
+    let (tree_authority, _) = find_tree_authority(&merkle_tree);
+    let tree_authority_account = get_account(context, &tree_authority).await;
+    let tree_config =
+        TreeConfig::try_deserialize(&mut tree_authority_account.data.as_ref()).unwrap();
+    assert_eq!(tree_config.num_minted, 1);
+    // The NFT only exists as a leaf of the tree.
+    assert_account_empty(context, &nft_info.mint.pubkey()).await;
+    assert_account_empty(context, &nft_info.metadata_pubkey).await;
+}
+
+#[tokio::test]
+async fn compressed_nft_merkle_tree_mismatch() {
+    let mut context = candy_machine_program_test().start_with_context().await;
+    let context = &mut context;
+    let mut candy_manager = CandyManagerBuilder::default(context).await;
+
+    let candy_data = CandyConfigBuilder::new(&candy_manager)
+        .set_compressed_nft_settings(Some(CompressedNftSettings {
+            merkle_tree: Pubkey::new_unique(),
+        }))
+        .build();
+    candy_manager.create(context, candy_data).await.unwrap();
+    candy_manager.fill_config_lines(context).await.unwrap();
+
+    let merkle_tree = Pubkey::new_unique();
+    let mut mint_payment_accounts = candy_manager.get_mint_payment_accounts(context).await;
+    mint_payment_accounts.extend([
+        find_tree_authority(&merkle_tree).0,
+        merkle_tree,
+        SPL_NOOP_PROGRAM_ID,
+        SPL_ACCOUNT_COMPRESSION_PROGRAM_ID,
+        mpl_bubblegum::id(),
+    ]);
+    let nft_info = prepare_nft(&candy_manager.minter).await;
+    let tx_result = mint_compressed_nft_with_accounts(
+        context,
+        &candy_manager,
+        &nft_info,
+        &mint_payment_accounts,
+    )
+    .await;
+    assert_tx_failed_with_error_code(tx_result, CandyError::MerkleTreeMismatch);
+}
+
+async fn mint_compressed_nft_with_accounts(
+    context: &mut ProgramTestContext,
+    candy_manager: &CandyManager,
+    nft_info: &MasterEditionManager,
+    mint_payment_accounts: &[Pubkey],
+) -> SolanaProgramTestResult {
+    let (candy_machine_creator, creator_bump) =
+        find_candy_creator(&candy_manager.candy_machine.pubkey());
+    let mint_price = candy_manager.get_mint_price(context).await;
+    let spl_token_allowlist_info = candy_manager.get_spl_token_allowlist_info(context).await;
+    let allowlist_accounts = candy_manager.get_allowlist_accounts(context).await;
+    mint_nft(
+        context,
+        &candy_manager.candy_machine.pubkey(),
+        &candy_machine_creator,
+        creator_bump,
+        &candy_manager.treasury_wallet,
+        &candy_manager.creator_authority.pubkey(),
+        &candy_manager.minter,
+        &candy_manager.minter.pubkey(),
+        nft_info,
+        candy_manager.token_info.clone(),
+        spl_token_allowlist_info,
+        candy_manager.collection_info.clone(),
+        candy_manager.freeze_info.clone(),
+        mint_payment_accounts,
+        false,
+        None,
+        mint_price,
+        None,
+        &candy_manager.randomness_account,
+        &allowlist_accounts,
+    )
+    .await
+}
@@ -0,0 +1,183 @@
+#![cfg(feature = "test-bpf")]
+#![allow(dead_code)]
+
+use formfn_candy_machine::{
+    constants::CONFIG_ARRAY_START, CandyError, ConfigLine, ConfigLineSettings,
+};
+use mpl_token_metadata::state::{MAX_NAME_LENGTH, MAX_URI_LENGTH};
+use solana_program_test::*;
+use solana_sdk::signer::Signer;
+
+use crate::core::helpers::get_account;
+use crate::utils::{
+    candy_machine_program_test,
+    helpers::{assert_tx_failed_with_error_code, get_config_line_name, make_config_lines},
+    CandyConfigBuilder, CandyManagerBuilder,
+};
+
+mod core;
+mod utils;
+
+#[tokio::test]
+async fn replace_and_remove_config_lines() {
+    let mut context = candy_machine_program_test().start_with_context().await;
+    let context = &mut context;
+    let mut candy_manager = CandyManagerBuilder::default(context).await;
+    let candy_data = CandyConfigBuilder::new(&candy_manager)
+        .set_items_available(10)
+        .build();
+    candy_manager.create(context, candy_data).await.unwrap();
+    candy_manager.fill_config_lines(context).await.unwrap();
+
+    let replacement = ConfigLine {
+        name: "Replaced".to_string(),
+        uri: "Replaced URI".to_string(),
+    };
+    candy_manager
+        .replace_config_lines(context, 1, vec![replacement])
+        .await
+        .unwrap();
+
+    let parsed = candy_manager.parse_config_lines(context).await;
+    assert_eq!(parsed.config_line_count_number, 10);
+    assert_eq!(parsed.config_lines[0].name, get_config_line_name(0));
+    assert_eq!(parsed.config_lines[1].name, "Replaced");
+    assert_eq!(parsed.config_lines[1].uri, "Replaced URI");
+
+    candy_manager
+        .remove_config_lines(context, 8, 2)
+        .await
+        .unwrap();
+    let parsed = candy_manager.parse_config_lines(context).await;
+    assert_eq!(parsed.config_line_count_number, 8);
+
+    // Removed lines can't be replaced, only added again.
+    let tx_result = candy_manager
+        .replace_config_lines(context, 9, make_config_lines(9, 1))
+        .await;
+    assert_tx_failed_with_error_code(tx_result, CandyError::ConfigLineNotLoaded);
+
+    // Removing a line twice doesn't change the count again.
+    candy_manager
+        .remove_config_lines(context, 9, 1)
+        .await
+        .unwrap();
+    let parsed = candy_manager.parse_config_lines(context).await;
+    assert_eq!(parsed.config_line_count_number, 8);
+
+    let tx_result = candy_manager.remove_config_lines(context, 9, 2).await;
+    assert_tx_failed_with_error_code(tx_result, CandyError::IndexGreaterThanLength);
+
+    candy_manager
+        .add_config_lines(context, 8, make_config_lines(8, 2))
+        .await
+        .unwrap();
+    let parsed = candy_manager.parse_config_lines(context).await;
+    assert_eq!(parsed.config_line_count_number, 10);
+
+    candy_manager.mint_nft(context, None, None).await.unwrap();
+
+    let tx_result = candy_manager
+        .replace_config_lines(context, 1, make_config_lines(1, 1))
+        .await;
+    assert_tx_failed_with_error_code(tx_result, CandyError::NoChangingConfigLinesDuringMint);
+    let tx_result = candy_manager.remove_config_lines(context, 1, 1).await;
+    assert_tx_failed_with_error_code(tx_result, CandyError::NoChangingConfigLinesDuringMint);
+}
+
+#[tokio::test]
+async fn config_lines_too_long() {
+    let mut context = candy_machine_program_test().start_with_context().await;
+    let context = &mut context;
+    let mut candy_manager = CandyManagerBuilder::default(context).await;
+    let candy_data = CandyConfigBuilder::new(&candy_manager)
+        .set_items_available(10)
+        .build();
+    candy_manager.create(context, candy_data).await.unwrap();
+
+    let long_name = ConfigLine {
+        name: "a".repeat(MAX_NAME_LENGTH + 1),
+        uri: "uri".to_string(),
+    };
+    let tx_result = candy_manager
+        .add_config_lines(context, 0, vec![long_name])
+        .await;
+    assert_tx_failed_with_error_code(tx_result, CandyError::ConfigLineNameTooLong);
+
+    let long_uri = ConfigLine {
+        name: "name".to_string(),
+        uri: "a".repeat(MAX_URI_LENGTH + 1),
+    };
+    let tx_result = candy_manager
+        .add_config_lines(context, 0, vec![long_uri])
+        .await;
+    assert_tx_failed_with_error_code(tx_result, CandyError::ConfigLineUriTooLong);
+}
+
+#[tokio::test]
+async fn config_line_settings() {
+    let mut context = candy_machine_program_test().start_with_context().await;
+    let context = &mut context;
+    let mut candy_manager = CandyManagerBuilder::default(context).await;
+    let config_line_settings = ConfigLineSettings {
+        prefix_name: "Item #".to_string(),
+        name_length: 2,
+        prefix_uri: "https://arweave.net/".to_string(),
+        uri_length: 10,
+    };
+
+    let invalid_settings = ConfigLineSettings {
+        name_length: MAX_NAME_LENGTH as u32,
+        ..config_line_settings.clone()
+    };
+    let candy_data = CandyConfigBuilder::new(&candy_manager)
+        .set_items_available(3)
+        .set_config_line_settings(Some(invalid_settings))
+        .build();
+    let tx_result = candy_manager.create(context, candy_data).await;
+    assert_tx_failed_with_error_code(tx_result, CandyError::InvalidConfigLineSettings);
+
+    let candy_data = CandyConfigBuilder::new(&candy_manager)
+        .set_items_available(3)
+        .set_config_line_settings(Some(config_line_settings))
+        .build();
+    candy_manager.create(context, candy_data).await.unwrap();
+
+    let candy_account = get_account(context, &candy_manager.candy_machine.pubkey()).await;
+    assert_eq!(
+        candy_account.data.len(),
+        CONFIG_ARRAY_START + 4 + 3 * 12 + 8 + 2
+    );
+
+    let config_lines = (0..3)
+        .map(|i| ConfigLine {
+            name: i.to_string(),
+            uri: format!("{}.json", i),
+        })
+        .collect();
+    candy_manager
+        .add_config_lines(context, 0, config_lines)
+        .await
+        .unwrap();
+
+    let tx_result = candy_manager
+        .replace_config_lines(
+            context,
+            0,
+            vec![ConfigLine {
+                name: "100".to_string(),
+                uri: "0.json".to_string(),
+            }],
+        )
+        .await;
+    assert_tx_failed_with_error_code(tx_result, CandyError::ConfigLineNameTooLong);
+
+    let parsed = candy_manager.parse_config_lines(context).await;
+    assert_eq!(parsed.config_line_count_number, 3);
+    assert_eq!(parsed.config_lines[1].name, "Item #1");
+    assert_eq!(parsed.config_lines[1].uri, "https://arweave.net/1.json");
+
+    for _ in 0..3 {
+        candy_manager.mint_nft(context, None, None).await.unwrap();
+    }
+}
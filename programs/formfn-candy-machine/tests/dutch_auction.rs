@@ -0,0 +1,376 @@
+#![cfg(feature = "test-bpf")]
+#![allow(dead_code)]
+
+use chrono::Duration;
+use formfn_candy_machine::{CandyError, DutchAuctionSettings};
+use solana_program::pubkey::Pubkey;
+use solana_program_test::*;
+use solana_sdk::signature::{Keypair, Signer};
+
+use crate::core::helpers::{airdrop, get_balance, new_funded_keypair, prepare_nft};
+use crate::utils::{
+    candy_machine_program_test, claim_dutch_auction_rebate,
+    helpers::{
+        assert_tx_failed_with_error_code, find_buyer_info_account_pda, find_candy_creator,
+        get_current_unix_timestamp, sol,
+    },
+    mint_nft, CandyConfigBuilder, CandyManager, CandyManagerBuilder,
+};
+
+mod core;
+mod utils;
+
+const START_PRICE: u64 = 1_000_000_000;
+const FLOOR_PRICE: u64 = 200_000_000;
+const PRICE_DECREMENT: u64 = 100_000_000;
+// Long enough that the price can't change between building and sending a
+// transaction in the tests.
+const DECREMENT_INTERVAL: i64 = 10 * 60;
+
+fn get_dutch_auction_settings(rebate_enabled: bool) -> DutchAuctionSettings {
+    DutchAuctionSettings {
+        start_price: START_PRICE,
+        floor_price: FLOOR_PRICE,
+        price_decrement: PRICE_DECREMENT,
+        decrement_interval: DECREMENT_INTERVAL,
+        rebate_enabled,
+    }
+}
+
+#[tokio::test]
+async fn invalid_dutch_auction_settings() {
+    let mut context = candy_machine_program_test().start_with_context().await;
+    let context = &mut context;
+    let mut candy_manager = CandyManagerBuilder::default(context).await;
+
+    // The start price is below the floor price.
+    let candy_data = CandyConfigBuilder::new(&candy_manager)
+        .set_dutch_auction_settings(Some(DutchAuctionSettings {
+            start_price: FLOOR_PRICE - 1,
+            ..get_dutch_auction_settings(false)
+        }))
+        .build();
+    let tx_result = candy_manager.create(context, candy_data).await;
+    assert_tx_failed_with_error_code(tx_result, CandyError::InvalidDutchAuctionSettings);
+
+    // The price never declines.
+    let candy_data = CandyConfigBuilder::new(&candy_manager)
+        .set_dutch_auction_settings(Some(DutchAuctionSettings {
+            price_decrement: 0,
+            ..get_dutch_auction_settings(false)
+        }))
+        .build();
+    let tx_result = candy_manager.create(context, candy_data).await;
+    assert_tx_failed_with_error_code(tx_result, CandyError::InvalidDutchAuctionSettings);
+
+    let candy_data = CandyConfigBuilder::new(&candy_manager)
+        .set_dutch_auction_settings(Some(DutchAuctionSettings {
+            decrement_interval: 0,
+            ..get_dutch_auction_settings(false)
+        }))
+        .build();
+    let tx_result = candy_manager.create(context, candy_data).await;
+    assert_tx_failed_with_error_code(tx_result, CandyError::InvalidDutchAuctionSettings);
+}
+
+#[tokio::test]
+async fn mint_with_dutch_auction_price() {
+    let mut context = candy_machine_program_test().start_with_context().await;
+    let context = &mut context;
+    let mut candy_manager = CandyManagerBuilder::default(context).await;
+
+    // Two price decrements have passed since the public sale started.
+    let public_sale_start_time =
+        get_current_unix_timestamp() - 2 * DECREMENT_INTERVAL - Duration::minutes(1).num_seconds();
+    let candy_data = CandyConfigBuilder::new(&candy_manager)
+        .set_items_available(5)
+        .set_public_sale_start_time(public_sale_start_time)
+        .set_dutch_auction_settings(Some(get_dutch_auction_settings(false)))
+        .build();
+    candy_manager.create(context, candy_data).await.unwrap();
+    candy_manager.fill_config_lines(context).await.unwrap();
+
+    let expected_price = START_PRICE - 2 * PRICE_DECREMENT;
+    assert_eq!(
+        candy_manager.get_mint_price_for_proof(context, &None).await,
+        expected_price
+    );
+
+    candy_manager
+        .mint_and_assert_successful(context, Some(expected_price), true, None)
+        .await
+        .unwrap();
+
+    // The price never drops below the floor price.
+    let mut candy_data = candy_manager.get_candy(context).await.data;
+    candy_data.public_sale_start_time = get_current_unix_timestamp() - 100 * DECREMENT_INTERVAL;
+    candy_manager
+        .update(context, None, candy_data)
+        .await
+        .unwrap();
+
+    candy_manager
+        .mint_and_assert_successful(context, Some(FLOOR_PRICE), true, None)
+        .await
+        .unwrap();
+
+    // The Dutch auction settings are locked once minting starts.
+    let mut candy_data = candy_manager.get_candy(context).await.data;
+    candy_data.dutch_auction_settings = None;
+    let tx_result = candy_manager.update(context, None, candy_data).await;
+    assert_tx_failed_with_error_code(tx_result, CandyError::CannotChangeDutchAuctionAfterMint);
+}
+
+/// Sells out a candy machine of two items in a Dutch auction with rebates, the
+/// first at the start price and the second three price decrements later.
+/// Returns the clearing price.
+async fn sell_out_dutch_auction(
+    context: &mut ProgramTestContext,
+    candy_manager: &mut CandyManager,
+) -> u64 {
+    let candy_data = CandyConfigBuilder::new(candy_manager)
+        .set_items_available(2)
+        .set_public_sale_start_time(
+            get_current_unix_timestamp() - Duration::minutes(1).num_seconds(),
+        )
+        .set_dutch_auction_settings(Some(get_dutch_auction_settings(true)))
+        .build();
+    candy_manager.create(context, candy_data).await.unwrap();
+    candy_manager.fill_config_lines(context).await.unwrap();
+
+    let treasury_start_balance = get_balance(context, &candy_manager.treasury_wallet).await;
+
+    // The first mint pays the start price.
+    candy_manager
+        .mint_and_assert_successful(context, None, true, None)
+        .await
+        .unwrap();
+
+    assert_eq!(
+        get_balance(context, &candy_manager.treasury_wallet).await - treasury_start_balance,
+        FLOOR_PRICE,
+        "Only the floor price should be paid out before the auction ends"
+    );
+
+    let tx_result = candy_manager.claim_dutch_auction_rebate(context).await;
+    assert_tx_failed_with_error_code(tx_result, CandyError::DutchAuctionNotEnded);
+
+    // The second mint happens three price decrements later and sells out the
+    // candy machine, which ends the auction.
+    let mut candy_data = candy_manager.get_candy(context).await.data;
+    candy_data.public_sale_start_time =
+        get_current_unix_timestamp() - 3 * DECREMENT_INTERVAL - Duration::minutes(1).num_seconds();
+    candy_manager
+        .update(context, None, candy_data)
+        .await
+        .unwrap();
+
+    candy_manager
+        .mint_and_assert_successful(context, None, true, None)
+        .await
+        .unwrap();
+
+    let clearing_price = START_PRICE - 3 * PRICE_DECREMENT;
+    let candy = candy_manager.get_candy(context).await;
+    assert_eq!(
+        candy.dutch_auction_rebate_state.clearing_price,
+        clearing_price
+    );
+    assert_eq!(candy.dutch_auction_rebate_state.items_sold, 2);
+
+    clearing_price
+}
+
+#[tokio::test]
+async fn claim_dutch_auction_rebate() {
+    let mut context = candy_machine_program_test().start_with_context().await;
+    let context = &mut context;
+    let mut candy_manager = CandyManagerBuilder::default(context).await;
+
+    let clearing_price = sell_out_dutch_auction(context, &mut candy_manager).await;
+
+    let minter_start_balance = get_balance(context, &candy_manager.minter.pubkey()).await;
+    candy_manager
+        .claim_dutch_auction_rebate(context)
+        .await
+        .unwrap();
+    let transaction_fee = 5000;
+    assert_eq!(
+        get_balance(context, &candy_manager.minter.pubkey()).await + transaction_fee
+            - minter_start_balance,
+        START_PRICE - clearing_price,
+        "Buyer should be rebated the difference to the clearing price"
+    );
+
+    let tx_result = candy_manager.claim_dutch_auction_rebate(context).await;
+    assert_tx_failed_with_error_code(tx_result, CandyError::NoDutchAuctionRebate);
+
+    let treasury_start_balance = get_balance(context, &candy_manager.treasury_wallet).await;
+    candy_manager
+        .release_dutch_auction_proceeds(context)
+        .await
+        .unwrap();
+    assert_eq!(
+        get_balance(context, &candy_manager.treasury_wallet).await - treasury_start_balance,
+        2 * (clearing_price - FLOOR_PRICE),
+        "Treasury wallet should receive the clearing price for each NFT"
+    );
+
+    let tx_result = candy_manager.release_dutch_auction_proceeds(context).await;
+    assert_tx_failed_with_error_code(tx_result, CandyError::DutchAuctionProceedsAlreadyReleased);
+}
+
+#[tokio::test]
+async fn withdraw_with_unclaimed_dutch_auction_rebate() {
+    let mut context = candy_machine_program_test().start_with_context().await;
+    let context = &mut context;
+    let mut candy_manager = CandyManagerBuilder::default(context).await;
+
+    let clearing_price = sell_out_dutch_auction(context, &mut candy_manager).await;
+
+    let tx_result = candy_manager.withdraw_funds(context).await;
+    assert_tx_failed_with_error_code(tx_result, CandyError::NoWithdrawWithDutchAuctionProceeds);
+
+    candy_manager
+        .release_dutch_auction_proceeds(context)
+        .await
+        .unwrap();
+    let candy = candy_manager.get_candy(context).await;
+    assert_eq!(
+        candy.dutch_auction_rebate_state.amount_held,
+        START_PRICE - clearing_price,
+        "Only the rebate should still be held"
+    );
+
+    let tx_result = candy_manager.withdraw_funds(context).await;
+    assert_tx_failed_with_error_code(
+        tx_result,
+        CandyError::NoWithdrawWithUnclaimedDutchAuctionRebates,
+    );
+
+    candy_manager
+        .claim_dutch_auction_rebate(context)
+        .await
+        .unwrap();
+    candy_manager.withdraw_funds(context).await.unwrap();
+}
+
+#[tokio::test]
+async fn dutch_auction_rebate_with_third_party_payer() {
+    let mut context = candy_machine_program_test().start_with_context().await;
+    let context = &mut context;
+    let mut candy_manager = CandyManagerBuilder::default(context).await;
+
+    let platform_fee_basis_points: u16 = 1000;
+    let platform_fee_wallet = Pubkey::new_unique();
+    // Fund the wallet so it exists before the first mint.
+    airdrop(context, &platform_fee_wallet, sol(1))
+        .await
+        .unwrap();
+
+    let candy_data = CandyConfigBuilder::new(&candy_manager)
+        .set_items_available(2)
+        .set_public_sale_start_time(
+            get_current_unix_timestamp() - Duration::minutes(1).num_seconds(),
+        )
+        .set_dutch_auction_settings(Some(get_dutch_auction_settings(true)))
+        .build();
+    candy_manager.create(context, candy_data).await.unwrap();
+    candy_manager.fill_config_lines(context).await.unwrap();
+    candy_manager
+        .update_platform_fee(
+            context,
+            platform_fee_basis_points,
+            Some(platform_fee_wallet),
+        )
+        .await
+        .unwrap();
+
+    // A third party pays the start price for an NFT minted to the recipient,
+    // and passes their own BuyerInfoAccount to record the rebate.
+    let payer = new_funded_keypair(context, sol(10)).await;
+    let recipient = Keypair::new();
+    let (candy_machine_creator, creator_bump) =
+        find_candy_creator(&candy_manager.candy_machine.pubkey());
+    let (payer_buyer_info_account, _) =
+        find_buyer_info_account_pda(&candy_manager.candy_machine.pubkey(), &payer.pubkey());
+    let mut mint_payment_accounts = candy_manager.get_mint_payment_accounts(context).await;
+    mint_payment_accounts.push(payer_buyer_info_account);
+
+    let nft_info = prepare_nft(&recipient).await;
+    let spl_token_allowlist_info = candy_manager.get_spl_token_allowlist_info(context).await;
+    let allowlist_accounts = candy_manager.get_allowlist_accounts(context).await;
+    mint_nft(
+        context,
+        &candy_manager.candy_machine.pubkey(),
+        &candy_machine_creator,
+        creator_bump,
+        &candy_manager.treasury_wallet,
+        &candy_manager.creator_authority.pubkey(),
+        &payer,
+        &recipient.pubkey(),
+        &nft_info,
+        candy_manager.token_info.clone(),
+        spl_token_allowlist_info,
+        candy_manager.collection_info.clone(),
+        candy_manager.freeze_info.clone(),
+        &mint_payment_accounts,
+        false,
+        None,
+        START_PRICE,
+        None,
+        &candy_manager.randomness_account,
+        &allowlist_accounts,
+    )
+    .await
+    .unwrap();
+
+    // The second mint happens three price decrements later and sells out the
+    // candy machine, which ends the auction.
+    let mut candy_data = candy_manager.get_candy(context).await.data;
+    candy_data.public_sale_start_time =
+        get_current_unix_timestamp() - 3 * DECREMENT_INTERVAL - Duration::minutes(1).num_seconds();
+    candy_manager
+        .update(context, None, candy_data)
+        .await
+        .unwrap();
+    candy_manager
+        .mint_and_assert_successful(context, None, true, None)
+        .await
+        .unwrap();
+
+    let clearing_price = START_PRICE - 3 * PRICE_DECREMENT;
+
+    // The rebate is owed to the payer, not the recipient.
+    let payer_start_balance = get_balance(context, &payer.pubkey()).await;
+    claim_dutch_auction_rebate(context, &candy_manager.candy_machine.pubkey(), &payer)
+        .await
+        .unwrap();
+    let transaction_fee = 5000;
+    assert_eq!(
+        get_balance(context, &payer.pubkey()).await + transaction_fee - payer_start_balance,
+        START_PRICE - clearing_price,
+        "Payer should be rebated the difference to the clearing price"
+    );
+
+    // The proceeds are paid out like a mint payment, including the platform
+    // fee.
+    let proceeds = 2 * (clearing_price - FLOOR_PRICE);
+    let platform_fee = proceeds * platform_fee_basis_points as u64 / 10000;
+    let treasury_start_balance = get_balance(context, &candy_manager.treasury_wallet).await;
+    let platform_fee_start_balance = get_balance(context, &platform_fee_wallet).await;
+    candy_manager
+        .release_dutch_auction_proceeds(context)
+        .await
+        .unwrap();
+    assert_eq!(
+        get_balance(context, &platform_fee_wallet).await - platform_fee_start_balance,
+        platform_fee,
+        "Platform fee wallet should receive the platform fee of the proceeds"
+    );
+    assert_eq!(
+        get_balance(context, &candy_manager.treasury_wallet).await - treasury_start_balance,
+        proceeds - platform_fee,
+        "Treasury wallet should receive the proceeds minus the platform fee"
+    );
+}
@@ -0,0 +1,91 @@
+#![cfg(feature = "test-bpf")]
+#![allow(dead_code)]
+
+use anchor_lang::{AccountDeserialize, AccountSerialize};
+use formfn_candy_machine::{
+    constants::{
+        COLLECTIONS_FEATURE_INDEX, FEATURES_VERSION, FREEZE_FEATURE_INDEX,
+        FREEZE_LOCK_FEATURE_INDEX,
+    },
+    layout::{CANDY_MACHINE_VERSION, VERSION_OFFSET},
+    CandyError, CandyMachine,
+};
+use solana_program_test::*;
+use solana_sdk::{account::AccountSharedData, signer::Signer};
+
+use crate::core::helpers::get_account;
+use crate::utils::{
+    candy_machine_program_test, helpers::assert_tx_failed_with_error_code, CandyConfigBuilder,
+    CandyManagerBuilder, FreezeConfig, DEFAULT_UUID,
+};
+
+mod core;
+mod utils;
+
+#[tokio::test]
+async fn migrate_legacy_feature_flags() {
+    let mut context = candy_machine_program_test().start_with_context().await;
+    let context = &mut context;
+    let mut candy_manager = CandyManagerBuilder::new()
+        .set_freeze(FreezeConfig::new(true, 60 * 60))
+        .build(context)
+        .await;
+    let candy_data = CandyConfigBuilder::new(&candy_manager).build();
+    candy_manager.create(context, candy_data).await.unwrap();
+
+    // The uuid is no longer overwritten with feature flags.
+    let candy = candy_manager.get_candy(context).await;
+    assert_eq!(candy.data.uuid, DEFAULT_UUID);
+    assert_eq!(candy.features_version, FEATURES_VERSION);
+    assert_eq!(candy.features, 0);
+
+    // Rewrite the account the way it was stored before the features bitfield,
+    // with freeze and freeze lock enabled in the uuid.
+    let candy_pubkey = candy_manager.candy_machine.pubkey();
+    let mut account = get_account(context, &candy_pubkey).await;
+    let mut candy = CandyMachine::try_deserialize(&mut account.data.as_ref()).unwrap();
+    candy.features_version = 0;
+    candy.features = 0;
+    candy.data.uuid = "011000".to_string();
+    candy
+        .try_serialize(&mut account.data.as_mut_slice())
+        .unwrap();
+    context.set_account(&candy_pubkey, &AccountSharedData::from(account));
+
+    let candy = candy_manager.get_candy(context).await;
+    assert!(candy.is_feature_active(FREEZE_FEATURE_INDEX));
+    assert!(candy.is_feature_active(FREEZE_LOCK_FEATURE_INDEX));
+    assert!(!candy.is_feature_active(COLLECTIONS_FEATURE_INDEX));
+
+    candy_manager.migrate_feature_flags(context).await.unwrap();
+
+    let candy = candy_manager.get_candy(context).await;
+    assert_eq!(candy.features_version, FEATURES_VERSION);
+    assert_eq!(
+        candy.features,
+        (1 << FREEZE_FEATURE_INDEX) | (1 << FREEZE_LOCK_FEATURE_INDEX)
+    );
+    assert_eq!(candy.data.uuid, "011000");
+
+    // Migrating again leaves the features as they are.
+    candy_manager.migrate_feature_flags(context).await.unwrap();
+    let migrated_candy = candy_manager.get_candy(context).await;
+    assert_eq!(migrated_candy.features, candy.features);
+}
+
+#[tokio::test]
+async fn migrate_feature_flags_requires_current_version() {
+    let mut context = candy_machine_program_test().start_with_context().await;
+    let context = &mut context;
+    let mut candy_manager = CandyManagerBuilder::default(context).await;
+    let candy_data = CandyConfigBuilder::new(&candy_manager).build();
+    candy_manager.create(context, candy_data).await.unwrap();
+
+    let candy_pubkey = candy_manager.candy_machine.pubkey();
+    let mut account = get_account(context, &candy_pubkey).await;
+    account.data[VERSION_OFFSET] = CANDY_MACHINE_VERSION - 1;
+    context.set_account(&candy_pubkey, &AccountSharedData::from(account));
+
+    let tx_result = candy_manager.migrate_feature_flags(context).await;
+    assert_tx_failed_with_error_code(tx_result, CandyError::CandyMachineNeedsMigration);
+}
@@ -9,13 +9,16 @@ use formfn_candy_machine::constants::{
     FREEZE_FEATURE_INDEX, FREEZE_FEE, FREEZE_LOCK_FEATURE_INDEX, MAX_FREEZE_TIME,
 };
 use formfn_candy_machine::{
-    is_feature_active, FreezePda, MintPhase, SplTokenAllowlistMode::BurnEveryTime,
+    CandyError, FreezePda, MintPhase, PurchaseCounters, RefundReceipt,
+    SplTokenAllowlistMode::BurnEveryTime,
 };
 
 use crate::core::helpers::{
     get_balance, get_token_balance, new_funded_keypair, update_blockhash_to_slot,
 };
-use crate::utils::helpers::test_start;
+use crate::utils::helpers::{
+    assert_tx_failed_with_error_code, find_refund_receipt_pda, test_start,
+};
 use crate::utils::FreezeConfig;
 use crate::{
     core::helpers::{assert_account_empty, clone_keypair},
@@ -185,14 +188,8 @@ async fn freeze_update() {
 
     let candy_machine_account = candy_manager.get_candy(context).await;
     assert_account_empty(context, &candy_manager.freeze_info.pda).await;
-    assert!(!is_feature_active(
-        &candy_machine_account.data.uuid,
-        FREEZE_FEATURE_INDEX
-    ));
-    assert!(!is_feature_active(
-        &candy_machine_account.data.uuid,
-        FREEZE_LOCK_FEATURE_INDEX
-    ));
+    assert!(!candy_machine_account.is_feature_active(FREEZE_FEATURE_INDEX));
+    assert!(!candy_machine_account.is_feature_active(FREEZE_LOCK_FEATURE_INDEX));
 
     candy_manager.set_freeze(context).await.unwrap();
     candy_manager
@@ -232,9 +229,9 @@ async fn freeze_update() {
     expected_freeze_pda.allow_thaw = true;
     let freeze_pda = candy_manager.get_freeze_pda(context).await;
     assert_eq!(freeze_pda, expected_freeze_pda);
-    let uuid = candy_manager.get_candy(context).await.data.uuid;
-    assert!(!is_feature_active(&uuid, FREEZE_FEATURE_INDEX));
-    assert!(is_feature_active(&uuid, FREEZE_LOCK_FEATURE_INDEX));
+    let candy_machine_account = candy_manager.get_candy(context).await;
+    assert!(!candy_machine_account.is_feature_active(FREEZE_FEATURE_INDEX));
+    assert!(candy_machine_account.is_feature_active(FREEZE_LOCK_FEATURE_INDEX));
 
     candy_manager
         .thaw_nft(context, &new_nft, &random_key)
@@ -470,3 +467,137 @@ async fn mint_out_unfreeze() {
     let post_balance = get_balance(context, &candy_manager.formfn_authority.pubkey()).await;
     assert!(post_balance - pre_balance >= sol(2));
 }
+
+#[tokio::test]
+async fn refund_nft_during_freeze() {
+    let mut context = candy_machine_program_test().start_with_context().await;
+    let context = &mut context;
+
+    let mut candy_manager = CandyManagerBuilder::new()
+        .set_freeze(FreezeConfig::new(true, MAX_FREEZE_TIME))
+        .build(context)
+        .await;
+
+    let candy_data = CandyConfigBuilder::new(&candy_manager)
+        .set_items_available(2)
+        .build();
+    candy_manager
+        .create(context, candy_data.clone())
+        .await
+        .unwrap();
+    candy_manager.fill_config_lines(context).await.unwrap();
+    candy_manager.set_freeze(context).await.unwrap();
+
+    let new_nft = candy_manager
+        .mint_and_assert_successful(context, Some(sol(1)), true, None)
+        .await
+        .unwrap();
+    candy_manager.assert_frozen(context, &new_nft).await;
+
+    let refund_receipt = candy_manager
+        .get_refund_receipt(context, &new_nft.mint.pubkey())
+        .await;
+    assert_eq!(refund_receipt.amount, sol(1));
+    assert_eq!(refund_receipt.payer, candy_manager.minter.pubkey());
+
+    let start_balance = get_balance(context, &candy_manager.minter.pubkey()).await;
+    candy_manager.refund_nft(context, &new_nft).await.unwrap();
+    let end_balance = get_balance(context, &candy_manager.minter.pubkey()).await;
+
+    // The mint price, freeze fee and refund receipt rent are returned, minus
+    // the transaction fee.
+    let rent = context.banks_client.get_rent().await.unwrap();
+    assert_eq!(
+        end_balance - start_balance,
+        sol(1) + FREEZE_FEE + rent.minimum_balance(RefundReceipt::SIZE) - 5000
+    );
+    assert_eq!(
+        get_token_balance(context, &new_nft.token_account).await,
+        0,
+        "Refunded NFT should be burned"
+    );
+    assert_account_empty(
+        context,
+        &find_refund_receipt_pda(
+            &candy_manager.candy_machine.pubkey(),
+            &new_nft.mint.pubkey(),
+        )
+        .0,
+    )
+    .await;
+
+    let candy_machine = candy_manager.get_candy(context).await;
+    assert_eq!(candy_machine.items_redeemed, 0);
+    let freeze_pda = candy_manager.get_freeze_pda(context).await;
+    assert_eq!(freeze_pda.frozen_count, 0);
+
+    // The NFT can only be refunded once.
+    candy_manager
+        .refund_nft(context, &new_nft)
+        .await
+        .unwrap_err();
+
+    // The config line of the refunded NFT can be minted again.
+    let nft1 = candy_manager
+        .mint_and_assert_successful(context, Some(sol(1)), true, None)
+        .await
+        .unwrap();
+    candy_manager
+        .mint_and_assert_successful(context, Some(sol(1)), true, None)
+        .await
+        .unwrap();
+
+    // Minting out ends the refund window.
+    let tx_result = candy_manager.refund_nft(context, &nft1).await;
+    assert_tx_failed_with_error_code(tx_result, CandyError::RefundWindowClosed);
+}
+
+#[tokio::test]
+async fn refund_nft_releases_limit_per_address() {
+    let mut context = candy_machine_program_test().start_with_context().await;
+    let context = &mut context;
+
+    let mut candy_manager = CandyManagerBuilder::new()
+        .set_freeze(FreezeConfig::new(true, MAX_FREEZE_TIME))
+        .build(context)
+        .await;
+
+    let candy_data = CandyConfigBuilder::new(&candy_manager)
+        .set_items_available(2)
+        .set_limit_per_address(1)
+        .build();
+    candy_manager.create(context, candy_data).await.unwrap();
+    candy_manager.fill_config_lines(context).await.unwrap();
+    candy_manager.set_freeze(context).await.unwrap();
+
+    let new_nft = candy_manager
+        .mint_and_assert_successful(context, Some(sol(1)), true, None)
+        .await
+        .unwrap();
+    let refund_receipt = candy_manager
+        .get_refund_receipt(context, &new_nft.mint.pubkey())
+        .await;
+    assert_eq!(refund_receipt.recipient, candy_manager.minter.pubkey());
+    assert_eq!(
+        refund_receipt.purchase_counters,
+        PurchaseCounters {
+            public_phase: true,
+            total: true,
+            ..PurchaseCounters::default()
+        }
+    );
+    candy_manager
+        .mint_and_assert_failure(context, None, CandyError::BuyLimitPerAddressExceeded)
+        .await;
+
+    candy_manager.refund_nft(context, &new_nft).await.unwrap();
+    let buyer_info_account = candy_manager.get_buyer_info_account(context).await;
+    assert_eq!(buyer_info_account.number_bought_public_phase, 0);
+    assert_eq!(buyer_info_account.number_bought_total, 0);
+
+    // The refunded NFT no longer counts towards the limit.
+    candy_manager
+        .mint_and_assert_successful(context, Some(sol(1)), true, None)
+        .await
+        .unwrap();
+}
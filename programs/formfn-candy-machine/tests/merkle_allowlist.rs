@@ -3,7 +3,7 @@
 
 use formfn_candy_machine::{
     constants::NUMBER_OF_MERKLE_ROOTS_TO_STORE, BuyerMerkleAllowlistProofData, CandyError,
-    MintPhase,
+    MerkleAllowlistRoot, MintPhase,
 };
 use merkle_test_utils::get_allowlist_config_data;
 use solana_program_test::*;
@@ -13,8 +13,9 @@ use crate::{
     core::helpers::{airdrop, clone_keypair},
     utils::{
         candy_machine_program_test,
-        helpers::{assert_tx_failed_with_error_code, sol},
+        helpers::{assert_tx_failed_with_error_code, get_current_unix_timestamp, sol},
         merkle_test_utils, CandyConfigBuilder, CandyManagerBuilder,
+        MAX_ROOT_NUMBER_PER_APPEND_MERKLE_ALLOWLIST_TX_FOR_TEST,
     },
 };
 
@@ -88,10 +89,10 @@ async fn create_merkle_allowlist() {
     );
 
     for (index, onchain_root) in allowlist_config.merkle_allowlist_data.iter().enumerate() {
-        let expected_root = candy_end.data.merkle_allowlist_root_list[index];
+        let expected_root = &candy_end.data.merkle_allowlist_root_list[index];
         for i in 0..32 {
             assert_eq!(
-                onchain_root.root[i], expected_root[i],
+                onchain_root.root[i], expected_root.root[i],
                 "Roots should match."
             )
         }
@@ -223,6 +224,145 @@ async fn mint_with_merkle_allowlist() {
     );
 }
 
+#[tokio::test]
+async fn mint_with_merkle_allowlist_root_settings() {
+    let allowlist_config = get_allowlist_config_data();
+
+    let mut context = candy_machine_program_test().start_with_context().await;
+    let context = &mut context;
+    let mut candy_manager = CandyManagerBuilder::new()
+        .set_candy_machine(allowlist_config.candy_machine_keypair)
+        .set_minter(allowlist_config.first_minter_keypair)
+        .set_collection(true)
+        .build(context)
+        .await;
+
+    let candy_data = CandyConfigBuilder::new(&candy_manager)
+        .set_items_available(10)
+        .enable_mint_phase(MintPhase::Allowlist)
+        .build();
+    candy_manager.create(context, candy_data).await.unwrap();
+    candy_manager.fill_config_lines(context).await.unwrap();
+    candy_manager.set_collection(context).await.unwrap();
+
+    let buyer = &allowlist_config.allowlist_buyers[0];
+    let buyer_keypair = Keypair::from_bytes(&buyer.keypair_object.secret_key).unwrap();
+    candy_manager.set_new_minter_keypair(buyer_keypair);
+    airdrop(context, &candy_manager.minter.pubkey(), sol(10))
+        .await
+        .unwrap();
+
+    let merkle_allowlist_proof_data = BuyerMerkleAllowlistProofData {
+        amount: buyer.amount,
+        proof: buyer.proof.clone(),
+        root_index_for_proof: buyer.merkle_tree_index,
+    };
+
+    let merkle_allowlist_data = &allowlist_config.merkle_allowlist_data;
+    let now = get_current_unix_timestamp();
+    let get_roots_with_buyer_root_settings = |start_time: Option<i64>,
+                                              end_time: Option<i64>,
+                                              price: Option<u64>|
+     -> Vec<MerkleAllowlistRoot> {
+        merkle_allowlist_data
+            .iter()
+            .enumerate()
+            .map(|(index, data)| {
+                if index == buyer.merkle_tree_index as usize {
+                    MerkleAllowlistRoot {
+                        root: data.root,
+                        start_time,
+                        end_time,
+                        price,
+                    }
+                } else {
+                    MerkleAllowlistRoot::from(data.root)
+                }
+            })
+            .collect()
+    };
+
+    // Roots with a start_time at or after their end_time are rejected.
+    let tx_result = candy_manager
+        .append_merkle_allowlist_roots_with_settings(
+            context,
+            vec![MerkleAllowlistRoot {
+                root: merkle_allowlist_data[0].root,
+                start_time: Some(now),
+                end_time: Some(now),
+                price: None,
+            }],
+        )
+        .await;
+    assert_tx_failed_with_error_code(tx_result, CandyError::InvalidMerkleAllowlistRootSettings);
+
+    // The buyer's root unlocks in the future, so minting should fail.
+    for roots in get_roots_with_buyer_root_settings(Some(now + 60 * 60), None, None)
+        .chunks(MAX_ROOT_NUMBER_PER_APPEND_MERKLE_ALLOWLIST_TX_FOR_TEST)
+    {
+        candy_manager
+            .append_merkle_allowlist_roots_with_settings(context, roots.to_vec())
+            .await
+            .unwrap();
+    }
+    candy_manager
+        .mint_and_assert_failure(
+            context,
+            Some(merkle_allowlist_proof_data.clone()),
+            CandyError::MerkleAllowlistRootNotLive,
+        )
+        .await;
+
+    // The buyer's root has ended, so minting should fail.
+    candy_manager
+        .clear_merkle_allowlist_roots(context)
+        .await
+        .unwrap();
+    for roots in get_roots_with_buyer_root_settings(Some(now - 60 * 60), Some(now - 60), None)
+        .chunks(MAX_ROOT_NUMBER_PER_APPEND_MERKLE_ALLOWLIST_TX_FOR_TEST)
+    {
+        candy_manager
+            .append_merkle_allowlist_roots_with_settings(context, roots.to_vec())
+            .await
+            .unwrap();
+    }
+    candy_manager
+        .mint_and_assert_failure(
+            context,
+            Some(merkle_allowlist_proof_data.clone()),
+            CandyError::MerkleAllowlistRootNotLive,
+        )
+        .await;
+
+    // The buyer's root is live and overrides the mint price.
+    let root_price = sol(2);
+    candy_manager
+        .clear_merkle_allowlist_roots(context)
+        .await
+        .unwrap();
+    for roots in get_roots_with_buyer_root_settings(
+        Some(now - 60 * 60),
+        Some(now + 60 * 60),
+        Some(root_price),
+    )
+    .chunks(MAX_ROOT_NUMBER_PER_APPEND_MERKLE_ALLOWLIST_TX_FOR_TEST)
+    {
+        candy_manager
+            .append_merkle_allowlist_roots_with_settings(context, roots.to_vec())
+            .await
+            .unwrap();
+    }
+    candy_manager
+        .mint_and_assert_successful(
+            context,
+            Some(root_price),
+            false,
+            Some(merkle_allowlist_proof_data),
+        )
+        .await
+        .unwrap();
+}
+
 #[tokio::test]
 async fn clear_merkle_allowlist() {
     let allowlist_config = get_allowlist_config_data();
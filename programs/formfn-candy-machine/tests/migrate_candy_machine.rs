@@ -0,0 +1,155 @@
+#![cfg(feature = "test-bpf")]
+#![allow(dead_code)]
+
+use anchor_lang::{AccountSerialize, AnchorSerialize, Discriminator};
+use formfn_candy_machine::{
+    constants::CONFIG_ARRAY_START,
+    layout::{get_config_array_start, CANDY_MACHINE_VERSION, VERSION_OFFSET},
+    CandyError, CandyMachine, MerkleAllowlistRoot, MintOrder,
+};
+use solana_program_test::*;
+use solana_sdk::{
+    account::{AccountSharedData, WritableAccount},
+    signer::{keypair::Keypair, Signer},
+};
+
+use crate::core::helpers::get_account;
+use crate::utils::{
+    candy_machine_program_test, helpers::assert_tx_failed_with_error_code, CandyConfigBuilder,
+    CandyManagerBuilder,
+};
+
+mod core;
+mod utils;
+
+/// Serializes the header of a candy machine the way it was stored before the
+/// account was versioned, with the tail of the header filled with stale bytes.
+fn serialize_v0_header(
+    candy: &CandyMachine,
+    sequential_mint_order_enabled: bool,
+    merkle_allowlist_root_list: &[[u8; 32]],
+) -> Vec<u8> {
+    let data = &candy.data;
+    let mut header = CandyMachine::discriminator().to_vec();
+    let buf = &mut header;
+    candy.formfn_authority.serialize(buf).unwrap();
+    candy.creator_authority.serialize(buf).unwrap();
+    candy.treasury_wallet.serialize(buf).unwrap();
+    candy.treasury_mint.serialize(buf).unwrap();
+    candy.items_redeemed.serialize(buf).unwrap();
+    data.uuid.serialize(buf).unwrap();
+    data.price.serialize(buf).unwrap();
+    data.premint_price.serialize(buf).unwrap();
+    data.allowlist_price.serialize(buf).unwrap();
+    data.symbol.serialize(buf).unwrap();
+    data.seller_fee_basis_points.serialize(buf).unwrap();
+    data.max_supply.serialize(buf).unwrap();
+    data.items_available.serialize(buf).unwrap();
+    data.is_mutable.serialize(buf).unwrap();
+    data.allowlist_sale_start_time.serialize(buf).unwrap();
+    data.public_sale_start_time.serialize(buf).unwrap();
+    data.public_sale_end_time.serialize(buf).unwrap();
+    data.creators.serialize(buf).unwrap();
+    data.omni_mint_wallets.serialize(buf).unwrap();
+    data.hidden_settings.serialize(buf).unwrap();
+    data.bot_protection_enabled.serialize(buf).unwrap();
+    data.limit_per_address.serialize(buf).unwrap();
+    sequential_mint_order_enabled.serialize(buf).unwrap();
+    merkle_allowlist_root_list.to_vec().serialize(buf).unwrap();
+    data.spl_token_allowlist_settings.serialize(buf).unwrap();
+
+    // CONFIG_ARRAY_START before the account was versioned.
+    let config_array_start = get_config_array_start(0).unwrap();
+    assert_eq!(config_array_start, 4092);
+    header.resize(config_array_start, u8::MAX);
+    header
+}
+
+/// Returns the header a candy machine is expected to have after migrating.
+fn serialize_current_header(candy: &CandyMachine) -> Vec<u8> {
+    let mut header = vec![0; CONFIG_ARRAY_START];
+    candy.try_serialize(&mut header.as_mut_slice()).unwrap();
+    header
+}
+
+#[tokio::test]
+async fn migrate_unversioned_candy_machine() {
+    let mut context = candy_machine_program_test().start_with_context().await;
+    let context = &mut context;
+    let mut candy_manager = CandyManagerBuilder::default(context).await;
+    let candy_data = CandyConfigBuilder::new(&candy_manager)
+        .set_items_available(5)
+        .set_mint_order(MintOrder::SequentialPremint)
+        .build();
+    candy_manager.create(context, candy_data).await.unwrap();
+    candy_manager.fill_config_lines(context).await.unwrap();
+    candy_manager.mint_nft(context, None, None).await.unwrap();
+
+    let candy_pubkey = candy_manager.candy_machine.pubkey();
+    let account = get_account(context, &candy_pubkey).await;
+    assert_eq!(account.data[8], CANDY_MACHINE_VERSION);
+    let mut candy = candy_manager.get_candy(context).await;
+
+    // Rewrite the account in the layout it had before it was versioned, which
+    // has a plain merkle root and stores the mint order as a bool.
+    let merkle_root = [7; 32];
+    let mut legacy_data = serialize_v0_header(&candy, true, &[merkle_root]);
+    legacy_data.extend_from_slice(&account.data[CONFIG_ARRAY_START..]);
+    let legacy_account = AccountSharedData::create(
+        account.lamports,
+        legacy_data,
+        formfn_candy_machine::id(),
+        false,
+        account.rent_epoch,
+    );
+    context.set_account(&candy_pubkey, &legacy_account);
+
+    candy_manager
+        .migrate_candy_machine(context, 0)
+        .await
+        .unwrap();
+
+    // The fields added since then are unset, and the feature flags are still
+    // read from the uuid until migrate_feature_flags is called.
+    candy.features_version = 0;
+    candy.data.merkle_allowlist_root_list = vec![MerkleAllowlistRoot::from(merkle_root)];
+    let migrated_account = get_account(context, &candy_pubkey).await;
+    assert_eq!(migrated_account.data.len(), account.data.len());
+    assert_eq!(
+        migrated_account.data[..CONFIG_ARRAY_START],
+        serialize_current_header(&candy)[..]
+    );
+    // The config lines and mint bitmask are back where they were.
+    assert_eq!(
+        migrated_account.data[CONFIG_ARRAY_START..],
+        account.data[CONFIG_ARRAY_START..]
+    );
+
+    let tx_result = candy_manager
+        .migrate_candy_machine(context, CANDY_MACHINE_VERSION)
+        .await;
+    assert_tx_failed_with_error_code(tx_result, CandyError::CandyMachineAlreadyMigrated);
+
+    candy_manager.mint_nft(context, None, None).await.unwrap();
+    assert_eq!(candy_manager.get_candy(context).await.items_redeemed, 2);
+}
+
+#[tokio::test]
+async fn unmigrated_candy_machine_is_rejected() {
+    let mut context = candy_machine_program_test().start_with_context().await;
+    let context = &mut context;
+    let mut candy_manager = CandyManagerBuilder::default(context).await;
+    let candy_data = CandyConfigBuilder::new(&candy_manager).build();
+    candy_manager.create(context, candy_data).await.unwrap();
+
+    let candy_pubkey = candy_manager.candy_machine.pubkey();
+    let mut account = get_account(context, &candy_pubkey).await;
+    account.data[VERSION_OFFSET] = CANDY_MACHINE_VERSION - 1;
+    context.set_account(&candy_pubkey, &AccountSharedData::from(account));
+
+    let platform_fee_wallet = Keypair::new().pubkey();
+    let tx_result = candy_manager
+        .update_platform_fee(context, 100, Some(platform_fee_wallet))
+        .await;
+    assert_tx_failed_with_error_code(tx_result, CandyError::CandyMachineNeedsMigration);
+}
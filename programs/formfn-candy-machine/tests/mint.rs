@@ -2,17 +2,21 @@
 #![allow(dead_code)]
 
 use anchor_client::solana_sdk::transaction::Transaction;
-use formfn_candy_machine::{CandyError, SplTokenAllowlistMode};
+use formfn_candy_machine::{CandyError, MintPhase, SplTokenAllowlistMode};
 use solana_program::pubkey::Pubkey;
 use solana_program_test::*;
 use solana_sdk::account::{AccountSharedData, WritableAccount};
 use solana_sdk::{signature::Keypair, signer::Signer};
+use spl_associated_token_account::get_associated_token_address;
 
-use crate::core::helpers::{prepare_nft, update_blockhash};
-use crate::utils::helpers::{assert_tx_failed_with_error_code, find_candy_creator};
+use crate::core::helpers::{
+    clone_keypair, get_balance, get_token_account, new_funded_keypair, prepare_nft,
+    update_blockhash,
+};
+use crate::utils::helpers::{assert_tx_failed_with_error_code, find_candy_creator, sol};
 use crate::utils::{
     candy_machine_program_test, mint_nft, mint_nft_ix, CandyConfigBuilder, CandyManagerBuilder,
-    SplTokenAllowlistConfig, SplTokenAllowlistInfo,
+    FreezeConfig, SplTokenAllowlistConfig, SplTokenAllowlistInfo,
 };
 
 pub mod core;
@@ -50,6 +54,8 @@ async fn fail_metadata_not_blank() {
 
     let mint_price = candy_manager.get_mint_price(context).await;
 
+    let spl_token_allowlist_info = candy_manager.get_spl_token_allowlist_info(context).await;
+    let allowlist_accounts = candy_manager.get_allowlist_accounts(context).await;
     let tx_result = mint_nft(
         context,
         &candy_manager.candy_machine.pubkey(),
@@ -58,14 +64,19 @@ async fn fail_metadata_not_blank() {
         &candy_manager.treasury_wallet,
         &candy_manager.creator_authority.pubkey(),
         &candy_manager.minter,
+        &candy_manager.minter.pubkey(),
         &nft_info,
         candy_manager.token_info.clone(),
-        candy_manager.spl_token_allowlist_info.clone(),
+        spl_token_allowlist_info,
         candy_manager.collection_info.clone(),
         candy_manager.freeze_info.clone(),
+        &[],
         false,
         None,
         mint_price,
+        None,
+        &candy_manager.randomness_account,
+        &allowlist_accounts,
     )
     .await;
     assert_tx_failed_with_error_code(tx_result, CandyError::MetadataAccountMustBeEmpty);
@@ -119,14 +130,19 @@ async fn metadata_check_before_bot_tax() {
         &candy_manager.treasury_wallet,
         &candy_manager.creator_authority.pubkey(),
         &candy_manager.minter,
+        &candy_manager.minter.pubkey(),
         &nft_info,
         candy_manager.token_info.clone(),
         candy_manager.spl_token_allowlist_info.clone(),
         candy_manager.collection_info.clone(),
         candy_manager.freeze_info.clone(),
+        &[],
         false,
         None,
         mint_price,
+        None,
+        &candy_manager.randomness_account,
+        &candy_manager.get_allowlist_accounts(context).await,
     );
 
     ix[0].accounts.pop();
@@ -145,3 +161,254 @@ async fn metadata_check_before_bot_tax() {
         .map_err(|e| e.into());
     assert_tx_failed_with_error_code(tx_result, CandyError::MetadataAccountMustBeEmpty);
 }
+
+#[tokio::test]
+async fn mint_with_third_party_payer() {
+    let mut context = candy_machine_program_test().start_with_context().await;
+    let context = &mut context;
+    let mut candy_manager = CandyManagerBuilder::default(context).await;
+
+    let candy_data = CandyConfigBuilder::new(&candy_manager)
+        .set_items_available(3)
+        .set_limit_per_address(1)
+        .build();
+    candy_manager
+        .create(context, candy_data.clone())
+        .await
+        .unwrap();
+    candy_manager.fill_config_lines(context).await.unwrap();
+
+    let payer = new_funded_keypair(context, sol(10)).await;
+    let recipient = Keypair::new();
+
+    let (candy_machine_creator, creator_bump) =
+        find_candy_creator(&candy_manager.candy_machine.pubkey());
+    let mint_price = candy_manager.get_mint_price(context).await;
+
+    let nft_info = prepare_nft(&recipient).await;
+    let spl_token_allowlist_info = candy_manager.get_spl_token_allowlist_info(context).await;
+    let allowlist_accounts = candy_manager.get_allowlist_accounts(context).await;
+    mint_nft(
+        context,
+        &candy_manager.candy_machine.pubkey(),
+        &candy_machine_creator,
+        creator_bump,
+        &candy_manager.treasury_wallet,
+        &candy_manager.creator_authority.pubkey(),
+        &payer,
+        &recipient.pubkey(),
+        &nft_info,
+        candy_manager.token_info.clone(),
+        spl_token_allowlist_info,
+        candy_manager.collection_info.clone(),
+        candy_manager.freeze_info.clone(),
+        &[],
+        false,
+        None,
+        mint_price,
+        None,
+        &candy_manager.randomness_account,
+        &allowlist_accounts,
+    )
+    .await
+    .unwrap();
+
+    let recipient_token_account = get_token_account(
+        context,
+        &get_associated_token_address(&recipient.pubkey(), &nft_info.mint.pubkey()),
+    )
+    .await
+    .unwrap();
+    assert_eq!(
+        recipient_token_account.amount, 1,
+        "Recipient should own the NFT"
+    );
+    assert_eq!(
+        get_balance(context, &recipient.pubkey()).await,
+        0,
+        "Recipient should not pay for the mint"
+    );
+
+    // The limit per address follows the recipient, so a different payer
+    // cannot mint to the same recipient again.
+    let nft_info = prepare_nft(&recipient).await;
+    let spl_token_allowlist_info = candy_manager.get_spl_token_allowlist_info(context).await;
+    let allowlist_accounts = candy_manager.get_allowlist_accounts(context).await;
+    let tx_result = mint_nft(
+        context,
+        &candy_manager.candy_machine.pubkey(),
+        &candy_machine_creator,
+        creator_bump,
+        &candy_manager.treasury_wallet,
+        &candy_manager.creator_authority.pubkey(),
+        &candy_manager.minter,
+        &recipient.pubkey(),
+        &nft_info,
+        candy_manager.token_info.clone(),
+        spl_token_allowlist_info,
+        candy_manager.collection_info.clone(),
+        candy_manager.freeze_info.clone(),
+        &[],
+        false,
+        None,
+        mint_price,
+        None,
+        &candy_manager.randomness_account,
+        &allowlist_accounts,
+    )
+    .await;
+    assert_tx_failed_with_error_code(tx_result, CandyError::BuyLimitPerAddressExceeded);
+
+    // The payer's own limit is untouched.
+    let nft_info = prepare_nft(&payer).await;
+    let spl_token_allowlist_info = candy_manager.get_spl_token_allowlist_info(context).await;
+    let allowlist_accounts = candy_manager.get_allowlist_accounts(context).await;
+    mint_nft(
+        context,
+        &candy_manager.candy_machine.pubkey(),
+        &candy_machine_creator,
+        creator_bump,
+        &candy_manager.treasury_wallet,
+        &candy_manager.creator_authority.pubkey(),
+        &payer,
+        &payer.pubkey(),
+        &nft_info,
+        candy_manager.token_info.clone(),
+        spl_token_allowlist_info,
+        candy_manager.collection_info.clone(),
+        candy_manager.freeze_info.clone(),
+        &[],
+        false,
+        None,
+        mint_price,
+        None,
+        &candy_manager.randomness_account,
+        &allowlist_accounts,
+    )
+    .await
+    .unwrap();
+}
+
+#[tokio::test]
+async fn gasless_mint_rejected_when_freeze_is_enabled() {
+    let mut context = candy_machine_program_test().start_with_context().await;
+    let context = &mut context;
+
+    let mut candy_manager = CandyManagerBuilder::new()
+        .set_freeze(FreezeConfig::new(true, 60 * 60))
+        .build(context)
+        .await;
+
+    let candy_data = CandyConfigBuilder::new(&candy_manager).build();
+    candy_manager
+        .create(context, candy_data.clone())
+        .await
+        .unwrap();
+    candy_manager.fill_config_lines(context).await.unwrap();
+    candy_manager.set_freeze(context).await.unwrap();
+
+    let payer = new_funded_keypair(context, sol(10)).await;
+    let recipient = clone_keypair(&candy_manager.minter);
+
+    let (candy_machine_creator, creator_bump) =
+        find_candy_creator(&candy_manager.candy_machine.pubkey());
+    let mint_price = candy_manager.get_mint_price(context).await;
+
+    // Freezing the NFT delegates the recipient's token account, which the
+    // recipient has to sign for.
+    let nft_info = prepare_nft(&recipient).await;
+    let spl_token_allowlist_info = candy_manager.get_spl_token_allowlist_info(context).await;
+    let allowlist_accounts = candy_manager.get_allowlist_accounts(context).await;
+    let tx_result = mint_nft(
+        context,
+        &candy_manager.candy_machine.pubkey(),
+        &candy_machine_creator,
+        creator_bump,
+        &candy_manager.treasury_wallet,
+        &candy_manager.creator_authority.pubkey(),
+        &payer,
+        &recipient.pubkey(),
+        &nft_info,
+        candy_manager.token_info.clone(),
+        spl_token_allowlist_info,
+        candy_manager.collection_info.clone(),
+        candy_manager.freeze_info.clone(),
+        &[],
+        false,
+        None,
+        mint_price,
+        None,
+        &candy_manager.randomness_account,
+        &allowlist_accounts,
+    )
+    .await;
+    assert_tx_failed_with_error_code(tx_result, CandyError::RecipientMustSign);
+
+    // The recipient can still mint by signing themselves.
+    candy_manager.mint_nft(context, None, None).await.unwrap();
+}
+
+#[tokio::test]
+async fn gasless_mint_rejected_when_spl_token_allowlist_token_is_burned() {
+    let mut context = candy_machine_program_test().start_with_context().await;
+    let context = &mut context;
+
+    let spl_token_allowlist_settings =
+        SplTokenAllowlistConfig::new(SplTokenAllowlistMode::BurnEveryTime);
+    let mut candy_manager = CandyManagerBuilder::new()
+        .set_spl_token_allowlist_config(spl_token_allowlist_settings.clone())
+        .build(context)
+        .await;
+
+    let candy_data = CandyConfigBuilder::new(&candy_manager)
+        .enable_mint_phase(MintPhase::Allowlist)
+        .set_spl_token_allowlist_settings(SplTokenAllowlistConfig::to_candy_format(
+            spl_token_allowlist_settings,
+            &candy_manager.spl_token_allowlist_info.mint,
+        ))
+        .build();
+    candy_manager
+        .create(context, candy_data.clone())
+        .await
+        .unwrap();
+    candy_manager.fill_config_lines(context).await.unwrap();
+
+    let payer = new_funded_keypair(context, sol(10)).await;
+    let recipient = clone_keypair(&candy_manager.minter);
+
+    let (candy_machine_creator, creator_bump) =
+        find_candy_creator(&candy_manager.candy_machine.pubkey());
+    let mint_price = candy_manager.get_mint_price(context).await;
+
+    // Burning the allowlist token requires the recipient's signature.
+    let nft_info = prepare_nft(&recipient).await;
+    let spl_token_allowlist_info = candy_manager.get_spl_token_allowlist_info(context).await;
+    let allowlist_accounts = candy_manager.get_allowlist_accounts(context).await;
+    let tx_result = mint_nft(
+        context,
+        &candy_manager.candy_machine.pubkey(),
+        &candy_machine_creator,
+        creator_bump,
+        &candy_manager.treasury_wallet,
+        &candy_manager.creator_authority.pubkey(),
+        &payer,
+        &recipient.pubkey(),
+        &nft_info,
+        candy_manager.token_info.clone(),
+        spl_token_allowlist_info,
+        candy_manager.collection_info.clone(),
+        candy_manager.freeze_info.clone(),
+        &[],
+        false,
+        None,
+        mint_price,
+        None,
+        &candy_manager.randomness_account,
+        &allowlist_accounts,
+    )
+    .await;
+    assert_tx_failed_with_error_code(tx_result, CandyError::RecipientMustSign);
+
+    // The recipient can still mint by signing themselves.
+    candy_manager.mint_nft(context, None, None).await.unwrap();
+}
@@ -1,15 +1,20 @@
 #![cfg(feature = "test-bpf")]
 #![allow(dead_code)]
 
-use formfn_candy_machine::CandyError;
+use formfn_candy_machine::{constants::MAX_MINT_MANY_QUANTITY, CandyError};
+use mpl_token_metadata::state::Collection;
+use solana_program::pubkey::Pubkey;
 use solana_program_test::*;
 use solana_sdk::{signature::Keypair, signer::Signer};
 
-use crate::core::helpers::get_balance;
+use crate::core::{
+    helpers::{airdrop, get_balance},
+    MetadataManager,
+};
 use crate::utils::{
     candy_machine_program_test,
     helpers::{lamports, sol},
-    CandyConfigBuilder, CandyManagerBuilder,
+    CandyConfigBuilder, CandyManagerBuilder, FreezeConfig,
 };
 
 mod core;
@@ -80,3 +85,216 @@ async fn mint_many() {
         balance_diff, total_sol_spend, candy_end.items_redeemed
     );
 }
+
+#[tokio::test]
+async fn mint_many_in_single_transaction() {
+    let mut context = candy_machine_program_test().start_with_context().await;
+    let context = &mut context;
+
+    let nft_total_supply_for_mint = 5;
+    let price_in_sol = sol(1);
+
+    let mut candy_manager = CandyManagerBuilder::new()
+        .set_sol_airdrop_size_for_minter(lamports(sol(20)))
+        .build(context)
+        .await;
+
+    let candy_data = CandyConfigBuilder::new(&candy_manager)
+        .set_items_available(nft_total_supply_for_mint)
+        .set_price(price_in_sol)
+        .build();
+    candy_manager
+        .create(context, candy_data.clone())
+        .await
+        .unwrap();
+    candy_manager.fill_config_lines(context).await.unwrap();
+
+    candy_manager
+        .mint_many_and_assert_failure(context, 0, None, CandyError::InvalidMintQuantity)
+        .await;
+    candy_manager
+        .mint_many_and_assert_failure(
+            context,
+            MAX_MINT_MANY_QUANTITY as u8 + 1,
+            None,
+            CandyError::InvalidMintQuantity,
+        )
+        .await;
+
+    candy_manager
+        .mint_many_and_assert_successful(context, 2, None)
+        .await
+        .unwrap();
+    candy_manager
+        .mint_many_and_assert_successful(context, 2, None)
+        .await
+        .unwrap();
+
+    // Only one item is left, so minting two should fail.
+    candy_manager
+        .mint_many_and_assert_failure(context, 2, None, CandyError::CandyMachineEmpty)
+        .await;
+
+    candy_manager
+        .mint_many_and_assert_successful(context, 1, None)
+        .await
+        .unwrap();
+
+    let candy_end = candy_manager.get_candy(context).await;
+    assert_eq!(
+        candy_end.items_redeemed, nft_total_supply_for_mint,
+        "Total redeemed should equal all available nfts"
+    );
+}
+
+#[tokio::test]
+async fn mint_many_respects_limit_per_address() {
+    let mut context = candy_machine_program_test().start_with_context().await;
+    let context = &mut context;
+
+    let mut candy_manager = CandyManagerBuilder::new()
+        .set_sol_airdrop_size_for_minter(lamports(sol(20)))
+        .build(context)
+        .await;
+
+    let candy_data = CandyConfigBuilder::new(&candy_manager)
+        .set_items_available(10)
+        .set_limit_per_address(3)
+        .build();
+    candy_manager
+        .create(context, candy_data.clone())
+        .await
+        .unwrap();
+    candy_manager.fill_config_lines(context).await.unwrap();
+
+    candy_manager
+        .mint_many_and_assert_successful(context, 2, None)
+        .await
+        .unwrap();
+
+    // A mint which would take the buyer past the limit fails as a whole.
+    candy_manager
+        .mint_many_and_assert_failure(context, 2, None, CandyError::BuyLimitPerAddressExceeded)
+        .await;
+
+    candy_manager
+        .mint_many_and_assert_successful(context, 1, None)
+        .await
+        .unwrap();
+
+    let candy_end = candy_manager.get_candy(context).await;
+    assert_eq!(candy_end.items_redeemed, 3);
+}
+
+#[tokio::test]
+async fn mint_many_refund_receipts_cover_the_held_payment() {
+    let mut context = candy_machine_program_test().start_with_context().await;
+    let context = &mut context;
+
+    let quantity = 3;
+    // The platform fee leaves a held payment which doesn't divide evenly by
+    // the quantity.
+    let price = sol(1) + 7;
+    let platform_fee_basis_points = 477;
+    let platform_fee_wallet = Pubkey::new_unique();
+    airdrop(context, &platform_fee_wallet, sol(1))
+        .await
+        .unwrap();
+
+    let mut candy_manager = CandyManagerBuilder::new()
+        .set_sol_airdrop_size_for_minter(lamports(sol(20)))
+        .set_freeze(FreezeConfig::new(true, 60 * 60))
+        .build(context)
+        .await;
+
+    let candy_data = CandyConfigBuilder::new(&candy_manager)
+        .set_items_available(quantity)
+        .set_price(price)
+        .build();
+    candy_manager
+        .create(context, candy_data.clone())
+        .await
+        .unwrap();
+    candy_manager.fill_config_lines(context).await.unwrap();
+    candy_manager.set_freeze(context).await.unwrap();
+    candy_manager
+        .update_platform_fee(
+            context,
+            platform_fee_basis_points,
+            Some(platform_fee_wallet),
+        )
+        .await
+        .unwrap();
+
+    let new_nfts = candy_manager
+        .mint_many_and_assert_successful(context, quantity as u8, None)
+        .await
+        .unwrap();
+
+    let total_price = price * quantity;
+    let platform_fee = total_price * platform_fee_basis_points as u64 / 10000;
+    let held_payment = total_price - platform_fee;
+    assert_ne!(held_payment % quantity, 0);
+
+    let mut refund_amounts = Vec::new();
+    for new_nft in new_nfts.iter() {
+        let refund_receipt = candy_manager
+            .get_refund_receipt(context, &new_nft.mint.pubkey())
+            .await;
+        refund_amounts.push(refund_receipt.amount);
+    }
+
+    // The last NFT receives the remainder, so refunding every NFT returns the
+    // whole held payment.
+    let refund_amount = held_payment / quantity;
+    assert_eq!(
+        refund_amounts,
+        vec![
+            refund_amount,
+            refund_amount,
+            held_payment - 2 * refund_amount
+        ]
+    );
+    assert_eq!(refund_amounts.iter().sum::<u64>(), held_payment);
+}
+
+#[tokio::test]
+async fn mint_many_into_collection() {
+    let mut context = candy_machine_program_test().start_with_context().await;
+    let context = &mut context;
+
+    let mut candy_manager = CandyManagerBuilder::new()
+        .set_sol_airdrop_size_for_minter(lamports(sol(20)))
+        .set_collection(true)
+        .build(context)
+        .await;
+
+    let candy_data = CandyConfigBuilder::new(&candy_manager)
+        .set_items_available(5)
+        .build();
+    candy_manager
+        .create(context, candy_data.clone())
+        .await
+        .unwrap();
+    candy_manager.fill_config_lines(context).await.unwrap();
+    candy_manager.set_collection(context).await.unwrap();
+
+    // Each NFT is verified by the set_collection_during_mint instruction
+    // following the mint at its index.
+    let new_nfts = candy_manager
+        .mint_many_and_assert_successful(context, 3, None)
+        .await
+        .unwrap();
+
+    for new_nft in new_nfts.iter() {
+        let metadata =
+            MetadataManager::get_data_from_account(context, &new_nft.metadata_pubkey).await;
+        assert_eq!(
+            metadata.collection,
+            Some(Collection {
+                verified: true,
+                key: candy_manager.collection_info.mint.pubkey(),
+            })
+        );
+    }
+}
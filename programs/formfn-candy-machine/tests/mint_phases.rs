@@ -6,21 +6,25 @@ use solana_program_test::*;
 use solana_sdk::{signature::Keypair, signature::Signer};
 
 use formfn_candy_machine::{
-    BuyerMerkleAllowlistProofData, CandyError, CandyMachineData, MintPhase,
-    SplTokenAllowlistMode::BurnEveryTime,
+    constants::MAX_MINT_PHASES, BuyerMerkleAllowlistProofData, CandyError, CandyMachineData,
+    MintPhase, MintPhaseAllowlistSource, SplTokenAllowlistMode::BurnEveryTime,
 };
 use merkle_test_utils::get_empty_merkle_tree_node;
 use utils::{
-    helpers::{assert_tx_failed_with_error_code, get_current_unix_timestamp, sol},
-    CandyConfigBuilder,
+    helpers::{
+        assert_tx_failed_with_error_code, find_candy_creator, get_current_unix_timestamp, sol,
+    },
+    CandyConfigBuilder, DEFAULT_PRICE,
 };
 
 use crate::utils::{
-    candy_machine_program_test, merkle_test_utils, CandyManagerBuilder, SplTokenAllowlistConfig,
-    DEFAULT_SOL_AIRDROP_SIZE,
+    candy_machine_program_test, merkle_test_utils, mint_nft, CandyManagerBuilder,
+    SplTokenAllowlistConfig, DEFAULT_SOL_AIRDROP_SIZE,
 };
 
-use crate::core::helpers::{airdrop, clone_keypair};
+use crate::core::helpers::{
+    airdrop, assert_account_empty, clone_keypair, new_funded_keypair, prepare_nft,
+};
 
 mod core;
 mod utils;
@@ -345,3 +349,338 @@ async fn omni_minters_not_creator_authority_can_always_mint() {
         .await
         .unwrap();
 }
+
+#[tokio::test]
+async fn create_candy_machine_with_invalid_configured_mint_phases() {
+    let mut context = candy_machine_program_test().start_with_context().await;
+    let context = &mut context;
+    let mut candy_manager = CandyManagerBuilder::default(context).await;
+
+    let now = get_current_unix_timestamp();
+
+    // Mint phases out of order.
+    let candy_data = CandyConfigBuilder::new(&candy_manager)
+        .add_mint_phase(
+            now + Duration::minutes(2).num_seconds(),
+            DEFAULT_PRICE,
+            MintPhaseAllowlistSource::Public,
+            0,
+        )
+        .add_mint_phase(
+            now + Duration::minutes(1).num_seconds(),
+            DEFAULT_PRICE,
+            MintPhaseAllowlistSource::Public,
+            0,
+        )
+        .build();
+    let tx_result = candy_manager.create(context, candy_data).await;
+    assert_tx_failed_with_error_code(tx_result, CandyError::CandyMachineInvalidMintPhases);
+
+    // Mint phase starting after the sale ends.
+    let candy_data = CandyConfigBuilder::new(&candy_manager)
+        .set_public_sale_end_time(now + Duration::minutes(5).num_seconds())
+        .add_mint_phase(
+            now + Duration::minutes(6).num_seconds(),
+            DEFAULT_PRICE,
+            MintPhaseAllowlistSource::Public,
+            0,
+        )
+        .build();
+    let tx_result = candy_manager.create(context, candy_data).await;
+    assert_tx_failed_with_error_code(tx_result, CandyError::CandyMachineInvalidMintPhases);
+
+    // Too many mint phases.
+    let mut candy_config_builder = CandyConfigBuilder::new(&candy_manager);
+    for i in 0..=MAX_MINT_PHASES {
+        candy_config_builder = candy_config_builder.add_mint_phase(
+            now + Duration::minutes(i as i64 + 1).num_seconds(),
+            DEFAULT_PRICE,
+            MintPhaseAllowlistSource::Public,
+            0,
+        );
+    }
+    let tx_result = candy_manager
+        .create(context, candy_config_builder.build())
+        .await;
+    assert_tx_failed_with_error_code(tx_result, CandyError::TooManyMintPhases);
+
+    // SPL token allowlist phase without SPL token allowlist settings.
+    let candy_data = CandyConfigBuilder::new(&candy_manager)
+        .add_mint_phase(
+            now - Duration::minutes(1).num_seconds(),
+            DEFAULT_PRICE,
+            MintPhaseAllowlistSource::SplTokenAllowlist,
+            0,
+        )
+        .build();
+    let tx_result = candy_manager.create(context, candy_data).await;
+    assert_tx_failed_with_error_code(tx_result, CandyError::InvalidAllowlistSettings);
+}
+
+#[tokio::test]
+async fn mint_during_configured_mint_phases() {
+    let mut context = candy_machine_program_test().start_with_context().await;
+    let context = &mut context;
+    let mut candy_manager = CandyManagerBuilder::default(context).await;
+
+    let now = get_current_unix_timestamp();
+    let first_phase_price = DEFAULT_PRICE / 4;
+    let second_phase_price = DEFAULT_PRICE / 2;
+
+    let candy_data = CandyConfigBuilder::new(&candy_manager)
+        .add_mint_phase(
+            now - Duration::minutes(30).num_seconds(),
+            first_phase_price,
+            MintPhaseAllowlistSource::Public,
+            1,
+        )
+        .add_mint_phase(
+            now + Duration::minutes(30).num_seconds(),
+            second_phase_price,
+            MintPhaseAllowlistSource::Public,
+            2,
+        )
+        .build();
+
+    candy_manager.create(context, candy_data).await.unwrap();
+    candy_manager.fill_config_lines(context).await.unwrap();
+
+    candy_manager
+        .mint_and_assert_successful(context, Some(first_phase_price), false, None)
+        .await
+        .unwrap();
+
+    candy_manager
+        .mint_and_assert_failure(context, None, CandyError::BuyLimitPerAddressExceeded)
+        .await;
+
+    // Move the second phase into the past so it becomes the active phase.
+    let candy_data = CandyConfigBuilder::new(&candy_manager)
+        .add_mint_phase(
+            now - Duration::minutes(30).num_seconds(),
+            first_phase_price,
+            MintPhaseAllowlistSource::Public,
+            1,
+        )
+        .add_mint_phase(
+            now - Duration::minutes(1).num_seconds(),
+            second_phase_price,
+            MintPhaseAllowlistSource::Public,
+            2,
+        )
+        .build();
+
+    candy_manager
+        .update(context, None, candy_data)
+        .await
+        .unwrap();
+
+    for _ in 0..2 {
+        candy_manager
+            .mint_and_assert_successful(context, Some(second_phase_price), false, None)
+            .await
+            .unwrap();
+    }
+
+    candy_manager
+        .mint_and_assert_failure(context, None, CandyError::BuyLimitPerAddressExceeded)
+        .await;
+
+    let buyer_info_account = candy_manager.get_buyer_info_account(context).await;
+    assert_eq!(buyer_info_account.number_bought_per_mint_phase[0], 1);
+    assert_eq!(buyer_info_account.number_bought_per_mint_phase[1], 2);
+}
+
+#[tokio::test]
+async fn cannot_change_mint_phases_after_mint() {
+    let mut context = candy_machine_program_test().start_with_context().await;
+    let context = &mut context;
+    let mut candy_manager = CandyManagerBuilder::default(context).await;
+
+    let now = get_current_unix_timestamp();
+    let first_phase_start_time = now - Duration::minutes(30).num_seconds();
+    let second_phase_start_time = now + Duration::minutes(30).num_seconds();
+    let candy_data = CandyConfigBuilder::new(&candy_manager)
+        .add_mint_phase(
+            first_phase_start_time,
+            DEFAULT_PRICE,
+            MintPhaseAllowlistSource::Public,
+            0,
+        )
+        .add_mint_phase(
+            second_phase_start_time,
+            DEFAULT_PRICE,
+            MintPhaseAllowlistSource::MerkleAllowlist,
+            0,
+        )
+        .build();
+
+    candy_manager.create(context, candy_data).await.unwrap();
+    candy_manager.fill_config_lines(context).await.unwrap();
+    candy_manager
+        .mint_and_assert_successful(context, Some(DEFAULT_PRICE), false, None)
+        .await
+        .unwrap();
+
+    // Removing a phase.
+    let candy_data = CandyConfigBuilder::new(&candy_manager)
+        .add_mint_phase(
+            first_phase_start_time,
+            DEFAULT_PRICE,
+            MintPhaseAllowlistSource::Public,
+            0,
+        )
+        .build();
+    let tx_result = candy_manager.update(context, None, candy_data).await;
+    assert_tx_failed_with_error_code(tx_result, CandyError::CannotChangeMintPhasesAfterMint);
+
+    // Swapping the allowlist sources of the phases.
+    let candy_data = CandyConfigBuilder::new(&candy_manager)
+        .add_mint_phase(
+            first_phase_start_time,
+            DEFAULT_PRICE,
+            MintPhaseAllowlistSource::MerkleAllowlist,
+            0,
+        )
+        .add_mint_phase(
+            second_phase_start_time,
+            DEFAULT_PRICE,
+            MintPhaseAllowlistSource::Public,
+            0,
+        )
+        .build();
+    let tx_result = candy_manager.update(context, None, candy_data).await;
+    assert_tx_failed_with_error_code(tx_result, CandyError::CannotChangeMintPhasesAfterMint);
+
+    // Repricing a phase keeps it at its index.
+    let candy_data = CandyConfigBuilder::new(&candy_manager)
+        .add_mint_phase(
+            first_phase_start_time,
+            DEFAULT_PRICE / 2,
+            MintPhaseAllowlistSource::Public,
+            0,
+        )
+        .add_mint_phase(
+            second_phase_start_time,
+            DEFAULT_PRICE,
+            MintPhaseAllowlistSource::MerkleAllowlist,
+            0,
+        )
+        .build();
+    candy_manager
+        .update(context, None, candy_data)
+        .await
+        .unwrap();
+}
+
+#[tokio::test]
+async fn mint_before_first_configured_mint_phase() {
+    let mut context = candy_machine_program_test().start_with_context().await;
+    let context = &mut context;
+    let mut candy_manager = CandyManagerBuilder::default(context).await;
+
+    let now = get_current_unix_timestamp();
+    let candy_data = CandyConfigBuilder::new(&candy_manager)
+        .add_mint_phase(
+            now + Duration::minutes(30).num_seconds(),
+            DEFAULT_PRICE,
+            MintPhaseAllowlistSource::MerkleAllowlist,
+            0,
+        )
+        .build();
+
+    candy_manager.create(context, candy_data).await.unwrap();
+
+    candy_manager
+        .mint_and_assert_bot_tax(context, None, None)
+        .await
+        .unwrap();
+}
+
+#[tokio::test]
+async fn omni_minter_payer_cannot_mint_to_other_recipient() {
+    let mut context = candy_machine_program_test().start_with_context().await;
+    let context = &mut context;
+    let mut candy_manager = CandyManagerBuilder::default(context).await;
+
+    let omni_minter = new_funded_keypair(context, sol(DEFAULT_SOL_AIRDROP_SIZE)).await;
+    let candy_data = CandyConfigBuilder::new(&candy_manager)
+        .set_items_available(2)
+        .enable_mint_phase(MintPhase::Premint)
+        .add_omni_mint_wallet(omni_minter.pubkey())
+        .build();
+    candy_manager.create(context, candy_data).await.unwrap();
+    candy_manager.fill_config_lines(context).await.unwrap();
+
+    let (candy_machine_creator, creator_bump) =
+        find_candy_creator(&candy_manager.candy_machine.pubkey());
+    let mint_price = candy_manager.get_mint_price(context).await;
+
+    // Omni minters are checked against the recipient, like the limits per
+    // address, so an omni minter can't mint to someone else during premint.
+    let recipient = Keypair::new();
+    let nft_info = prepare_nft(&recipient).await;
+    let spl_token_allowlist_info = candy_manager.get_spl_token_allowlist_info(context).await;
+    let allowlist_accounts = candy_manager.get_allowlist_accounts(context).await;
+    mint_nft(
+        context,
+        &candy_manager.candy_machine.pubkey(),
+        &candy_machine_creator,
+        creator_bump,
+        &candy_manager.treasury_wallet,
+        &candy_manager.creator_authority.pubkey(),
+        &omni_minter,
+        &recipient.pubkey(),
+        &nft_info,
+        candy_manager.token_info.clone(),
+        spl_token_allowlist_info,
+        candy_manager.collection_info.clone(),
+        candy_manager.freeze_info.clone(),
+        &[],
+        false,
+        None,
+        mint_price,
+        None,
+        &candy_manager.randomness_account,
+        &allowlist_accounts,
+    )
+    .await
+    .unwrap();
+    assert_eq!(
+        candy_manager.get_candy(context).await.items_redeemed,
+        0,
+        "Omni minter payer should be bot taxed when minting to another recipient"
+    );
+    assert_account_empty(context, &nft_info.metadata_pubkey).await;
+
+    // Anyone can pay for a mint to the omni minter.
+    let payer = new_funded_keypair(context, sol(DEFAULT_SOL_AIRDROP_SIZE)).await;
+    let nft_info = prepare_nft(&omni_minter).await;
+    let spl_token_allowlist_info = candy_manager.get_spl_token_allowlist_info(context).await;
+    let allowlist_accounts = candy_manager.get_allowlist_accounts(context).await;
+    mint_nft(
+        context,
+        &candy_manager.candy_machine.pubkey(),
+        &candy_machine_creator,
+        creator_bump,
+        &candy_manager.treasury_wallet,
+        &candy_manager.creator_authority.pubkey(),
+        &payer,
+        &omni_minter.pubkey(),
+        &nft_info,
+        candy_manager.token_info.clone(),
+        spl_token_allowlist_info,
+        candy_manager.collection_info.clone(),
+        candy_manager.freeze_info.clone(),
+        &[],
+        false,
+        None,
+        mint_price,
+        None,
+        &candy_manager.randomness_account,
+        &allowlist_accounts,
+    )
+    .await
+    .unwrap();
+    assert_eq!(candy_manager.get_candy(context).await.items_redeemed, 1);
+}
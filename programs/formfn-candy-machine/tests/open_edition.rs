@@ -0,0 +1,105 @@
+#![cfg(feature = "test-bpf")]
+#![allow(dead_code)]
+
+use formfn_candy_machine::{CandyError, HiddenSettings};
+use mpl_token_metadata::state::{Edition, Key, TokenMetadataAccount};
+use solana_program_test::*;
+use solana_sdk::signer::Signer;
+
+use crate::core::{helpers::get_account, MasterEditionManager, MetadataManager};
+use crate::utils::{
+    candy_machine_program_test, helpers::assert_tx_failed_with_error_code, CandyConfigBuilder,
+    CandyManagerBuilder,
+};
+
+mod core;
+mod utils;
+
+#[tokio::test]
+async fn mint_open_edition() {
+    let mut context = candy_machine_program_test().start_with_context().await;
+    let context = &mut context;
+    let mut candy_manager = CandyManagerBuilder::default(context).await;
+
+    let candy_data = CandyConfigBuilder::new(&candy_manager)
+        .set_open_edition_enabled(true)
+        .set_items_available(u64::MAX)
+        .build();
+    candy_manager.create(context, candy_data).await.unwrap();
+
+    // Open editions don't have config lines.
+    let tx_result = candy_manager.fill_config_lines(context).await;
+    assert_tx_failed_with_error_code(tx_result, CandyError::NotSupportedForOpenEditions);
+
+    let metadata_info = MetadataManager::new(&candy_manager.formfn_authority);
+    metadata_info
+        .create_v2(
+            context,
+            "Open Edition".to_string(),
+            "OPEN".to_string(),
+            "URI".to_string(),
+            None,
+            0,
+            true,
+            Some(&candy_manager.formfn_authority.pubkey()),
+            None,
+            None,
+        )
+        .await
+        .unwrap();
+    let master_edition_info = MasterEditionManager::new(&metadata_info);
+    master_edition_info.create_v3(context, None).await.unwrap();
+
+    candy_manager
+        .set_open_edition(context, &master_edition_info)
+        .await
+        .unwrap();
+    let open_edition_pda = candy_manager.get_open_edition_pda(context).await;
+    assert_eq!(open_edition_pda.mint, master_edition_info.mint.pubkey());
+
+    for edition in 1..=2 {
+        let nft_info = candy_manager.mint_nft(context, None, None).await.unwrap();
+        let edition_account = get_account(context, &nft_info.edition_pubkey).await;
+        let print_edition = Edition::safe_deserialize(&edition_account.data).unwrap();
+        assert_eq!(print_edition.key, Key::EditionV1);
+        assert_eq!(print_edition.parent, master_edition_info.edition_pubkey);
+        assert_eq!(print_edition.edition, edition);
+
+        let metadata =
+            MetadataManager::get_data_from_account(context, &nft_info.metadata_pubkey).await;
+        assert_eq!(
+            metadata.update_authority,
+            candy_manager.creator_authority.pubkey()
+        );
+    }
+
+    let candy = candy_manager.get_candy(context).await;
+    assert_eq!(candy.items_redeemed, 2);
+    let master_edition = master_edition_info.get_data(context).await;
+    assert_eq!(master_edition.supply, 2);
+
+    // Open edition mode can't be turned off.
+    let new_data = CandyConfigBuilder::new(&candy_manager)
+        .set_items_available(u64::MAX)
+        .build();
+    let tx_result = candy_manager.update(context, None, new_data).await;
+    assert_tx_failed_with_error_code(tx_result, CandyError::CannotChangeOpenEditionMode);
+}
+
+#[tokio::test]
+async fn invalid_open_edition_settings() {
+    let mut context = candy_machine_program_test().start_with_context().await;
+    let context = &mut context;
+    let mut candy_manager = CandyManagerBuilder::default(context).await;
+
+    let candy_data = CandyConfigBuilder::new(&candy_manager)
+        .set_open_edition_enabled(true)
+        .set_hidden_settings(HiddenSettings {
+            name: "Open Edition".to_string(),
+            uri: "URI".to_string(),
+            hash: [0; 32],
+        })
+        .build();
+    let tx_result = candy_manager.create(context, candy_data).await;
+    assert_tx_failed_with_error_code(tx_result, CandyError::InvalidOpenEditionSettings);
+}
@@ -0,0 +1,169 @@
+#![cfg(feature = "test-bpf")]
+#![allow(dead_code)]
+
+use formfn_candy_machine::{
+    constants::MAX_PAYMENT_OPTIONS, CandyError, DutchAuctionSettings, MerkleAllowlistRoot,
+    MintPhaseAllowlistSource,
+};
+use solana_program::pubkey::Pubkey;
+use solana_program_test::*;
+
+use crate::utils::{
+    candy_machine_program_test,
+    helpers::{assert_tx_failed_with_error_code, sol},
+    CandyConfigBuilder, CandyManagerBuilder, FreezeConfig, TokenInfo, DEFAULT_PRICE,
+};
+
+mod core;
+mod utils;
+
+#[tokio::test]
+async fn invalid_payment_options() {
+    let mut context = candy_machine_program_test().start_with_context().await;
+    let context = &mut context;
+    let mut candy_manager = CandyManagerBuilder::default(context).await;
+
+    let mut candy_config_builder = CandyConfigBuilder::new(&candy_manager);
+    for _ in 0..MAX_PAYMENT_OPTIONS + 1 {
+        candy_config_builder =
+            candy_config_builder.add_payment_option(None, Pubkey::new_unique(), sol(1));
+    }
+    let tx_result = candy_manager
+        .create(context, candy_config_builder.build())
+        .await;
+    assert_tx_failed_with_error_code(tx_result, CandyError::TooManyPaymentOptions);
+
+    // Payment options can't be combined with a Dutch auction.
+    let candy_data = CandyConfigBuilder::new(&candy_manager)
+        .add_payment_option(None, Pubkey::new_unique(), sol(1))
+        .set_dutch_auction_settings(Some(DutchAuctionSettings {
+            start_price: sol(2),
+            floor_price: sol(1),
+            price_decrement: sol(1),
+            decrement_interval: 60,
+            rebate_enabled: false,
+        }))
+        .build();
+    let tx_result = candy_manager.create(context, candy_data).await;
+    assert_tx_failed_with_error_code(tx_result, CandyError::InvalidPaymentOptions);
+
+    // Nor with discounted phase prices, which payment options would bypass.
+    let candy_data = CandyConfigBuilder::new(&candy_manager)
+        .add_payment_option(None, Pubkey::new_unique(), sol(1))
+        .set_allowlist_price(DEFAULT_PRICE / 2)
+        .build();
+    let tx_result = candy_manager.create(context, candy_data).await;
+    assert_tx_failed_with_error_code(tx_result, CandyError::InvalidPaymentOptions);
+
+    let candy_data = CandyConfigBuilder::new(&candy_manager)
+        .add_payment_option(None, Pubkey::new_unique(), sol(1))
+        .add_mint_phase(0, DEFAULT_PRICE / 2, MintPhaseAllowlistSource::Public, 0)
+        .build();
+    let tx_result = candy_manager.create(context, candy_data).await;
+    assert_tx_failed_with_error_code(tx_result, CandyError::InvalidPaymentOptions);
+
+    // Or with a merkle allowlist root price.
+    let candy_data = CandyConfigBuilder::new(&candy_manager)
+        .add_payment_option(None, Pubkey::new_unique(), sol(1))
+        .build();
+    candy_manager.create(context, candy_data).await.unwrap();
+    let tx_result = candy_manager
+        .append_merkle_allowlist_roots_with_settings(
+            context,
+            vec![MerkleAllowlistRoot {
+                root: [1; 32],
+                start_time: None,
+                end_time: None,
+                price: Some(DEFAULT_PRICE / 2),
+            }],
+        )
+        .await;
+    assert_tx_failed_with_error_code(tx_result, CandyError::InvalidPaymentOptions);
+}
+
+#[tokio::test]
+async fn mint_with_spl_token_payment_option() {
+    let mut context = candy_machine_program_test().start_with_context().await;
+    let context = &mut context;
+    let mut candy_manager = CandyManagerBuilder::default(context).await;
+
+    // The candy machine takes SOL, and the SPL token as a payment option.
+    let spl_token_info = TokenInfo {
+        set: true,
+        ..candy_manager.token_info.clone()
+    };
+    let spl_token_price = 1;
+    let candy_data = CandyConfigBuilder::new(&candy_manager)
+        .add_payment_option(
+            Some(spl_token_info.mint),
+            spl_token_info.auth_account,
+            spl_token_price,
+        )
+        .build();
+    candy_manager.create(context, candy_data).await.unwrap();
+    candy_manager.fill_config_lines(context).await.unwrap();
+
+    // The treasury wallet must belong to the payment option.
+    candy_manager.set_payment_option(
+        Some(0),
+        spl_token_info.clone(),
+        candy_manager.treasury_wallet,
+    );
+    let tx_result = candy_manager.mint_nft(context, None, None).await;
+    assert_tx_failed_with_error_code(tx_result, CandyError::TreasuryWalletMismatch);
+
+    candy_manager.set_payment_option(Some(1), spl_token_info.clone(), spl_token_info.auth_account);
+    let tx_result = candy_manager.mint_nft(context, None, None).await;
+    assert_tx_failed_with_error_code(tx_result, CandyError::InvalidPaymentOptionIndex);
+
+    candy_manager.set_payment_option(Some(0), spl_token_info.clone(), spl_token_info.auth_account);
+    candy_manager
+        .mint_and_assert_successful(context, Some(spl_token_price), true, None)
+        .await
+        .unwrap();
+}
+
+#[tokio::test]
+async fn mint_with_payment_options_and_freeze() {
+    let mut context = candy_machine_program_test().start_with_context().await;
+    let context = &mut context;
+    let mut candy_manager = CandyManagerBuilder::new()
+        .set_freeze(FreezeConfig::new(true, 60 * 60))
+        .build(context)
+        .await;
+
+    let sol_token_info = candy_manager.token_info.clone();
+    let sol_treasury_wallet = candy_manager.treasury_wallet;
+    let spl_token_info = TokenInfo {
+        set: true,
+        ..candy_manager.token_info.clone()
+    };
+    let spl_token_price = 1;
+    let candy_data = CandyConfigBuilder::new(&candy_manager)
+        .add_payment_option(
+            Some(spl_token_info.mint),
+            spl_token_info.auth_account,
+            spl_token_price,
+        )
+        .build();
+    candy_manager.create(context, candy_data).await.unwrap();
+    candy_manager.fill_config_lines(context).await.unwrap();
+
+    // Setting the freeze requires the FreezePda ATA of each SPL token payment
+    // option.
+    candy_manager.set_payment_option(Some(0), spl_token_info.clone(), spl_token_info.auth_account);
+    candy_manager.set_freeze(context).await.unwrap();
+
+    // The payment is held by the FreezePda ATA of the payment option mint.
+    candy_manager
+        .mint_and_assert_successful(context, Some(spl_token_price), true, None)
+        .await
+        .unwrap();
+
+    // Paying with SOL still holds the payment in the FreezePda.
+    candy_manager.set_payment_option(None, sol_token_info, sol_treasury_wallet);
+    candy_manager
+        .mint_and_assert_successful(context, Some(sol(1)), true, None)
+        .await
+        .unwrap();
+}
@@ -0,0 +1,131 @@
+#![cfg(feature = "test-bpf")]
+#![allow(dead_code)]
+
+use formfn_candy_machine::{CandyError, PrimarySaleSplit};
+use solana_program::pubkey::Pubkey;
+use solana_program_test::*;
+use solana_sdk::signature::{Keypair, Signer};
+
+use crate::core::helpers::{airdrop, get_balance};
+use crate::utils::{
+    candy_machine_program_test,
+    helpers::{assert_tx_failed_with_error_code, sol},
+    update_platform_fee, CandyConfigBuilder, CandyManagerBuilder,
+};
+
+mod core;
+mod utils;
+
+#[tokio::test]
+async fn invalid_platform_fee() {
+    let mut context = candy_machine_program_test().start_with_context().await;
+    let context = &mut context;
+    let mut candy_manager = CandyManagerBuilder::default(context).await;
+    let candy_data = CandyConfigBuilder::new(&candy_manager).build();
+    candy_manager.create(context, candy_data).await.unwrap();
+
+    let tx_result = candy_manager
+        .update_platform_fee(context, 10001, Some(Pubkey::new_unique()))
+        .await;
+    assert_tx_failed_with_error_code(tx_result, CandyError::InvalidPlatformFee);
+
+    let tx_result = candy_manager.update_platform_fee(context, 500, None).await;
+    assert_tx_failed_with_error_code(tx_result, CandyError::InvalidPlatformFee);
+
+    // Only the formfn_authority may update the platform fee.
+    let creator_authority = Keypair::new();
+    airdrop(context, &creator_authority.pubkey(), sol(1))
+        .await
+        .unwrap();
+    let tx_result = update_platform_fee(
+        context,
+        &candy_manager.candy_machine.pubkey(),
+        &creator_authority,
+        500,
+        Some(creator_authority.pubkey()),
+    )
+    .await;
+    assert!(tx_result.is_err());
+}
+
+#[tokio::test]
+async fn mint_with_platform_fee() {
+    let mut context = candy_machine_program_test().start_with_context().await;
+    let context = &mut context;
+    let mut candy_manager = CandyManagerBuilder::default(context).await;
+
+    let price = sol(1);
+    let platform_fee_basis_points = 500;
+    let platform_fee_wallet = Pubkey::new_unique();
+    let split_wallet = Pubkey::new_unique();
+    // Fund the wallets so they exist before the first mint.
+    airdrop(context, &platform_fee_wallet, sol(1))
+        .await
+        .unwrap();
+    airdrop(context, &split_wallet, sol(1)).await.unwrap();
+
+    let candy_data = CandyConfigBuilder::new(&candy_manager)
+        .set_items_available(5)
+        .set_price(price)
+        .build();
+    candy_manager.create(context, candy_data).await.unwrap();
+    candy_manager.fill_config_lines(context).await.unwrap();
+    candy_manager
+        .update_platform_fee(
+            context,
+            platform_fee_basis_points,
+            Some(platform_fee_wallet),
+        )
+        .await
+        .unwrap();
+
+    let platform_fee = price * platform_fee_basis_points as u64 / 10000;
+    let treasury_start_balance = get_balance(context, &candy_manager.treasury_wallet).await;
+    let platform_fee_start_balance = get_balance(context, &platform_fee_wallet).await;
+
+    candy_manager
+        .mint_and_assert_successful(context, None, true, None)
+        .await
+        .unwrap();
+
+    assert_eq!(
+        get_balance(context, &platform_fee_wallet).await - platform_fee_start_balance,
+        platform_fee,
+        "Platform fee wallet should receive the platform fee"
+    );
+    assert_eq!(
+        get_balance(context, &candy_manager.treasury_wallet).await - treasury_start_balance,
+        price - platform_fee,
+        "Treasury wallet should receive the mint price minus the platform fee"
+    );
+
+    // The platform fee is taken before the rest of the price is split.
+    let mut candy_data = candy_manager.get_candy(context).await.data;
+    candy_data.primary_sale_splits = vec![PrimarySaleSplit {
+        address: split_wallet,
+        basis_points: 10000,
+    }];
+    candy_manager
+        .update(context, None, candy_data)
+        .await
+        .unwrap();
+
+    let platform_fee_start_balance = get_balance(context, &platform_fee_wallet).await;
+    let split_start_balance = get_balance(context, &split_wallet).await;
+
+    candy_manager
+        .mint_many_and_assert_successful(context, 2, None)
+        .await
+        .unwrap();
+
+    assert_eq!(
+        get_balance(context, &platform_fee_wallet).await - platform_fee_start_balance,
+        2 * platform_fee,
+        "Platform fee wallet should receive the platform fee for each NFT"
+    );
+    assert_eq!(
+        get_balance(context, &split_wallet).await - split_start_balance,
+        2 * (price - platform_fee),
+        "Split wallet should receive the mint price minus the platform fee"
+    );
+}
@@ -4,9 +4,12 @@
 use solana_program_test::*;
 use solana_sdk::signature::Keypair;
 
-use formfn_candy_machine::{BuyerMerkleAllowlistProofData, MintPhase};
+use formfn_candy_machine::{
+    BondingCurveMode, BondingCurveSettings, BuyerMerkleAllowlistProofData, CandyError,
+    DutchAuctionSettings, MintPhase,
+};
 use solana_sdk::signer::Signer;
-use utils::helpers::sol;
+use utils::helpers::{assert_tx_failed_with_error_code, sol};
 use utils::{CandyConfigBuilder, DEFAULT_PRICE};
 
 use crate::utils::{candy_machine_program_test, get_allowlist_config_data, CandyManagerBuilder};
@@ -166,3 +169,125 @@ async fn mint_with_merkle_allowlist_for_free() {
         .await
         .unwrap();
 }
+
+#[tokio::test]
+async fn invalid_bonding_curve_settings() {
+    let mut context = candy_machine_program_test().start_with_context().await;
+    let context = &mut context;
+    let mut candy_manager = CandyManagerBuilder::default(context).await;
+
+    // A step curve needs a step size.
+    let candy_data = CandyConfigBuilder::new(&candy_manager)
+        .set_bonding_curve_settings(Some(BondingCurveSettings {
+            mode: BondingCurveMode::Step,
+            start_price: sol(1),
+            price_increment: sol(1),
+            items_per_step: 0,
+        }))
+        .build();
+    let tx_result = candy_manager.create(context, candy_data).await;
+    assert_tx_failed_with_error_code(tx_result, CandyError::InvalidBondingCurveSettings);
+
+    // A bonding curve can't be combined with a Dutch auction.
+    let candy_data = CandyConfigBuilder::new(&candy_manager)
+        .set_bonding_curve_settings(Some(BondingCurveSettings {
+            mode: BondingCurveMode::Linear,
+            start_price: sol(1),
+            price_increment: sol(1),
+            items_per_step: 0,
+        }))
+        .set_dutch_auction_settings(Some(DutchAuctionSettings {
+            start_price: sol(2),
+            floor_price: sol(1),
+            price_decrement: sol(1),
+            decrement_interval: 60,
+            rebate_enabled: false,
+        }))
+        .build();
+    let tx_result = candy_manager.create(context, candy_data).await;
+    assert_tx_failed_with_error_code(tx_result, CandyError::InvalidBondingCurveSettings);
+}
+
+#[tokio::test]
+async fn mint_with_linear_bonding_curve() {
+    let mut context = candy_machine_program_test().start_with_context().await;
+    let context = &mut context;
+    let mut candy_manager = CandyManagerBuilder::default(context).await;
+
+    let start_price = sol(1);
+    let price_increment = sol(1) / 10;
+    let candy_data = CandyConfigBuilder::new(&candy_manager)
+        .set_items_available(5)
+        .set_bonding_curve_settings(Some(BondingCurveSettings {
+            mode: BondingCurveMode::Linear,
+            start_price,
+            price_increment,
+            items_per_step: 0,
+        }))
+        .build();
+    candy_manager.create(context, candy_data).await.unwrap();
+    candy_manager.fill_config_lines(context).await.unwrap();
+
+    for items_redeemed in 0..2 {
+        let expected_price = start_price + items_redeemed * price_increment;
+        assert_eq!(candy_manager.get_mint_price(context).await, expected_price);
+        candy_manager
+            .mint_and_assert_successful(context, Some(expected_price), true, None)
+            .await
+            .unwrap();
+    }
+
+    // Minting several NFTs at once charges each NFT its price on the curve,
+    // which mint_many_and_assert_successful checks.
+    candy_manager
+        .mint_many_and_assert_successful(context, 3, None)
+        .await
+        .unwrap();
+
+    // The curve can't be repriced mid-sale.
+    let mut candy_data = candy_manager.get_candy(context).await.data;
+    candy_data.bonding_curve_settings = Some(BondingCurveSettings {
+        mode: BondingCurveMode::Linear,
+        start_price,
+        price_increment: price_increment * 2,
+        items_per_step: 0,
+    });
+    let tx_result = candy_manager.update(context, None, candy_data).await;
+    assert_tx_failed_with_error_code(tx_result, CandyError::CannotChangeBondingCurveAfterMint);
+}
+
+#[tokio::test]
+async fn mint_with_step_bonding_curve() {
+    let mut context = candy_machine_program_test().start_with_context().await;
+    let context = &mut context;
+    let mut candy_manager = CandyManagerBuilder::default(context).await;
+
+    let start_price = sol(1);
+    let price_increment = sol(1) / 2;
+    let candy_data = CandyConfigBuilder::new(&candy_manager)
+        .set_items_available(5)
+        .set_bonding_curve_settings(Some(BondingCurveSettings {
+            mode: BondingCurveMode::Step,
+            start_price,
+            price_increment,
+            items_per_step: 2,
+        }))
+        .build();
+    candy_manager.create(context, candy_data).await.unwrap();
+    candy_manager.fill_config_lines(context).await.unwrap();
+
+    // The price only increases every 2 items.
+    for expected_price in [
+        start_price,
+        start_price,
+        start_price + price_increment,
+        start_price + price_increment,
+        start_price + 2 * price_increment,
+    ] {
+        assert_eq!(candy_manager.get_mint_price(context).await, expected_price);
+        candy_manager
+            .mint_and_assert_successful(context, Some(expected_price), true, None)
+            .await
+            .unwrap();
+    }
+}
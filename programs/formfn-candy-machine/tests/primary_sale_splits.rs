@@ -0,0 +1,162 @@
+#![cfg(feature = "test-bpf")]
+#![allow(dead_code)]
+
+use formfn_candy_machine::{constants::MAX_PRIMARY_SALE_SPLITS, CandyError, PrimarySaleSplit};
+use solana_program::pubkey::Pubkey;
+use solana_program_test::*;
+
+use crate::core::helpers::{airdrop, get_balance};
+use crate::utils::{
+    candy_machine_program_test,
+    helpers::{assert_tx_failed_with_error_code, sol},
+    CandyConfigBuilder, CandyManagerBuilder, FreezeConfig,
+};
+
+mod core;
+mod utils;
+
+#[tokio::test]
+async fn invalid_primary_sale_splits() {
+    let mut context = candy_machine_program_test().start_with_context().await;
+    let context = &mut context;
+    let mut candy_manager = CandyManagerBuilder::default(context).await;
+
+    // Splits which don't add up to 100%.
+    let candy_data = CandyConfigBuilder::new(&candy_manager)
+        .add_primary_sale_split(Pubkey::new_unique(), 5000)
+        .add_primary_sale_split(Pubkey::new_unique(), 4000)
+        .build();
+    let tx_result = candy_manager.create(context, candy_data).await;
+    assert_tx_failed_with_error_code(tx_result, CandyError::InvalidPrimarySaleSplits);
+
+    // A split with a zero share.
+    let candy_data = CandyConfigBuilder::new(&candy_manager)
+        .add_primary_sale_split(Pubkey::new_unique(), 10000)
+        .add_primary_sale_split(Pubkey::new_unique(), 0)
+        .build();
+    let tx_result = candy_manager.create(context, candy_data).await;
+    assert_tx_failed_with_error_code(tx_result, CandyError::InvalidPrimarySaleSplits);
+
+    let mut candy_config_builder = CandyConfigBuilder::new(&candy_manager);
+    for _ in 0..MAX_PRIMARY_SALE_SPLITS + 1 {
+        candy_config_builder = candy_config_builder.add_primary_sale_split(Pubkey::new_unique(), 1);
+    }
+    let tx_result = candy_manager
+        .create(context, candy_config_builder.build())
+        .await;
+    assert_tx_failed_with_error_code(tx_result, CandyError::TooManyPrimarySaleSplits);
+}
+
+#[tokio::test]
+async fn mint_with_primary_sale_splits() {
+    let mut context = candy_machine_program_test().start_with_context().await;
+    let context = &mut context;
+    let mut candy_manager = CandyManagerBuilder::default(context).await;
+
+    let price = sol(1);
+    let split_wallets = vec![
+        (Pubkey::new_unique(), 5000),
+        (Pubkey::new_unique(), 3000),
+        (Pubkey::new_unique(), 2000),
+    ];
+
+    let mut candy_config_builder = CandyConfigBuilder::new(&candy_manager)
+        .set_items_available(5)
+        .set_price(price);
+    for (address, basis_points) in split_wallets.iter() {
+        // Fund the split wallets so they exist before the first mint.
+        airdrop(context, address, sol(1)).await.unwrap();
+        candy_config_builder = candy_config_builder.add_primary_sale_split(*address, *basis_points);
+    }
+    candy_manager
+        .create(context, candy_config_builder.build())
+        .await
+        .unwrap();
+    candy_manager.fill_config_lines(context).await.unwrap();
+
+    let treasury_start_balance = get_balance(context, &candy_manager.treasury_wallet).await;
+    let mut split_start_balances = Vec::new();
+    for (address, _) in split_wallets.iter() {
+        split_start_balances.push(get_balance(context, address).await);
+    }
+
+    candy_manager
+        .mint_and_assert_successful(context, None, true, None)
+        .await
+        .unwrap();
+
+    for ((address, basis_points), start_balance) in
+        split_wallets.iter().zip(split_start_balances.iter())
+    {
+        let end_balance = get_balance(context, address).await;
+        assert_eq!(
+            end_balance - start_balance,
+            price * *basis_points as u64 / 10000,
+            "Split wallet should receive its share of the mint price"
+        );
+    }
+
+    assert_eq!(
+        get_balance(context, &candy_manager.treasury_wallet).await,
+        treasury_start_balance,
+        "Treasury wallet should not receive the mint price when splits are set"
+    );
+
+    // Minting several NFTs at once splits the total price.
+    let mut split_start_balances = Vec::new();
+    for (address, _) in split_wallets.iter() {
+        split_start_balances.push(get_balance(context, address).await);
+    }
+
+    candy_manager
+        .mint_many_and_assert_successful(context, 2, None)
+        .await
+        .unwrap();
+
+    for ((address, basis_points), start_balance) in
+        split_wallets.iter().zip(split_start_balances.iter())
+    {
+        let end_balance = get_balance(context, address).await;
+        assert_eq!(
+            end_balance - start_balance,
+            2 * price * *basis_points as u64 / 10000,
+            "Split wallet should receive its share of the total mint price"
+        );
+    }
+}
+
+#[tokio::test]
+async fn primary_sale_splits_with_freeze() {
+    let mut context = candy_machine_program_test().start_with_context().await;
+    let context = &mut context;
+    let mut candy_manager = CandyManagerBuilder::new()
+        .set_freeze(FreezeConfig::new(true, 60 * 60))
+        .build(context)
+        .await;
+
+    // The frozen funds are unlocked to a single wallet, so freeze can't be set
+    // with splits.
+    let candy_data = CandyConfigBuilder::new(&candy_manager)
+        .add_primary_sale_split(Pubkey::new_unique(), 10000)
+        .build();
+    candy_manager.create(context, candy_data).await.unwrap();
+
+    let tx_result = candy_manager.set_freeze(context).await;
+    assert_tx_failed_with_error_code(tx_result, CandyError::NoPrimarySaleSplitsWithFreeze);
+
+    // Nor can splits be added once freeze is set.
+    let mut candy_data = candy_manager.get_candy(context).await.data;
+    candy_data.primary_sale_splits = vec![];
+    candy_manager
+        .update(context, None, candy_data.clone())
+        .await
+        .unwrap();
+    candy_manager.set_freeze(context).await.unwrap();
+
+    candy_data.primary_sale_splits = vec![PrimarySaleSplit {
+        address: Pubkey::new_unique(),
+        basis_points: 10000,
+    }];
+    let tx_result = candy_manager.update(context, None, candy_data).await;
+    assert_tx_failed_with_error_code(tx_result, CandyError::NoPrimarySaleSplitsWithFreeze);
+}
@@ -0,0 +1,233 @@
+#![cfg(feature = "test-bpf")]
+#![allow(dead_code)]
+
+use formfn_candy_machine::{CandyError, ProgrammableNftSettings};
+use mpl_token_metadata::{
+    pda::find_token_record_account,
+    state::{
+        Collection, TokenDelegateRole, TokenMetadataAccount, TokenRecord, TokenStandard, TokenState,
+    },
+};
+use solana_program::pubkey::Pubkey;
+use solana_program_test::*;
+use solana_sdk::signer::Signer;
+
+use crate::core::{
+    helpers::{clone_keypair, get_account, prepare_nft},
+    MasterEditionManager, MetadataManager,
+};
+use crate::utils::{
+    candy_machine_program_test,
+    helpers::{assert_tx_failed_with_error_code, find_candy_creator},
+    mint_nft, CandyConfigBuilder, CandyManager, CandyManagerBuilder, FreezeConfig,
+    SolanaProgramTestResult, TOKEN_AUTH_RULES_PROGRAM_ID,
+};
+
+mod core;
+mod utils;
+
+#[tokio::test]
+async fn mint_programmable_nft() {
+    let mut context = candy_machine_program_test().start_with_context().await;
+    let context = &mut context;
+    let mut candy_manager = CandyManagerBuilder::default(context).await;
+
+    let candy_data = CandyConfigBuilder::new(&candy_manager)
+        .set_programmable_nft_settings(Some(ProgrammableNftSettings { rule_set: None }))
+        .build();
+    candy_manager
+        .create(context, candy_data.clone())
+        .await
+        .unwrap();
+    candy_manager.fill_config_lines(context).await.unwrap();
+
+    let nft_info = candy_manager.mint_nft(context, None, None).await.unwrap();
+    let metadata = MetadataManager::get_data_from_account(context, &nft_info.metadata_pubkey).await;
+    assert_eq!(
+        metadata.token_standard,
+        Some(TokenStandard::ProgrammableNonFungible)
+    );
+    assert!(metadata.primary_sale_happened);
+    assert_eq!(
+        metadata.update_authority,
+        candy_manager.creator_authority.pubkey()
+    );
+
+    // The token standard can't change once something has been minted.
+    let new_data = CandyConfigBuilder::new(&candy_manager).build();
+    let tx_result = candy_manager.update(context, None, new_data).await;
+    assert_tx_failed_with_error_code(tx_result, CandyError::CannotChangeProgrammableNftSettings);
+}
+
+#[tokio::test]
+async fn freeze_programmable_nft() {
+    let mut context = candy_machine_program_test().start_with_context().await;
+    let context = &mut context;
+    let mut candy_manager = CandyManagerBuilder::new()
+        .set_freeze(FreezeConfig::new(true, 60 * 60))
+        .build(context)
+        .await;
+
+    let candy_data = CandyConfigBuilder::new(&candy_manager)
+        .set_programmable_nft_settings(Some(ProgrammableNftSettings { rule_set: None }))
+        .build();
+    candy_manager
+        .create(context, candy_data.clone())
+        .await
+        .unwrap();
+    candy_manager.fill_config_lines(context).await.unwrap();
+    candy_manager.set_freeze(context).await.unwrap();
+
+    let nft_info = candy_manager.mint_nft(context, None, None).await.unwrap();
+
+    // A programmable NFT is frozen by delegating it to the FreezePda, which
+    // then locks its token record.
+    let token_record = get_token_record(context, &nft_info).await;
+    assert_eq!(token_record.state, TokenState::Locked);
+    assert_eq!(token_record.delegate, Some(candy_manager.freeze_info.pda));
+    assert_eq!(token_record.delegate_role, Some(TokenDelegateRole::Utility));
+    assert_eq!(candy_manager.get_freeze_pda(context).await.frozen_count, 1);
+
+    // Thawing unlocks the token record, and thawing as the owner also revokes
+    // the FreezePda delegate.
+    candy_manager.remove_freeze(context).await.unwrap();
+    candy_manager
+        .thaw_nft(
+            context,
+            &nft_info,
+            &clone_keypair(&candy_manager.formfn_authority),
+        )
+        .await
+        .unwrap();
+    let token_record = get_token_record(context, &nft_info).await;
+    assert_eq!(token_record.state, TokenState::Unlocked);
+    assert_eq!(token_record.delegate, Some(candy_manager.freeze_info.pda));
+
+    candy_manager
+        .thaw_nft(context, &nft_info, &nft_info.owner)
+        .await
+        .unwrap();
+    let token_record = get_token_record(context, &nft_info).await;
+    assert_eq!(token_record.state, TokenState::Unlocked);
+    assert_eq!(token_record.delegate, None);
+}
+
+#[tokio::test]
+async fn programmable_nft_rule_set_mismatch() {
+    let mut context = candy_machine_program_test().start_with_context().await;
+    let context = &mut context;
+    let mut candy_manager = CandyManagerBuilder::default(context).await;
+
+    let candy_data = CandyConfigBuilder::new(&candy_manager)
+        .set_programmable_nft_settings(Some(ProgrammableNftSettings {
+            rule_set: Some(Pubkey::new_unique()),
+        }))
+        .build();
+    candy_manager
+        .create(context, candy_data.clone())
+        .await
+        .unwrap();
+    candy_manager.fill_config_lines(context).await.unwrap();
+
+    let nft_info = prepare_nft(&candy_manager.minter).await;
+    let mut mint_payment_accounts = candy_manager.get_mint_payment_accounts(context).await;
+    mint_payment_accounts.extend([
+        find_token_record_account(&nft_info.mint.pubkey(), &nft_info.token_account).0,
+        TOKEN_AUTH_RULES_PROGRAM_ID,
+        Pubkey::new_unique(),
+    ]);
+    let tx_result =
+        mint_programmable_nft(context, &candy_manager, &nft_info, &mint_payment_accounts).await;
+    assert_tx_failed_with_error_code(tx_result, CandyError::RuleSetMismatch);
+}
+
+#[tokio::test]
+async fn mint_programmable_nft_into_collection() {
+    let mut context = candy_machine_program_test().start_with_context().await;
+    let context = &mut context;
+    let mut candy_manager = CandyManagerBuilder::new()
+        .set_collection(true)
+        .build(context)
+        .await;
+
+    let candy_data = CandyConfigBuilder::new(&candy_manager)
+        .set_programmable_nft_settings(Some(ProgrammableNftSettings { rule_set: None }))
+        .build();
+    candy_manager
+        .create(context, candy_data.clone())
+        .await
+        .unwrap();
+    candy_manager.fill_config_lines(context).await.unwrap();
+    candy_manager.set_collection(context).await.unwrap();
+
+    // The collection PDA has to be the one derived from the candy machine.
+    let nft_info = prepare_nft(&candy_manager.minter).await;
+    let mut mint_payment_accounts = candy_manager.get_mint_payment_accounts(context).await;
+    mint_payment_accounts.extend([
+        find_token_record_account(&nft_info.mint.pubkey(), &nft_info.token_account).0,
+        Pubkey::new_unique(),
+    ]);
+    let tx_result =
+        mint_programmable_nft(context, &candy_manager, &nft_info, &mint_payment_accounts).await;
+    assert_tx_failed_with_error_code(tx_result, CandyError::MismatchedCollectionPda);
+
+    let nft_info = candy_manager.mint_nft(context, None, None).await.unwrap();
+    let metadata = MetadataManager::get_data_from_account(context, &nft_info.metadata_pubkey).await;
+    assert_eq!(
+        metadata.token_standard,
+        Some(TokenStandard::ProgrammableNonFungible)
+    );
+    assert_eq!(
+        metadata.collection,
+        Some(Collection {
+            verified: true,
+            key: candy_manager.collection_info.mint.pubkey(),
+        })
+    );
+}
+
+async fn mint_programmable_nft(
+    context: &mut ProgramTestContext,
+    candy_manager: &CandyManager,
+    nft_info: &MasterEditionManager,
+    mint_payment_accounts: &[Pubkey],
+) -> SolanaProgramTestResult {
+    let (candy_machine_creator, creator_bump) =
+        find_candy_creator(&candy_manager.candy_machine.pubkey());
+    let mint_price = candy_manager.get_mint_price(context).await;
+    let spl_token_allowlist_info = candy_manager.get_spl_token_allowlist_info(context).await;
+    let allowlist_accounts = candy_manager.get_allowlist_accounts(context).await;
+    mint_nft(
+        context,
+        &candy_manager.candy_machine.pubkey(),
+        &candy_machine_creator,
+        creator_bump,
+        &candy_manager.treasury_wallet,
+        &candy_manager.creator_authority.pubkey(),
+        &candy_manager.minter,
+        &candy_manager.minter.pubkey(),
+        nft_info,
+        candy_manager.token_info.clone(),
+        spl_token_allowlist_info,
+        candy_manager.collection_info.clone(),
+        candy_manager.freeze_info.clone(),
+        mint_payment_accounts,
+        false,
+        None,
+        mint_price,
+        None,
+        &candy_manager.randomness_account,
+        &allowlist_accounts,
+    )
+    .await
+}
+
+async fn get_token_record(
+    context: &mut ProgramTestContext,
+    nft_info: &MasterEditionManager,
+) -> TokenRecord {
+    let (token_record, _) =
+        find_token_record_account(&nft_info.mint.pubkey(), &nft_info.token_account);
+    let account = get_account(context, &token_record).await;
+    TokenRecord::safe_deserialize(&account.data).unwrap()
+}
@@ -0,0 +1,99 @@
+#![cfg(feature = "test-bpf")]
+#![allow(dead_code)]
+
+use formfn_candy_machine::{get_space_for_candy, CandyError};
+use solana_program_test::*;
+use solana_sdk::signer::Signer;
+
+use crate::core::helpers::get_account;
+use crate::utils::{
+    candy_machine_program_test,
+    helpers::{assert_tx_failed_with_error_code, get_config_line_name, make_config_lines},
+    CandyConfigBuilder, CandyManagerBuilder,
+};
+
+mod core;
+mod utils;
+
+#[tokio::test]
+async fn grow_candy_machine_before_mint() {
+    let mut context = candy_machine_program_test().start_with_context().await;
+    let context = &mut context;
+    let mut candy_manager = CandyManagerBuilder::default(context).await;
+    let candy_data = CandyConfigBuilder::new(&candy_manager)
+        .set_items_available(5)
+        .build();
+    candy_manager.create(context, candy_data).await.unwrap();
+    candy_manager.fill_config_lines(context).await.unwrap();
+
+    // An account can only grow by MAX_PERMITTED_DATA_INCREASE bytes at once.
+    let tx_result = candy_manager.resize_candy_machine(context, 1000).await;
+    assert_tx_failed_with_error_code(tx_result, CandyError::ResizeExceedsMaxDataIncrease);
+
+    candy_manager
+        .resize_candy_machine(context, 12)
+        .await
+        .unwrap();
+
+    let candy = candy_manager.get_candy(context).await;
+    assert_eq!(candy.data.items_available, 12);
+    let candy_account = get_account(context, &candy_manager.candy_machine.pubkey()).await;
+    assert_eq!(
+        candy_account.data.len(),
+        get_space_for_candy(candy.data).unwrap()
+    );
+
+    let parsed = candy_manager.parse_config_lines(context).await;
+    assert_eq!(parsed.config_line_count_number, 5);
+    assert_eq!(parsed.config_lines[4].name, get_config_line_name(4));
+
+    candy_manager
+        .add_config_lines(context, 5, make_config_lines(5, 7))
+        .await
+        .unwrap();
+    let parsed = candy_manager.parse_config_lines(context).await;
+    assert_eq!(parsed.config_line_count_number, 12);
+
+    candy_manager.mint_nft(context, None, None).await.unwrap();
+
+    let tx_result = candy_manager.resize_candy_machine(context, 20).await;
+    assert_tx_failed_with_error_code(tx_result, CandyError::NoResizingAfterMint);
+}
+
+#[tokio::test]
+async fn shrink_candy_machine_before_mint() {
+    let mut context = candy_machine_program_test().start_with_context().await;
+    let context = &mut context;
+    let mut candy_manager = CandyManagerBuilder::default(context).await;
+    let candy_data = CandyConfigBuilder::new(&candy_manager)
+        .set_items_available(20)
+        .build();
+    candy_manager.create(context, candy_data).await.unwrap();
+    candy_manager.fill_config_lines(context).await.unwrap();
+
+    let tx_result = candy_manager.resize_candy_machine(context, 0).await;
+    assert_tx_failed_with_error_code(tx_result, CandyError::InvalidItemsAvailable);
+
+    candy_manager
+        .resize_candy_machine(context, 3)
+        .await
+        .unwrap();
+
+    let candy = candy_manager.get_candy(context).await;
+    assert_eq!(candy.data.items_available, 3);
+    let candy_account = get_account(context, &candy_manager.candy_machine.pubkey()).await;
+    assert_eq!(
+        candy_account.data.len(),
+        get_space_for_candy(candy.data).unwrap()
+    );
+
+    let parsed = candy_manager.parse_config_lines(context).await;
+    assert_eq!(parsed.config_line_count_number, 3);
+    assert_eq!(parsed.config_lines[2].name, get_config_line_name(2));
+
+    for _ in 0..3 {
+        candy_manager.mint_nft(context, None, None).await.unwrap();
+    }
+    let tx_result = candy_manager.mint_nft(context, None, None).await;
+    assert_tx_failed_with_error_code(tx_result, CandyError::CandyMachineEmpty);
+}
@@ -8,11 +8,16 @@ use crate::{
     core::helpers::{airdrop, clone_keypair, strip_empty_bytes_from_string},
     utils::{
         candy_machine_program_test,
-        helpers::{get_config_line_name, sol},
+        helpers::{
+            assert_tx_failed_with_error_code, get_config_line_name, get_current_unix_timestamp, sol,
+        },
         CandyConfigBuilder, CandyManagerBuilder,
     },
 };
-use formfn_candy_machine::MintPhase;
+use formfn_candy_machine::{
+    get_shuffled_order, CandyError, HiddenSettings, MintOrder, MintPhase, ShuffleSettings,
+};
+use solana_program::keccak;
 
 mod core;
 mod utils;
@@ -34,7 +39,7 @@ async fn creator_authority_can_pre_mint_in_sequential_order() {
         .unwrap();
 
     let mut candy_data = CandyConfigBuilder::new(&candy_manager)
-        .set_sequential_mint_order_enabled(true)
+        .set_mint_order(MintOrder::SequentialPremint)
         .enable_mint_phase(MintPhase::Premint)
         .build();
 
@@ -65,3 +70,199 @@ async fn creator_authority_can_pre_mint_in_sequential_order() {
         );
     }
 }
+
+#[tokio::test]
+async fn mint_in_sequential_order_in_public_phase() {
+    let mut context = candy_machine_program_test().start_with_context().await;
+    let context = &mut context;
+    let mut candy_manager = CandyManagerBuilder::default(context).await;
+
+    let candy_data = CandyConfigBuilder::new(&candy_manager)
+        .set_items_available(5)
+        .set_mint_order(MintOrder::Sequential)
+        .enable_mint_phase(MintPhase::Public)
+        .build();
+    candy_manager.create(context, candy_data).await.unwrap();
+    candy_manager.fill_config_lines(context).await.unwrap();
+
+    for i in 0..5 {
+        let nft = candy_manager.mint_nft(context, None, None).await.unwrap();
+        let metadata = nft.get_metadata(context).await;
+        let name = strip_empty_bytes_from_string(metadata.data.name);
+        assert_eq!(name, get_config_line_name(i));
+    }
+
+    let mut candy_data = candy_manager.get_candy(context).await.data;
+    candy_data.mint_order = MintOrder::Random;
+    let tx_result = candy_manager.update(context, None, candy_data).await;
+    assert_tx_failed_with_error_code(tx_result, CandyError::CannotChangeMintOrderAfterMint);
+}
+
+#[tokio::test]
+async fn reveal_shuffled_mint_order_after_sell_out() {
+    let mut context = candy_machine_program_test().start_with_context().await;
+    let context = &mut context;
+    let mut candy_manager = CandyManagerBuilder::default(context).await;
+
+    let seed = [7; 32];
+    let hidden_settings = HiddenSettings {
+        name: "Hidden".to_string(),
+        uri: "https://example.com/hidden.json".to_string(),
+        hash: [0; 32],
+    };
+
+    // A shuffled mint order needs a seed commitment.
+    let candy_data = CandyConfigBuilder::new(&candy_manager)
+        .set_items_available(4)
+        .set_hidden_settings(hidden_settings.clone())
+        .set_mint_order(MintOrder::ShuffledAtReveal)
+        .build();
+    let tx_result = candy_manager.create(context, candy_data).await;
+    assert_tx_failed_with_error_code(tx_result, CandyError::InvalidShuffleSettings);
+
+    let candy_data = CandyConfigBuilder::new(&candy_manager)
+        .set_items_available(4)
+        .set_hidden_settings(hidden_settings)
+        .set_mint_order(MintOrder::ShuffledAtReveal)
+        .set_shuffle_settings(Some(ShuffleSettings {
+            seed_commitment: keccak::hash(&seed).0,
+            seed: None,
+        }))
+        .build();
+    candy_manager.create(context, candy_data).await.unwrap();
+
+    for _ in 0..3 {
+        candy_manager.mint_nft(context, None, None).await.unwrap();
+    }
+    let tx_result = candy_manager.reveal_shuffle_seed(context, seed).await;
+    assert_tx_failed_with_error_code(tx_result, CandyError::CandyMachineNotSoldOut);
+
+    candy_manager.mint_nft(context, None, None).await.unwrap();
+    let tx_result = candy_manager.reveal_shuffle_seed(context, [8; 32]).await;
+    assert_tx_failed_with_error_code(tx_result, CandyError::InvalidShuffleSeed);
+
+    candy_manager
+        .reveal_shuffle_seed(context, seed)
+        .await
+        .unwrap();
+    let candy = candy_manager.get_candy(context).await;
+    assert_eq!(candy.data.shuffle_settings.unwrap().seed, Some(seed));
+
+    let tx_result = candy_manager.reveal_shuffle_seed(context, seed).await;
+    assert_tx_failed_with_error_code(tx_result, CandyError::ShuffleSeedAlreadyRevealed);
+
+    let mut order = get_shuffled_order(&seed, 4);
+    order.sort_unstable();
+    assert_eq!(order, vec![0, 1, 2, 3]);
+}
+
+#[tokio::test]
+async fn reveal_shuffled_mint_order_after_sale_ends() {
+    let mut context = candy_machine_program_test().start_with_context().await;
+    let context = &mut context;
+    let mut candy_manager = CandyManagerBuilder::default(context).await;
+
+    let seed = [7; 32];
+    let candy_data = CandyConfigBuilder::new(&candy_manager)
+        .set_items_available(4)
+        .set_hidden_settings(HiddenSettings {
+            name: "Hidden".to_string(),
+            uri: "https://example.com/hidden.json".to_string(),
+            hash: [0; 32],
+        })
+        .set_mint_order(MintOrder::ShuffledAtReveal)
+        .set_shuffle_settings(Some(ShuffleSettings {
+            seed_commitment: keccak::hash(&seed).0,
+            seed: None,
+        }))
+        .build();
+    candy_manager.create(context, candy_data).await.unwrap();
+
+    for _ in 0..2 {
+        candy_manager.mint_nft(context, None, None).await.unwrap();
+    }
+    let tx_result = candy_manager.reveal_shuffle_seed(context, seed).await;
+    assert_tx_failed_with_error_code(tx_result, CandyError::CandyMachineNotSoldOut);
+
+    // End the sale before it sold out.
+    let mut candy_data = candy_manager.get_candy(context).await.data;
+    let public_sale_end_time = candy_data.public_sale_end_time;
+    candy_data.public_sale_end_time = get_current_unix_timestamp() - 1;
+    candy_manager
+        .update(context, None, candy_data)
+        .await
+        .unwrap();
+
+    candy_manager
+        .reveal_shuffle_seed(context, seed)
+        .await
+        .unwrap();
+
+    // Reopening the sale doesn't allow minting once the order is known.
+    let mut candy_data = candy_manager.get_candy(context).await.data;
+    candy_data.public_sale_end_time = public_sale_end_time;
+    candy_manager
+        .update(context, None, candy_data)
+        .await
+        .unwrap();
+    let tx_result = candy_manager.mint_nft(context, None, None).await;
+    assert_tx_failed_with_error_code(tx_result, CandyError::ShuffleSeedAlreadyRevealed);
+}
+
+#[tokio::test]
+async fn shuffled_mint_order_is_only_applied_off_chain() {
+    let mut context = candy_machine_program_test().start_with_context().await;
+    let context = &mut context;
+    let mut candy_manager = CandyManagerBuilder::default(context).await;
+
+    let seed = [7; 32];
+    let candy_data = CandyConfigBuilder::new(&candy_manager)
+        .set_items_available(4)
+        .set_hidden_settings(HiddenSettings {
+            name: "Hidden".to_string(),
+            uri: "https://example.com/hidden.json".to_string(),
+            hash: [0; 32],
+        })
+        .set_mint_order(MintOrder::ShuffledAtReveal)
+        .set_shuffle_settings(Some(ShuffleSettings {
+            seed_commitment: keccak::hash(&seed).0,
+            seed: None,
+        }))
+        .build();
+    candy_manager.create(context, candy_data).await.unwrap();
+
+    // The NFTs are minted sequentially with the hidden settings.
+    let mut nfts = Vec::new();
+    for i in 0..4 {
+        let nft = candy_manager.mint_nft(context, None, None).await.unwrap();
+        let metadata = nft.get_metadata(context).await;
+        let name = strip_empty_bytes_from_string(metadata.data.name);
+        assert_eq!(name, format!("Hidden#{}", i + 1));
+        nfts.push(nft);
+    }
+
+    candy_manager
+        .reveal_shuffle_seed(context, seed)
+        .await
+        .unwrap();
+
+    // Revealing the seed doesn't change the NFTs, the shuffled order is only
+    // applied when they're revealed off-chain.
+    for (i, nft) in nfts.iter().enumerate() {
+        let metadata = nft.get_metadata(context).await;
+        let name = strip_empty_bytes_from_string(metadata.data.name);
+        assert_eq!(name, format!("Hidden#{}", i + 1));
+        assert_eq!(
+            strip_empty_bytes_from_string(metadata.data.uri),
+            "https://example.com/hidden.json"
+        );
+    }
+
+    // Anyone can compute the same order from the revealed seed.
+    let candy = candy_manager.get_candy(context).await;
+    let revealed_seed = candy.data.shuffle_settings.unwrap().seed.unwrap();
+    assert_eq!(
+        get_shuffled_order(&revealed_seed, candy.data.items_available),
+        get_shuffled_order(&seed, 4)
+    );
+}
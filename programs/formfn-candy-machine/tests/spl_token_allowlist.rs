@@ -1,19 +1,26 @@
 #![cfg(feature = "test-bpf")]
 #![allow(dead_code)]
 
+use solana_program::pubkey::Pubkey;
 use solana_program_test::*;
 use solana_sdk::{signature::Keypair, signer::Signer};
 
 use formfn_candy_machine::{
-    MintPhase,
+    constants::MAX_SPL_TOKEN_ALLOWLIST_TOKENS,
+    CandyError, MintPhase,
     SplTokenAllowlistMode::{BurnEveryTime, NeverBurn},
+    SplTokenAllowlistSettings, SplTokenAllowlistToken,
 };
 use spl_associated_token_account::get_associated_token_address;
 use utils::CandyConfigBuilder;
 
-use crate::core::helpers::{airdrop, get_token_balance};
+use crate::core::helpers::{
+    airdrop, clone_keypair, create_mint, get_token_balance, mint_to_wallets, mint_tokens,
+};
 use crate::utils::{
-    candy_machine_program_test, helpers::sol, CandyManagerBuilder, SplTokenAllowlistConfig,
+    candy_machine_program_test,
+    helpers::{assert_tx_failed_with_error_code, sol},
+    CandyManagerBuilder, SplTokenAllowlistConfig,
 };
 
 mod core;
@@ -229,3 +236,126 @@ async fn can_mint_in_public_phase_with_spl_token_allowlist_set() {
         .await
         .unwrap();
 }
+
+#[tokio::test]
+async fn mint_using_multiple_spl_token_allowlist_tokens() {
+    let mut context = candy_machine_program_test().start_with_context().await;
+    let context = &mut context;
+    let mut candy_manager = CandyManagerBuilder::default(context).await;
+    let minter = candy_manager.minter.pubkey();
+    let mint_authority = clone_keypair(&candy_manager.formfn_authority);
+
+    let wl_mint = create_mint(context, &mint_authority.pubkey(), None, 0, None)
+        .await
+        .unwrap()
+        .pubkey();
+    let project_mint = create_mint(context, &mint_authority.pubkey(), None, 0, None)
+        .await
+        .unwrap()
+        .pubkey();
+    let wl_token_account = mint_to_wallets(context, &wl_mint, &mint_authority, vec![(minter, 5)])
+        .await
+        .unwrap()[0];
+    let project_token_account =
+        mint_to_wallets(context, &project_mint, &mint_authority, vec![(minter, 999)])
+            .await
+            .unwrap()[0];
+
+    // Burn 3 WL tokens and hold 1000 project tokens for each NFT.
+    let candy_data = CandyConfigBuilder::new(&candy_manager)
+        .enable_mint_phase(MintPhase::Allowlist)
+        .set_allowlist_price(1)
+        .set_spl_token_allowlist_tokens(vec![
+            SplTokenAllowlistToken {
+                mint: wl_mint,
+                burn_amount: 3,
+                min_balance: 0,
+            },
+            SplTokenAllowlistToken {
+                mint: project_mint,
+                burn_amount: 0,
+                min_balance: 1000,
+            },
+        ])
+        .build();
+    candy_manager.create(context, candy_data).await.unwrap();
+    candy_manager.fill_config_lines(context).await.unwrap();
+    candy_manager.spl_token_allowlist_accounts =
+        vec![wl_token_account, wl_mint, project_token_account];
+
+    // Nothing is burned unless the recipient holds all of the tokens.
+    candy_manager
+        .mint_and_assert_bot_tax(context, None, None)
+        .await
+        .unwrap();
+    assert_eq!(get_token_balance(context, &wl_token_account).await, 5);
+
+    mint_tokens(
+        context,
+        &mint_authority,
+        &project_mint,
+        &project_token_account,
+        1,
+        None,
+    )
+    .await
+    .unwrap();
+    candy_manager
+        .mint_and_assert_successful(context, Some(1), false, None)
+        .await
+        .unwrap();
+    assert_eq!(get_token_balance(context, &wl_token_account).await, 2);
+    assert_eq!(
+        get_token_balance(context, &project_token_account).await,
+        1000
+    );
+
+    // The remaining WL tokens aren't enough for another mint.
+    candy_manager
+        .mint_and_assert_bot_tax(context, None, None)
+        .await
+        .unwrap();
+}
+
+#[tokio::test]
+async fn invalid_spl_token_allowlist_tokens() {
+    let mut context = candy_machine_program_test().start_with_context().await;
+    let context = &mut context;
+    let mut candy_manager = CandyManagerBuilder::default(context).await;
+    let spl_token_allowlist_token = SplTokenAllowlistToken {
+        mint: Pubkey::new_unique(),
+        burn_amount: 1,
+        min_balance: 1,
+    };
+
+    // The single token settings and the token list can't both be set.
+    let candy_data = CandyConfigBuilder::new(&candy_manager)
+        .set_spl_token_allowlist_settings(SplTokenAllowlistSettings {
+            mode: BurnEveryTime,
+            mint: spl_token_allowlist_token.mint,
+        })
+        .set_spl_token_allowlist_tokens(vec![spl_token_allowlist_token.clone()])
+        .build();
+    let tx_result = candy_manager.create(context, candy_data).await;
+    assert_tx_failed_with_error_code(tx_result, CandyError::InvalidAllowlistSettings);
+
+    // A token which is neither burned nor held.
+    let candy_data = CandyConfigBuilder::new(&candy_manager)
+        .set_spl_token_allowlist_tokens(vec![SplTokenAllowlistToken {
+            burn_amount: 0,
+            min_balance: 0,
+            ..spl_token_allowlist_token.clone()
+        }])
+        .build();
+    let tx_result = candy_manager.create(context, candy_data).await;
+    assert_tx_failed_with_error_code(tx_result, CandyError::InvalidAllowlistSettings);
+
+    let candy_data = CandyConfigBuilder::new(&candy_manager)
+        .set_spl_token_allowlist_tokens(vec![
+            spl_token_allowlist_token;
+            MAX_SPL_TOKEN_ALLOWLIST_TOKENS + 1
+        ])
+        .build();
+    let tx_result = candy_manager.create(context, candy_data).await;
+    assert_tx_failed_with_error_code(tx_result, CandyError::TooManySplTokenAllowlistTokens);
+}
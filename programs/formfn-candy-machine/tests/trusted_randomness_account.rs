@@ -0,0 +1,116 @@
+#![cfg(feature = "test-bpf")]
+#![allow(dead_code)]
+
+use std::collections::HashSet;
+
+use formfn_candy_machine::{CandyError, TrustedRandomnessAccount};
+use solana_program::{pubkey::Pubkey, sysvar};
+use solana_program_test::*;
+use solana_sdk::account::{AccountSharedData, WritableAccount};
+
+use crate::{
+    core::helpers::strip_empty_bytes_from_string,
+    utils::{
+        candy_machine_program_test, helpers::assert_tx_failed_with_error_code, CandyConfigBuilder,
+        CandyManagerBuilder,
+    },
+};
+
+mod core;
+mod utils;
+
+const RANDOMNESS_OFFSET: u32 = 8;
+
+/// Stands in for whoever the authority trusts by writing the randomness
+/// directly. The owner isn't checked.
+fn set_trusted_randomness(context: &mut ProgramTestContext, address: &Pubkey, data: Vec<u8>) {
+    context.set_account(
+        address,
+        &AccountSharedData::create(1_000_000_000, data, Pubkey::new_unique(), false, 0),
+    );
+}
+
+#[tokio::test]
+async fn mint_with_trusted_randomness_account() {
+    let mut context = candy_machine_program_test().start_with_context().await;
+    let context = &mut context;
+    let mut candy_manager = CandyManagerBuilder::default(context).await;
+
+    let randomness_account = Pubkey::new_unique();
+    let candy_data = CandyConfigBuilder::new(&candy_manager)
+        .set_items_available(5)
+        .set_trusted_randomness_account(Some(TrustedRandomnessAccount {
+            address: randomness_account,
+            offset: RANDOMNESS_OFFSET,
+        }))
+        .build();
+    candy_manager.create(context, candy_data).await.unwrap();
+    candy_manager.fill_config_lines(context).await.unwrap();
+
+    // The SlotHashes sysvar can't be used once the candy machine has a trusted
+    // randomness account.
+    let tx_result = candy_manager.mint_nft(context, None, None).await;
+    assert_tx_failed_with_error_code(tx_result, CandyError::IncorrectTrustedRandomnessAccount);
+
+    candy_manager.randomness_account = randomness_account;
+    set_trusted_randomness(
+        context,
+        &randomness_account,
+        vec![7; RANDOMNESS_OFFSET as usize + 16],
+    );
+    let tx_result = candy_manager.mint_nft(context, None, None).await;
+    assert_tx_failed_with_error_code(tx_result, CandyError::InvalidTrustedRandomnessAccountData);
+
+    let mut names = HashSet::new();
+    for i in 0..5u8 {
+        set_trusted_randomness(
+            context,
+            &randomness_account,
+            vec![i; RANDOMNESS_OFFSET as usize + 32],
+        );
+        let nft = candy_manager.mint_nft(context, None, None).await.unwrap();
+        let metadata = nft.get_metadata(context).await;
+        names.insert(strip_empty_bytes_from_string(metadata.data.name));
+    }
+    // Every mint picked a different unminted config line.
+    assert_eq!(names.len(), 5);
+    assert_eq!(candy_manager.get_candy(context).await.items_redeemed, 5);
+
+    let tx_result = candy_manager.mint_nft(context, None, None).await;
+    assert_tx_failed_with_error_code(tx_result, CandyError::CandyMachineEmpty);
+
+    let mut candy_data = candy_manager.get_candy(context).await.data;
+    candy_data.trusted_randomness_account = None;
+    let tx_result = candy_manager.update(context, None, candy_data).await;
+    assert_tx_failed_with_error_code(
+        tx_result,
+        CandyError::CannotChangeTrustedRandomnessAccountAfterMint,
+    );
+}
+
+#[tokio::test]
+async fn mint_all_items_with_slot_hashes() {
+    let mut context = candy_machine_program_test().start_with_context().await;
+    let context = &mut context;
+    let mut candy_manager = CandyManagerBuilder::default(context).await;
+    assert_eq!(candy_manager.randomness_account, sysvar::slot_hashes::id());
+
+    let candy_data = CandyConfigBuilder::new(&candy_manager)
+        .set_items_available(10)
+        .build();
+    candy_manager.create(context, candy_data).await.unwrap();
+    candy_manager.fill_config_lines(context).await.unwrap();
+
+    // Mints in the same slot still pick different config lines, and the last
+    // unminted ones are found without probing past the end.
+    let mut names = HashSet::new();
+    for _ in 0..10 {
+        let nft = candy_manager.mint_nft(context, None, None).await.unwrap();
+        let metadata = nft.get_metadata(context).await;
+        names.insert(strip_empty_bytes_from_string(metadata.data.name));
+    }
+    assert_eq!(names.len(), 10);
+
+    let tx_result = candy_manager.mint_nft(context, None, None).await;
+    assert_tx_failed_with_error_code(tx_result, CandyError::CandyMachineEmpty);
+}
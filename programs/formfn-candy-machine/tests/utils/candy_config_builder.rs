@@ -4,7 +4,11 @@ use solana_program::native_token::LAMPORTS_PER_SOL;
 use solana_program::pubkey::Pubkey;
 
 use formfn_candy_machine::{
-    CandyMachineData, Creator, HiddenSettings, MintPhase, SplTokenAllowlistSettings,
+    BondingCurveSettings, CandyMachineData, CollectionAllowlistSettings, CompressedNftSettings,
+    ConfigLineSettings, Creator, DutchAuctionSettings, HiddenSettings, MintCaps, MintOrder,
+    MintPhase, MintPhaseAllowlistSource, MintPhaseSettings, PaymentOption, PhaseLimits,
+    PrimarySaleSplit, ProgrammableNftSettings, ShuffleSettings, SplTokenAllowlistSettings,
+    SplTokenAllowlistToken, TrustedRandomnessAccount,
 };
 use solana_sdk::signer::Signer;
 
@@ -26,11 +30,26 @@ pub struct CandyConfigBuilder {
     spl_token_allowlist_settings: Option<SplTokenAllowlistSettings>,
     bot_protection_enabled: bool,
     limit_per_address: u16,
-    sequential_mint_order_enabled: bool,
+    mint_order: MintOrder,
     items_available: u64,
     price: u64,
     premint_price: Option<u64>,
     allowlist_price: Option<u64>,
+    mint_phases: Vec<MintPhaseSettings>,
+    primary_sale_splits: Vec<PrimarySaleSplit>,
+    dutch_auction_settings: Option<DutchAuctionSettings>,
+    bonding_curve_settings: Option<BondingCurveSettings>,
+    payment_options: Vec<PaymentOption>,
+    programmable_nft_settings: Option<ProgrammableNftSettings>,
+    compressed_nft_settings: Option<CompressedNftSettings>,
+    open_edition_enabled: bool,
+    config_line_settings: Option<ConfigLineSettings>,
+    trusted_randomness_account: Option<TrustedRandomnessAccount>,
+    shuffle_settings: Option<ShuffleSettings>,
+    phase_limits: PhaseLimits,
+    mint_caps: MintCaps,
+    collection_allowlist_settings: Option<CollectionAllowlistSettings>,
+    spl_token_allowlist_tokens: Vec<SplTokenAllowlistToken>,
 }
 
 impl CandyConfigBuilder {
@@ -59,10 +78,25 @@ impl CandyConfigBuilder {
             bot_protection_enabled: false,
             limit_per_address: 0,
             items_available: DEFAULT_ITEMS_AVAILABLE,
-            sequential_mint_order_enabled: false,
+            mint_order: MintOrder::Random,
             price: DEFAULT_PRICE,
             premint_price: None,
             allowlist_price: None,
+            mint_phases: vec![],
+            primary_sale_splits: vec![],
+            dutch_auction_settings: None,
+            bonding_curve_settings: None,
+            payment_options: vec![],
+            programmable_nft_settings: None,
+            compressed_nft_settings: None,
+            open_edition_enabled: false,
+            config_line_settings: None,
+            trusted_randomness_account: None,
+            shuffle_settings: None,
+            phase_limits: PhaseLimits::default(),
+            mint_caps: MintCaps::default(),
+            collection_allowlist_settings: None,
+            spl_token_allowlist_tokens: Vec::new(),
         }
     }
 
@@ -129,11 +163,42 @@ impl CandyConfigBuilder {
         self
     }
 
-    pub fn set_sequential_mint_order_enabled(
+    pub fn set_phase_limits(mut self, phase_limits: PhaseLimits) -> CandyConfigBuilder {
+        self.phase_limits = phase_limits;
+        self
+    }
+
+    pub fn set_mint_caps(mut self, mint_caps: MintCaps) -> CandyConfigBuilder {
+        self.mint_caps = mint_caps;
+        self
+    }
+
+    pub fn set_collection_allowlist_settings(
+        mut self,
+        collection_allowlist_settings: Option<CollectionAllowlistSettings>,
+    ) -> CandyConfigBuilder {
+        self.collection_allowlist_settings = collection_allowlist_settings;
+        self
+    }
+
+    pub fn set_spl_token_allowlist_tokens(
+        mut self,
+        spl_token_allowlist_tokens: Vec<SplTokenAllowlistToken>,
+    ) -> CandyConfigBuilder {
+        self.spl_token_allowlist_tokens = spl_token_allowlist_tokens;
+        self
+    }
+
+    pub fn set_mint_order(mut self, mint_order: MintOrder) -> CandyConfigBuilder {
+        self.mint_order = mint_order;
+        self
+    }
+
+    pub fn set_shuffle_settings(
         mut self,
-        sequential_mint_order_enabled: bool,
+        shuffle_settings: Option<ShuffleSettings>,
     ) -> CandyConfigBuilder {
-        self.sequential_mint_order_enabled = sequential_mint_order_enabled;
+        self.shuffle_settings = shuffle_settings;
         self
     }
 
@@ -157,6 +222,101 @@ impl CandyConfigBuilder {
         self
     }
 
+    pub fn add_mint_phase(
+        mut self,
+        start_time: i64,
+        price: u64,
+        allowlist_source: MintPhaseAllowlistSource,
+        limit_per_address: u16,
+    ) -> CandyConfigBuilder {
+        self.mint_phases.push(MintPhaseSettings {
+            start_time,
+            price,
+            allowlist_source,
+            limit_per_address,
+        });
+        self
+    }
+
+    pub fn add_primary_sale_split(
+        mut self,
+        address: Pubkey,
+        basis_points: u16,
+    ) -> CandyConfigBuilder {
+        self.primary_sale_splits.push(PrimarySaleSplit {
+            address,
+            basis_points,
+        });
+        self
+    }
+
+    pub fn add_payment_option(
+        mut self,
+        treasury_mint: Option<Pubkey>,
+        treasury_wallet: Pubkey,
+        price: u64,
+    ) -> CandyConfigBuilder {
+        self.payment_options.push(PaymentOption {
+            treasury_mint,
+            treasury_wallet,
+            price,
+        });
+        self
+    }
+
+    pub fn set_dutch_auction_settings(
+        mut self,
+        dutch_auction_settings: Option<DutchAuctionSettings>,
+    ) -> CandyConfigBuilder {
+        self.dutch_auction_settings = dutch_auction_settings;
+        self
+    }
+
+    pub fn set_bonding_curve_settings(
+        mut self,
+        bonding_curve_settings: Option<BondingCurveSettings>,
+    ) -> CandyConfigBuilder {
+        self.bonding_curve_settings = bonding_curve_settings;
+        self
+    }
+
+    pub fn set_programmable_nft_settings(
+        mut self,
+        programmable_nft_settings: Option<ProgrammableNftSettings>,
+    ) -> CandyConfigBuilder {
+        self.programmable_nft_settings = programmable_nft_settings;
+        self
+    }
+
+    pub fn set_compressed_nft_settings(
+        mut self,
+        compressed_nft_settings: Option<CompressedNftSettings>,
+    ) -> CandyConfigBuilder {
+        self.compressed_nft_settings = compressed_nft_settings;
+        self
+    }
+
+    pub fn set_open_edition_enabled(mut self, open_edition_enabled: bool) -> CandyConfigBuilder {
+        self.open_edition_enabled = open_edition_enabled;
+        self
+    }
+
+    pub fn set_config_line_settings(
+        mut self,
+        config_line_settings: Option<ConfigLineSettings>,
+    ) -> CandyConfigBuilder {
+        self.config_line_settings = config_line_settings;
+        self
+    }
+
+    pub fn set_trusted_randomness_account(
+        mut self,
+        trusted_randomness_account: Option<TrustedRandomnessAccount>,
+    ) -> CandyConfigBuilder {
+        self.trusted_randomness_account = trusted_randomness_account;
+        self
+    }
+
     pub fn enable_mint_phase(self, mint_phase: MintPhase) -> CandyConfigBuilder {
         let now = get_current_unix_timestamp();
         let (allowlist_sale_start_time, public_sale_start_time, public_sale_end_time) =
@@ -213,7 +373,22 @@ impl CandyConfigBuilder {
             bot_protection_enabled: self.bot_protection_enabled,
             limit_per_address: self.limit_per_address,
             merkle_allowlist_root_list: vec![],
-            sequential_mint_order_enabled: self.sequential_mint_order_enabled,
+            mint_order: self.mint_order,
+            mint_phases: self.mint_phases,
+            primary_sale_splits: self.primary_sale_splits,
+            dutch_auction_settings: self.dutch_auction_settings,
+            bonding_curve_settings: self.bonding_curve_settings,
+            payment_options: self.payment_options,
+            programmable_nft_settings: self.programmable_nft_settings,
+            compressed_nft_settings: self.compressed_nft_settings,
+            open_edition_enabled: self.open_edition_enabled,
+            config_line_settings: self.config_line_settings,
+            trusted_randomness_account: self.trusted_randomness_account,
+            shuffle_settings: self.shuffle_settings,
+            phase_limits: self.phase_limits,
+            mint_caps: self.mint_caps,
+            collection_allowlist_settings: self.collection_allowlist_settings,
+            spl_token_allowlist_tokens: self.spl_token_allowlist_tokens,
         }
     }
 }
@@ -2,13 +2,18 @@ use std::fmt::Debug;
 
 use anchor_lang::AccountDeserialize;
 use formfn_candy_machine::{
-    cmp_pubkeys, BuyerInfoAccount, BuyerMerkleAllowlistProofData, CandyError,
-    SplTokenAllowlistSettings,
+    cmp_pubkeys, BuyerInfoAccount, BuyerMerkleAllowlistProofData, CandyError, MerkleAllowlistRoot,
+    MintPhase, MintPhaseAllowlistSource, SplTokenAllowlistSettings,
+};
+use mpl_token_metadata::pda::{
+    find_collection_authority_account, find_master_edition_account, find_metadata_account,
+    find_token_record_account,
 };
-use mpl_token_metadata::pda::find_collection_authority_account;
 use solana_program::clock::Clock;
+use solana_program::instruction::AccountMeta;
 use solana_program::program_option::COption;
 use solana_program::pubkey::Pubkey;
+use solana_program::sysvar;
 use solana_program_test::ProgramTestContext;
 use solana_sdk::{
     signature::{Keypair, Signer},
@@ -20,14 +25,20 @@ use spl_token::state::AccountState;
 use formfn_candy_machine::constants::{FREEZE_FEATURE_INDEX, FREEZE_LOCK_FEATURE_INDEX};
 use formfn_candy_machine::{
     constants::BOT_FEE,
-    is_feature_active, CandyMachine, CandyMachineData, CollectionPda, FreezePda,
-    SplTokenAllowlistMode,
+    CandyMachine, CandyMachineData, CollectionAllowlistRecord, CollectionPda, ConfigLine,
+    FreezePda, OpenEditionPda, RefundReceipt, SplTokenAllowlistMode,
     SplTokenAllowlistMode::{BurnEveryTime, NeverBurn},
 };
 
-use crate::utils::{remove_freeze, set_freeze, thaw_nft, unlock_funds};
+use crate::utils::{
+    refund_nft, remove_freeze, set_freeze, set_open_edition, thaw_nft, unlock_funds,
+};
 use crate::{
-    core::helpers::create_associated_token_account, utils::helpers::find_buyer_info_account_pda,
+    core::helpers::create_associated_token_account,
+    utils::helpers::{
+        find_buyer_info_account_pda, find_edition_marker_pda, find_open_edition_pda,
+        find_refund_receipt_pda, find_tree_authority,
+    },
 };
 use crate::{
     core::helpers::update_blockhash,
@@ -43,17 +54,20 @@ use crate::{
         MasterEditionManager, MetadataManager,
     },
     utils::{
-        add_all_config_lines, clear_merkle_allowlist_roots,
+        add_all_config_lines, add_config_lines, claim_dutch_auction_rebate,
+        clear_merkle_allowlist_roots, compact_candy_machine,
         helpers::{find_candy_creator, find_collection_pda, sol},
-        initialize_candy_machine, mint_nft, remove_collection, set_collection,
-        update_candy_machine, SolanaProgramTestResult,
+        initialize_candy_machine, migrate_candy_machine, migrate_feature_flags, mint_many_nft,
+        mint_nft, release_dutch_auction_proceeds, remove_collection, remove_config_lines,
+        replace_config_lines, resize_candy_machine, reveal_shuffle_seed, set_collection,
+        update_candy_machine, update_platform_fee, withdraw_funds, SolanaProgramTestResult,
     },
 };
 
 use super::helpers::{
     get_current_unix_timestamp, parse_candy_machine_config_lines, ParsedConfigLinesResult,
 };
-use super::{append_merkle_allowlist_roots, DEFAULT_SOL_AIRDROP_SIZE};
+use super::{append_merkle_allowlist_roots, DEFAULT_SOL_AIRDROP_SIZE, TOKEN_AUTH_RULES_PROGRAM_ID};
 
 #[derive(Debug)]
 pub struct CandyManagerBuilder {
@@ -178,6 +192,16 @@ pub struct CandyManager {
     pub spl_token_allowlist_info: SplTokenAllowlistInfo,
     pub freeze_info: FreezeInfo,
     pub bot_protection_enabled: bool,
+    // The payment option mints are paid with, or None for the treasury mint.
+    pub payment_option_index: Option<u8>,
+    // The account mints read their randomness from, the SlotHashes sysvar
+    // unless the candy machine has a trusted randomness account.
+    pub randomness_account: Pubkey,
+    // The token accounts and burned mints passed for spl_token_allowlist_tokens.
+    pub spl_token_allowlist_accounts: Vec<Pubkey>,
+    // The gating NFT accounts passed when the candy machine has a collection
+    // allowlist: token account, metadata, edition and, if used once, the record.
+    pub collection_allowlist_accounts: Vec<Pubkey>,
 }
 
 impl Clone for CandyManager {
@@ -193,6 +217,10 @@ impl Clone for CandyManager {
             spl_token_allowlist_info: self.spl_token_allowlist_info.clone(),
             freeze_info: self.freeze_info.clone(),
             bot_protection_enabled: self.bot_protection_enabled,
+            payment_option_index: self.payment_option_index,
+            randomness_account: self.randomness_account,
+            spl_token_allowlist_accounts: self.spl_token_allowlist_accounts.clone(),
+            collection_allowlist_accounts: self.collection_allowlist_accounts.clone(),
         }
     }
 }
@@ -534,6 +562,10 @@ impl CandyManager {
             spl_token_allowlist_info,
             freeze_info,
             bot_protection_enabled,
+            payment_option_index: None,
+            randomness_account: sysvar::slot_hashes::id(),
+            spl_token_allowlist_accounts: Vec::new(),
+            collection_allowlist_accounts: Vec::new(),
         }
     }
 
@@ -545,6 +577,20 @@ impl CandyManager {
         self.minter = new_minter_keypair;
     }
 
+    /// Pays for the following mints with the given payment option, or with the
+    /// treasury mint if None. The token_info and treasury_wallet must match the
+    /// selected payment option.
+    pub fn set_payment_option(
+        &mut self,
+        payment_option_index: Option<u8>,
+        token_info: TokenInfo,
+        treasury_wallet: Pubkey,
+    ) {
+        self.payment_option_index = payment_option_index;
+        self.token_info = token_info;
+        self.treasury_wallet = treasury_wallet;
+    }
+
     pub async fn init(
         context: &mut ProgramTestContext,
         collection: bool,
@@ -666,6 +712,68 @@ impl CandyManager {
         )
     }
 
+    /// Returns whether the SPL token allowlist accounts and the collection
+    /// allowlist accounts are passed to a mint now, which is only during
+    /// allowlist phases which check them.
+    async fn get_allowlist_accounts_layout(
+        &self,
+        context: &mut ProgramTestContext,
+    ) -> (bool, bool) {
+        let candy = self.get_candy(context).await;
+        let now = context
+            .banks_client
+            .get_sysvar::<Clock>()
+            .await
+            .unwrap()
+            .unix_timestamp;
+        let is_allowlist_phase = candy.get_mint_phase(now) == MintPhase::Allowlist;
+        let allowlist_source = candy
+            .get_active_mint_phase_index(now)
+            .map(|mint_phase_index| {
+                candy.data.mint_phases[mint_phase_index]
+                    .allowlist_source
+                    .clone()
+            });
+        let is_checked = |source: MintPhaseAllowlistSource| {
+            is_allowlist_phase
+                && allowlist_source
+                    .as_ref()
+                    .map_or(true, |active| *active == source)
+        };
+
+        (
+            is_checked(MintPhaseAllowlistSource::SplTokenAllowlist),
+            is_checked(MintPhaseAllowlistSource::CollectionAllowlist),
+        )
+    }
+
+    /// Returns the spl_token_allowlist_info passed to a mint now, which is
+    /// unset outside of allowlist phases which check the SPL token allowlist.
+    pub async fn get_spl_token_allowlist_info(
+        &self,
+        context: &mut ProgramTestContext,
+    ) -> SplTokenAllowlistInfo {
+        let (spl_token_allowlist_accounts, _) = self.get_allowlist_accounts_layout(context).await;
+        SplTokenAllowlistInfo {
+            set: self.spl_token_allowlist_info.set && spl_token_allowlist_accounts,
+            ..self.spl_token_allowlist_info.clone()
+        }
+    }
+
+    /// Returns the allowlist accounts passed after spl_token_allowlist_info.
+    pub async fn get_allowlist_accounts(&self, context: &mut ProgramTestContext) -> Vec<Pubkey> {
+        let (spl_token_allowlist_accounts, collection_allowlist_accounts) =
+            self.get_allowlist_accounts_layout(context).await;
+        let mut allowlist_accounts = Vec::new();
+        if spl_token_allowlist_accounts {
+            allowlist_accounts.extend(self.spl_token_allowlist_accounts.iter());
+        }
+        if collection_allowlist_accounts {
+            allowlist_accounts.extend(self.collection_allowlist_accounts.iter());
+        }
+        allowlist_accounts
+    }
+
     pub async fn get_candy(&self, context: &mut ProgramTestContext) -> CandyMachine {
         let account = get_account(context, &self.candy_machine.pubkey()).await;
         CandyMachine::try_deserialize(&mut account.data.as_ref()).unwrap()
@@ -676,11 +784,27 @@ impl CandyManager {
         CollectionPda::try_deserialize(&mut account.data.as_ref()).unwrap()
     }
 
+    pub async fn get_open_edition_pda(&self, context: &mut ProgramTestContext) -> OpenEditionPda {
+        let (open_edition_pda, _) = find_open_edition_pda(&self.candy_machine.pubkey());
+        let account = get_account(context, &open_edition_pda).await;
+        OpenEditionPda::try_deserialize(&mut account.data.as_ref()).unwrap()
+    }
+
     pub async fn get_freeze_pda(&self, context: &mut ProgramTestContext) -> FreezePda {
         let account = get_account(context, &self.freeze_info.pda).await;
         FreezePda::try_deserialize(&mut account.data.as_ref()).unwrap()
     }
 
+    pub async fn get_refund_receipt(
+        &self,
+        context: &mut ProgramTestContext,
+        mint: &Pubkey,
+    ) -> RefundReceipt {
+        let (refund_receipt, _) = find_refund_receipt_pda(&self.candy_machine.pubkey(), mint);
+        let account = get_account(context, &refund_receipt).await;
+        RefundReceipt::try_deserialize(&mut account.data.as_ref()).unwrap()
+    }
+
     pub async fn parse_config_lines(
         &self,
         context: &mut ProgramTestContext,
@@ -704,7 +828,33 @@ impl CandyManager {
         let now = get_current_unix_timestamp();
         let candy_machine = self.get_candy(context).await;
         let mint_phase = CandyMachine::get_mint_phase(&candy_machine, now);
-        CandyMachine::get_mint_price(&candy_machine, &mint_phase)
+        CandyMachine::get_mint_price(&candy_machine, &mint_phase, now)
+    }
+
+    /// Returns the mint price, accounting for the price override of the Merkle
+    /// allowlist root the proof is for (if any).
+    pub async fn get_mint_price_for_proof(
+        &self,
+        context: &mut ProgramTestContext,
+        buyer_merkle_allowlist_proof_data: &Option<BuyerMerkleAllowlistProofData>,
+    ) -> u64 {
+        let now = get_current_unix_timestamp();
+        let candy_machine = self.get_candy(context).await;
+        if let Some(payment_option) = candy_machine
+            .get_payment_option(self.payment_option_index)
+            .unwrap()
+        {
+            return payment_option.price;
+        }
+
+        let mint_phase = CandyMachine::get_mint_phase(&candy_machine, now);
+        let merkle_allowlist_root_price = match (&mint_phase, buyer_merkle_allowlist_proof_data) {
+            (MintPhase::Allowlist, Some(proof_data)) => candy_machine
+                .get_merkle_allowlist_root_price(proof_data.root_index_for_proof as usize),
+            _ => None,
+        };
+        merkle_allowlist_root_price
+            .unwrap_or_else(|| CandyMachine::get_mint_price(&candy_machine, &mint_phase, now))
     }
 
     pub async fn assert_freeze_set(
@@ -715,14 +865,8 @@ impl CandyManager {
         let freeze_pda_account = self.get_freeze_pda(context).await;
         let candy_machine_account = self.get_candy(context).await;
         assert_eq!(*expected_freeze_pda, freeze_pda_account);
-        assert!(is_feature_active(
-            &candy_machine_account.data.uuid,
-            FREEZE_FEATURE_INDEX
-        ));
-        assert!(is_feature_active(
-            &candy_machine_account.data.uuid,
-            FREEZE_LOCK_FEATURE_INDEX
-        ));
+        assert!(candy_machine_account.is_feature_active(FREEZE_FEATURE_INDEX));
+        assert!(candy_machine_account.is_feature_active(FREEZE_LOCK_FEATURE_INDEX));
         freeze_pda_account
     }
 
@@ -825,6 +969,24 @@ impl CandyManager {
         Ok(())
     }
 
+    pub async fn set_open_edition(
+        &mut self,
+        context: &mut ProgramTestContext,
+        master_edition_info: &MasterEditionManager,
+    ) -> SolanaProgramTestResult {
+        let logger = CandyTestLogger::new_start("Set Open Edition");
+        set_open_edition(
+            context,
+            &self.candy_machine.pubkey(),
+            &self.formfn_authority,
+            &self.creator_authority.pubkey(),
+            master_edition_info,
+        )
+        .await?;
+        logger.end();
+        Ok(())
+    }
+
     #[allow(dead_code)]
     pub async fn remove_collection(
         &mut self,
@@ -843,6 +1005,63 @@ impl CandyManager {
         Ok(())
     }
 
+    pub async fn add_config_lines(
+        &mut self,
+        context: &mut ProgramTestContext,
+        index: u32,
+        config_lines: Vec<ConfigLine>,
+    ) -> SolanaProgramTestResult {
+        let logger = CandyTestLogger::new_start("Add Config Lines");
+        add_config_lines(
+            context,
+            &self.candy_machine.pubkey(),
+            &self.formfn_authority,
+            index,
+            config_lines,
+        )
+        .await?;
+        logger.end();
+        Ok(())
+    }
+
+    pub async fn remove_config_lines(
+        &mut self,
+        context: &mut ProgramTestContext,
+        index: u32,
+        count: u32,
+    ) -> SolanaProgramTestResult {
+        let logger = CandyTestLogger::new_start("Remove Config Lines");
+        remove_config_lines(
+            context,
+            &self.candy_machine.pubkey(),
+            &self.formfn_authority,
+            index,
+            count,
+        )
+        .await?;
+        logger.end();
+        Ok(())
+    }
+
+    pub async fn replace_config_lines(
+        &mut self,
+        context: &mut ProgramTestContext,
+        index: u32,
+        config_lines: Vec<ConfigLine>,
+    ) -> SolanaProgramTestResult {
+        let logger = CandyTestLogger::new_start("Replace Config Lines");
+        replace_config_lines(
+            context,
+            &self.candy_machine.pubkey(),
+            &self.formfn_authority,
+            index,
+            config_lines,
+        )
+        .await?;
+        logger.end();
+        Ok(())
+    }
+
     pub async fn fill_config_lines(
         &mut self,
         context: &mut ProgramTestContext,
@@ -886,10 +1105,168 @@ impl CandyManager {
         Ok(())
     }
 
+    pub async fn update_platform_fee(
+        &mut self,
+        context: &mut ProgramTestContext,
+        platform_fee_basis_points: u16,
+        platform_fee_wallet: Option<Pubkey>,
+    ) -> SolanaProgramTestResult {
+        let logger = CandyTestLogger::new_start("Update Platform Fee");
+        update_platform_fee(
+            context,
+            &self.candy_machine.pubkey(),
+            &self.formfn_authority,
+            platform_fee_basis_points,
+            platform_fee_wallet,
+        )
+        .await?;
+        logger.end();
+        Ok(())
+    }
+
+    pub async fn migrate_feature_flags(
+        &mut self,
+        context: &mut ProgramTestContext,
+    ) -> SolanaProgramTestResult {
+        let logger = CandyTestLogger::new_start("Migrate Feature Flags");
+        migrate_feature_flags(
+            context,
+            &self.candy_machine.pubkey(),
+            &self.formfn_authority,
+        )
+        .await?;
+        logger.end();
+        Ok(())
+    }
+
+    pub async fn migrate_candy_machine(
+        &mut self,
+        context: &mut ProgramTestContext,
+        from_version: u8,
+    ) -> SolanaProgramTestResult {
+        let logger = CandyTestLogger::new_start("Migrate Candy Machine");
+        migrate_candy_machine(
+            context,
+            &self.candy_machine.pubkey(),
+            &self.formfn_authority,
+            from_version,
+        )
+        .await?;
+        logger.end();
+        Ok(())
+    }
+
+    pub async fn compact_candy_machine(
+        &mut self,
+        context: &mut ProgramTestContext,
+    ) -> SolanaProgramTestResult {
+        let logger = CandyTestLogger::new_start("Compact Candy Machine");
+        compact_candy_machine(
+            context,
+            &self.candy_machine.pubkey(),
+            &self.formfn_authority,
+        )
+        .await?;
+        logger.end();
+        Ok(())
+    }
+
+    pub async fn resize_candy_machine(
+        &mut self,
+        context: &mut ProgramTestContext,
+        items_available: u64,
+    ) -> SolanaProgramTestResult {
+        let logger = CandyTestLogger::new_start("Resize Candy Machine");
+        resize_candy_machine(
+            context,
+            &self.candy_machine.pubkey(),
+            &self.formfn_authority,
+            items_available,
+        )
+        .await?;
+        logger.end();
+        Ok(())
+    }
+
+    pub async fn reveal_shuffle_seed(
+        &mut self,
+        context: &mut ProgramTestContext,
+        seed: [u8; 32],
+    ) -> SolanaProgramTestResult {
+        let logger = CandyTestLogger::new_start("Reveal Shuffle Seed");
+        reveal_shuffle_seed(
+            context,
+            &self.candy_machine.pubkey(),
+            &self.formfn_authority,
+            seed,
+        )
+        .await?;
+        logger.end();
+        Ok(())
+    }
+
+    pub async fn claim_dutch_auction_rebate(
+        &mut self,
+        context: &mut ProgramTestContext,
+    ) -> SolanaProgramTestResult {
+        let logger = CandyTestLogger::new_start("Claim Dutch Auction Rebate");
+        claim_dutch_auction_rebate(context, &self.candy_machine.pubkey(), &self.minter).await?;
+        logger.end();
+        Ok(())
+    }
+
+    pub async fn release_dutch_auction_proceeds(
+        &mut self,
+        context: &mut ProgramTestContext,
+    ) -> SolanaProgramTestResult {
+        let logger = CandyTestLogger::new_start("Release Dutch Auction Proceeds");
+        let payment_accounts = self.get_mint_payment_accounts(context).await;
+        release_dutch_auction_proceeds(
+            context,
+            &self.candy_machine.pubkey(),
+            &self.treasury_wallet,
+            &self.minter,
+            &payment_accounts,
+        )
+        .await?;
+        logger.end();
+        Ok(())
+    }
+
+    pub async fn withdraw_funds(
+        &mut self,
+        context: &mut ProgramTestContext,
+    ) -> SolanaProgramTestResult {
+        let logger = CandyTestLogger::new_start("Withdraw Funds");
+        withdraw_funds(
+            context,
+            &self.candy_machine.pubkey(),
+            &self.formfn_authority,
+        )
+        .await?;
+        logger.end();
+        Ok(())
+    }
+
     pub async fn append_merkle_allowlist_roots(
         &mut self,
         context: &mut ProgramTestContext,
         roots_to_append: Vec<[u8; 32]>,
+    ) -> SolanaProgramTestResult {
+        self.append_merkle_allowlist_roots_with_settings(
+            context,
+            roots_to_append
+                .into_iter()
+                .map(MerkleAllowlistRoot::from)
+                .collect(),
+        )
+        .await
+    }
+
+    pub async fn append_merkle_allowlist_roots_with_settings(
+        &mut self,
+        context: &mut ProgramTestContext,
+        roots_to_append: Vec<MerkleAllowlistRoot>,
     ) -> SolanaProgramTestResult {
         let logger = CandyTestLogger::new_start("Update Candy Machine Merkle Allowlist Roots");
         append_merkle_allowlist_roots(
@@ -960,12 +1337,39 @@ impl CandyManager {
         formfn_authority: &Keypair,
     ) -> SolanaProgramTestResult {
         let logger = CandyTestLogger::new_start("Thaw NFT");
+        let programmable_nft_accounts = self
+            .get_programmable_nft_thaw_accounts(context, nft_info)
+            .await;
         thaw_nft(
             context,
             &self.candy_machine.pubkey(),
             formfn_authority,
             &self.freeze_info,
             nft_info,
+            &programmable_nft_accounts,
+        )
+        .await?;
+        logger.end();
+        Ok(())
+    }
+
+    pub async fn refund_nft(
+        &mut self,
+        context: &mut ProgramTestContext,
+        nft_info: &MasterEditionManager,
+    ) -> SolanaProgramTestResult {
+        let logger = CandyTestLogger::new_start("Refund NFT");
+        let refund_receipt = self
+            .get_refund_receipt(context, &nft_info.mint.pubkey())
+            .await;
+        refund_nft(
+            context,
+            &self.candy_machine.pubkey(),
+            &self.freeze_info,
+            nft_info,
+            &refund_receipt.payer,
+            &refund_receipt.recipient,
+            refund_receipt.treasury_mint,
         )
         .await?;
         logger.end();
@@ -989,6 +1393,148 @@ impl CandyManager {
         Ok(())
     }
 
+    /// Returns the accounts which receive the platform fee and the primary
+    /// sale splits, which are the wallet addresses or their ATAs if the candy
+    /// machine uses an SPL token.
+    pub async fn get_mint_payment_accounts(&self, context: &mut ProgramTestContext) -> Vec<Pubkey> {
+        let candy = self.get_candy(context).await;
+        let treasury_mint = match candy.get_payment_option(self.payment_option_index).unwrap() {
+            Some(payment_option) => payment_option.treasury_mint,
+            None => candy.treasury_mint,
+        };
+        let get_payment_account = |address: &Pubkey| match treasury_mint {
+            Some(treasury_mint) => get_associated_token_address(address, &treasury_mint),
+            None => *address,
+        };
+
+        candy
+            .get_platform_fee_wallet()
+            .iter()
+            .chain(
+                candy
+                    .data
+                    .primary_sale_splits
+                    .iter()
+                    .map(|primary_sale_split| &primary_sale_split.address),
+            )
+            .map(get_payment_account)
+            .collect()
+    }
+
+    /// The accounts passed after the payment accounts for each NFT, which are
+    /// the refund receipt if the freeze feature is active and the token record
+    /// if the candy machine mints programmable NFTs.
+    pub async fn get_nft_accounts(
+        &self,
+        context: &mut ProgramTestContext,
+        nft_info: &MasterEditionManager,
+    ) -> Vec<Pubkey> {
+        let candy = self.get_candy(context).await;
+        let mut accounts = vec![];
+        if self.freeze_info.set {
+            accounts.push(
+                find_refund_receipt_pda(&self.candy_machine.pubkey(), &nft_info.mint.pubkey()).0,
+            );
+        }
+        if candy.is_programmable() {
+            accounts.push(
+                find_token_record_account(&nft_info.mint.pubkey(), &nft_info.token_account).0,
+            );
+        }
+        accounts
+    }
+
+    /// The accounts passed after the accounts of each NFT when the candy
+    /// machine mints programmable NFTs.
+    pub async fn get_programmable_nft_accounts(
+        &self,
+        context: &mut ProgramTestContext,
+    ) -> Vec<Pubkey> {
+        let candy = self.get_candy(context).await;
+        if !candy.is_programmable() {
+            return vec![];
+        }
+
+        let mut accounts = vec![];
+        if let Some(rule_set) = candy.get_rule_set() {
+            accounts.push(TOKEN_AUTH_RULES_PROGRAM_ID);
+            accounts.push(rule_set);
+        }
+        if self.collection_info.set {
+            accounts.push(self.collection_info.pda);
+        }
+        accounts
+    }
+
+    /// The accounts passed to thaw a programmable NFT. The token metadata
+    /// program stands in for the rule set accounts if there is no rule set.
+    pub async fn get_programmable_nft_thaw_accounts(
+        &self,
+        context: &mut ProgramTestContext,
+        nft_info: &MasterEditionManager,
+    ) -> Vec<AccountMeta> {
+        let candy = self.get_candy(context).await;
+        if !candy.is_programmable() {
+            return vec![];
+        }
+
+        let (auth_rules_program, auth_rules) = match candy.get_rule_set() {
+            Some(rule_set) => (TOKEN_AUTH_RULES_PROGRAM_ID, rule_set),
+            None => (mpl_token_metadata::id(), mpl_token_metadata::id()),
+        };
+        vec![
+            AccountMeta::new(nft_info.metadata_pubkey, false),
+            AccountMeta::new(
+                find_token_record_account(&nft_info.mint.pubkey(), &nft_info.token_account).0,
+                false,
+            ),
+            AccountMeta::new_readonly(sysvar::instructions::id(), false),
+            AccountMeta::new_readonly(auth_rules_program, false),
+            AccountMeta::new_readonly(auth_rules, false),
+        ]
+    }
+
+    /// The accounts passed after the accounts of the NFT when the candy
+    /// machine mints compressed NFTs.
+    pub async fn get_compressed_nft_accounts(
+        &self,
+        context: &mut ProgramTestContext,
+    ) -> Vec<Pubkey> {
+        let candy = self.get_candy(context).await;
+        let merkle_tree = match candy.data.compressed_nft_settings {
+            Some(settings) => settings.merkle_tree,
+            None => return vec![],
+        };
+
+        vec![
+            find_tree_authority(&merkle_tree).0,
+            merkle_tree,
+            SPL_NOOP_PROGRAM_ID,
+            SPL_ACCOUNT_COMPRESSION_PROGRAM_ID,
+            mpl_bubblegum::id(),
+        ]
+    }
+
+    /// The accounts passed after the accounts of the NFT when the candy
+    /// machine mints open editions.
+    pub async fn get_open_edition_accounts(&self, context: &mut ProgramTestContext) -> Vec<Pubkey> {
+        let candy = self.get_candy(context).await;
+        if !candy.is_open_edition() {
+            return vec![];
+        }
+
+        let (open_edition_pda, _) = find_open_edition_pda(&self.candy_machine.pubkey());
+        let master_edition_mint = self.get_open_edition_pda(context).await.mint;
+        vec![
+            open_edition_pda,
+            master_edition_mint,
+            find_metadata_account(&master_edition_mint).0,
+            find_master_edition_account(&master_edition_mint).0,
+            get_associated_token_address(&open_edition_pda, &master_edition_mint),
+            find_edition_marker_pda(&master_edition_mint, candy.items_redeemed + 1),
+        ]
+    }
+
     pub async fn mint_nft(
         &mut self,
         context: &mut ProgramTestContext,
@@ -1006,8 +1552,17 @@ impl CandyManager {
             self.bot_protection_enabled
         };
 
-        let mint_price = self.get_mint_price(context).await;
-
+        let mint_price = self
+            .get_mint_price_for_proof(context, &buyer_merkle_allowlist_proof_data)
+            .await;
+        let mut mint_payment_accounts = self.get_mint_payment_accounts(context).await;
+        mint_payment_accounts.extend(self.get_nft_accounts(context, &nft_info).await);
+        mint_payment_accounts.extend(self.get_programmable_nft_accounts(context).await);
+        mint_payment_accounts.extend(self.get_compressed_nft_accounts(context).await);
+        mint_payment_accounts.extend(self.get_open_edition_accounts(context).await);
+
+        let spl_token_allowlist_info = self.get_spl_token_allowlist_info(context).await;
+        let allowlist_accounts = self.get_allowlist_accounts(context).await;
         mint_nft(
             context,
             &self.candy_machine.pubkey(),
@@ -1016,20 +1571,73 @@ impl CandyManager {
             &self.treasury_wallet,
             &self.creator_authority.pubkey(),
             &self.minter,
+            &self.minter.pubkey(),
             &nft_info,
             self.token_info.clone(),
-            self.spl_token_allowlist_info.clone(),
+            spl_token_allowlist_info,
             self.collection_info.clone(),
             self.freeze_info.clone(),
+            &mint_payment_accounts,
             add_bot_signer,
             buyer_merkle_allowlist_proof_data,
             mint_price,
+            self.payment_option_index,
+            &self.randomness_account,
+            &allowlist_accounts,
         )
         .await?;
         logger.end();
         Ok(nft_info)
     }
 
+    pub async fn mint_many_nft(
+        &mut self,
+        context: &mut ProgramTestContext,
+        quantity: u8,
+        buyer_merkle_allowlist_proof_data: Option<BuyerMerkleAllowlistProofData>,
+    ) -> SolanaProgramTestResult<Vec<MasterEditionManager>> {
+        let logger = CandyTestLogger::new_start("Mint Many NFTs");
+        let mut nft_infos = Vec::new();
+        for _ in 0..quantity {
+            nft_infos.push(prepare_nft(&self.minter).await);
+        }
+        let (candy_machine_creator, creator_bump) =
+            find_candy_creator(&self.candy_machine.pubkey());
+
+        let mint_price = self
+            .get_mint_price_for_proof(context, &buyer_merkle_allowlist_proof_data)
+            .await;
+        let mint_payment_accounts = self.get_mint_payment_accounts(context).await;
+
+        let spl_token_allowlist_info = self.get_spl_token_allowlist_info(context).await;
+        let allowlist_accounts = self.get_allowlist_accounts(context).await;
+        mint_many_nft(
+            context,
+            &self.candy_machine.pubkey(),
+            &candy_machine_creator,
+            creator_bump,
+            &self.treasury_wallet,
+            &self.creator_authority.pubkey(),
+            &self.minter,
+            &self.minter.pubkey(),
+            &nft_infos,
+            self.token_info.clone(),
+            spl_token_allowlist_info,
+            self.collection_info.clone(),
+            self.freeze_info.clone(),
+            &mint_payment_accounts,
+            self.bot_protection_enabled,
+            buyer_merkle_allowlist_proof_data,
+            mint_price,
+            self.payment_option_index,
+            &self.randomness_account,
+            &allowlist_accounts,
+        )
+        .await?;
+        logger.end();
+        Ok(nft_infos)
+    }
+
     pub async fn mint_and_assert_successful(
         &mut self,
         context: &mut ProgramTestContext,
@@ -1071,8 +1679,18 @@ impl CandyManager {
             find_buyer_info_account_pda(&self.candy_machine.pubkey(), &self.minter.pubkey());
         let buyer_edition_info_account_before_minting =
             get_account_if_exists(context, &buyer_edition_info_account_pda).await;
-        let buyer_info_account_should_be_created =
-            candy_start.data.limit_per_address > 0 || buyer_merkle_allowlist_proof_data.is_some();
+        // The record is the last gating account, if the gating NFT is used once.
+        let collection_allowlist_record = self.collection_allowlist_accounts.get(3).copied();
+        let collection_allowlist_record_before_minting = match collection_allowlist_record {
+            Some(record) => get_account_if_exists(context, &record).await.unwrap(),
+            None => None,
+        };
+        let mint_phase_limit_per_address = candy_start
+            .get_active_mint_phase_settings(get_current_unix_timestamp())
+            .map_or(0, |settings| settings.limit_per_address);
+        let buyer_info_account_should_be_created = candy_start.data.limit_per_address > 0
+            || mint_phase_limit_per_address > 0
+            || buyer_merkle_allowlist_proof_data.is_some();
 
         let mut new_nft = self
             .mint_nft(context, None, buyer_merkle_allowlist_proof_data)
@@ -1146,8 +1764,23 @@ impl CandyManager {
             }
             if self.freeze_info.set {
                 let freeze_pda = self.get_freeze_pda(context).await;
-                fees += freeze_pda.freeze_fee;
+                let rent = context.banks_client.get_rent().await.unwrap();
+                fees += freeze_pda.freeze_fee + rent.minimum_balance(RefundReceipt::SIZE);
             };
+            if let (Some(record), None) = (
+                collection_allowlist_record,
+                collection_allowlist_record_before_minting,
+            ) {
+                if get_account_if_exists(context, &record)
+                    .await
+                    .unwrap()
+                    .is_some()
+                {
+                    // The mint created the CollectionAllowlistRecord.
+                    let rent = context.banks_client.get_rent().await.unwrap();
+                    fees += rent.minimum_balance(CollectionAllowlistRecord::SIZE);
+                }
+            }
             fees
         };
 
@@ -1243,6 +1876,89 @@ impl CandyManager {
         assert_tx_failed_with_error_code(tx_result, expected_candy_error);
     }
 
+    pub async fn mint_many_and_assert_successful(
+        &mut self,
+        context: &mut ProgramTestContext,
+        quantity: u8,
+        buyer_merkle_allowlist_proof_data: Option<BuyerMerkleAllowlistProofData>,
+    ) -> SolanaProgramTestResult<Vec<MasterEditionManager>> {
+        let candy_start = self.get_candy(context).await;
+        let mint_price = self
+            .get_mint_price_for_proof(context, &buyer_merkle_allowlist_proof_data)
+            .await;
+        let mint_phase = CandyMachine::get_mint_phase(&candy_start, get_current_unix_timestamp());
+        let total_mint_price = candy_start
+            .get_bonding_curve_total_price(&mint_phase, quantity as u64)
+            .unwrap_or(mint_price * quantity as u64);
+        let start_wallet_balance = get_balance(context, &self.treasury_wallet).await;
+
+        let new_nfts = self
+            .mint_many_nft(context, quantity, buyer_merkle_allowlist_proof_data)
+            .await?;
+
+        let candy_end = self.get_candy(context).await;
+        let end_wallet_balance = get_balance(context, &self.treasury_wallet).await;
+
+        assert_eq!(
+            candy_start.items_redeemed + quantity as u64,
+            candy_end.items_redeemed,
+            "Items redeemed should increase by the mint quantity."
+        );
+
+        if !self.token_info.set
+            && !self.freeze_info.set
+            && candy_start.data.primary_sale_splits.is_empty()
+            && candy_start.get_platform_fee_wallet().is_none()
+        {
+            assert_eq!(
+                end_wallet_balance - start_wallet_balance,
+                total_mint_price,
+                "CM wallet balance should increase by the price of each NFT."
+            );
+        }
+
+        for new_nft in new_nfts.iter() {
+            let metadata =
+                MetadataManager::get_data_from_account(context, &new_nft.metadata_pubkey).await;
+            let associated_token_account =
+                get_associated_token_address(&self.minter.pubkey(), &metadata.mint);
+            let associated_token_account = get_token_account(context, &associated_token_account)
+                .await
+                .unwrap();
+            assert_eq!(
+                associated_token_account.amount, 1,
+                "Minter is not the owner"
+            );
+
+            if self.collection_info.set {
+                assert_eq!(
+                    &metadata.collection.as_ref().unwrap().key,
+                    &self.collection_info.mint.pubkey(),
+                    "Collection key wasn't set correctly!"
+                );
+                assert!(
+                    &metadata.collection.as_ref().unwrap().verified,
+                    "Collection wasn't verified!"
+                );
+            }
+        }
+
+        Ok(new_nfts)
+    }
+
+    pub async fn mint_many_and_assert_failure(
+        &mut self,
+        context: &mut ProgramTestContext,
+        quantity: u8,
+        buyer_merkle_allowlist_proof_data: Option<BuyerMerkleAllowlistProofData>,
+        expected_candy_error: CandyError,
+    ) -> () {
+        let tx_result = self
+            .mint_many_nft(context, quantity, buyer_merkle_allowlist_proof_data)
+            .await;
+        assert_tx_failed_with_error_code(tx_result, expected_candy_error);
+    }
+
     pub async fn mint_and_assert_bot_tax(
         &mut self,
         context: &mut ProgramTestContext,
@@ -1,5 +1,26 @@
+use solana_program::{pubkey, pubkey::Pubkey};
+
 pub const DEFAULT_SOL_AIRDROP_SIZE: u64 = 50;
 
+// The Metaplex token auth rules program, which owns programmable NFT rule sets.
+pub const TOKEN_AUTH_RULES_PROGRAM_ID: Pubkey =
+    pubkey!("auth9SigNpDKz4sJJ1DfCTuZrZNSAgh9sFD3rboVmgg");
+
+// The programs Bubblegum CPIs into to mint compressed NFTs.
+pub const SPL_ACCOUNT_COMPRESSION_PROGRAM_ID: Pubkey =
+    pubkey!("cmtDvXumGCrqC1Age74AVPhSRVXJMd8PJS91L8KbNCK");
+pub const SPL_NOOP_PROGRAM_ID: Pubkey = pubkey!("noopb9bkMVfRPU8AsbpTUg8AQkHtKwMYZiFUjNRtMmV");
+
+// The smallest Bubblegum tree: a max depth of 3 and a max buffer size of 8,
+// without a canopy.
+pub const TEST_MERKLE_TREE_MAX_DEPTH: u32 = 3;
+pub const TEST_MERKLE_TREE_MAX_BUFFER_SIZE: u32 = 8;
+// The 56 byte account compression header, then the sequence_number,
+// active_index and buffer_size of the ConcurrentMerkleTree followed by a
+// 32 + 32 * max_depth + 8 byte path for each buffer entry and the rightmost
+// proof.
+pub const TEST_MERKLE_TREE_ACCOUNT_SIZE: usize = 56 + 24 + (8 + 1) * (32 + 32 * 3 + 8);
+
 // Keypair for the local/dev/test bot signer authority public key: antiDV8bRvF4XTeRqmyHV1jpHD4Lvz7gKBKBBRQb8ir
 pub const BOT_SIGNER_AUTHORITY_SECRET: &[u8; 64] = &[
     149, 20, 248, 180, 209, 250, 93, 153, 76, 164, 228, 237, 88, 103, 194, 12, 27, 137, 124, 161,
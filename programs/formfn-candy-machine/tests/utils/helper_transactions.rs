@@ -12,10 +12,8 @@ use solana_sdk::{
 };
 
 use formfn_candy_machine::{
-    constants::{CONFIG_ARRAY_START, CONFIG_LINE_SIZE},
-    utils::cmp_pubkeys,
-    BuyerMerkleAllowlistProofData, CandyMachine, CandyMachineData, ConfigLine,
-    SplTokenAllowlistMode::BurnEveryTime,
+    constants::CONFIG_ARRAY_START, utils::cmp_pubkeys, BuyerMerkleAllowlistProofData, CandyMachine,
+    CandyMachineData, ConfigLine, MerkleAllowlistRoot, SplTokenAllowlistMode::BurnEveryTime,
 };
 use spl_associated_token_account::get_associated_token_address;
 
@@ -23,8 +21,13 @@ use crate::{
     core::{helpers::update_blockhash, MasterEditionManager},
     utils::{
         candy_manager::{CollectionInfo, SplTokenAllowlistInfo, TokenInfo},
-        helpers::{find_buyer_info_account_pda, get_bot_signer_keypair, make_config_lines},
-        FreezeInfo, SolanaProgramTestResult,
+        helpers::{
+            find_buyer_info_account_pda, find_open_edition_pda, find_refund_receipt_pda,
+            find_tree_authority, get_bot_signer_keypair, make_config_lines,
+        },
+        FreezeInfo, SolanaProgramTestResult, SPL_ACCOUNT_COMPRESSION_PROGRAM_ID,
+        SPL_NOOP_PROGRAM_ID, TEST_MERKLE_TREE_ACCOUNT_SIZE, TEST_MERKLE_TREE_MAX_BUFFER_SIZE,
+        TEST_MERKLE_TREE_MAX_DEPTH,
     },
 };
 
@@ -34,6 +37,83 @@ pub fn candy_machine_program_test() -> ProgramTest {
     program
 }
 
+/// Also loads the programs used to mint compressed NFTs.
+pub fn compressed_nft_program_test() -> ProgramTest {
+    let mut program = candy_machine_program_test();
+    program.add_program("mpl_bubblegum", mpl_bubblegum::id(), None);
+    program.add_program(
+        "spl_account_compression",
+        SPL_ACCOUNT_COMPRESSION_PROGRAM_ID,
+        None,
+    );
+    program.add_program("spl_noop", SPL_NOOP_PROGRAM_ID, None);
+    program
+}
+
+/// Creates an empty Bubblegum tree, which the given tree delegate can mint
+/// into. Returns the merkle tree.
+pub async fn create_compressed_nft_tree(
+    context: &mut ProgramTestContext,
+    tree_creator: &Keypair,
+    tree_delegate: &Pubkey,
+) -> SolanaProgramTestResult<Pubkey> {
+    let merkle_tree = Keypair::new();
+    let (tree_authority, _) = find_tree_authority(&merkle_tree.pubkey());
+
+    let rent = context.banks_client.get_rent().await?;
+    let create_ix = system_instruction::create_account(
+        &tree_creator.pubkey(),
+        &merkle_tree.pubkey(),
+        rent.minimum_balance(TEST_MERKLE_TREE_ACCOUNT_SIZE),
+        TEST_MERKLE_TREE_ACCOUNT_SIZE as u64,
+        &SPL_ACCOUNT_COMPRESSION_PROGRAM_ID,
+    );
+
+    let create_tree_ix = Instruction {
+        program_id: mpl_bubblegum::id(),
+        data: mpl_bubblegum::instruction::CreateTree {
+            max_depth: TEST_MERKLE_TREE_MAX_DEPTH,
+            max_buffer_size: TEST_MERKLE_TREE_MAX_BUFFER_SIZE,
+            public: None,
+        }
+        .data(),
+        accounts: mpl_bubblegum::accounts::CreateTree {
+            tree_authority,
+            merkle_tree: merkle_tree.pubkey(),
+            payer: tree_creator.pubkey(),
+            tree_creator: tree_creator.pubkey(),
+            log_wrapper: SPL_NOOP_PROGRAM_ID,
+            compression_program: SPL_ACCOUNT_COMPRESSION_PROGRAM_ID,
+            system_program: system_program::id(),
+        }
+        .to_account_metas(None),
+    };
+
+    let set_tree_delegate_ix = Instruction {
+        program_id: mpl_bubblegum::id(),
+        data: mpl_bubblegum::instruction::SetTreeDelegate {}.data(),
+        accounts: mpl_bubblegum::accounts::SetTreeDelegate {
+            tree_authority,
+            tree_creator: tree_creator.pubkey(),
+            new_tree_delegate: *tree_delegate,
+            merkle_tree: merkle_tree.pubkey(),
+            system_program: system_program::id(),
+        }
+        .to_account_metas(None),
+    };
+
+    update_blockhash(context).await?;
+    let tx = Transaction::new_signed_with_payer(
+        &[create_ix, create_tree_ix, set_tree_delegate_ix],
+        Some(&tree_creator.pubkey()),
+        &[tree_creator, &merkle_tree],
+        context.last_blockhash,
+    );
+    context.banks_client.process_transaction(tx).await?;
+
+    Ok(merkle_tree.pubkey())
+}
+
 pub async fn initialize_candy_machine(
     context: &mut ProgramTestContext,
     candy_account: &Keypair,
@@ -44,15 +124,16 @@ pub async fn initialize_candy_machine(
     token_info: TokenInfo,
 ) -> SolanaProgramTestResult {
     let items_available = candy_data.items_available;
-    let candy_account_size = if candy_data.hidden_settings.is_some() {
-        CONFIG_ARRAY_START
-    } else {
-        CONFIG_ARRAY_START
-            + 4
-            + items_available as usize * CONFIG_LINE_SIZE
-            + 8
-            + 2 * (items_available as usize / 8 + 1)
-    };
+    let candy_account_size =
+        if candy_data.hidden_settings.is_some() || candy_data.open_edition_enabled {
+            CONFIG_ARRAY_START
+        } else {
+            CONFIG_ARRAY_START
+                + 4
+                + items_available as usize * candy_data.get_config_line_size()
+                + 8
+                + 2 * (items_available as usize / 8 + 1)
+        };
 
     let rent = context.banks_client.get_rent().await?;
     let lamports = rent.minimum_balance(candy_account_size);
@@ -143,11 +224,220 @@ pub async fn update_candy_machine(
         .map_err(|e| e.into())
 }
 
+pub async fn update_platform_fee(
+    context: &mut ProgramTestContext,
+    candy_machine: &Pubkey,
+    formfn_authority: &Keypair,
+    platform_fee_basis_points: u16,
+    platform_fee_wallet: Option<Pubkey>,
+) -> SolanaProgramTestResult {
+    let accounts = formfn_candy_machine::accounts::UpdatePlatformFee {
+        candy_machine: *candy_machine,
+        formfn_authority: formfn_authority.pubkey(),
+    }
+    .to_account_metas(None);
+
+    let data = formfn_candy_machine::instruction::UpdatePlatformFee {
+        platform_fee_basis_points,
+        platform_fee_wallet,
+    }
+    .data();
+    let update_ix = Instruction {
+        program_id: formfn_candy_machine::id(),
+        data,
+        accounts,
+    };
+
+    update_blockhash(context).await?;
+    let tx = Transaction::new_signed_with_payer(
+        &[update_ix],
+        Some(&formfn_authority.pubkey()),
+        &[formfn_authority],
+        context.last_blockhash,
+    );
+
+    context
+        .banks_client
+        .process_transaction(tx)
+        .await
+        .map_err(|e| e.into())
+}
+
+pub async fn migrate_feature_flags(
+    context: &mut ProgramTestContext,
+    candy_machine: &Pubkey,
+    formfn_authority: &Keypair,
+) -> SolanaProgramTestResult {
+    let accounts = formfn_candy_machine::accounts::MigrateFeatureFlags {
+        candy_machine: *candy_machine,
+        formfn_authority: formfn_authority.pubkey(),
+    }
+    .to_account_metas(None);
+
+    let data = formfn_candy_machine::instruction::MigrateFeatureFlags {}.data();
+    let migrate_ix = Instruction {
+        program_id: formfn_candy_machine::id(),
+        data,
+        accounts,
+    };
+
+    update_blockhash(context).await?;
+    let tx = Transaction::new_signed_with_payer(
+        &[migrate_ix],
+        Some(&formfn_authority.pubkey()),
+        &[formfn_authority],
+        context.last_blockhash,
+    );
+
+    context
+        .banks_client
+        .process_transaction(tx)
+        .await
+        .map_err(|e| e.into())
+}
+
+pub async fn migrate_candy_machine(
+    context: &mut ProgramTestContext,
+    candy_machine: &Pubkey,
+    formfn_authority: &Keypair,
+    from_version: u8,
+) -> SolanaProgramTestResult {
+    let accounts = formfn_candy_machine::accounts::MigrateCandyMachine {
+        candy_machine: *candy_machine,
+        formfn_authority: formfn_authority.pubkey(),
+        system_program: system_program::id(),
+    }
+    .to_account_metas(None);
+
+    let data = formfn_candy_machine::instruction::MigrateCandyMachine { from_version }.data();
+    let migrate_ix = Instruction {
+        program_id: formfn_candy_machine::id(),
+        data,
+        accounts,
+    };
+
+    update_blockhash(context).await?;
+    let tx = Transaction::new_signed_with_payer(
+        &[migrate_ix],
+        Some(&formfn_authority.pubkey()),
+        &[formfn_authority],
+        context.last_blockhash,
+    );
+
+    context
+        .banks_client
+        .process_transaction(tx)
+        .await
+        .map_err(|e| e.into())
+}
+
+pub async fn compact_candy_machine(
+    context: &mut ProgramTestContext,
+    candy_machine: &Pubkey,
+    formfn_authority: &Keypair,
+) -> SolanaProgramTestResult {
+    let accounts = formfn_candy_machine::accounts::CompactCandyMachine {
+        candy_machine: *candy_machine,
+        formfn_authority: formfn_authority.pubkey(),
+    }
+    .to_account_metas(None);
+
+    let data = formfn_candy_machine::instruction::CompactCandyMachine {}.data();
+    let compact_ix = Instruction {
+        program_id: formfn_candy_machine::id(),
+        data,
+        accounts,
+    };
+
+    update_blockhash(context).await?;
+    let tx = Transaction::new_signed_with_payer(
+        &[compact_ix],
+        Some(&formfn_authority.pubkey()),
+        &[formfn_authority],
+        context.last_blockhash,
+    );
+
+    context
+        .banks_client
+        .process_transaction(tx)
+        .await
+        .map_err(|e| e.into())
+}
+
+pub async fn resize_candy_machine(
+    context: &mut ProgramTestContext,
+    candy_machine: &Pubkey,
+    formfn_authority: &Keypair,
+    items_available: u64,
+) -> SolanaProgramTestResult {
+    let accounts = formfn_candy_machine::accounts::ResizeCandyMachine {
+        candy_machine: *candy_machine,
+        formfn_authority: formfn_authority.pubkey(),
+        system_program: system_program::id(),
+    }
+    .to_account_metas(None);
+
+    let data = formfn_candy_machine::instruction::ResizeCandyMachine { items_available }.data();
+    let resize_ix = Instruction {
+        program_id: formfn_candy_machine::id(),
+        data,
+        accounts,
+    };
+
+    update_blockhash(context).await?;
+    let tx = Transaction::new_signed_with_payer(
+        &[resize_ix],
+        Some(&formfn_authority.pubkey()),
+        &[formfn_authority],
+        context.last_blockhash,
+    );
+
+    context
+        .banks_client
+        .process_transaction(tx)
+        .await
+        .map_err(|e| e.into())
+}
+
+pub async fn reveal_shuffle_seed(
+    context: &mut ProgramTestContext,
+    candy_machine: &Pubkey,
+    formfn_authority: &Keypair,
+    seed: [u8; 32],
+) -> SolanaProgramTestResult {
+    let accounts = formfn_candy_machine::accounts::RevealShuffleSeed {
+        candy_machine: *candy_machine,
+        formfn_authority: formfn_authority.pubkey(),
+    }
+    .to_account_metas(None);
+
+    let data = formfn_candy_machine::instruction::RevealShuffleSeed { seed }.data();
+    let reveal_ix = Instruction {
+        program_id: formfn_candy_machine::id(),
+        data,
+        accounts,
+    };
+
+    update_blockhash(context).await?;
+    let tx = Transaction::new_signed_with_payer(
+        &[reveal_ix],
+        Some(&formfn_authority.pubkey()),
+        &[formfn_authority],
+        context.last_blockhash,
+    );
+
+    context
+        .banks_client
+        .process_transaction(tx)
+        .await
+        .map_err(|e| e.into())
+}
+
 pub async fn append_merkle_allowlist_roots(
     context: &mut ProgramTestContext,
     candy_machine: &Pubkey,
     formfn_authority: &Keypair,
-    roots_to_append: Vec<[u8; 32]>,
+    roots_to_append: Vec<MerkleAllowlistRoot>,
 ) -> SolanaProgramTestResult {
     let accounts = formfn_candy_machine::accounts::AppendMerkleAllowlistRoots {
         formfn_authority: formfn_authority.pubkey(),
@@ -213,6 +503,116 @@ pub async fn clear_merkle_allowlist_roots(
         .map_err(|e| e.into())
 }
 
+pub async fn claim_dutch_auction_rebate(
+    context: &mut ProgramTestContext,
+    candy_machine: &Pubkey,
+    buyer: &Keypair,
+) -> SolanaProgramTestResult {
+    let (buyer_info_account, _) = find_buyer_info_account_pda(candy_machine, &buyer.pubkey());
+    let accounts = formfn_candy_machine::accounts::ClaimDutchAuctionRebate {
+        candy_machine: *candy_machine,
+        buyer: buyer.pubkey(),
+        buyer_info_account,
+    }
+    .to_account_metas(None);
+
+    let data = formfn_candy_machine::instruction::ClaimDutchAuctionRebate {}.data();
+
+    let claim_ix = Instruction {
+        program_id: formfn_candy_machine::id(),
+        data,
+        accounts,
+    };
+
+    update_blockhash(context).await?;
+    let tx = Transaction::new_signed_with_payer(
+        &[claim_ix],
+        Some(&buyer.pubkey()),
+        &[buyer],
+        context.last_blockhash,
+    );
+
+    context
+        .banks_client
+        .process_transaction(tx)
+        .await
+        .map_err(|e| e.into())
+}
+
+pub async fn release_dutch_auction_proceeds(
+    context: &mut ProgramTestContext,
+    candy_machine: &Pubkey,
+    treasury_wallet: &Pubkey,
+    payer: &Keypair,
+    // The platform fee wallet and primary sale split addresses.
+    payment_accounts: &[Pubkey],
+) -> SolanaProgramTestResult {
+    let mut accounts = formfn_candy_machine::accounts::ReleaseDutchAuctionProceeds {
+        candy_machine: *candy_machine,
+        treasury_wallet: *treasury_wallet,
+    }
+    .to_account_metas(None);
+    for payment_account in payment_accounts.iter() {
+        accounts.push(AccountMeta::new(*payment_account, false));
+    }
+
+    let data = formfn_candy_machine::instruction::ReleaseDutchAuctionProceeds {}.data();
+
+    let release_ix = Instruction {
+        program_id: formfn_candy_machine::id(),
+        data,
+        accounts,
+    };
+
+    update_blockhash(context).await?;
+    let tx = Transaction::new_signed_with_payer(
+        &[release_ix],
+        Some(&payer.pubkey()),
+        &[payer],
+        context.last_blockhash,
+    );
+
+    context
+        .banks_client
+        .process_transaction(tx)
+        .await
+        .map_err(|e| e.into())
+}
+
+pub async fn withdraw_funds(
+    context: &mut ProgramTestContext,
+    candy_machine: &Pubkey,
+    formfn_authority: &Keypair,
+) -> SolanaProgramTestResult {
+    let accounts = formfn_candy_machine::accounts::WithdrawFunds {
+        candy_machine: *candy_machine,
+        formfn_authority: formfn_authority.pubkey(),
+    }
+    .to_account_metas(None);
+
+    let data = formfn_candy_machine::instruction::WithdrawFunds {}.data();
+
+    let withdraw_ix = Instruction {
+        program_id: formfn_candy_machine::id(),
+        data,
+        accounts,
+    };
+
+    update_blockhash(context).await?;
+    let tx = Transaction::new_signed_with_payer(
+        &[withdraw_ix],
+        Some(&formfn_authority.pubkey()),
+        &[formfn_authority],
+        context.last_blockhash,
+    );
+
+    context
+        .banks_client
+        .process_transaction(tx)
+        .await
+        .map_err(|e| e.into())
+}
+
 pub async fn add_config_lines(
     context: &mut ProgramTestContext,
     candy_machine: &Pubkey,
@@ -254,6 +654,80 @@ pub async fn add_config_lines(
         .map_err(|e| e.into())
 }
 
+pub async fn remove_config_lines(
+    context: &mut ProgramTestContext,
+    candy_machine: &Pubkey,
+    formfn_authority: &Keypair,
+    index: u32,
+    count: u32,
+) -> SolanaProgramTestResult {
+    let accounts = formfn_candy_machine::accounts::RemoveConfigLines {
+        candy_machine: *candy_machine,
+        formfn_authority: formfn_authority.pubkey(),
+    }
+    .to_account_metas(None);
+
+    let data = formfn_candy_machine::instruction::RemoveConfigLines { index, count }.data();
+    let remove_config_lines_ix = Instruction {
+        program_id: formfn_candy_machine::id(),
+        data,
+        accounts,
+    };
+
+    update_blockhash(context).await?;
+    let tx = Transaction::new_signed_with_payer(
+        &[remove_config_lines_ix],
+        Some(&formfn_authority.pubkey()),
+        &[formfn_authority],
+        context.last_blockhash,
+    );
+
+    context
+        .banks_client
+        .process_transaction(tx)
+        .await
+        .map_err(|e| e.into())
+}
+
+pub async fn replace_config_lines(
+    context: &mut ProgramTestContext,
+    candy_machine: &Pubkey,
+    formfn_authority: &Keypair,
+    index: u32,
+    config_lines: Vec<ConfigLine>,
+) -> SolanaProgramTestResult {
+    let accounts = formfn_candy_machine::accounts::ReplaceConfigLines {
+        candy_machine: *candy_machine,
+        formfn_authority: formfn_authority.pubkey(),
+    }
+    .to_account_metas(None);
+
+    let data = formfn_candy_machine::instruction::ReplaceConfigLines {
+        index,
+        config_lines,
+    }
+    .data();
+    let replace_config_lines_ix = Instruction {
+        program_id: formfn_candy_machine::id(),
+        data,
+        accounts,
+    };
+
+    update_blockhash(context).await?;
+    let tx = Transaction::new_signed_with_payer(
+        &[replace_config_lines_ix],
+        Some(&formfn_authority.pubkey()),
+        &[formfn_authority],
+        context.last_blockhash,
+    );
+
+    context
+        .banks_client
+        .process_transaction(tx)
+        .await
+        .map_err(|e| e.into())
+}
+
 pub async fn add_all_config_lines(
     context: &mut ProgramTestContext,
     candy_machine: &Pubkey,
@@ -296,33 +770,72 @@ pub async fn add_all_config_lines(
         .await?;
     }
 
-    Ok(())
+    Ok(())
+}
+
+pub async fn set_collection(
+    context: &mut ProgramTestContext,
+    candy_machine: &Pubkey,
+    formfn_authority: &Keypair,
+    creator_authority: &Keypair,
+    collection_info: &CollectionInfo,
+) -> SolanaProgramTestResult {
+    let accounts = formfn_candy_machine::accounts::SetCollection {
+        candy_machine: *candy_machine,
+        formfn_authority: formfn_authority.pubkey(),
+        creator_authority: creator_authority.pubkey(),
+        collection_pda: collection_info.pda,
+        payer: formfn_authority.pubkey(),
+        system_program: system_program::id(),
+        rent: sysvar::rent::id(),
+        metadata: collection_info.metadata,
+        mint: collection_info.mint.pubkey(),
+        edition: collection_info.master_edition,
+        collection_authority_record: collection_info.authority_record,
+        token_metadata_program: mpl_token_metadata::id(),
+    }
+    .to_account_metas(None);
+
+    let data = formfn_candy_machine::instruction::SetCollection {}.data();
+    let set_ix = Instruction {
+        program_id: formfn_candy_machine::id(),
+        data,
+        accounts,
+    };
+
+    update_blockhash(context).await?;
+    let tx = Transaction::new_signed_with_payer(
+        &[set_ix],
+        Some(&formfn_authority.pubkey()),
+        &[formfn_authority],
+        context.last_blockhash,
+    );
+
+    context
+        .banks_client
+        .process_transaction(tx)
+        .await
+        .map_err(|e| e.into())
 }
 
-pub async fn set_collection(
+pub async fn remove_collection(
     context: &mut ProgramTestContext,
     candy_machine: &Pubkey,
     formfn_authority: &Keypair,
-    creator_authority: &Keypair,
     collection_info: &CollectionInfo,
 ) -> SolanaProgramTestResult {
-    let accounts = formfn_candy_machine::accounts::SetCollection {
+    let accounts = formfn_candy_machine::accounts::RemoveCollection {
         candy_machine: *candy_machine,
         formfn_authority: formfn_authority.pubkey(),
-        creator_authority: creator_authority.pubkey(),
         collection_pda: collection_info.pda,
-        payer: formfn_authority.pubkey(),
-        system_program: system_program::id(),
-        rent: sysvar::rent::id(),
         metadata: collection_info.metadata,
         mint: collection_info.mint.pubkey(),
-        edition: collection_info.master_edition,
         collection_authority_record: collection_info.authority_record,
         token_metadata_program: mpl_token_metadata::id(),
     }
     .to_account_metas(None);
 
-    let data = formfn_candy_machine::instruction::SetCollection {}.data();
+    let data = formfn_candy_machine::instruction::RemoveCollection {}.data();
     let set_ix = Instruction {
         program_id: formfn_candy_machine::id(),
         data,
@@ -344,24 +857,36 @@ pub async fn set_collection(
         .map_err(|e| e.into())
 }
 
-pub async fn remove_collection(
+pub async fn set_open_edition(
     context: &mut ProgramTestContext,
     candy_machine: &Pubkey,
     formfn_authority: &Keypair,
-    collection_info: &CollectionInfo,
+    creator_authority: &Pubkey,
+    master_edition_info: &MasterEditionManager,
 ) -> SolanaProgramTestResult {
-    let accounts = formfn_candy_machine::accounts::RemoveCollection {
+    let open_edition_pda = find_open_edition_pda(candy_machine).0;
+    let accounts = formfn_candy_machine::accounts::SetOpenEdition {
         candy_machine: *candy_machine,
         formfn_authority: formfn_authority.pubkey(),
-        collection_pda: collection_info.pda,
-        metadata: collection_info.metadata,
-        mint: collection_info.mint.pubkey(),
-        collection_authority_record: collection_info.authority_record,
+        creator_authority: *creator_authority,
+        open_edition_pda,
+        open_edition_token_account: get_associated_token_address(
+            &open_edition_pda,
+            &master_edition_info.mint.pubkey(),
+        ),
+        formfn_authority_token_account: master_edition_info.token_account,
+        metadata: master_edition_info.metadata_pubkey,
+        mint: master_edition_info.mint.pubkey(),
+        edition: master_edition_info.edition_pubkey,
         token_metadata_program: mpl_token_metadata::id(),
+        token_program: spl_token::id(),
+        associated_token_program: AssociatedToken::id(),
+        system_program: system_program::id(),
+        rent: sysvar::rent::id(),
     }
     .to_account_metas(None);
 
-    let data = formfn_candy_machine::instruction::RemoveCollection {}.data();
+    let data = formfn_candy_machine::instruction::SetOpenEdition {}.data();
     let set_ix = Instruction {
         program_id: formfn_candy_machine::id(),
         data,
@@ -467,8 +992,9 @@ pub async fn thaw_nft(
     signer: &Keypair,
     freeze_info: &FreezeInfo,
     nft_info: &MasterEditionManager,
+    programmable_nft_accounts: &[AccountMeta],
 ) -> SolanaProgramTestResult {
-    let accounts = formfn_candy_machine::accounts::ThawNFT {
+    let mut accounts = formfn_candy_machine::accounts::ThawNFT {
         freeze_pda: freeze_info.pda,
         candy_machine: *candy_machine,
         token_account: nft_info.token_account,
@@ -481,6 +1007,7 @@ pub async fn thaw_nft(
         system_program: system_program::id(),
     }
     .to_account_metas(None);
+    accounts.extend_from_slice(programmable_nft_accounts);
 
     let data = formfn_candy_machine::instruction::ThawNft {}.data();
     let set_ix = Instruction {
@@ -503,6 +1030,62 @@ pub async fn thaw_nft(
         .map_err(|e| e.into())
 }
 
+pub async fn refund_nft(
+    context: &mut ProgramTestContext,
+    candy_machine: &Pubkey,
+    freeze_info: &FreezeInfo,
+    nft_info: &MasterEditionManager,
+    payer: &Pubkey,
+    recipient: &Pubkey,
+    treasury_mint: Option<Pubkey>,
+) -> SolanaProgramTestResult {
+    let mint = nft_info.mint.pubkey();
+    let mut accounts = formfn_candy_machine::accounts::RefundNFT {
+        candy_machine: *candy_machine,
+        freeze_pda: freeze_info.pda,
+        refund_receipt: find_refund_receipt_pda(candy_machine, &mint).0,
+        token_account: nft_info.token_account,
+        owner: nft_info.owner.pubkey(),
+        mint,
+        edition: nft_info.edition_pubkey,
+        payer: *payer,
+        buyer_info_account: find_buyer_info_account_pda(candy_machine, recipient).0,
+        token_program: spl_token::ID,
+        token_metadata_program: mpl_token_metadata::ID,
+    }
+    .to_account_metas(None);
+    if let Some(treasury_mint) = treasury_mint {
+        accounts.push(AccountMeta::new(
+            freeze_info.find_freeze_ata(&treasury_mint),
+            false,
+        ));
+        accounts.push(AccountMeta::new(
+            get_associated_token_address(payer, &treasury_mint),
+            false,
+        ));
+    }
+
+    let data = formfn_candy_machine::instruction::RefundNft {}.data();
+    let refund_ix = Instruction {
+        program_id: formfn_candy_machine::id(),
+        data,
+        accounts,
+    };
+    update_blockhash(context).await?;
+    let tx = Transaction::new_signed_with_payer(
+        &[refund_ix],
+        Some(&nft_info.owner.pubkey()),
+        &[&nft_info.owner],
+        context.last_blockhash,
+    );
+
+    context
+        .banks_client
+        .process_transaction(tx)
+        .await
+        .map_err(|e| e.into())
+}
+
 pub async fn unlock_funds(
     context: &mut ProgramTestContext,
     candy_machine: &Pubkey,
@@ -554,28 +1137,34 @@ pub fn mint_nft_ix(
     creator_bump: u8,
     treasury_wallet: &Pubkey,
     creator_authority: &Pubkey,
-    buyer: &Keypair,
+    payer: &Keypair,
+    recipient: &Pubkey,
     new_nft: &MasterEditionManager,
     token_info: TokenInfo,
     spl_token_allowlist_info: SplTokenAllowlistInfo,
     collection_info: CollectionInfo,
     freeze_info: FreezeInfo,
+    mint_payment_accounts: &[Pubkey],
     should_set_bot_signer_authority_as_signer: bool,
     buyer_merkle_allowlist_proof_data: Option<BuyerMerkleAllowlistProofData>,
     mint_price: u64,
+    payment_option_index: Option<u8>,
+    randomness_account: &Pubkey,
+    allowlist_accounts: &[Pubkey],
 ) -> Vec<Instruction> {
     let metadata = new_nft.metadata_pubkey;
     let master_edition = new_nft.edition_pubkey;
     let mint = new_nft.mint.pubkey();
 
-    let (buyer_info_account, _) = find_buyer_info_account_pda(&candy_machine, &buyer.pubkey());
+    let (buyer_info_account, _) = find_buyer_info_account_pda(&candy_machine, recipient);
 
-    let buyer_token_account = get_associated_token_address(&buyer.pubkey(), &mint);
+    let recipient_token_account = get_associated_token_address(recipient, &mint);
 
     let mut accounts = formfn_candy_machine::accounts::MintNFT {
         candy_machine: *candy_machine,
         candy_machine_creator: *candy_creator_pda,
-        buyer: buyer.pubkey(),
+        payer: payer.pubkey(),
+        recipient: *recipient,
         treasury_wallet: *treasury_wallet,
         metadata,
         mint,
@@ -585,11 +1174,11 @@ pub fn mint_nft_ix(
         token_program: spl_token::id(),
         system_program: system_program::id(),
         rent: sysvar::rent::id(),
-        recent_slothashes: sysvar::slot_hashes::id(),
+        recent_slothashes: *randomness_account,
         instruction_sysvar_account: sysvar::instructions::id(),
         bot_signer_authority: get_bot_signer_keypair().pubkey(),
         buyer_info_account,
-        buyer_token_account,
+        recipient_token_account,
         ata_program: AssociatedToken::id(),
     }
     .to_account_metas(None);
@@ -612,13 +1201,16 @@ pub fn mint_nft_ix(
         }
     }
 
+    for allowlist_account in allowlist_accounts.iter() {
+        accounts.push(AccountMeta::new(*allowlist_account, false));
+    }
+
     if token_info.set {
         accounts.push(AccountMeta::new(token_info.minter_account, false));
     }
 
     if freeze_info.set {
         accounts.push(AccountMeta::new(freeze_info.pda, false));
-        accounts.push(AccountMeta::new(new_nft.token_account, false));
         if token_info.set {
             accounts.push(AccountMeta::new(
                 freeze_info.find_freeze_ata(&token_info.mint),
@@ -627,12 +1219,17 @@ pub fn mint_nft_ix(
         }
     }
 
-    let (_, buyer_info_account_bump) = find_buyer_info_account_pda(&candy_machine, &buyer.pubkey());
+    for mint_payment_account in mint_payment_accounts.iter() {
+        accounts.push(AccountMeta::new(*mint_payment_account, false));
+    }
+
+    let (_, buyer_info_account_bump) = find_buyer_info_account_pda(&candy_machine, recipient);
     let data = formfn_candy_machine::instruction::MintNft {
         creator_bump,
         buyer_info_account_bump,
         buyer_merkle_allowlist_proof_data,
         expected_price: mint_price,
+        payment_option_index,
     }
     .data();
 
@@ -651,31 +1248,46 @@ pub fn mint_nft_ix(
     instructions.push(mint_ix);
 
     if collection_info.set {
-        let accounts = formfn_candy_machine::accounts::SetCollectionDuringMint {
-            candy_machine: *candy_machine,
-            metadata,
-            buyer: buyer.pubkey(),
-            collection_pda: collection_info.pda,
-            token_metadata_program: mpl_token_metadata::id(),
-            instruction_sysvar_account: sysvar::instructions::id(),
-            collection_mint: collection_info.mint.pubkey(),
-            collection_metadata: collection_info.metadata,
-            collection_master_edition: collection_info.master_edition,
-            creator_authority: *creator_authority,
-            collection_authority_record: collection_info.authority_record,
-        }
-        .to_account_metas(None);
-        let data = formfn_candy_machine::instruction::SetCollectionDuringMint {}.data();
-        let set_ix = Instruction {
-            program_id: formfn_candy_machine::id(),
-            data,
-            accounts,
-        };
-        instructions.push(set_ix)
+        instructions.push(set_collection_during_mint_ix(
+            candy_machine,
+            &metadata,
+            payer,
+            creator_authority,
+            &collection_info,
+        ));
     }
     instructions
 }
 
+pub fn set_collection_during_mint_ix(
+    candy_machine: &Pubkey,
+    metadata: &Pubkey,
+    payer: &Keypair,
+    creator_authority: &Pubkey,
+    collection_info: &CollectionInfo,
+) -> Instruction {
+    let accounts = formfn_candy_machine::accounts::SetCollectionDuringMint {
+        candy_machine: *candy_machine,
+        metadata: *metadata,
+        buyer: payer.pubkey(),
+        collection_pda: collection_info.pda,
+        token_metadata_program: mpl_token_metadata::id(),
+        instruction_sysvar_account: sysvar::instructions::id(),
+        collection_mint: collection_info.mint.pubkey(),
+        collection_metadata: collection_info.metadata,
+        collection_master_edition: collection_info.master_edition,
+        creator_authority: *creator_authority,
+        collection_authority_record: collection_info.authority_record,
+    }
+    .to_account_metas(None);
+    let data = formfn_candy_machine::instruction::SetCollectionDuringMint {}.data();
+    Instruction {
+        program_id: formfn_candy_machine::id(),
+        data,
+        accounts,
+    }
+}
+
 #[allow(clippy::too_many_arguments)]
 pub async fn mint_nft(
     context: &mut ProgramTestContext,
@@ -684,15 +1296,20 @@ pub async fn mint_nft(
     creator_bump: u8,
     wallet: &Pubkey,
     creator_authority: &Pubkey,
-    buyer: &Keypair,
+    payer: &Keypair,
+    recipient: &Pubkey,
     new_nft: &MasterEditionManager,
     token_info: TokenInfo,
     spl_token_allowlist_info: SplTokenAllowlistInfo,
     collection_info: CollectionInfo,
     freeze_info: FreezeInfo,
+    mint_payment_accounts: &[Pubkey],
     should_add_bot_signer: bool,
     buyer_merkle_allowlist_proof_data: Option<BuyerMerkleAllowlistProofData>,
     mint_price: u64,
+    payment_option_index: Option<u8>,
+    randomness_account: &Pubkey,
+    allowlist_accounts: &[Pubkey],
 ) -> SolanaProgramTestResult {
     let ins = mint_nft_ix(
         candy_machine,
@@ -700,26 +1317,236 @@ pub async fn mint_nft(
         creator_bump,
         wallet,
         creator_authority,
-        buyer,
+        payer,
+        recipient,
         new_nft,
         token_info,
         spl_token_allowlist_info,
         collection_info,
         freeze_info,
+        mint_payment_accounts,
         should_add_bot_signer,
         buyer_merkle_allowlist_proof_data,
         mint_price,
+        payment_option_index,
+        randomness_account,
+        allowlist_accounts,
     );
     let bot_signer = get_bot_signer_keypair();
     let signers = if should_add_bot_signer {
-        vec![buyer, &new_nft.mint, &bot_signer]
+        vec![payer, &new_nft.mint, &bot_signer]
     } else {
-        vec![buyer, &new_nft.mint]
+        vec![payer, &new_nft.mint]
     };
     update_blockhash(context).await?;
     let tx = Transaction::new_signed_with_payer(
         &ins,
-        Some(&buyer.pubkey()),
+        Some(&payer.pubkey()),
+        &signers,
+        context.last_blockhash,
+    );
+
+    context
+        .banks_client
+        .process_transaction(tx)
+        .await
+        .map_err(|e| e.into())
+}
+
+#[allow(clippy::too_many_arguments)]
+pub fn mint_many_nft_ix(
+    candy_machine: &Pubkey,
+    candy_creator_pda: &Pubkey,
+    creator_bump: u8,
+    treasury_wallet: &Pubkey,
+    creator_authority: &Pubkey,
+    payer: &Keypair,
+    recipient: &Pubkey,
+    new_nfts: &[MasterEditionManager],
+    token_info: TokenInfo,
+    spl_token_allowlist_info: SplTokenAllowlistInfo,
+    collection_info: CollectionInfo,
+    freeze_info: FreezeInfo,
+    mint_payment_accounts: &[Pubkey],
+    should_set_bot_signer_authority_as_signer: bool,
+    buyer_merkle_allowlist_proof_data: Option<BuyerMerkleAllowlistProofData>,
+    mint_price: u64,
+    payment_option_index: Option<u8>,
+    randomness_account: &Pubkey,
+    allowlist_accounts: &[Pubkey],
+) -> Vec<Instruction> {
+    let (buyer_info_account, buyer_info_account_bump) =
+        find_buyer_info_account_pda(&candy_machine, recipient);
+
+    let mut accounts = formfn_candy_machine::accounts::MintManyNFT {
+        candy_machine: *candy_machine,
+        candy_machine_creator: *candy_creator_pda,
+        payer: payer.pubkey(),
+        recipient: *recipient,
+        treasury_wallet: *treasury_wallet,
+        creator_authority: *creator_authority,
+        token_metadata_program: mpl_token_metadata::id(),
+        token_program: spl_token::id(),
+        system_program: system_program::id(),
+        rent: sysvar::rent::id(),
+        recent_slothashes: *randomness_account,
+        instruction_sysvar_account: sysvar::instructions::id(),
+        bot_signer_authority: get_bot_signer_keypair().pubkey(),
+        buyer_info_account,
+        ata_program: AssociatedToken::id(),
+    }
+    .to_account_metas(None);
+
+    if should_set_bot_signer_authority_as_signer {
+        for account in accounts.iter_mut() {
+            if cmp_pubkeys(&account.pubkey, &get_bot_signer_keypair().pubkey()) {
+                account.is_signer = true;
+            }
+        }
+    }
+
+    if spl_token_allowlist_info.set {
+        accounts.push(AccountMeta::new(
+            spl_token_allowlist_info.minter_account,
+            false,
+        ));
+        if spl_token_allowlist_info.spl_token_allowlist_config.burn == BurnEveryTime {
+            accounts.push(AccountMeta::new(spl_token_allowlist_info.mint, false));
+        }
+    }
+
+    for allowlist_account in allowlist_accounts.iter() {
+        accounts.push(AccountMeta::new(*allowlist_account, false));
+    }
+
+    if token_info.set {
+        accounts.push(AccountMeta::new(token_info.minter_account, false));
+    }
+
+    if freeze_info.set {
+        accounts.push(AccountMeta::new(freeze_info.pda, false));
+        if token_info.set {
+            accounts.push(AccountMeta::new(
+                freeze_info.find_freeze_ata(&token_info.mint),
+                false,
+            ));
+        }
+    }
+
+    for mint_payment_account in mint_payment_accounts.iter() {
+        accounts.push(AccountMeta::new(*mint_payment_account, false));
+    }
+
+    for new_nft in new_nfts.iter() {
+        let mint = new_nft.mint.pubkey();
+        accounts.push(AccountMeta::new(mint, true));
+        accounts.push(AccountMeta::new(new_nft.metadata_pubkey, false));
+        accounts.push(AccountMeta::new(new_nft.edition_pubkey, false));
+        accounts.push(AccountMeta::new(
+            get_associated_token_address(recipient, &mint),
+            false,
+        ));
+        if freeze_info.set {
+            accounts.push(AccountMeta::new(
+                find_refund_receipt_pda(candy_machine, &mint).0,
+                false,
+            ));
+        }
+    }
+
+    let data = formfn_candy_machine::instruction::MintManyNft {
+        creator_bump,
+        buyer_info_account_bump,
+        buyer_merkle_allowlist_proof_data,
+        quantity: new_nfts.len() as u8,
+        expected_price: mint_price,
+        payment_option_index,
+    }
+    .data();
+
+    let compute_budget_ix = ComputeBudgetInstruction::set_compute_unit_limit(1_400_000);
+
+    let mint_ix = Instruction {
+        program_id: formfn_candy_machine::id(),
+        data,
+        accounts,
+    };
+
+    let mut instructions = vec![compute_budget_ix, mint_ix];
+
+    // The collection of each NFT is set by its own instruction, in the order
+    // the NFTs are minted.
+    if collection_info.set {
+        for new_nft in new_nfts.iter() {
+            instructions.push(set_collection_during_mint_ix(
+                candy_machine,
+                &new_nft.metadata_pubkey,
+                payer,
+                creator_authority,
+                &collection_info,
+            ));
+        }
+    }
+
+    instructions
+}
+
+#[allow(clippy::too_many_arguments)]
+pub async fn mint_many_nft(
+    context: &mut ProgramTestContext,
+    candy_machine: &Pubkey,
+    candy_creator_pda: &Pubkey,
+    creator_bump: u8,
+    wallet: &Pubkey,
+    creator_authority: &Pubkey,
+    payer: &Keypair,
+    recipient: &Pubkey,
+    new_nfts: &[MasterEditionManager],
+    token_info: TokenInfo,
+    spl_token_allowlist_info: SplTokenAllowlistInfo,
+    collection_info: CollectionInfo,
+    freeze_info: FreezeInfo,
+    mint_payment_accounts: &[Pubkey],
+    should_add_bot_signer: bool,
+    buyer_merkle_allowlist_proof_data: Option<BuyerMerkleAllowlistProofData>,
+    mint_price: u64,
+    payment_option_index: Option<u8>,
+    randomness_account: &Pubkey,
+    allowlist_accounts: &[Pubkey],
+) -> SolanaProgramTestResult {
+    let ins = mint_many_nft_ix(
+        candy_machine,
+        candy_creator_pda,
+        creator_bump,
+        wallet,
+        creator_authority,
+        payer,
+        recipient,
+        new_nfts,
+        token_info,
+        spl_token_allowlist_info,
+        collection_info,
+        freeze_info,
+        mint_payment_accounts,
+        should_add_bot_signer,
+        buyer_merkle_allowlist_proof_data,
+        mint_price,
+        payment_option_index,
+        randomness_account,
+        allowlist_accounts,
+    );
+    let bot_signer = get_bot_signer_keypair();
+    let mut signers = vec![payer];
+    for new_nft in new_nfts.iter() {
+        signers.push(&new_nft.mint);
+    }
+    if should_add_bot_signer {
+        signers.push(&bot_signer);
+    }
+    update_blockhash(context).await?;
+    let tx = Transaction::new_signed_with_payer(
+        &ins,
+        Some(&payer.pubkey()),
         &signers,
         context.last_blockhash,
     );
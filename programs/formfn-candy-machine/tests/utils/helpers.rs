@@ -3,9 +3,11 @@ use arrayref::array_ref;
 use chrono::Utc;
 use console::style;
 use enum_index::EnumIndex;
-use formfn_candy_machine::constants::{CONFIG_ARRAY_START, CONFIG_LINE_SIZE};
-use formfn_candy_machine::{CandyError, CandyMachine};
-use mpl_token_metadata::state::{MAX_NAME_LENGTH, MAX_URI_LENGTH};
+use formfn_candy_machine::constants::CONFIG_ARRAY_START;
+use formfn_candy_machine::{
+    CandyError, CandyMachine, CollectionAllowlistRecord, OpenEditionPda, RefundReceipt,
+};
+use mpl_token_metadata::state::EDITION_MARKER_BIT_SIZE;
 use solana_program::{native_token::LAMPORTS_PER_SOL, pubkey::Pubkey};
 use solana_sdk::account::Account;
 use solana_sdk::instruction::InstructionError;
@@ -54,6 +56,31 @@ pub fn find_collection_pda(candy_machine_key: &Pubkey) -> (Pubkey, u8) {
     )
 }
 
+pub fn find_open_edition_pda(candy_machine_key: &Pubkey) -> (Pubkey, u8) {
+    Pubkey::find_program_address(
+        &[
+            OpenEditionPda::PREFIX.as_bytes(),
+            candy_machine_key.as_ref(),
+        ],
+        &formfn_candy_machine::id(),
+    )
+}
+
+pub fn find_edition_marker_pda(master_edition_mint: &Pubkey, edition: u64) -> Pubkey {
+    let program_id = mpl_token_metadata::id();
+    Pubkey::find_program_address(
+        &[
+            mpl_token_metadata::state::PREFIX.as_bytes(),
+            program_id.as_ref(),
+            master_edition_mint.as_ref(),
+            mpl_token_metadata::state::EDITION.as_bytes(),
+            (edition / EDITION_MARKER_BIT_SIZE).to_string().as_bytes(),
+        ],
+        &program_id,
+    )
+    .0
+}
+
 pub fn find_buyer_info_account_pda(candy_machine: &Pubkey, buyer: &Pubkey) -> (Pubkey, u8) {
     let seeds = &[
         BUYER_INFO_ACCOUNT_PREFIX.as_bytes(),
@@ -63,6 +90,31 @@ pub fn find_buyer_info_account_pda(candy_machine: &Pubkey, buyer: &Pubkey) -> (P
     Pubkey::find_program_address(seeds, &formfn_candy_machine::id())
 }
 
+pub fn find_collection_allowlist_record_pda(
+    candy_machine: &Pubkey,
+    gating_mint: &Pubkey,
+) -> (Pubkey, u8) {
+    let seeds = &[
+        CollectionAllowlistRecord::PREFIX.as_bytes(),
+        candy_machine.as_ref(),
+        gating_mint.as_ref(),
+    ];
+    Pubkey::find_program_address(seeds, &formfn_candy_machine::id())
+}
+
+pub fn find_refund_receipt_pda(candy_machine: &Pubkey, mint: &Pubkey) -> (Pubkey, u8) {
+    let seeds = &[
+        RefundReceipt::PREFIX.as_bytes(),
+        candy_machine.as_ref(),
+        mint.as_ref(),
+    ];
+    Pubkey::find_program_address(seeds, &formfn_candy_machine::id())
+}
+
+pub fn find_tree_authority(merkle_tree: &Pubkey) -> (Pubkey, u8) {
+    Pubkey::find_program_address(&[merkle_tree.as_ref()], &mpl_bubblegum::id())
+}
+
 pub fn find_freeze_ata(freeze_info: &FreezeInfo, token_info: &TokenInfo) -> Pubkey {
     get_associated_token_address(&freeze_info.pda, &token_info.mint)
 }
@@ -133,7 +185,8 @@ pub fn parse_candy_machine_config_lines(
     candy_machine_account: Account,
 ) -> ParsedConfigLinesResult {
     let config_lines_start = CONFIG_ARRAY_START + 4;
-    let config_lines_length = CONFIG_LINE_SIZE * candy_machine_state.data.items_available as usize;
+    let config_line_size = candy_machine_state.data.get_config_line_size();
+    let config_lines_length = config_line_size * candy_machine_state.data.items_available as usize;
 
     let config_line_data_slice: &[u8] =
         &candy_machine_account.data[config_lines_start..config_lines_start + config_lines_length];
@@ -146,37 +199,16 @@ pub fn parse_candy_machine_config_lines(
 
     let config_line_count = config_line_count_number as usize;
 
-    let mut config_lines: Vec<ConfigLine> = Vec::with_capacity(config_line_count);
-
-    for n in 0..config_line_count {
-        let index_offset = if n == 0 { 0 } else { n * CONFIG_LINE_SIZE };
-
-        let mut name_vec = Vec::with_capacity(MAX_NAME_LENGTH);
-        let mut uri_vec = Vec::with_capacity(MAX_URI_LENGTH);
-
-        for i in 4..4 + MAX_NAME_LENGTH {
-            let index = i + index_offset;
-            if config_line_data_slice[index] == 0 {
-                break;
-            }
-            name_vec.push(config_line_data_slice[index])
-        }
-
-        for i in 8 + MAX_NAME_LENGTH..8 + MAX_NAME_LENGTH + MAX_URI_LENGTH {
-            let index = i + index_offset;
-            if config_line_data_slice[index] == 0 {
-                break;
-            }
-            uri_vec.push(config_line_data_slice[index])
-        }
-
-        let config_line: ConfigLine = ConfigLine {
-            name: String::from_utf8(name_vec).unwrap(),
-            uri: String::from_utf8(uri_vec).unwrap(),
-        };
-
-        config_lines.push(config_line);
-    }
+    let config_lines: Vec<ConfigLine> = config_line_data_slice
+        .chunks(config_line_size)
+        .take(config_line_count)
+        .map(|config_line| {
+            candy_machine_state
+                .data
+                .parse_config_line(config_line)
+                .unwrap()
+        })
+        .collect();
 
     ParsedConfigLinesResult {
         config_lines,
@@ -696,8 +696,12 @@ cat >$CONFIG_FILE <<-EOM
     "shdwStorageAccount": $SHDW,
     "isMutable": true,
     "limitPerAddress": 0,
+    "premintLimitPerAddress": 0,
+    "allowlistLimitPerAddress": 0,
+    "totalLimitPerAddress": 0,
+    "allowlistItemsCap": 0,
     "botProtectionEnabled": false,
-    "sequentialMintOrderEnabled": false,
+    "mintOrder": "random",
     "creatorAuthority": "$WALLET_ADDRESS",
     "creators": $CREATORS
 }
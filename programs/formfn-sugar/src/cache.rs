@@ -54,6 +54,15 @@ pub struct CacheProgram {
     pub candy_machine_creator: String,
     #[serde(rename = "collectionMint")]
     pub collection_mint: String,
+    // Base58 seed of a shuffled mint order, which has to stay secret until
+    // the candy machine sells out or its sale ends. This is the only copy of
+    // the seed, and reveal can't apply the shuffled order without it.
+    #[serde(
+        rename = "shuffleSeed",
+        default,
+        skip_serializing_if = "String::is_empty"
+    )]
+    pub shuffle_seed: String,
 }
 
 impl CacheProgram {
@@ -62,6 +71,7 @@ impl CacheProgram {
             candy_machine: String::new(),
             candy_machine_creator: String::new(),
             collection_mint: String::new(),
+            shuffle_seed: String::new(),
         }
     }
 
@@ -72,6 +82,7 @@ impl CacheProgram {
             candy_machine: candy_machine.to_string(),
             candy_machine_creator: candy_machine_creator_pda.to_string(),
             collection_mint: String::new(),
+            shuffle_seed: String::new(),
         }
     }
 }
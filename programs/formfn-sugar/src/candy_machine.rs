@@ -2,13 +2,15 @@ use anchor_client::{solana_sdk::pubkey::Pubkey, Client, ClientError};
 use anyhow::{anyhow, Result};
 pub use formfn_candy_machine::ID as CANDY_MACHINE_ID;
 use formfn_candy_machine::{
-    CandyMachine, CandyMachineData, SplTokenAllowlistMode, SplTokenAllowlistSettings,
+    BondingCurveSettings, CandyMachine, CandyMachineData, DutchAuctionSettings,
+    MintPhaseAllowlistSource, MintPhaseSettings, PaymentOption, PrimarySaleSplit,
+    SplTokenAllowlistMode, SplTokenAllowlistSettings, SplTokenAllowlistToken,
 };
 use spl_token::id as token_program_id;
 
 use crate::{
     common::FloatConversionError,
-    config::{data::SugarConfig, price_as_lamports, ConfigData},
+    config::{config_time_as_timestamp, data::SugarConfig, price_as_lamports, ConfigData},
     setup::setup_client,
     utils::{check_spl_token, f64_to_u64_safe},
 };
@@ -31,7 +33,17 @@ pub fn parse_config_price(
     config: &ConfigData,
     price_to_parse: f64,
 ) -> Result<u64> {
-    let parsed_price = if let Some(spl_token) = config.spl_token {
+    parse_price_for_mint(client, config.spl_token, price_to_parse)
+}
+
+/// Convert a price to base units of the SPL token, or lamports if no token
+/// is given.
+pub fn parse_price_for_mint(
+    client: &Client,
+    spl_token: Option<Pubkey>,
+    price_to_parse: f64,
+) -> Result<u64> {
+    let parsed_price = if let Some(spl_token) = spl_token {
         let token_program = client.program(token_program_id());
         let token_mint = check_spl_token(&token_program, &spl_token.to_string())?;
 
@@ -66,6 +78,90 @@ pub fn parse_config_price(
     Ok(parsed_price)
 }
 
+pub fn parse_config_mint_phases(
+    client: &Client,
+    config: &ConfigData,
+) -> Result<Vec<MintPhaseSettings>> {
+    config
+        .mint_phases
+        .iter()
+        .map(|mint_phase| {
+            Ok(MintPhaseSettings {
+                start_time: config_time_as_timestamp(&mint_phase.start_time)?,
+                price: parse_config_price(client, config, mint_phase.price)?,
+                allowlist_source: mint_phase.allowlist_source.to_candy_format(),
+                limit_per_address: mint_phase.limit_per_address,
+            })
+        })
+        .collect()
+}
+
+pub fn parse_config_dutch_auction_settings(
+    client: &Client,
+    config: &ConfigData,
+) -> Result<Option<DutchAuctionSettings>> {
+    config
+        .dutch_auction
+        .as_ref()
+        .map(|dutch_auction| {
+            Ok(DutchAuctionSettings {
+                start_price: parse_config_price(client, config, dutch_auction.start_price)?,
+                floor_price: parse_config_price(client, config, dutch_auction.floor_price)?,
+                price_decrement: parse_config_price(client, config, dutch_auction.price_decrement)?,
+                decrement_interval: dutch_auction.decrement_interval,
+                rebate_enabled: dutch_auction.rebate_enabled,
+            })
+        })
+        .transpose()
+}
+
+pub fn parse_config_bonding_curve_settings(
+    client: &Client,
+    config: &ConfigData,
+) -> Result<Option<BondingCurveSettings>> {
+    config
+        .bonding_curve
+        .as_ref()
+        .map(|bonding_curve| {
+            Ok(BondingCurveSettings {
+                mode: bonding_curve.mode.to_candy_format(),
+                start_price: parse_config_price(client, config, bonding_curve.start_price)?,
+                price_increment: parse_config_price(client, config, bonding_curve.price_increment)?,
+                items_per_step: bonding_curve.items_per_step,
+            })
+        })
+        .transpose()
+}
+
+pub fn parse_config_primary_sale_splits(config: &ConfigData) -> Vec<PrimarySaleSplit> {
+    config
+        .primary_sale_splits
+        .iter()
+        .map(|primary_sale_split| primary_sale_split.to_candy_format())
+        .collect()
+}
+
+pub fn parse_config_payment_options(
+    client: &Client,
+    config: &ConfigData,
+) -> Result<Vec<PaymentOption>> {
+    config
+        .payment_options
+        .iter()
+        .map(|payment_option| {
+            Ok(PaymentOption {
+                treasury_mint: payment_option.spl_token,
+                treasury_wallet: payment_option.treasury_wallet,
+                price: parse_price_for_mint(
+                    client,
+                    payment_option.spl_token,
+                    payment_option.price,
+                )?,
+            })
+        })
+        .collect()
+}
+
 pub fn get_candy_machine_state(
     sugar_config: &SugarConfig,
     candy_machine_id: &Pubkey,
@@ -96,6 +192,7 @@ pub fn print_candy_machine_state(state: CandyMachine) {
     println!("Wallet {:?}", state.treasury_wallet);
     println!("Token mint: {:?}", state.treasury_mint);
     println!("Items redeemed: {:?}", state.items_redeemed);
+    println!("Features: {:#b}", state.get_features());
     print_candy_machine_data(&state.data);
 }
 
@@ -113,6 +210,70 @@ pub fn print_candy_machine_data(data: &CandyMachineData) {
     println!("Items available: {:?}", data.items_available);
 
     print_spl_token_allowlist_settings(&data.spl_token_allowlist_settings);
+    print_spl_token_allowlist_tokens(&data.spl_token_allowlist_tokens);
+    print_mint_phases(&data.mint_phases);
+    print_primary_sale_splits(&data.primary_sale_splits);
+    print_dutch_auction_settings(&data.dutch_auction_settings);
+    print_bonding_curve_settings(&data.bonding_curve_settings);
+}
+
+fn print_bonding_curve_settings(bonding_curve_settings: &Option<BondingCurveSettings>) {
+    match bonding_curve_settings {
+        Some(settings) => println!(
+            "Bonding curve: {:?}, start price {:?}, increment {:?}, items per step {:?}",
+            settings.mode, settings.start_price, settings.price_increment, settings.items_per_step
+        ),
+        None => println!("No bonding curve"),
+    }
+}
+
+fn print_dutch_auction_settings(dutch_auction_settings: &Option<DutchAuctionSettings>) {
+    match dutch_auction_settings {
+        Some(settings) => println!(
+            "Dutch auction: start price {:?}, floor price {:?}, decrement {:?} every {:?} seconds, rebate enabled {:?}",
+            settings.start_price,
+            settings.floor_price,
+            settings.price_decrement,
+            settings.decrement_interval,
+            settings.rebate_enabled
+        ),
+        None => println!("No Dutch auction"),
+    }
+}
+
+fn print_primary_sale_splits(primary_sale_splits: &[PrimarySaleSplit]) {
+    if primary_sale_splits.is_empty() {
+        println!("No primary sale splits");
+    }
+
+    for primary_sale_split in primary_sale_splits.iter() {
+        println!(
+            "Primary sale split: {:?} ({:?} basis points)",
+            primary_sale_split.address, primary_sale_split.basis_points
+        );
+    }
+}
+
+pub fn get_allowlist_source_label(allowlist_source: &MintPhaseAllowlistSource) -> &'static str {
+    match allowlist_source {
+        MintPhaseAllowlistSource::Public => "public",
+        MintPhaseAllowlistSource::MerkleAllowlist => "merkle allowlist",
+        MintPhaseAllowlistSource::SplTokenAllowlist => "SPL token allowlist",
+        MintPhaseAllowlistSource::CollectionAllowlist => "collection allowlist",
+    }
+}
+
+fn print_mint_phases(mint_phases: &[MintPhaseSettings]) {
+    for (index, mint_phase) in mint_phases.iter().enumerate() {
+        println!(
+            "Mint phase {}: start time {:?}, price {:?}, {}, limit per address {:?}",
+            index + 1,
+            mint_phase.start_time,
+            mint_phase.price,
+            get_allowlist_source_label(&mint_phase.allowlist_source),
+            mint_phase.limit_per_address
+        );
+    }
 }
 
 fn print_spl_token_allowlist_settings(settings: &Option<SplTokenAllowlistSettings>) {
@@ -126,3 +287,12 @@ fn print_spl_token_allowlist_settings(settings: &Option<SplTokenAllowlistSetting
         println!("No SPL token allowlist mint settings");
     }
 }
+
+fn print_spl_token_allowlist_tokens(tokens: &[SplTokenAllowlistToken]) {
+    for token in tokens {
+        println!(
+            "SPL token allowlist token: mint {:?}, burn amount {}, min balance {}",
+            token.mint, token.burn_amount, token.min_balance
+        );
+    }
+}
@@ -210,6 +210,15 @@ pub enum Commands {
         /// Address of candy machine to mint from.
         #[clap(long)]
         candy_machine: Option<String>,
+
+        /// Index of the payment option to pay with, defaults to the candy machine price
+        #[clap(long)]
+        payment_option: Option<u8>,
+
+        /// Mint of the NFT from the allowlist collection held by the receiver,
+        /// needed to mint during a collection allowlist phase
+        #[clap(long)]
+        gating_mint: Option<String>,
     },
 
     /// Reveal the NFTs from a hidden settings candy machine
@@ -1,10 +1,11 @@
 use std::str::FromStr;
 
-use anchor_client::solana_sdk::pubkey::Pubkey;
+use anchor_client::solana_sdk::{pubkey::Pubkey, system_program, sysvar};
+use anchor_lang::prelude::AccountMeta;
 use anyhow::Result;
 use console::style;
 use formfn_candy_machine::{accounts as nft_accounts, instruction as nft_instruction};
-use mpl_token_metadata::{pda::find_collection_authority_account, state::Metadata};
+use mpl_token_metadata::state::Metadata;
 
 use crate::{
     cache::load_cache,
@@ -142,8 +143,12 @@ pub fn remove_collection(
 ) -> Result<Signature> {
     let payer = program.payer();
 
-    let collection_authority_record =
-        find_collection_authority_account(collection_mint_pubkey, collection_pda_pubkey).0;
+    let collection_authority_record = find_collection_authority_record_pda(
+        collection_mint_pubkey,
+        collection_pda_pubkey,
+        &payer,
+        candy_machine_state.is_programmable(),
+    );
 
     let (collection_metadata_pubkey, collection_metadata) = collection_metadata_info;
 
@@ -160,7 +165,7 @@ pub fn remove_collection(
         ));
     }
 
-    let builder = program
+    let mut builder = program
         .request()
         .accounts(nft_accounts::RemoveCollection {
             candy_machine: *candy_pubkey,
@@ -173,6 +178,13 @@ pub fn remove_collection(
         })
         .args(nft_instruction::RemoveCollection);
 
+    if candy_machine_state.is_programmable() {
+        builder = builder.accounts(vec![
+            AccountMeta::new_readonly(system_program::ID, false),
+            AccountMeta::new_readonly(sysvar::instructions::ID, false),
+        ]);
+    }
+
     let sig = builder.send()?;
 
     Ok(sig)
@@ -1,12 +1,12 @@
 use std::str::FromStr;
 
 use anchor_client::solana_sdk::{pubkey::Pubkey, system_program, sysvar};
+use anchor_lang::prelude::AccountMeta;
 use anyhow::Result;
 use console::style;
 use formfn_candy_machine::{accounts as nft_accounts, instruction as nft_instruction};
 use mpl_token_metadata::{
     error::MetadataError,
-    pda::find_collection_authority_account,
     state::{MasterEditionV2, Metadata},
 };
 
@@ -165,8 +165,12 @@ pub fn set_collection(
     let (collection_metadata_pubkey, collection_metadata) = collection_metadata_info;
     let (collection_edition_pubkey, collection_edition) = collection_edition_info;
 
-    let collection_authority_record =
-        find_collection_authority_account(collection_mint_pubkey, &collection_pda_pubkey).0;
+    let collection_authority_record = find_collection_authority_record_pda(
+        collection_mint_pubkey,
+        &collection_pda_pubkey,
+        &payer,
+        candy_machine_state.is_programmable(),
+    );
 
     if collection_metadata.update_authority != payer {
         return Err(anyhow!(CustomCandyError::AuthorityMismatch(
@@ -185,7 +189,7 @@ pub fn set_collection(
         ));
     }
 
-    let builder = program
+    let mut builder = program
         .request()
         .accounts(nft_accounts::SetCollection {
             candy_machine: *candy_pubkey,
@@ -203,6 +207,13 @@ pub fn set_collection(
         })
         .args(nft_instruction::SetCollection);
 
+    if candy_machine_state.is_programmable() {
+        builder = builder.accounts(vec![AccountMeta::new_readonly(
+            sysvar::instructions::ID,
+            false,
+        )]);
+    }
+
     let sig = builder.send()?;
 
     Ok(sig)
@@ -9,9 +9,16 @@ use anchor_client::solana_sdk::{
 pub use anyhow::{anyhow, Result};
 use chrono::prelude::*;
 use formfn_candy_machine::{
-    Creator as CandyCreator, HiddenSettings as CandyHiddenSettings,
-    SplTokenAllowlistMode as CandySplTokenAllowlistMode,
+    BondingCurveMode as CandyBondingCurveMode,
+    CollectionAllowlistSettings as CandyCollectionAllowlistSettings, ConfigLine,
+    ConfigLineSettings as CandyConfigLineSettings, Creator as CandyCreator,
+    HiddenSettings as CandyHiddenSettings, MintOrder as CandyMintOrder,
+    MintPhaseAllowlistSource as CandyMintPhaseAllowlistSource,
+    PrimarySaleSplit as CandyPrimarySaleSplit,
+    ProgrammableNftSettings as CandyProgrammableNftSettings,
+    TrustedRandomnessAccount as CandyTrustedRandomnessAccount, SplTokenAllowlistMode as CandySplTokenAllowlistMode,
     SplTokenAllowlistSettings as CandySplTokenAllowlistSettings,
+    SplTokenAllowlistToken as CandySplTokenAllowlistToken,
 };
 use serde::{Deserialize, Deserializer, Serialize, Serializer};
 
@@ -67,10 +74,47 @@ pub struct ConfigData {
 
     pub public_sale_end_time: Option<String>,
 
+    #[serde(default)]
+    pub mint_phases: Vec<MintPhaseConfig>,
+
+    #[serde(default)]
+    pub primary_sale_splits: Vec<PrimarySaleSplit>,
+
+    #[serde(default)]
+    pub platform_fee_basis_points: u16,
+
+    #[serde(default)]
+    #[serde(deserialize_with = "to_option_pubkey")]
+    #[serde(serialize_with = "to_option_string")]
+    pub platform_fee_wallet: Option<Pubkey>,
+
+    pub dutch_auction: Option<DutchAuctionConfig>,
+
+    pub bonding_curve: Option<BondingCurveConfig>,
+
+    #[serde(default)]
+    pub payment_options: Vec<PaymentOptionConfig>,
+
+    pub programmable_nft: Option<ProgrammableNftConfig>,
+
+    pub compressed_nft: Option<CompressedNftConfig>,
+
+    pub open_edition: Option<OpenEditionConfig>,
+
     pub spl_token_allowlist_settings: Option<SplTokenAllowlistSettings>,
 
+    #[serde(default)]
+    pub spl_token_allowlist_tokens: Vec<SplTokenAllowlistToken>,
+
+    #[serde(default)]
+    pub collection_allowlist_settings: Option<CollectionAllowlistSettings>,
+
     pub hidden_settings: Option<HiddenSettings>,
 
+    pub config_line_settings: Option<ConfigLineSettings>,
+
+    pub trusted_randomness_account: Option<TrustedRandomnessAccount>,
+
     pub upload_method: UploadMethod,
 
     pub is_mutable: bool,
@@ -83,9 +127,22 @@ pub struct ConfigData {
 
     pub limit_per_address: u16,
 
+    #[serde(default)]
+    pub premint_limit_per_address: u16,
+
+    #[serde(default)]
+    pub allowlist_limit_per_address: u16,
+
+    #[serde(default)]
+    pub total_limit_per_address: u16,
+
+    #[serde(default)]
+    pub allowlist_items_cap: u64,
+
     pub bot_protection_enabled: bool,
 
-    pub sequential_mint_order_enabled: bool,
+    #[serde(default)]
+    pub mint_order: MintOrder,
 
     #[serde(serialize_with = "to_option_string")]
     pub nft_storage_auth_token: Option<String>,
@@ -191,6 +248,47 @@ impl SplTokenAllowlistSettings {
     }
 }
 
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct SplTokenAllowlistToken {
+    #[serde(deserialize_with = "to_pubkey")]
+    #[serde(serialize_with = "to_string")]
+    pub mint: Pubkey,
+    #[serde(default)]
+    pub burn_amount: u64,
+    #[serde(default)]
+    pub min_balance: u64,
+}
+
+impl SplTokenAllowlistToken {
+    pub fn to_candy_format(&self) -> CandySplTokenAllowlistToken {
+        CandySplTokenAllowlistToken {
+            mint: self.mint,
+            burn_amount: self.burn_amount,
+            min_balance: self.min_balance,
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct CollectionAllowlistSettings {
+    #[serde(deserialize_with = "to_pubkey")]
+    #[serde(serialize_with = "to_string")]
+    pub collection_mint: Pubkey,
+    #[serde(default)]
+    pub use_once: bool,
+}
+
+impl CollectionAllowlistSettings {
+    pub fn to_candy_format(&self) -> CandyCollectionAllowlistSettings {
+        CandyCollectionAllowlistSettings {
+            collection_mint: self.collection_mint,
+            use_once: self.use_once,
+        }
+    }
+}
+
 #[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
 #[serde(rename_all = "camelCase")]
 pub enum SplTokenAllowlistMode {
@@ -219,6 +317,111 @@ impl FromStr for SplTokenAllowlistMode {
     }
 }
 
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub enum MintOrder {
+    Random,
+    SequentialPremint,
+    Sequential,
+    // Deploy commits to a random seed which is kept in the cache file, and
+    // reveal publishes it and applies the shuffled order. The program mints
+    // sequentially, so the shuffled order only exists once reveal updates the
+    // NFTs.
+    ShuffledAtReveal,
+}
+
+impl Default for MintOrder {
+    fn default() -> Self {
+        MintOrder::Random
+    }
+}
+
+impl MintOrder {
+    pub fn to_candy_format(&self) -> CandyMintOrder {
+        match self {
+            MintOrder::Random => CandyMintOrder::Random,
+            MintOrder::SequentialPremint => CandyMintOrder::SequentialPremint,
+            MintOrder::Sequential => CandyMintOrder::Sequential,
+            MintOrder::ShuffledAtReveal => CandyMintOrder::ShuffledAtReveal,
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct MintPhaseConfig {
+    pub start_time: String,
+    pub price: f64,
+    pub allowlist_source: MintPhaseAllowlistSource,
+    #[serde(default)]
+    pub limit_per_address: u16,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct DutchAuctionConfig {
+    pub start_price: f64,
+    pub floor_price: f64,
+    pub price_decrement: f64,
+    // In seconds.
+    pub decrement_interval: i64,
+    #[serde(default)]
+    pub rebate_enabled: bool,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct BondingCurveConfig {
+    pub mode: BondingCurveMode,
+    pub start_price: f64,
+    pub price_increment: f64,
+    // Only used by the step mode.
+    #[serde(default)]
+    pub items_per_step: u64,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub enum BondingCurveMode {
+    Linear,
+    Step,
+}
+
+impl BondingCurveMode {
+    pub fn to_candy_format(&self) -> CandyBondingCurveMode {
+        match self {
+            BondingCurveMode::Linear => CandyBondingCurveMode::Linear,
+            BondingCurveMode::Step => CandyBondingCurveMode::Step,
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub enum MintPhaseAllowlistSource {
+    Public,
+    MerkleAllowlist,
+    SplTokenAllowlist,
+    CollectionAllowlist,
+}
+
+impl MintPhaseAllowlistSource {
+    pub fn to_candy_format(&self) -> CandyMintPhaseAllowlistSource {
+        match self {
+            MintPhaseAllowlistSource::Public => CandyMintPhaseAllowlistSource::Public,
+            MintPhaseAllowlistSource::MerkleAllowlist => {
+                CandyMintPhaseAllowlistSource::MerkleAllowlist
+            }
+            MintPhaseAllowlistSource::SplTokenAllowlist => {
+                CandyMintPhaseAllowlistSource::SplTokenAllowlist
+            }
+            MintPhaseAllowlistSource::CollectionAllowlist => {
+                CandyMintPhaseAllowlistSource::CollectionAllowlist
+            }
+        }
+    }
+}
+
 #[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
 pub struct HiddenSettings {
     name: String,
@@ -242,6 +445,78 @@ impl HiddenSettings {
     }
 }
 
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ConfigLineSettings {
+    pub prefix_name: String,
+    pub name_length: u32,
+    pub prefix_uri: String,
+    pub uri_length: u32,
+}
+
+impl ConfigLineSettings {
+    pub fn to_candy_format(&self) -> CandyConfigLineSettings {
+        CandyConfigLineSettings {
+            prefix_name: self.prefix_name.clone(),
+            name_length: self.name_length,
+            prefix_uri: self.prefix_uri.clone(),
+            uri_length: self.uri_length,
+        }
+    }
+
+    /// Removes the shared prefixes from a config line, which is how config
+    /// lines are stored on-chain with config line settings.
+    pub fn strip_prefixes(&self, config_line: ConfigLine) -> Result<ConfigLine> {
+        let name = config_line
+            .name
+            .strip_prefix(&self.prefix_name)
+            .filter(|name| name.len() <= self.name_length as usize)
+            .ok_or_else(|| {
+                anyhow!(
+                    "Name '{}' doesn't start with '{}' followed by at most {} characters",
+                    config_line.name,
+                    self.prefix_name,
+                    self.name_length
+                )
+            })?;
+        let uri = config_line
+            .uri
+            .strip_prefix(&self.prefix_uri)
+            .filter(|uri| uri.len() <= self.uri_length as usize)
+            .ok_or_else(|| {
+                anyhow!(
+                    "Uri '{}' doesn't start with '{}' followed by at most {} characters",
+                    config_line.uri,
+                    self.prefix_uri,
+                    self.uri_length
+                )
+            })?;
+
+        Ok(ConfigLine {
+            name: name.to_string(),
+            uri: uri.to_string(),
+        })
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct TrustedRandomnessAccount {
+    #[serde(deserialize_with = "to_pubkey")]
+    #[serde(serialize_with = "to_string")]
+    pub address: Pubkey,
+    pub offset: u32,
+}
+
+impl TrustedRandomnessAccount {
+    pub fn to_candy_format(&self) -> CandyTrustedRandomnessAccount {
+        CandyTrustedRandomnessAccount {
+            address: self.address,
+            offset: self.offset,
+        }
+    }
+}
+
 #[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
 #[serde(rename_all = "snake_case")]
 pub enum UploadMethod {
@@ -285,6 +560,82 @@ impl Creator {
     }
 }
 
+#[derive(Debug, Clone, Deserialize, Default, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct PrimarySaleSplit {
+    #[serde(deserialize_with = "to_pubkey")]
+    #[serde(serialize_with = "to_string")]
+    pub address: Pubkey,
+    pub basis_points: u16,
+}
+
+impl PrimarySaleSplit {
+    pub fn to_candy_format(&self) -> CandyPrimarySaleSplit {
+        CandyPrimarySaleSplit {
+            address: self.address,
+            basis_points: self.basis_points,
+        }
+    }
+}
+
+/// An additional currency the candy machine accepts. Leaving out the SPL
+/// token means the option is paid in SOL.
+#[derive(Debug, Clone, Deserialize, Default, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct PaymentOptionConfig {
+    #[serde(default)]
+    #[serde(deserialize_with = "to_option_pubkey")]
+    #[serde(serialize_with = "to_option_string")]
+    pub spl_token: Option<Pubkey>,
+    #[serde(deserialize_with = "to_pubkey")]
+    #[serde(serialize_with = "to_string")]
+    pub treasury_wallet: Pubkey,
+    pub price: f64,
+}
+
+/// Mint programmable NFTs, optionally enforcing a token authorization rule
+/// set on transfers.
+#[derive(Debug, Clone, Deserialize, Default, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ProgrammableNftConfig {
+    #[serde(default)]
+    #[serde(deserialize_with = "to_option_pubkey")]
+    #[serde(serialize_with = "to_option_string")]
+    pub rule_set: Option<Pubkey>,
+}
+
+impl ProgrammableNftConfig {
+    pub fn to_candy_format(&self) -> CandyProgrammableNftSettings {
+        CandyProgrammableNftSettings {
+            rule_set: self.rule_set,
+        }
+    }
+}
+
+/// Mint compressed NFTs into a Bubblegum Merkle tree created by deploy. The
+/// tree holds up to 2^max_depth NFTs.
+#[derive(Debug, Clone, Deserialize, Default, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct CompressedNftConfig {
+    pub max_depth: u32,
+    pub max_buffer_size: u32,
+}
+
+/// Mint numbered print editions of a single master edition, which deploy
+/// creates from the only cache item. The sale ends at publicSaleEndTime.
+#[derive(Debug, Clone, Deserialize, Default, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct OpenEditionConfig {
+    // None for no cap on the number of editions.
+    pub max_editions: Option<u64>,
+}
+
+impl OpenEditionConfig {
+    pub fn get_items_available(&self) -> u64 {
+        self.max_editions.unwrap_or(u64::MAX)
+    }
+}
+
 #[derive(Debug, Clone, Serialize)]
 pub enum Cluster {
     Devnet,
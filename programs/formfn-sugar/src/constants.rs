@@ -6,6 +6,15 @@ pub use mpl_token_metadata::state::{
 /// Metaplex program id.
 pub const METAPLEX_PROGRAM_ID: &str = "metaqbxxUerdq28cj1RbAWkYQm3ybzjb6a8bt518x1s";
 
+/// Token authorization rules program id, used by programmable NFT rule sets.
+pub const AUTH_RULES_PROGRAM_ID: &str = "auth9SigNpDKz4sJJ1DfCTuZrZNSAgh9sFD3rboVmgg";
+
+/// SPL account compression program id, which owns compressed NFT Merkle trees.
+pub const ACCOUNT_COMPRESSION_PROGRAM_ID: &str = "cmtDvXumGCrqC1Age74AVPhSRVXJMd8PJS91L8KbNCK";
+
+/// SPL noop program id, used by Bubblegum to log compressed NFT changes.
+pub const NOOP_PROGRAM_ID: &str = "noopb9bkMVfRPU8AsbpTUg8AQkHtKwMYZiFUjNRtMmV";
+
 pub const STRING_LEN_SIZE: usize = 4;
 
 pub const CONFIG_NAME_OFFSET: usize = STRING_LEN_SIZE;
@@ -16,7 +16,7 @@ use url::Url;
 use crate::{
     candy_machine::CANDY_MACHINE_ID,
     config::{
-        parse_string_as_date, AwsConfig, ConfigData, Creator, HiddenSettings,
+        parse_string_as_date, AwsConfig, ConfigData, Creator, HiddenSettings, MintOrder,
         SplTokenAllowlistMode, SplTokenAllowlistSettings, UploadMethod,
     },
     constants::*,
@@ -183,9 +183,24 @@ pub fn process_create_config(args: CreateConfigArgs) -> Result<()> {
         .with_prompt("Do you want to enable bot protection for minting?")
         .interact()?;
 
-    config_data.sequential_mint_order_enabled = Confirm::with_theme(&theme)
-        .with_prompt("Do you want to enable sequential minting for the creator during the pre-mint phase? The default is a random mint order.")
-        .interact()?;
+    let mint_order_options = vec![
+        "Random",
+        "Sequential during the pre-mint phase",
+        "Sequential",
+        "Sequential, shuffled at reveal (hidden settings only)",
+    ];
+    config_data.mint_order = match Select::with_theme(&theme)
+        .with_prompt("In which order should the NFTs be minted?")
+        .items(&mint_order_options)
+        .default(0)
+        .interact()
+        .unwrap()
+    {
+        1 => MintOrder::SequentialPremint,
+        2 => MintOrder::Sequential,
+        3 => MintOrder::ShuffledAtReveal,
+        _ => MintOrder::Random,
+    };
 
     config_data.limit_per_address = if !Confirm::with_theme(&theme)
         .with_prompt("Do you want to add a buy limit per address? The default is unlimited.")
@@ -202,6 +217,28 @@ pub fn process_create_config(args: CreateConfigArgs) -> Result<()> {
             .expect("Failed to parse number into u16 that should have already been validated.")
     };
 
+    if Confirm::with_theme(&theme)
+        .with_prompt(
+            "Do you want to add buy limits per address for the premint and allowlist phases? The default is unlimited.",
+        )
+        .interact()?
+    {
+        config_data.premint_limit_per_address = Input::with_theme(&theme)
+            .with_prompt("What is the buy limit per omni mint wallet during the premint phase? Enter 0 for unlimited.")
+            .validate_with(number_validator)
+            .interact()
+            .unwrap()
+            .parse::<u16>()
+            .expect("Failed to parse number into u16 that should have already been validated.");
+        config_data.allowlist_limit_per_address = Input::with_theme(&theme)
+            .with_prompt("What is the buy limit per wallet during the allowlist phase? Enter 0 for unlimited.")
+            .validate_with(number_validator)
+            .interact()
+            .unwrap()
+            .parse::<u16>()
+            .expect("Failed to parse number into u16 that should have already been validated.");
+    }
+
     // number
 
     config_data.number = if num_files > 0 && (num_files % 2) == 0 && Confirm::with_theme(&theme)
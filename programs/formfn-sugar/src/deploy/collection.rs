@@ -1,8 +1,11 @@
-use anchor_client::{solana_sdk::pubkey::Pubkey, Client};
+use anchor_client::{
+    solana_sdk::{pubkey::Pubkey, sysvar::instructions},
+    Client,
+};
+use anchor_lang::prelude::AccountMeta;
 use anyhow::Result;
 use mpl_token_metadata::{
     instruction::{create_master_edition_v3, create_metadata_accounts_v3},
-    pda::find_collection_authority_account,
     state::Creator,
 };
 use spl_associated_token_account::get_associated_token_address;
@@ -16,7 +19,10 @@ use crate::{
     candy_machine::CANDY_MACHINE_ID,
     common::*,
     config::ConfigData,
-    pdas::{find_collection_pda, find_master_edition_pda, find_metadata_pda},
+    pdas::{
+        find_collection_authority_record_pda, find_collection_pda, find_master_edition_pda,
+        find_metadata_pda,
+    },
 };
 
 pub fn create_and_set_collection(
@@ -119,10 +125,15 @@ pub fn create_and_set_collection(
     );
 
     let collection_pda_pubkey = find_collection_pda(&candy_pubkey).0;
-    let collection_authority_record =
-        find_collection_authority_account(&collection_mint.pubkey(), &collection_pda_pubkey).0;
+    let is_programmable = config_data.programmable_nft.is_some();
+    let collection_authority_record = find_collection_authority_record_pda(
+        &collection_mint.pubkey(),
+        &collection_pda_pubkey,
+        &payer,
+        is_programmable,
+    );
 
-    let builder = program
+    let mut builder = program
         .request()
         .instruction(create_mint_account_ix)
         .instruction(init_mint_ix)
@@ -147,6 +158,10 @@ pub fn create_and_set_collection(
         })
         .args(nft_instruction::SetCollection);
 
+    if is_programmable {
+        builder = builder.accounts(vec![AccountMeta::new_readonly(instructions::ID, false)]);
+    }
+
     let sig = builder.send()?;
     collection_item.on_chain = true;
     cache.program.collection_mint = collection_mint.pubkey().to_string();
@@ -36,6 +36,7 @@ pub struct TxInfo {
 pub fn generate_config_lines(
     num_items: u64,
     cache_items: &CacheItems,
+    config_line_settings: Option<&ConfigLineSettings>,
 ) -> Result<Vec<Vec<(u32, ConfigLine)>>> {
     let mut config_lines: Vec<Vec<(u32, ConfigLine)>> = Vec::new();
     let mut current: Vec<(u32, ConfigLine)> = Vec::new();
@@ -61,9 +62,14 @@ pub fn generate_config_lines(
                 tx_size = 0;
             }
         } else {
-            let config_line = item
+            let mut config_line = item
                 .to_config_line()
                 .expect("Could not convert item to config line");
+            if let Some(config_line_settings) = config_line_settings {
+                config_line = config_line_settings
+                    .strip_prefixes(config_line)
+                    .map_err(|err| DeployError::AddConfigLineFailed(err.to_string()))?;
+            }
 
             let size = (2 * STRING_LEN_SIZE) + config_line.name.len() + config_line.uri.len();
 
@@ -8,14 +8,23 @@ use anyhow::Result;
 use chrono::{Duration, Utc};
 use formfn_candy_machine::{
     accounts as nft_accounts, get_space_for_candy, instruction as nft_instruction,
-    CandyMachineData, Creator as CandyCreator,
+    CandyMachineData, Creator as CandyCreator, MintCaps, PhaseLimits,
 };
 pub use mpl_token_metadata::state::{
     MAX_CREATOR_LIMIT, MAX_NAME_LENGTH, MAX_SYMBOL_LENGTH, MAX_URI_LENGTH,
 };
 use solana_program::native_token::LAMPORTS_PER_SOL;
 
-use crate::{candy_machine::parse_config_price, common::*, config::data::*, deploy::errors::*};
+use crate::{
+    candy_machine::{
+        parse_config_bonding_curve_settings, parse_config_dutch_auction_settings,
+        parse_config_mint_phases, parse_config_payment_options, parse_config_price,
+        parse_config_primary_sale_splits,
+    },
+    common::*,
+    config::data::*,
+    deploy::errors::*,
+};
 
 /// Create the candy machine data struct.
 pub fn create_candy_machine_data(
@@ -75,6 +84,16 @@ pub fn create_candy_machine_data(
         None => None,
     };
 
+    let mint_phases = parse_config_mint_phases(client, config)?;
+    let primary_sale_splits = parse_config_primary_sale_splits(config);
+    let dutch_auction_settings = parse_config_dutch_auction_settings(client, config)?;
+    let bonding_curve_settings = parse_config_bonding_curve_settings(client, config)?;
+    let payment_options = parse_config_payment_options(client, config)?;
+    let programmable_nft_settings = config
+        .programmable_nft
+        .as_ref()
+        .map(|s| s.to_candy_format());
+
     let omni_mint_wallets: Vec<Pubkey> = creators.iter().map(|creator| creator.address).collect();
 
     let data = CandyMachineData {
@@ -92,12 +111,51 @@ pub fn create_candy_machine_data(
         omni_mint_wallets,
         spl_token_allowlist_settings,
         hidden_settings,
-        items_available: config.number,
+        items_available: match &config.open_edition {
+            Some(open_edition_config) => open_edition_config.get_items_available(),
+            None => config.number,
+        },
         limit_per_address: config.limit_per_address,
         bot_protection_enabled: config.bot_protection_enabled,
-        sequential_mint_order_enabled: config.sequential_mint_order_enabled,
+        mint_order: config.mint_order.to_candy_format(),
         merkle_allowlist_root_list: Vec::new(),
         allowlist_sale_start_time,
+        mint_phases,
+        primary_sale_splits,
+        dutch_auction_settings,
+        bonding_curve_settings,
+        payment_options,
+        programmable_nft_settings,
+        // Set by deploy once the Merkle tree is created.
+        compressed_nft_settings: None,
+        open_edition_enabled: config.open_edition.is_some(),
+        config_line_settings: config
+            .config_line_settings
+            .as_ref()
+            .map(|settings| settings.to_candy_format()),
+        trusted_randomness_account: config
+            .trusted_randomness_account
+            .as_ref()
+            .map(|trusted_randomness_account| trusted_randomness_account.to_candy_format()),
+        // Set by deploy, which generates the seed.
+        shuffle_settings: None,
+        phase_limits: PhaseLimits {
+            premint_limit_per_address: config.premint_limit_per_address,
+            allowlist_limit_per_address: config.allowlist_limit_per_address,
+        },
+        mint_caps: MintCaps {
+            total_limit_per_address: config.total_limit_per_address,
+            allowlist_items_cap: config.allowlist_items_cap,
+        },
+        collection_allowlist_settings: config
+            .collection_allowlist_settings
+            .as_ref()
+            .map(|settings| settings.to_candy_format()),
+        spl_token_allowlist_tokens: config
+            .spl_token_allowlist_tokens
+            .iter()
+            .map(|token| token.to_candy_format())
+            .collect(),
     };
 
     Ok(data)
@@ -174,3 +232,29 @@ pub fn initialize_candy_machine(
         }
     }
 }
+
+/// Send the `update_platform_fee` instruction to the candy machine program.
+pub fn update_platform_fee(
+    config_data: &ConfigData,
+    candy_pubkey: &Pubkey,
+    program: &Program,
+) -> Result<Signature> {
+    let tx = program
+        .request()
+        .accounts(nft_accounts::UpdatePlatformFee {
+            candy_machine: *candy_pubkey,
+            formfn_authority: program.payer(),
+        })
+        .args(nft_instruction::UpdatePlatformFee {
+            platform_fee_basis_points: config_data.platform_fee_basis_points,
+            platform_fee_wallet: config_data.platform_fee_wallet,
+        });
+
+    match tx.send() {
+        Ok(sig) => Ok(sig),
+        Err(e) => {
+            println!("\nAn error occurred updating the platform fee: {:?}", e);
+            Err(anyhow!(e))
+        }
+    }
+}
@@ -2,10 +2,14 @@ pub mod collection;
 pub mod config_lines;
 pub mod errors;
 pub mod initialize;
+pub mod open_edition;
 pub mod process;
+pub mod tree;
 
 pub use collection::*;
 pub use config_lines::*;
 pub use errors::*;
 pub use initialize::*;
+pub use open_edition::*;
 pub use process::*;
+pub use tree::*;
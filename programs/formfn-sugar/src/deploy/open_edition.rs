@@ -0,0 +1,162 @@
+use anchor_client::{solana_sdk::pubkey::Pubkey, Client};
+use anyhow::Result;
+use mpl_token_metadata::{
+    instruction::{create_master_edition_v3, create_metadata_accounts_v3},
+    state::Creator,
+};
+use spl_associated_token_account::get_associated_token_address;
+use spl_associated_token_account::instruction::create_associated_token_account;
+use spl_token::{
+    instruction::{initialize_mint, mint_to},
+    ID as TOKEN_PROGRAM_ID,
+};
+
+use crate::{
+    candy_machine::CANDY_MACHINE_ID,
+    common::*,
+    config::ConfigData,
+    pdas::{find_master_edition_pda, find_metadata_pda, find_open_edition_pda},
+};
+
+/// Create the master edition NFT from the only cache item and hand it to the
+/// candy machine, which mints print editions of it.
+pub fn create_and_set_open_edition(
+    client: &Client,
+    candy_pubkey: Pubkey,
+    cache: &mut Cache,
+    config_data: &ConfigData,
+) -> Result<(Signature, Pubkey)> {
+    let program = client.program(CANDY_MACHINE_ID);
+    let payer = program.payer();
+
+    let master_mint = Keypair::new();
+    let master_item: &mut CacheItem = match cache.items.get_mut("0") {
+        Some(item) => item,
+        None => {
+            return Err(anyhow!("Trying to create and set open edition when master edition item info isn't in cache! This shouldn't happen!"));
+        }
+    };
+
+    // Allocate memory for the account
+    let min_rent = program
+        .rpc()
+        .get_minimum_balance_for_rent_exemption(MINT_LAYOUT as usize)?;
+
+    // Create mint account
+    let create_mint_account_ix = system_instruction::create_account(
+        &payer,
+        &master_mint.pubkey(),
+        min_rent,
+        MINT_LAYOUT,
+        &TOKEN_PROGRAM_ID,
+    );
+
+    // Initialize mint ix
+    let init_mint_ix = initialize_mint(
+        &TOKEN_PROGRAM_ID,
+        &master_mint.pubkey(),
+        &payer,
+        Some(&payer),
+        0,
+    )?;
+
+    let ata_pubkey = get_associated_token_address(&payer, &master_mint.pubkey());
+
+    // Create associated account instruction
+    let create_assoc_account_ix =
+        create_associated_token_account(&payer, &payer, &master_mint.pubkey(), &TOKEN_PROGRAM_ID);
+
+    // Mint to instruction
+    let mint_to_ix = mint_to(
+        &TOKEN_PROGRAM_ID,
+        &master_mint.pubkey(),
+        &ata_pubkey,
+        &payer,
+        &[],
+        1,
+    )?;
+
+    // Print editions copy the master edition creators, so they are the
+    // configured creators rather than the payer.
+    let creators = config_data
+        .creators
+        .iter()
+        .map(|creator| Creator {
+            address: creator.address,
+            verified: creator.address == payer,
+            share: creator.share,
+        })
+        .collect::<Vec<Creator>>();
+    let master_metadata_pubkey = find_metadata_pda(&master_mint.pubkey());
+
+    let create_metadata_account_ix = create_metadata_accounts_v3(
+        mpl_token_metadata::ID,
+        master_metadata_pubkey,
+        master_mint.pubkey(),
+        payer,
+        payer,
+        payer,
+        master_item.name.clone(),
+        config_data.symbol.clone(),
+        master_item.metadata_link.clone(),
+        Some(creators),
+        config_data.seller_fee_basis_points,
+        true,
+        config_data.is_mutable,
+        None,
+        None,
+        None,
+    );
+
+    let master_edition_pubkey = find_master_edition_pda(&master_mint.pubkey());
+
+    // No max supply, the candy machine items available caps the editions.
+    let create_master_edition_ix = create_master_edition_v3(
+        mpl_token_metadata::ID,
+        master_edition_pubkey,
+        master_mint.pubkey(),
+        payer,
+        payer,
+        master_metadata_pubkey,
+        payer,
+        None,
+    );
+
+    let open_edition_pda_pubkey = find_open_edition_pda(&candy_pubkey).0;
+
+    let sig = program
+        .request()
+        .instruction(create_mint_account_ix)
+        .instruction(init_mint_ix)
+        .instruction(create_assoc_account_ix)
+        .instruction(mint_to_ix)
+        .signer(&master_mint)
+        .instruction(create_metadata_account_ix)
+        .instruction(create_master_edition_ix)
+        .accounts(nft_accounts::SetOpenEdition {
+            candy_machine: candy_pubkey,
+            formfn_authority: payer,
+            creator_authority: config_data.creator_authority,
+            open_edition_pda: open_edition_pda_pubkey,
+            open_edition_token_account: get_associated_token_address(
+                &open_edition_pda_pubkey,
+                &master_mint.pubkey(),
+            ),
+            formfn_authority_token_account: ata_pubkey,
+            metadata: master_metadata_pubkey,
+            mint: master_mint.pubkey(),
+            edition: master_edition_pubkey,
+            token_metadata_program: mpl_token_metadata::ID,
+            token_program: TOKEN_PROGRAM_ID,
+            associated_token_program: spl_associated_token_account::ID,
+            system_program: system_program::id(),
+            rent: sysvar::rent::ID,
+        })
+        .args(nft_instruction::SetOpenEdition)
+        .send()?;
+
+    master_item.on_chain = true;
+    cache.sync_file()?;
+
+    Ok((sig, master_mint.pubkey()))
+}
@@ -14,6 +14,8 @@ use anchor_client::solana_sdk::{
 };
 use anyhow::Result;
 use console::style;
+use formfn_candy_machine::{CompressedNftSettings, ShuffleSettings};
+use solana_program::keccak;
 use spl_associated_token_account::get_associated_token_address;
 use std::fs;
 use std::fs::OpenOptions;
@@ -23,10 +25,11 @@ use crate::{
     cache::*,
     candy_machine::{get_candy_machine_state, CANDY_MACHINE_ID},
     common::*,
-    config::parser::get_config_data,
+    config::{parser::get_config_data, MintOrder},
     deploy::{
-        create_and_set_collection, create_candy_machine_data, errors::*, generate_config_lines,
-        initialize_candy_machine, upload_config_lines,
+        create_and_set_collection, create_and_set_open_edition, create_candy_machine_data,
+        create_merkle_tree, errors::*, generate_config_lines, initialize_candy_machine,
+        update_platform_fee, upload_config_lines,
     },
     hash::hash_and_update,
     merkle_allowlist::parse_merkle_allowlist_config,
@@ -90,6 +93,7 @@ pub async fn process_deploy(args: DeployArgs) -> Result<()> {
 
     let num_items = config_data.number;
     let hidden = config_data.hidden_settings.is_some();
+    let open_edition = config_data.open_edition.is_some();
     let collection_in_cache = cache.items.get("-1").is_some();
     let mut item_redeemed = false;
 
@@ -102,6 +106,11 @@ pub async fn process_deploy(args: DeployArgs) -> Result<()> {
             num_items,
             cache_items_sans_collection
         ));
+    } else if open_edition && num_items != 1 {
+        return Err(anyhow!(
+            "Open edition candy machines print editions of a single item, but {} items were found.",
+            num_items
+        ));
     } else {
         check_symbol(&config_data.symbol)?;
         check_seller_fee_basis_points(config_data.seller_fee_basis_points)?;
@@ -130,9 +139,30 @@ pub async fn process_deploy(args: DeployArgs) -> Result<()> {
 
         let candy_pubkey = candy_keypair.pubkey();
         let uuid = DEFAULT_UUID.to_string();
-        let candy_data = create_candy_machine_data(&client, &config_data, uuid)?;
+        let mut candy_data = create_candy_machine_data(&client, &config_data, uuid)?;
         let program = client.program(CANDY_MACHINE_ID);
 
+        if let Some(compressed_nft_config) = &config_data.compressed_nft {
+            let (sig, merkle_tree) =
+                create_merkle_tree(compressed_nft_config, &candy_pubkey, &program)?;
+            info!("Merkle tree {} created with sig: {}", merkle_tree, sig);
+            candy_data.compressed_nft_settings = Some(CompressedNftSettings { merkle_tree });
+        }
+
+        // The seed of a shuffled mint order is only committed to on-chain and
+        // stays in the cache file until reveal. The cache file holds the only
+        // copy of the seed.
+        let shuffle_seed: Option<[u8; 32]> = match config_data.mint_order {
+            MintOrder::ShuffledAtReveal => Some(rand::random()),
+            _ => None,
+        };
+        if let Some(seed) = &shuffle_seed {
+            candy_data.shuffle_settings = Some(ShuffleSettings {
+                seed_commitment: keccak::hash(seed).0,
+                seed: None,
+            });
+        }
+
         let treasury_wallet = match config_data.spl_token {
             Some(spl_token) => {
                 let spl_token_account_figured = if config_data.spl_token_account.is_some() {
@@ -174,16 +204,38 @@ pub async fn process_deploy(args: DeployArgs) -> Result<()> {
             program,
         )?;
         info!("Candy machine initialized with sig: {}", sig);
+
+        if config_data.platform_fee_basis_points > 0 {
+            let program = client.program(CANDY_MACHINE_ID);
+            let sig = update_platform_fee(&config_data, &candy_pubkey, &program)?;
+            info!("Platform fee set with sig: {}", sig);
+        }
         info!(
             "Candy machine created with address: {}",
             &candy_pubkey.to_string()
         );
 
         cache.program = CacheProgram::new_from_cm(&candy_pubkey);
+        if let Some(seed) = shuffle_seed {
+            cache.program.shuffle_seed = bs58::encode(seed).into_string();
+        }
         cache.sync_file()?;
 
         spinner.finish_and_clear();
 
+        if shuffle_seed.is_some() {
+            println!(
+                "{}{}",
+                WARNING_EMOJI,
+                style(format!(
+                    "The shuffle seed is only stored in the cache file {}. Back it up, \
+                    the NFTs can't be revealed without it.",
+                    args.cache
+                ))
+                .yellow()
+            );
+        }
+
         candy_pubkey
     } else {
         println!(
@@ -229,6 +281,12 @@ pub async fn process_deploy(args: DeployArgs) -> Result<()> {
 
         if item_redeemed {
             println!("\nAn item has already been minted and thus cannot modify the candy machine collection. Skipping...");
+        } else if config_data.compressed_nft.is_some() {
+            println!("\nCompressed NFTs can't be part of a collection. Skipping...");
+        } else if open_edition {
+            println!(
+                "\nPrint editions inherit the collection of their master edition. Skipping..."
+            );
         } else if collection_item.on_chain {
             println!("\nCollection mint already deployed.");
         } else {
@@ -248,7 +306,34 @@ pub async fn process_deploy(args: DeployArgs) -> Result<()> {
     }
 
     // Hidden Settings check needs to be the last action in this command, so we can update the hash with the final cache state.
-    if !hidden {
+    if open_edition {
+        let step_num = 2 + (collection_in_cache as u8);
+        println!(
+            "\n{} {}Creating and setting the open edition master edition",
+            style(format!("[{}/{}]", step_num, total_steps))
+                .bold()
+                .dim(),
+            PAPER_EMOJI
+        );
+
+        let master_item_on_chain = cache.items.get("0").map_or(false, |item| item.on_chain);
+        if item_redeemed || master_item_on_chain {
+            println!("\nOpen edition master edition already deployed.");
+        } else {
+            let pb = spinner_with_style();
+            pb.set_message("Sending create and set open edition transaction...");
+
+            let (_, master_mint) =
+                create_and_set_open_edition(&client, candy_pubkey, &mut cache, &config_data)?;
+
+            pb.finish_and_clear();
+            println!(
+                "{} {}",
+                style("Master edition mint ID:").bold(),
+                master_mint
+            );
+        }
+    } else if !hidden {
         let step_num = 2 + (collection_in_cache as u8);
         println!(
             "\n{} {}Writing config lines",
@@ -258,7 +343,11 @@ pub async fn process_deploy(args: DeployArgs) -> Result<()> {
             PAPER_EMOJI
         );
 
-        let config_lines = generate_config_lines(num_items, &cache.items)?;
+        let config_lines = generate_config_lines(
+            num_items,
+            &cache.items,
+            config_data.config_line_settings.as_ref(),
+        )?;
 
         let total_config_lines = config_lines
             .iter()
@@ -280,9 +369,13 @@ pub async fn process_deploy(args: DeployArgs) -> Result<()> {
                     .open("create/metadata_uris.txt");
                 match file {
                     Ok(mut file) => {
+                        let prefix_uri = config_data
+                            .config_line_settings
+                            .as_ref()
+                            .map_or("", |settings| settings.prefix_uri.as_str());
                         config_lines.iter().for_each(|chunk| {
                             chunk.iter().for_each(|line| {
-                                if let Err(e) = writeln!(file, "{}", line.1.uri) {
+                                if let Err(e) = writeln!(file, "{}{}", prefix_uri, line.1.uri) {
                                     eprintln!("Couldn't write to file: {}", e);
                                 }
                             })
@@ -0,0 +1,97 @@
+use anchor_client::solana_sdk::instruction::Instruction;
+use anchor_lang::{InstructionData, ToAccountMetas};
+
+use crate::{common::*, config::data::CompressedNftConfig, pdas::find_candy_machine_creator_pda};
+
+// Account header of a concurrent Merkle tree, followed by its sequence
+// number, active index and buffer size.
+const MERKLE_TREE_HEADER_SIZE: usize = 56 + 24;
+
+/// Size of a concurrent Merkle tree account. The tree keeps one change log per
+/// buffer slot, plus the rightmost path, which are each a root or leaf node,
+/// one node per level, an index and padding.
+pub fn get_merkle_tree_account_size(max_depth: u32, max_buffer_size: u32) -> usize {
+    let path_size = 32 + 32 * max_depth as usize + 8;
+
+    MERKLE_TREE_HEADER_SIZE + (max_buffer_size as usize + 1) * path_size
+}
+
+/// Create the Bubblegum Merkle tree compressed NFTs are minted into, and make
+/// the candy machine creator PDA its tree delegate so the candy machine can
+/// mint into it.
+pub fn create_merkle_tree(
+    compressed_nft_config: &CompressedNftConfig,
+    candy_pubkey: &Pubkey,
+    program: &Program,
+) -> Result<(Signature, Pubkey)> {
+    let payer = program.payer();
+    let merkle_tree = Keypair::new();
+    let (tree_authority, _) =
+        Pubkey::find_program_address(&[merkle_tree.pubkey().as_ref()], &mpl_bubblegum::id());
+    let (candy_machine_creator, _) = find_candy_machine_creator_pda(candy_pubkey);
+    let compression_program = Pubkey::from_str(ACCOUNT_COMPRESSION_PROGRAM_ID)?;
+    let log_wrapper = Pubkey::from_str(NOOP_PROGRAM_ID)?;
+
+    let tree_size = get_merkle_tree_account_size(
+        compressed_nft_config.max_depth,
+        compressed_nft_config.max_buffer_size,
+    );
+    let lamports = program
+        .rpc()
+        .get_minimum_balance_for_rent_exemption(tree_size)?;
+
+    info!(
+        "Creating Merkle tree with account size of: {} and address of: {}",
+        tree_size,
+        merkle_tree.pubkey()
+    );
+
+    let create_tree_ix = Instruction {
+        program_id: mpl_bubblegum::id(),
+        accounts: mpl_bubblegum::accounts::CreateTree {
+            tree_authority,
+            merkle_tree: merkle_tree.pubkey(),
+            payer,
+            tree_creator: payer,
+            log_wrapper,
+            compression_program,
+            system_program: system_program::id(),
+        }
+        .to_account_metas(None),
+        data: mpl_bubblegum::instruction::CreateTree {
+            max_depth: compressed_nft_config.max_depth,
+            max_buffer_size: compressed_nft_config.max_buffer_size,
+            public: Some(false),
+        }
+        .data(),
+    };
+
+    let set_tree_delegate_ix = Instruction {
+        program_id: mpl_bubblegum::id(),
+        accounts: mpl_bubblegum::accounts::SetTreeDelegate {
+            tree_authority,
+            tree_creator: payer,
+            new_tree_delegate: candy_machine_creator,
+            merkle_tree: merkle_tree.pubkey(),
+            system_program: system_program::id(),
+        }
+        .to_account_metas(None),
+        data: mpl_bubblegum::instruction::SetTreeDelegate {}.data(),
+    };
+
+    let sig = program
+        .request()
+        .instruction(system_instruction::create_account(
+            &payer,
+            &merkle_tree.pubkey(),
+            lamports,
+            tree_size as u64,
+            &compression_program,
+        ))
+        .instruction(create_tree_ix)
+        .instruction(set_tree_delegate_ix)
+        .signer(&merkle_tree)
+        .send()?;
+
+    Ok((sig, merkle_tree.pubkey()))
+}
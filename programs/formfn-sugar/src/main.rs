@@ -287,6 +287,8 @@ async fn run() -> Result<()> {
             number,
             receiver,
             candy_machine,
+            payment_option,
+            gating_mint,
         } => {
             process_mint(MintArgs {
                 keypair,
@@ -295,6 +297,8 @@ async fn run() -> Result<()> {
                 number,
                 receiver,
                 candy_machine,
+                payment_option,
+                gating_mint,
             })
             .await?
         }
@@ -3,12 +3,14 @@ use std::{str::FromStr, sync::Arc};
 use anchor_lang::{prelude::Pubkey, InstructionData, ToAccountMetas};
 use anyhow::{anyhow, Result};
 use console::style;
+use formfn_candy_machine::MerkleAllowlistRoot;
 use solana_program::instruction::Instruction;
 
 use crate::{
     cache::load_cache,
-    candy_machine::get_candy_machine_state,
+    candy_machine::{get_candy_machine_state, parse_config_price},
     common::{setup_client, sugar_setup},
+    config::{config_time_opt_as_timestamp, get_config_data},
     merkle_allowlist::{chunk_root_list_for_update_txs, parse_merkle_allowlist_config},
 };
 
@@ -50,12 +52,26 @@ pub async fn process_merkle_allowlist(args: ProcessMerkleAllowlistArgs) -> Resul
 
     let candy_machine_state = Arc::new(get_candy_machine_state(&sugar_config, &candy_pubkey)?);
 
-    let roots_to_add: Vec<[u8; 32]> = allowlist_config
+    let config_data = get_config_data(&args.config)?;
+    let roots_to_add = allowlist_config
         .merkle_allowlist_data
         .iter()
-        .map(|config_data| config_data.root)
-        .collect();
-    let chunked_roots_to_add: Vec<Vec<[u8; 32]>> = chunk_root_list_for_update_txs(roots_to_add);
+        .map(|merkle_allowlist_data| {
+            let price = match merkle_allowlist_data.price {
+                Some(price) => Some(parse_config_price(&client, &config_data, price)?),
+                None => None,
+            };
+
+            Ok(MerkleAllowlistRoot {
+                root: merkle_allowlist_data.root,
+                start_time: config_time_opt_as_timestamp(&merkle_allowlist_data.start_time)?,
+                end_time: config_time_opt_as_timestamp(&merkle_allowlist_data.end_time)?,
+                price,
+            })
+        })
+        .collect::<Result<Vec<MerkleAllowlistRoot>>>()?;
+    let chunked_roots_to_add: Vec<Vec<MerkleAllowlistRoot>> =
+        chunk_root_list_for_update_txs(roots_to_add);
 
     let tx_count = chunked_roots_to_add.len();
     let allowlist_size = allowlist_config
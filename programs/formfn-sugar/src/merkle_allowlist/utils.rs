@@ -3,7 +3,9 @@ use serde::{Deserialize, Serialize};
 use std::fs::File;
 use std::io::prelude::*;
 
-use formfn_candy_machine::constants::MAX_ROOT_NUMBER_PER_APPEND_MERKLE_ALLOWLIST_TX;
+use formfn_candy_machine::{
+    constants::MAX_ROOT_NUMBER_PER_APPEND_MERKLE_ALLOWLIST_TX, MerkleAllowlistRoot,
+};
 
 use super::MerkleAllowlistError;
 
@@ -22,6 +24,13 @@ pub struct MerkleAllowlistConfig {
 pub struct MerkleAllowlistConfigData {
     pub buyers: Vec<MerkleAllowlistBuyer>,
     pub root: MerkleRoot,
+    // Optional settings which bind this root to its own window and price.
+    #[serde(default)]
+    pub start_time: Option<String>,
+    #[serde(default)]
+    pub end_time: Option<String>,
+    #[serde(default)]
+    pub price: Option<f64>,
 }
 
 #[derive(Serialize, Deserialize, Debug)]
@@ -68,8 +77,10 @@ pub fn parse_merkle_allowlist_config(
  * Chunk the total roots list into smaller chunks. The chunk size is limited by
  * how many roots can find in a single transaction.
  */
-pub fn chunk_root_list_for_update_txs(roots_to_append: Vec<MerkleRoot>) -> Vec<Vec<MerkleRoot>> {
-    let chunked_roots_to_add: Vec<Vec<MerkleRoot>> = roots_to_append
+pub fn chunk_root_list_for_update_txs(
+    roots_to_append: Vec<MerkleAllowlistRoot>,
+) -> Vec<Vec<MerkleAllowlistRoot>> {
+    let chunked_roots_to_add: Vec<Vec<MerkleAllowlistRoot>> = roots_to_append
         .chunks(MAX_ROOT_NUMBER_PER_APPEND_MERKLE_ALLOWLIST_TX)
         .map(|x| x.to_vec())
         .collect();
@@ -15,9 +15,9 @@ use chrono::Utc;
 use console::style;
 use formfn_candy_machine::{
     accounts as nft_accounts, cmp_pubkeys, instruction as nft_instruction, CandyError,
-    CandyMachine, CollectionPda, SplTokenAllowlistMode,
+    CandyMachine, CollectionPda, MintPhase, MintPhaseAllowlistSource,
 };
-use mpl_token_metadata::pda::find_collection_authority_account;
+use mpl_token_metadata::pda::find_token_record_account;
 use solana_client::rpc_response::Response;
 use spl_associated_token_account::get_associated_token_address;
 use spl_token::{state::Account, ID as TOKEN_PROGRAM_ID};
@@ -39,6 +39,8 @@ pub struct MintArgs {
     pub number: Option<u64>,
     pub receiver: Option<String>,
     pub candy_machine: Option<String>,
+    pub payment_option: Option<u8>,
+    pub gating_mint: Option<String>,
 }
 
 pub async fn process_mint(args: MintArgs) -> Result<()> {
@@ -94,6 +96,43 @@ pub async fn process_mint(args: MintArgs) -> Result<()> {
     };
     println!("\nMinting to {}", &receiver_pubkey);
 
+    if candy_machine_state.data.bonding_curve_settings.is_some() {
+        let now = Utc::now().timestamp();
+        let mint_phase = CandyMachine::get_mint_phase(&candy_machine_state, now);
+        println!(
+            "Current price {}, next price {}",
+            candy_machine_state.get_mint_price(&mint_phase, now),
+            candy_machine_state.get_next_mint_price(&mint_phase, now)
+        );
+    }
+
+    let payment_option_index = args.payment_option;
+    if let Some(index) = payment_option_index {
+        match candy_machine_state.get_payment_option(Some(index)) {
+            Ok(Some(payment_option)) => println!(
+                "Paying with payment option {}, price {}",
+                index, payment_option.price
+            ),
+            _ => {
+                let error = anyhow!(CandyError::InvalidPaymentOptionIndex);
+                error!("{:?}", error);
+                return Err(error);
+            }
+        }
+    }
+
+    let gating_mint = match &args.gating_mint {
+        Some(gating_mint) => match Pubkey::from_str(gating_mint) {
+            Ok(gating_mint) => Some(gating_mint),
+            Err(_) => {
+                let error = anyhow!("Failed to parse gating mint: {}", gating_mint);
+                error!("{:?}", error);
+                return Err(error);
+            }
+        },
+        None => None,
+    };
+
     let number = args.number.unwrap_or(1);
     let available = candy_machine_state.data.items_available - candy_machine_state.items_redeemed;
 
@@ -119,6 +158,8 @@ pub async fn process_mint(args: MintArgs) -> Result<()> {
             candy_pubkey,
             Arc::clone(&candy_machine_state),
             Arc::clone(&collection_pda_info),
+            payment_option_index,
+            gating_mint,
         )
         .await
         {
@@ -153,6 +194,8 @@ pub async fn process_mint(args: MintArgs) -> Result<()> {
                     candy_pubkey,
                     candy_machine_state,
                     collection_pda_info,
+                    payment_option_index,
+                    gating_mint,
                 )
                 .await;
                 pb.inc(1);
@@ -196,11 +239,25 @@ pub async fn mint(
     candy_machine_id: Pubkey,
     candy_machine_state: Arc<CandyMachine>,
     collection_pda_info: Arc<Option<PdaInfo<CollectionPda>>>,
+    payment_option_index: Option<u8>,
+    gating_mint: Option<Pubkey>,
 ) -> Result<Signature> {
     let client = setup_client(&config)?;
     let program = client.program(CANDY_MACHINE_ID);
     let buyer = program.payer();
-    let treasury_wallet = candy_machine_state.treasury_wallet;
+
+    // The payment option replaces the treasury wallet, mint and price of the
+    // candy machine.
+    let payment_option = candy_machine_state
+        .get_payment_option(payment_option_index)
+        .map_err(|_| anyhow!(CandyError::InvalidPaymentOptionIndex))?;
+    let (treasury_wallet, treasury_mint) = match payment_option {
+        Some(payment_option) => (payment_option.treasury_wallet, payment_option.treasury_mint),
+        None => (
+            candy_machine_state.treasury_wallet,
+            candy_machine_state.treasury_mint,
+        ),
+    };
 
     let candy_machine_data = &candy_machine_state.data;
 
@@ -228,10 +285,28 @@ pub async fn mint(
 
     let mut additional_accounts: Vec<AccountMeta> = Vec::new();
 
-    // Check SPL token allowlist settings
-    if let Some(spl_token_allowlist_settings) = &candy_machine_data.spl_token_allowlist_settings {
+    // The allowlist accounts are only passed during allowlist phases which
+    // check them. Without a configured mint phase, every allowlist is checked.
+    let now = Utc::now().timestamp();
+    let is_allowlist_phase =
+        CandyMachine::get_mint_phase(&candy_machine_state, now) == MintPhase::Allowlist;
+    let allowlist_source = candy_machine_state
+        .get_active_mint_phase_index(now)
+        .map(|mint_phase_index| &candy_machine_data.mint_phases[mint_phase_index].allowlist_source);
+    let is_allowlist_checked = |source: MintPhaseAllowlistSource| {
+        is_allowlist_phase && allowlist_source.map_or(true, |active| *active == source)
+    };
+    let spl_token_allowlist_tokens =
+        if is_allowlist_checked(MintPhaseAllowlistSource::SplTokenAllowlist) {
+            candy_machine_data.get_spl_token_allowlist_tokens()
+        } else {
+            Vec::new()
+        };
+
+    // Check SPL token allowlist tokens
+    for spl_token_allowlist_token in spl_token_allowlist_tokens {
         let allowlist_token_account =
-            get_associated_token_address(&buyer, &spl_token_allowlist_settings.mint);
+            get_associated_token_address(&buyer, &spl_token_allowlist_token.mint);
 
         additional_accounts.push(AccountMeta {
             pubkey: allowlist_token_account,
@@ -239,7 +314,7 @@ pub async fn mint(
             is_writable: true,
         });
 
-        if spl_token_allowlist_settings.mode == SplTokenAllowlistMode::BurnEveryTime {
+        if spl_token_allowlist_token.burn_amount > 0 {
             let mut token_found = false;
 
             match program.rpc().get_account_data(&allowlist_token_account) {
@@ -247,9 +322,9 @@ pub async fn mint(
                     if !ata_data.is_empty() {
                         let account = Account::unpack_unchecked(&ata_data)?;
 
-                        if account.amount > 0 {
+                        if account.amount >= spl_token_allowlist_token.burn_amount {
                             additional_accounts.push(AccountMeta {
-                                pubkey: spl_token_allowlist_settings.mint,
+                                pubkey: spl_token_allowlist_token.mint,
                                 is_signer: false,
                                 is_writable: true,
                             });
@@ -273,7 +348,44 @@ pub async fn mint(
         }
     }
 
-    if let Some(treasury_mint) = candy_machine_state.treasury_mint {
+    // Check collection allowlist settings
+    if let (true, Some(collection_allowlist_settings)) = (
+        is_allowlist_checked(MintPhaseAllowlistSource::CollectionAllowlist),
+        &candy_machine_data.collection_allowlist_settings,
+    ) {
+        let gating_mint = gating_mint.ok_or_else(|| {
+            anyhow!(
+                "A gating mint from the allowlist collection {} is needed to mint.",
+                collection_allowlist_settings.collection_mint
+            )
+        })?;
+
+        additional_accounts.push(AccountMeta {
+            pubkey: get_associated_token_address(&buyer, &gating_mint),
+            is_signer: false,
+            is_writable: false,
+        });
+        additional_accounts.push(AccountMeta {
+            pubkey: find_metadata_pda(&gating_mint),
+            is_signer: false,
+            is_writable: false,
+        });
+        additional_accounts.push(AccountMeta {
+            pubkey: find_master_edition_pda(&gating_mint),
+            is_signer: false,
+            is_writable: false,
+        });
+
+        if collection_allowlist_settings.use_once {
+            additional_accounts.push(AccountMeta {
+                pubkey: find_collection_allowlist_record_pda(&candy_machine_id, &gating_mint),
+                is_signer: false,
+                is_writable: true,
+            });
+        }
+    }
+
+    if let Some(treasury_mint) = treasury_mint {
         let user_token_account_info = get_associated_token_address(&buyer, &treasury_mint);
 
         additional_accounts.push(AccountMeta {
@@ -283,6 +395,128 @@ pub async fn mint(
         });
     }
 
+    if let Some(platform_fee_wallet) = candy_machine_state.get_platform_fee_wallet() {
+        let platform_fee_account = match treasury_mint {
+            Some(treasury_mint) => {
+                get_associated_token_address(&platform_fee_wallet, &treasury_mint)
+            }
+            None => platform_fee_wallet,
+        };
+
+        additional_accounts.push(AccountMeta {
+            pubkey: platform_fee_account,
+            is_signer: false,
+            is_writable: true,
+        });
+    }
+
+    for primary_sale_split in candy_machine_state.data.primary_sale_splits.iter() {
+        let split_account = match treasury_mint {
+            Some(treasury_mint) => {
+                get_associated_token_address(&primary_sale_split.address, &treasury_mint)
+            }
+            None => primary_sale_split.address,
+        };
+
+        additional_accounts.push(AccountMeta {
+            pubkey: split_account,
+            is_signer: false,
+            is_writable: true,
+        });
+    }
+
+    // Programmable NFTs need the token record of the recipient token account,
+    // then the rule set accounts and the collection PDA.
+    if candy_machine_state.is_programmable() {
+        let recipient_token_account = get_associated_token_address(&buyer, &nft_mint.pubkey());
+        additional_accounts.push(AccountMeta {
+            pubkey: find_token_record_account(&nft_mint.pubkey(), &recipient_token_account).0,
+            is_signer: false,
+            is_writable: true,
+        });
+
+        if let Some(rule_set) = candy_machine_state.get_rule_set() {
+            additional_accounts.push(AccountMeta {
+                pubkey: Pubkey::from_str(AUTH_RULES_PROGRAM_ID)?,
+                is_signer: false,
+                is_writable: false,
+            });
+            additional_accounts.push(AccountMeta {
+                pubkey: rule_set,
+                is_signer: false,
+                is_writable: false,
+            });
+        }
+
+        if let Some((collection_pda_pubkey, _)) = collection_pda_info.as_ref() {
+            additional_accounts.push(AccountMeta {
+                pubkey: *collection_pda_pubkey,
+                is_signer: false,
+                is_writable: false,
+            });
+        }
+    }
+
+    // Compressed NFTs are minted into the candy machine Merkle tree, whose
+    // Bubblegum accounts are passed instead.
+    if let Some(compressed_nft_settings) = &candy_machine_state.data.compressed_nft_settings {
+        let merkle_tree = compressed_nft_settings.merkle_tree;
+        let (tree_authority, _) =
+            Pubkey::find_program_address(&[merkle_tree.as_ref()], &mpl_bubblegum::id());
+        additional_accounts.push(AccountMeta {
+            pubkey: tree_authority,
+            is_signer: false,
+            is_writable: true,
+        });
+        additional_accounts.push(AccountMeta {
+            pubkey: merkle_tree,
+            is_signer: false,
+            is_writable: true,
+        });
+        for program_id in [
+            Pubkey::from_str(NOOP_PROGRAM_ID)?,
+            Pubkey::from_str(ACCOUNT_COMPRESSION_PROGRAM_ID)?,
+            mpl_bubblegum::id(),
+        ] {
+            additional_accounts.push(AccountMeta {
+                pubkey: program_id,
+                is_signer: false,
+                is_writable: false,
+            });
+        }
+    }
+
+    // Open edition mints print the next edition of the master edition held
+    // by the open edition PDA.
+    if candy_machine_state.is_open_edition() {
+        let (open_edition_pda_pubkey, open_edition_pda) =
+            get_open_edition_pda(&candy_machine_id, &program)?;
+        let master_edition_mint = open_edition_pda.mint;
+        // The edition number is checked on chain, so read items_redeemed
+        // again in case other mints have changed it.
+        let candy_machine: CandyMachine = program.account(candy_machine_id)?;
+        for (pubkey, is_writable) in [
+            (open_edition_pda_pubkey, false),
+            (master_edition_mint, false),
+            (find_metadata_pda(&master_edition_mint), false),
+            (find_master_edition_pda(&master_edition_mint), true),
+            (
+                get_associated_token_address(&open_edition_pda_pubkey, &master_edition_mint),
+                false,
+            ),
+            (
+                find_edition_marker_pda(&master_edition_mint, candy_machine.items_redeemed + 1),
+                true,
+            ),
+        ] {
+            additional_accounts.push(AccountMeta {
+                pubkey,
+                is_signer: false,
+                is_writable,
+            });
+        }
+    }
+
     let metadata_pda = find_metadata_pda(&nft_mint.pubkey());
     let master_edition_pda = find_master_edition_pda(&nft_mint.pubkey());
     let (candy_machine_creator_pda, creator_bump) =
@@ -293,12 +527,20 @@ pub async fn mint(
     let (buyer_info_account, buyer_info_account_bump) =
         find_buyer_info_account_pda(&candy_machine_id, &buyer);
 
-    let buyer_token_account = get_associated_token_address(&buyer, &nft_mint.pubkey());
+    let recipient_token_account = get_associated_token_address(&buyer, &nft_mint.pubkey());
+
+    // Mints read their randomness from the trusted randomness account if the
+    // candy machine has one.
+    let recent_slothashes = match &candy_machine_state.data.trusted_randomness_account {
+        Some(trusted_randomness_account) => trusted_randomness_account.address,
+        None => sysvar::slot_hashes::ID,
+    };
 
     let mut accounts = formfn_candy_machine::accounts::MintNFT {
         candy_machine: candy_machine_id,
         candy_machine_creator: candy_machine_creator_pda,
-        buyer,
+        payer: buyer,
+        recipient: buyer,
         treasury_wallet,
         metadata: metadata_pda,
         mint: nft_mint.pubkey(),
@@ -308,11 +550,11 @@ pub async fn mint(
         token_program: TOKEN_PROGRAM_ID,
         system_program: system_program::id(),
         rent: sysvar::rent::ID,
-        recent_slothashes: sysvar::slot_hashes::ID,
+        recent_slothashes,
         instruction_sysvar_account: sysvar::instructions::ID,
         buyer_info_account,
         bot_signer_authority: bot_signer_authority.pubkey(),
-        buyer_token_account,
+        recipient_token_account,
         ata_program: AssociatedToken::id(),
     }
     .to_account_metas(None);
@@ -326,8 +568,18 @@ pub async fn mint(
         }
     }
 
-    let mint_phase = CandyMachine::get_mint_phase(&candy_machine_state, Utc::now().timestamp());
-    let mint_price = CandyMachine::get_mint_price(&candy_machine_state, &mint_phase);
+    let now = Utc::now().timestamp();
+    let mint_phase = CandyMachine::get_mint_phase(&candy_machine_state, now);
+    let mint_price = if let Some(payment_option) = payment_option {
+        payment_option.price
+    } else if candy_machine_data.bonding_curve_settings.is_some() {
+        // The bonding curve price depends on items_redeemed, which the other
+        // mints may have changed since candy_machine_state was fetched.
+        let candy_machine: CandyMachine = program.account(candy_machine_id)?;
+        CandyMachine::get_mint_price(&candy_machine, &mint_phase, now)
+    } else {
+        CandyMachine::get_mint_price(&candy_machine_state, &mint_phase, now)
+    };
 
     let mut mint_ix = program
         .request()
@@ -337,6 +589,7 @@ pub async fn mint(
             buyer_info_account_bump,
             buyer_merkle_allowlist_proof_data: None,
             expected_price: mint_price,
+            payment_option_index,
         });
 
     // Add additional accounts directly to the mint instruction otherwise it won't work.
@@ -355,8 +608,12 @@ pub async fn mint(
     }
 
     if let Some((collection_pda_pubkey, collection_pda)) = collection_pda_info.as_ref() {
-        let collection_authority_record =
-            find_collection_authority_account(&collection_pda.mint, collection_pda_pubkey).0;
+        let collection_authority_record = find_collection_authority_record_pda(
+            &collection_pda.mint,
+            collection_pda_pubkey,
+            &candy_machine_state.formfn_authority,
+            candy_machine_state.is_programmable(),
+        );
         builder = builder
             .accounts(nft_accounts::SetCollectionDuringMint {
                 candy_machine: candy_machine_id,
@@ -372,10 +629,20 @@ pub async fn mint(
                 collection_authority_record,
             })
             .args(nft_instruction::SetCollectionDuringMint {});
+
+        if candy_machine_state.is_programmable() {
+            builder = builder.accounts(vec![AccountMeta::new_readonly(system_program::ID, false)]);
+        }
     }
 
     let sig = builder.send()?;
 
+    // Compressed NFTs don't have a metadata account to check.
+    if candy_machine_state.is_compressed() {
+        info!("Minted! TxId: {}", sig);
+        return Ok(sig);
+    }
+
     if let Err(_) | Ok(Response { value: None, .. }) = program
         .rpc()
         .get_account_with_commitment(&metadata_pda, CommitmentConfig::confirmed())
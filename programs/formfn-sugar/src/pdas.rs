@@ -1,9 +1,18 @@
 use anchor_client::{solana_sdk::pubkey::Pubkey, ClientError, Program};
 use anyhow::{anyhow, Result};
-use formfn_candy_machine::{CollectionPda, BUYER_INFO_ACCOUNT_PREFIX};
+use formfn_candy_machine::{
+    CollectionAllowlistRecord, CollectionPda, OpenEditionPda, BUYER_INFO_ACCOUNT_PREFIX,
+};
 use mpl_token_metadata::{
-    pda::{find_master_edition_account, find_metadata_account},
-    state::{Key, MasterEditionV2, Metadata, TokenMetadataAccount, MAX_MASTER_EDITION_LEN},
+    instruction::MetadataDelegateRole,
+    pda::{
+        find_collection_authority_account, find_master_edition_account, find_metadata_account,
+        find_metadata_delegate_record_account,
+    },
+    state::{
+        Key, MasterEditionV2, Metadata, TokenMetadataAccount, EDITION, EDITION_MARKER_BIT_SIZE,
+        MAX_MASTER_EDITION_LEN, PREFIX,
+    },
     utils::try_from_slice_checked,
 };
 
@@ -84,6 +93,28 @@ pub fn find_collection_pda(candy_machine_id: &Pubkey) -> (Pubkey, u8) {
     Pubkey::find_program_address(collection_seeds, &CANDY_MACHINE_ID)
 }
 
+/// The account that lets the collection PDA verify minted NFTs. Programmable
+/// NFTs are verified by a collection delegate, whose record is derived from
+/// the collection update authority.
+pub fn find_collection_authority_record_pda(
+    collection_mint: &Pubkey,
+    collection_pda: &Pubkey,
+    collection_update_authority: &Pubkey,
+    is_programmable: bool,
+) -> Pubkey {
+    if is_programmable {
+        find_metadata_delegate_record_account(
+            collection_mint,
+            MetadataDelegateRole::Collection,
+            collection_update_authority,
+            collection_pda,
+        )
+        .0
+    } else {
+        find_collection_authority_account(collection_mint, collection_pda).0
+    }
+}
+
 pub fn get_collection_pda(
     candy_machine: &Pubkey,
     program: &Program,
@@ -101,6 +132,58 @@ pub fn get_collection_pda(
         })
 }
 
+pub fn find_open_edition_pda(candy_machine_id: &Pubkey) -> (Pubkey, u8) {
+    let open_edition_seeds = &[OpenEditionPda::PREFIX.as_bytes(), candy_machine_id.as_ref()];
+
+    Pubkey::find_program_address(open_edition_seeds, &CANDY_MACHINE_ID)
+}
+
+pub fn get_open_edition_pda(
+    candy_machine: &Pubkey,
+    program: &Program,
+) -> Result<PdaInfo<OpenEditionPda>> {
+    let open_edition_pda_pubkey = find_open_edition_pda(candy_machine).0;
+    program
+        .account(open_edition_pda_pubkey)
+        .map(|o| (open_edition_pda_pubkey, o))
+        .map_err(|e| match e {
+            ClientError::AccountNotFound => anyhow!("Candy Machine open edition is not set!"),
+            _ => anyhow!(
+                "Failed to deserialize open edition PDA account: {}",
+                &open_edition_pda_pubkey.to_string()
+            ),
+        })
+}
+
+/// The edition marker tracks which edition numbers of a master edition have
+/// been printed, EDITION_MARKER_BIT_SIZE editions per marker account.
+pub fn find_edition_marker_pda(master_edition_mint: &Pubkey, edition: u64) -> Pubkey {
+    let program_id = mpl_token_metadata::id();
+    let seeds = &[
+        PREFIX.as_bytes(),
+        program_id.as_ref(),
+        master_edition_mint.as_ref(),
+        EDITION.as_bytes(),
+        &(edition / EDITION_MARKER_BIT_SIZE).to_string().into_bytes(),
+    ];
+
+    Pubkey::find_program_address(seeds, &program_id).0
+}
+
+/// Marks an NFT from the allowlist collection as used, if each gating NFT can
+/// only be used once.
+pub fn find_collection_allowlist_record_pda(
+    candy_machine: &Pubkey,
+    gating_mint: &Pubkey,
+) -> Pubkey {
+    let seeds = &[
+        CollectionAllowlistRecord::PREFIX.as_bytes(),
+        candy_machine.as_ref(),
+        gating_mint.as_ref(),
+    ];
+    Pubkey::find_program_address(seeds, &CANDY_MACHINE_ID).0
+}
+
 pub fn find_buyer_info_account_pda(candy_machine: &Pubkey, buyer: &Pubkey) -> (Pubkey, u8) {
     let seeds = &[
         BUYER_INFO_ACCOUNT_PREFIX.as_bytes(),
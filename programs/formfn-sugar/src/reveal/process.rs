@@ -3,6 +3,9 @@ use std::sync::{Arc, Mutex};
 use anchor_client::solana_sdk::account::Account;
 use anchor_lang::AnchorDeserialize;
 use console::style;
+use formfn_candy_machine::{
+    accounts as nft_accounts, get_shuffled_order, instruction as nft_instruction, MintOrder,
+};
 use futures::future::join_all;
 use mpl_token_metadata::{
     instruction::update_metadata_accounts_v2,
@@ -16,7 +19,7 @@ use tokio::sync::Semaphore;
 
 use crate::{
     cache::load_cache,
-    candy_machine::CANDY_MACHINE_ID,
+    candy_machine::{get_candy_machine_state, CANDY_MACHINE_ID},
     common::*,
     config::{get_config_data, Cluster},
     pdas::{find_candy_machine_creator_pda, find_metadata_pda},
@@ -85,6 +88,42 @@ pub async fn process_reveal(args: RevealArgs) -> Result<()> {
         }
     };
 
+    // A shuffled mint order needs its seed revealed before the NFTs are
+    // matched to the cache items.
+    let candy_machine_state = get_candy_machine_state(&sugar_config, &candy_machine_id)?;
+    let shuffled_order = if candy_machine_state.data.mint_order == MintOrder::ShuffledAtReveal {
+        let seed = match candy_machine_state
+            .data
+            .shuffle_settings
+            .as_ref()
+            .and_then(|settings| settings.seed)
+        {
+            Some(seed) => seed,
+            None => {
+                let seed: [u8; 32] = bs58::decode(&cache.program.shuffle_seed)
+                    .into_vec()?
+                    .try_into()
+                    .map_err(|_| anyhow!("Missing or invalid shuffle seed in the cache file."))?;
+                let sig = program
+                    .request()
+                    .accounts(nft_accounts::RevealShuffleSeed {
+                        candy_machine: candy_machine_id,
+                        formfn_authority: program.payer(),
+                    })
+                    .args(nft_instruction::RevealShuffleSeed { seed })
+                    .send()?;
+                info!("Shuffle seed revealed with sig: {}", sig);
+                seed
+            }
+        };
+        Some(get_shuffled_order(
+            &seed,
+            candy_machine_state.data.items_available,
+        ))
+    } else {
+        None
+    };
+
     spinner.finish_with_message("Done");
 
     println!(
@@ -184,12 +223,27 @@ pub async fn process_reveal(args: RevealArgs) -> Result<()> {
         .collect();
 
     // Convert cache to make keys match NFT numbers.
-    let nft_lookup: HashMap<String, &CacheItem> = cache
-        .items
-        .iter()
-        .filter(|(k, _)| *k != "-1") // skip collection index
-        .map(|(k, item)| (increment_key(k), item))
-        .collect();
+    let nft_lookup: HashMap<String, &CacheItem> = match &shuffled_order {
+        // The NFT minted at each position gets the item at that index of the
+        // shuffled order.
+        Some(order) => order
+            .iter()
+            .enumerate()
+            .map(|(position, index)| {
+                let item = cache
+                    .items
+                    .get(&index.to_string())
+                    .ok_or_else(|| anyhow!("No cache item found for index: {index}"))?;
+                Ok((increment_key(&position.to_string()), item))
+            })
+            .collect::<Result<_>>()?,
+        None => cache
+            .items
+            .iter()
+            .filter(|(k, _)| *k != "-1") // skip collection index
+            .map(|(k, item)| (increment_key(k), item))
+            .collect(),
+    };
 
     spinner.finish_with_message("Done");
 
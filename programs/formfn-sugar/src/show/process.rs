@@ -2,15 +2,24 @@ use std::str::FromStr;
 
 use anchor_client::solana_sdk::{native_token::LAMPORTS_PER_SOL, pubkey::Pubkey};
 use anyhow::Result;
-use chrono::NaiveDateTime;
+use chrono::{NaiveDateTime, Utc};
 use console::style;
 use formfn_candy_machine::{
-    constants::{CONFIG_ARRAY_START, CONFIG_LINE_SIZE},
+    constants::{
+        COLLECTIONS_FEATURE_INDEX, CONFIG_ARRAY_START, FEATURES_VERSION, FREEZE_FEATURE_INDEX,
+        FREEZE_LOCK_FEATURE_INDEX,
+    },
     utils::is_feature_active,
-    SplTokenAllowlistMode,
+    BondingCurveMode, CandyMachine, SplTokenAllowlistMode,
 };
 
-use crate::{cache::load_cache, candy_machine::*, common::*, pdas::get_collection_pda, utils::*};
+use crate::{
+    cache::load_cache,
+    candy_machine::*,
+    common::*,
+    pdas::{get_collection_pda, get_open_edition_pda},
+    utils::*,
+};
 
 pub struct ShowArgs {
     pub keypair: Option<String>,
@@ -26,6 +35,28 @@ const SWAP_REMOVE_FEATURE_INDEX: usize = 1;
 // number of indices per line
 const PER_LINE: usize = 11;
 
+const FEATURE_NAMES: [(usize, &str); 3] = [
+    (COLLECTIONS_FEATURE_INDEX, "collections"),
+    (FREEZE_FEATURE_INDEX, "freeze"),
+    (FREEZE_LOCK_FEATURE_INDEX, "freeze lock"),
+];
+
+/// Decodes the candy machine features bitfield into feature names. Bits
+/// without a known feature are shown by index.
+fn get_feature_names(features: u64) -> Vec<String> {
+    (0..u64::BITS as usize)
+        .filter(|feature_index| is_feature_active(features, *feature_index))
+        .map(|feature_index| {
+            FEATURE_NAMES
+                .iter()
+                .find(|(index, _)| *index == feature_index)
+                .map_or(format!("bit {}", feature_index), |(_, name)| {
+                    name.to_string()
+                })
+        })
+        .collect()
+}
+
 pub fn process_show(args: ShowArgs) -> Result<()> {
     println!(
         "{} {}Looking up candy machine",
@@ -70,7 +101,7 @@ pub fn process_show(args: ShowArgs) -> Result<()> {
         };
 
     let candy_state = get_candy_machine_state(&sugar_config, &candy_machine_id)?;
-    let candy_data = candy_state.data;
+    let candy_data = candy_state.data.clone();
 
     pb.finish_and_clear();
 
@@ -84,6 +115,7 @@ pub fn process_show(args: ShowArgs) -> Result<()> {
     // candy machine state and data
 
     println!(" {}", style(":").dim());
+    print_with_style("", "version", candy_state.version.to_string());
     print_with_style("", "authority", candy_state.formfn_authority.to_string());
     print_with_style("", "wallet", candy_state.treasury_wallet.to_string());
     match collection_mint {
@@ -108,6 +140,17 @@ pub fn process_show(args: ShowArgs) -> Result<()> {
     );
 
     print_with_style("", "uuid", candy_data.uuid.to_string());
+    let feature_names = get_feature_names(candy_state.get_features());
+    let features = if feature_names.is_empty() {
+        "none".to_string()
+    } else {
+        feature_names.join(", ")
+    };
+    if candy_state.features_version < FEATURES_VERSION {
+        print_with_style("", "features", format!("{} (legacy uuid flags)", features));
+    } else {
+        print_with_style("", "features", features);
+    }
     print_with_style(
         "",
         "price",
@@ -117,6 +160,32 @@ pub fn process_show(args: ShowArgs) -> Result<()> {
             candy_data.price
         ),
     );
+
+    if candy_data.bonding_curve_settings.is_some() {
+        let now = Utc::now().timestamp();
+        let mint_phase = CandyMachine::get_mint_phase(&candy_state, now);
+        let current_price = candy_state.get_mint_price(&mint_phase, now);
+        let next_price = candy_state.get_next_mint_price(&mint_phase, now);
+        print_with_style(
+            "",
+            "current price",
+            format!(
+                "◎ {} ({})",
+                current_price as f64 / LAMPORTS_PER_SOL as f64,
+                current_price
+            ),
+        );
+        print_with_style(
+            "",
+            "next price",
+            format!(
+                "◎ {} ({})",
+                next_price as f64 / LAMPORTS_PER_SOL as f64,
+                next_price
+            ),
+        );
+    }
+
     print_with_style("", "symbol", candy_data.symbol.to_string());
     print_with_style(
         "",
@@ -142,6 +211,172 @@ pub fn process_show(args: ShowArgs) -> Result<()> {
         date.format("%a %B %e %Y %H:%M:%S UTC").to_string(),
     );
 
+    if candy_data.mint_phases.is_empty() {
+        print_with_style("", "mint phases", "none".to_string());
+    } else {
+        print_with_style("", "mint phases", "".to_string());
+    }
+
+    for (index, mint_phase) in candy_data.mint_phases.iter().enumerate() {
+        let date = NaiveDateTime::from_timestamp_opt(mint_phase.start_time, 0).unwrap();
+        let info = format!(
+            "{}, {}, price {}, limit per address {}",
+            date.format("%a %B %e %Y %H:%M:%S UTC"),
+            get_allowlist_source_label(&mint_phase.allowlist_source),
+            mint_phase.price,
+            mint_phase.limit_per_address
+        );
+        print_with_style(":   ", &(index + 1).to_string(), info);
+    }
+
+    if candy_data.primary_sale_splits.is_empty() {
+        print_with_style("", "primary sale splits", "none".to_string());
+    } else {
+        print_with_style("", "primary sale splits", "".to_string());
+    }
+
+    for (index, primary_sale_split) in candy_data.primary_sale_splits.iter().enumerate() {
+        let info = format!(
+            "{} ({}%, {} basis points)",
+            primary_sale_split.address,
+            primary_sale_split.basis_points as f64 / 100.0,
+            primary_sale_split.basis_points
+        );
+        print_with_style(":   ", &(index + 1).to_string(), info);
+    }
+
+    match &candy_data.dutch_auction_settings {
+        Some(settings) => {
+            print_with_style("", "dutch auction", "".to_string());
+            print_with_style(
+                ":   ",
+                "start price",
+                format!(
+                    "◎ {} ({})",
+                    settings.start_price as f64 / LAMPORTS_PER_SOL as f64,
+                    settings.start_price
+                ),
+            );
+            print_with_style(
+                ":   ",
+                "floor price",
+                format!(
+                    "◎ {} ({})",
+                    settings.floor_price as f64 / LAMPORTS_PER_SOL as f64,
+                    settings.floor_price
+                ),
+            );
+            print_with_style(
+                ":   ",
+                "price decrement",
+                format!(
+                    "◎ {} ({}) every {} seconds",
+                    settings.price_decrement as f64 / LAMPORTS_PER_SOL as f64,
+                    settings.price_decrement,
+                    settings.decrement_interval
+                ),
+            );
+            print_with_style(
+                ":   ",
+                "rebate enabled",
+                settings.rebate_enabled.to_string(),
+            );
+        }
+        None => print_with_style("", "dutch auction", "none".to_string()),
+    }
+
+    match &candy_data.bonding_curve_settings {
+        Some(settings) => {
+            let mode = match settings.mode {
+                BondingCurveMode::Linear => "linear".to_string(),
+                BondingCurveMode::Step => format!("step every {} items", settings.items_per_step),
+            };
+            print_with_style("", "bonding curve", mode);
+            print_with_style(
+                ":   ",
+                "start price",
+                format!(
+                    "◎ {} ({})",
+                    settings.start_price as f64 / LAMPORTS_PER_SOL as f64,
+                    settings.start_price
+                ),
+            );
+            print_with_style(
+                ":   ",
+                "price increment",
+                format!(
+                    "◎ {} ({})",
+                    settings.price_increment as f64 / LAMPORTS_PER_SOL as f64,
+                    settings.price_increment
+                ),
+            );
+        }
+        None => print_with_style("", "bonding curve", "none".to_string()),
+    }
+
+    if candy_data.payment_options.is_empty() {
+        print_with_style("", "payment options", "none".to_string());
+    } else {
+        print_with_style("", "payment options", "".to_string());
+    }
+
+    for (index, payment_option) in candy_data.payment_options.iter().enumerate() {
+        let price = match payment_option.treasury_mint {
+            Some(treasury_mint) => format!("{} of {}", payment_option.price, treasury_mint),
+            None => format!(
+                "◎ {} ({})",
+                payment_option.price as f64 / LAMPORTS_PER_SOL as f64,
+                payment_option.price
+            ),
+        };
+        let info = format!("{}, paid to {}", price, payment_option.treasury_wallet);
+        print_with_style(":   ", &index.to_string(), info);
+    }
+
+    match &candy_data.programmable_nft_settings {
+        Some(settings) => {
+            let rule_set = match settings.rule_set {
+                Some(rule_set) => rule_set.to_string(),
+                None => "none".to_string(),
+            };
+            print_with_style("", "programmable NFT rule set", rule_set);
+        }
+        None => print_with_style("", "programmable NFT", "no".to_string()),
+    }
+
+    match &candy_data.compressed_nft_settings {
+        Some(settings) => print_with_style(
+            "",
+            "compressed NFT merkle tree",
+            settings.merkle_tree.to_string(),
+        ),
+        None => print_with_style("", "compressed NFT", "no".to_string()),
+    }
+
+    if candy_data.open_edition_enabled {
+        match get_open_edition_pda(&candy_machine_id, &program) {
+            Ok((_, open_edition_pda)) => {
+                print_with_style("", "open edition master", open_edition_pda.mint.to_string())
+            }
+            Err(_) => print_with_style("", "open edition master", "not set".to_string()),
+        }
+    } else {
+        print_with_style("", "open edition", "no".to_string());
+    }
+
+    match candy_state.get_platform_fee_wallet() {
+        Some(platform_fee_wallet) => {
+            let info = format!(
+                "{} ({}%, {} basis points)",
+                platform_fee_wallet,
+                candy_state.platform_fee_basis_points as f64 / 100.0,
+                candy_state.platform_fee_basis_points
+            );
+            print_with_style("", "platform fee", info);
+        }
+        None => print_with_style("", "platform fee", "none".to_string()),
+    }
+
     print_with_style("", "creators", "".to_string());
 
     for (index, creator) in candy_data.creators.into_iter().enumerate() {
@@ -168,6 +403,96 @@ pub fn process_show(args: ShowArgs) -> Result<()> {
         print_with_style("", "hidden settings", "none".to_string());
     }
 
+    // config line settings
+    if let Some(config_line_settings) = candy_data.config_line_settings {
+        print_with_style("", "config line settings", "".to_string());
+        print_with_style(":   ", "name prefix", config_line_settings.prefix_name);
+        print_with_style(
+            ":   ",
+            "name length",
+            config_line_settings.name_length.to_string(),
+        );
+        print_with_style(":   ", "uri prefix", config_line_settings.prefix_uri);
+        print_with_style(
+            ":   ",
+            "uri length",
+            config_line_settings.uri_length.to_string(),
+        );
+    } else {
+        print_with_style("", "config line settings", "none".to_string());
+    }
+
+    // trusted randomness account
+    if let Some(trusted_randomness_account) = candy_data.trusted_randomness_account {
+        print_with_style("", "trusted randomness account", "".to_string());
+        print_with_style(":   ", "address", trusted_randomness_account.address.to_string());
+        print_with_style(":   ", "offset", trusted_randomness_account.offset.to_string());
+    } else {
+        print_with_style("", "trusted randomness account", "none".to_string());
+    }
+
+    // mint order
+    print_with_style("", "mint order", format!("{:?}", candy_data.mint_order));
+    if let Some(shuffle_settings) = candy_data.shuffle_settings {
+        print_with_style(
+            ":   ",
+            "seed commitment",
+            bs58::encode(shuffle_settings.seed_commitment).into_string(),
+        );
+        print_with_style(
+            ":   ",
+            "seed",
+            match shuffle_settings.seed {
+                Some(seed) => bs58::encode(seed).into_string(),
+                None => "not revealed".to_string(),
+            },
+        );
+    }
+
+    // phase limits
+    let phase_limits = candy_data.phase_limits;
+    print_with_style("", "phase limits per address", "".to_string());
+    print_with_style(
+        ":   ",
+        "premint",
+        match phase_limits.premint_limit_per_address {
+            0 => "unlimited".to_string(),
+            limit => limit.to_string(),
+        },
+    );
+    print_with_style(
+        ":   ",
+        "allowlist",
+        match phase_limits.allowlist_limit_per_address {
+            0 => "unlimited".to_string(),
+            limit => limit.to_string(),
+        },
+    );
+
+    // mint caps
+    let mint_caps = candy_data.mint_caps;
+    print_with_style(
+        "",
+        "total limit per address",
+        match mint_caps.total_limit_per_address {
+            0 => "unlimited".to_string(),
+            limit => limit.to_string(),
+        },
+    );
+    print_with_style(
+        "",
+        "allowlist items cap",
+        match mint_caps.allowlist_items_cap {
+            0 => "none".to_string(),
+            cap => cap.to_string(),
+        },
+    );
+    print_with_style(
+        "",
+        "allowlist items sold",
+        candy_state.allowlist_items_sold.to_string(),
+    );
+
     // SPL token allowlist settings
     if let Some(spl_token_allowlist_settings) = candy_data.spl_token_allowlist_settings {
         print_with_style("", "SPL token allowlist settings", "".to_string());
@@ -189,6 +514,43 @@ pub fn process_show(args: ShowArgs) -> Result<()> {
         print_with_style("", "SPL token allowlist settings", "none".to_string());
     }
 
+    // SPL token allowlist tokens
+    if candy_data.spl_token_allowlist_tokens.is_empty() {
+        print_with_style("", "SPL token allowlist tokens", "none".to_string());
+    } else {
+        print_with_style("", "SPL token allowlist tokens", "".to_string());
+        for spl_token_allowlist_token in &candy_data.spl_token_allowlist_tokens {
+            print_with_style(":   ", "mint", spl_token_allowlist_token.mint.to_string());
+            print_with_style(
+                ":   :   ",
+                "burn amount",
+                spl_token_allowlist_token.burn_amount.to_string(),
+            );
+            print_with_style(
+                ":   :   ",
+                "min balance",
+                spl_token_allowlist_token.min_balance.to_string(),
+            );
+        }
+    }
+
+    // collection allowlist settings
+    if let Some(collection_allowlist_settings) = candy_data.collection_allowlist_settings {
+        print_with_style("", "collection allowlist settings", "".to_string());
+        print_with_style(
+            ":   ",
+            "collection mint",
+            collection_allowlist_settings.collection_mint.to_string(),
+        );
+        print_with_style(
+            ":   ",
+            "use once",
+            collection_allowlist_settings.use_once.to_string(),
+        );
+    } else {
+        print_with_style("", "collection allowlist settings", "none".to_string());
+    }
+
     // unminted indices
 
     if args.unminted {
@@ -200,7 +562,7 @@ pub fn process_show(args: ShowArgs) -> Result<()> {
 
         let mut start = CONFIG_ARRAY_START
             + STRING_LEN_SIZE
-            + CONFIG_LINE_SIZE * candy_data.items_available as usize
+            + candy_state.data.get_config_line_size() * candy_data.items_available as usize
             + STRING_LEN_SIZE
             + candy_data
                 .items_available
@@ -214,10 +576,21 @@ pub fn process_show(args: ShowArgs) -> Result<()> {
         let data = program.rpc().get_account_data(&candy_machine_id)?;
 
         pb.finish_and_clear();
+
+        // Compacted candy machines no longer store their mint bitmask.
+        if data.len() <= CONFIG_ARRAY_START {
+            println!(
+                "\n{}{}",
+                PAPER_EMOJI,
+                style("The config lines of the candy machine have been compacted.").dim()
+            );
+            return Ok(());
+        }
+
         let mut index = 0;
         let mut indices = vec![];
 
-        if is_feature_active(&candy_data.uuid, SWAP_REMOVE_FEATURE_INDEX) {
+        if candy_state.is_feature_active(SWAP_REMOVE_FEATURE_INDEX) {
             start += 1; // needed to get around rounding precision
             let remaining = candy_data.items_available - candy_state.items_redeemed;
             for i in 0..remaining {
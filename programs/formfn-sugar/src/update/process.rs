@@ -7,17 +7,25 @@ use chrono::{Duration, Utc};
 use console::{style, Style};
 use dialoguer::{theme::ColorfulTheme, Confirm};
 use formfn_candy_machine::{
-    accounts as nft_accounts, instruction as nft_instruction, CandyMachineData,
+    accounts as nft_accounts, instruction as nft_instruction, CandyMachineData, MintCaps,
+    PhaseLimits,
 };
+use solana_program::entrypoint::MAX_PERMITTED_DATA_INCREASE;
 use spl_associated_token_account::get_associated_token_address;
 
 use crate::{
-    candy_machine::{get_candy_machine_state, parse_config_price, CANDY_MACHINE_ID},
+    candy_machine::{
+        get_candy_machine_state, parse_config_bonding_curve_settings,
+        parse_config_dutch_auction_settings, parse_config_mint_phases,
+        parse_config_payment_options, parse_config_price, parse_config_primary_sale_splits,
+        CANDY_MACHINE_ID,
+    },
     common::*,
     config::{
         data::{ConfigData, *},
         parser::get_config_data,
     },
+    deploy::update_platform_fee,
     utils::{
         assert_correct_authority, check_spl_token, check_spl_token_account, get_dialoguer_theme,
         read_candy_machine_pubkey_from_file, spinner_with_style,
@@ -147,6 +155,29 @@ pub fn process_update(args: UpdateArgs) -> Result<()> {
         },
     };
 
+    // update_candy_machine can't change the number of config lines.
+    if candy_machine_data.hidden_settings.is_none()
+        && !candy_machine_data.open_edition_enabled
+        && candy_machine_data.items_available != candy_machine_state.data.items_available
+    {
+        let pb = spinner_with_style();
+        pb.set_message("Sending resize transactions...");
+
+        let resize_signature = resize_candy_machine(
+            &program,
+            &candy_pubkey,
+            candy_machine_data.get_config_line_size(),
+            candy_machine_state.data.items_available,
+            candy_machine_data.items_available,
+        )?;
+
+        pb.finish_with_message(format!(
+            "{} {}",
+            style("Resize signature:").bold(),
+            resize_signature
+        ));
+    }
+
     let mut builder = program
         .request()
         .accounts(nft_accounts::UpdateCandyMachine {
@@ -175,6 +206,21 @@ pub fn process_update(args: UpdateArgs) -> Result<()> {
         update_signature
     ));
 
+    if config_data.platform_fee_basis_points != candy_machine_state.platform_fee_basis_points
+        || config_data.platform_fee_wallet != candy_machine_state.platform_fee_wallet
+    {
+        let pb = spinner_with_style();
+        pb.set_message("Sending update platform fee transaction...");
+
+        let platform_fee_signature = update_platform_fee(&config_data, &candy_pubkey, &program)?;
+
+        pb.finish_with_message(format!(
+            "{} {}",
+            style("Platform fee signature:").bold(),
+            platform_fee_signature
+        ));
+    }
+
     if let Some(new_authority) = args.new_authority {
         let pb = spinner_with_style();
         pb.set_message("Sending update authority transaction...");
@@ -202,6 +248,37 @@ pub fn process_update(args: UpdateArgs) -> Result<()> {
     Ok(())
 }
 
+/// Resizes the candy machine in steps, since an account can only grow by
+/// MAX_PERMITTED_DATA_INCREASE bytes per instruction.
+fn resize_candy_machine(
+    program: &Program,
+    candy_pubkey: &Pubkey,
+    config_line_size: usize,
+    items_available: u64,
+    new_items_available: u64,
+) -> Result<Signature> {
+    // Each item also takes two bits in the bitmasks.
+    let max_increase = (MAX_PERMITTED_DATA_INCREASE / (config_line_size + 1)) as u64;
+    let mut items_available = items_available;
+
+    loop {
+        items_available = new_items_available.min(items_available + max_increase);
+        let signature = program
+            .request()
+            .accounts(nft_accounts::ResizeCandyMachine {
+                candy_machine: *candy_pubkey,
+                formfn_authority: program.payer(),
+                system_program: system_program::id(),
+            })
+            .args(nft_instruction::ResizeCandyMachine { items_available })
+            .send()?;
+
+        if items_available == new_items_available {
+            return Ok(signature);
+        }
+    }
+}
+
 fn create_candy_machine_data(
     client: &Client,
     config: &ConfigData,
@@ -236,6 +313,16 @@ fn create_candy_machine_data(
         .map(|c| c.to_candy_format())
         .collect::<Result<Vec<formfn_candy_machine::Creator>>>()?;
 
+    let mint_phases = parse_config_mint_phases(client, config)?;
+    let primary_sale_splits = parse_config_primary_sale_splits(config);
+    let dutch_auction_settings = parse_config_dutch_auction_settings(client, config)?;
+    let bonding_curve_settings = parse_config_bonding_curve_settings(client, config)?;
+    let payment_options = parse_config_payment_options(client, config)?;
+    let programmable_nft_settings = config
+        .programmable_nft
+        .as_ref()
+        .map(|s| s.to_candy_format());
+
     let omni_mint_wallets: Vec<Pubkey> = creators.iter().map(|creator| creator.address).collect();
 
     let default_end_time = Utc::now().timestamp() + Duration::days(1).num_seconds();
@@ -255,12 +342,51 @@ fn create_candy_machine_data(
         omni_mint_wallets,
         spl_token_allowlist_settings,
         hidden_settings,
-        items_available: config.number,
+        items_available: match &config.open_edition {
+            Some(open_edition_config) => open_edition_config.get_items_available(),
+            None => config.number,
+        },
         limit_per_address: config.limit_per_address,
         bot_protection_enabled: config.bot_protection_enabled,
-        sequential_mint_order_enabled: config.sequential_mint_order_enabled,
+        mint_order: config.mint_order.to_candy_format(),
         merkle_allowlist_root_list: Vec::new(),
         allowlist_sale_start_time,
+        mint_phases,
+        primary_sale_splits,
+        dutch_auction_settings,
+        bonding_curve_settings,
+        payment_options,
+        programmable_nft_settings,
+        // The Merkle tree is created by deploy and can't be changed here.
+        compressed_nft_settings: candy_machine.compressed_nft_settings.clone(),
+        // Open edition mode can't be changed after the candy machine is created.
+        open_edition_enabled: candy_machine.open_edition_enabled,
+        // The config line layout can't be changed after the candy machine is
+        // created.
+        config_line_settings: candy_machine.config_line_settings.clone(),
+        trusted_randomness_account: config
+            .trusted_randomness_account
+            .as_ref()
+            .map(|trusted_randomness_account| trusted_randomness_account.to_candy_format()),
+        // The seed commitment is set by deploy and the seed by reveal.
+        shuffle_settings: candy_machine.shuffle_settings.clone(),
+        phase_limits: PhaseLimits {
+            premint_limit_per_address: config.premint_limit_per_address,
+            allowlist_limit_per_address: config.allowlist_limit_per_address,
+        },
+        mint_caps: MintCaps {
+            total_limit_per_address: config.total_limit_per_address,
+            allowlist_items_cap: config.allowlist_items_cap,
+        },
+        collection_allowlist_settings: config
+            .collection_allowlist_settings
+            .as_ref()
+            .map(|settings| settings.to_candy_format()),
+        spl_token_allowlist_tokens: config
+            .spl_token_allowlist_tokens
+            .iter()
+            .map(|token| token.to_candy_format())
+            .collect(),
     };
     Ok(data)
 }
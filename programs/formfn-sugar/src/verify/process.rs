@@ -2,7 +2,7 @@ use std::{thread, time::Duration};
 
 use anchor_lang::AccountDeserialize;
 use console::style;
-use formfn_candy_machine::constants::{CONFIG_ARRAY_START, CONFIG_LINE_SIZE};
+use formfn_candy_machine::constants::CONFIG_ARRAY_START;
 use formfn_candy_machine::CandyMachine;
 
 use crate::{
@@ -89,6 +89,7 @@ pub fn process_verify(args: VerifyArgs) -> Result<()> {
 
     if candy_machine.data.hidden_settings.is_none() {
         let num_items = candy_machine.data.items_available;
+        let config_line_size = candy_machine.data.get_config_line_size();
         let cache_items = &mut cache.items;
         let mut errors = Vec::new();
 
@@ -102,31 +103,17 @@ pub fn process_verify(args: VerifyArgs) -> Result<()> {
         };
 
         for i in 0..num_items {
-            let name_start = CONFIG_ARRAY_START
-                + STRING_LEN_SIZE
-                + CONFIG_LINE_SIZE * (i as usize)
-                + CONFIG_NAME_OFFSET;
-            let name_end = name_start + MAX_NAME_LENGTH;
-
-            let uri_start = CONFIG_ARRAY_START
-                + STRING_LEN_SIZE
-                + CONFIG_LINE_SIZE * (i as usize)
-                + CONFIG_URI_OFFSET;
-            let uri_end = uri_start + MAX_URI_LENGTH;
-
-            let name_error = format!("Failed to decode name for item {}", i);
-            let name = String::from_utf8(data[name_start..name_end].to_vec())
-                .expect(&name_error)
-                .trim_matches(char::from(0))
-                .to_string();
-
-            let uri_error = format!("Failed to decode uri for item {}", i);
-            let uri = String::from_utf8(data[uri_start..uri_end].to_vec())
-                .expect(&uri_error)
-                .trim_matches(char::from(0))
-                .to_string();
-
-            let on_chain_item = OnChainItem { name, uri };
+            let config_line_start =
+                CONFIG_ARRAY_START + STRING_LEN_SIZE + config_line_size * (i as usize);
+            let config_line = candy_machine
+                .data
+                .parse_config_line(&data[config_line_start..config_line_start + config_line_size])
+                .map_err(|_| anyhow!("Failed to decode config line for item {}", i))?;
+
+            let on_chain_item = OnChainItem {
+                name: config_line.name,
+                uri: config_line.uri,
+            };
             let cache_item = cache_items
                 .get_mut(&i.to_string())
                 .expect("Failed to get item from config.");